@@ -0,0 +1,184 @@
+use crate::{errors::to_py_err, module, perf, store::Store, wasmer_inner::wasmer};
+use pyo3::{exceptions::PyRuntimeError, prelude::*};
+use std::{
+    fs, io,
+    path::PathBuf,
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+use wasmer_cache::{Cache as _, FileSystemCache, Hash};
+
+/// An on-disk cache of compiled modules.
+///
+/// When a `Cache` is given to `Module`, the compiled artifact is
+/// stored in the cache directory the first time, and is
+/// transparently reused (deserialized) when the same module is
+/// constructed again.
+///
+/// The cache key is computed from the WebAssembly bytes, the engine,
+/// the configuration of the compiler (including its middlewares),
+/// the features, the target (triple and CPU features) and the Wasmer
+/// version, so that an artifact is never reused in an incompatible
+/// configuration. Modules compiled with a middleware calling a Python
+/// function, i.e. a `Middleware` or a `Metering` with a cost
+/// function, cannot be told apart: they are compiled every time, and
+/// never cached.
+///
+/// The directory is created if it does not exist. An artifact is
+/// written to a temporary file of the directory, then renamed, so
+/// that the processes sharing the directory never load a partially
+/// written artifact. If it cannot be written, a `RuntimeWarning` is
+/// emitted, and the compiled module is used anyway.
+///
+/// ## Example
+///
+/// ```py
+/// from wasmer import Store, Module, Cache
+/// import tempfile
+///
+/// store = Store()
+/// cache = Cache(tempfile.mkdtemp())
+///
+/// # The first time, the module is compiled and stored in the cache.
+/// module = Module(store, '(module)', cache=cache)
+///
+/// # The second time, the module is loaded from the cache.
+/// module = Module(store, '(module)', cache=cache)
+/// ```
+#[pyclass]
+#[pyo3(text_signature = "(path)")]
+pub struct Cache {
+    inner: FileSystemCache,
+    path: PathBuf,
+}
+
+impl Cache {
+    /// Compute the cache key of some WebAssembly bytes for a given
+    /// store, or `None` if the configuration of the store cannot be
+    /// described, see `Store::fingerprint`.
+    fn key(store: &Store, bytes: &[u8]) -> PyResult<Option<Hash>> {
+        let fingerprint = match store.fingerprint() {
+            Some(fingerprint) => fingerprint,
+            None => return Ok(None),
+        };
+        let target = store.inner()?.engine().target();
+        let mut cpu_features = target
+            .cpu_features()
+            .iter()
+            .map(|feature| feature.to_string())
+            .collect::<Vec<String>>();
+        cpu_features.sort();
+
+        let mut material = Hash::generate(bytes).to_string();
+
+        for part in &[
+            store.engine_name().as_str(),
+            store.compiler_name().map(String::as_str).unwrap_or(""),
+            fingerprint,
+            &target.triple().to_string(),
+            &cpu_features.join(","),
+            env!("CARGO_PKG_VERSION"),
+        ] {
+            material.push('\0');
+            material.push_str(part);
+        }
+
+        Ok(Some(Hash::generate(material.as_bytes())))
+    }
+
+    /// Load a module from the cache, or compile and store it, see
+    /// `module::compile`.
+    pub(crate) fn load_or_compile(
+        &mut self,
+        py: Python,
+        store: &Store,
+        bytes: &[u8],
+        progress: Option<PyObject>,
+    ) -> PyResult<wasmer::Module> {
+        let key = match Self::key(store, bytes)? {
+            Some(key) => key,
+            None => return module::compile(py, store, bytes, progress),
+        };
+
+        // SAFETY: The cache key includes everything that changes the
+        // compiled code: the engine, the configuration of the
+        // compiler and its middlewares, the features, the target and
+        // the Wasmer version. A cached artifact has thus been
+        // compiled the same way as `module::compile` would, as long as
+        // the cache directory is only written by `Cache`: a tampered
+        // artifact is undefined behavior. The artifacts are complete
+        // once they have their name, see `Cache::store`.
+        if let Ok(module) = unsafe { self.inner.load(store.inner()?, key) } {
+            perf::register(store, &module)?;
+
+            return Ok(module);
+        }
+
+        let module = module::compile(py, store, bytes, progress)?;
+
+        if let Err(error) = self.store(key, &module) {
+            PyErr::warn(
+                py,
+                py.import("builtins")?.getattr("RuntimeWarning")?,
+                &format!("Failed to store the module in the cache: {}", error),
+                1,
+            )?;
+        }
+
+        Ok(module)
+    }
+
+    /// Store a module in the cache, where `FileSystemCache::load`
+    /// looks for it.
+    ///
+    /// `FileSystemCache::store` truncates the artifact before writing
+    /// it, so that another process could load it partially written.
+    /// It is written to a temporary file of the cache directory
+    /// instead, which is then renamed atomically.
+    fn store(&self, key: Hash, module: &wasmer::Module) -> io::Result<()> {
+        static TEMPORARY_FILES: AtomicUsize = AtomicUsize::new(0);
+
+        let bytes = module.serialize().map_err(io::Error::other)?;
+        let path = self.path.join(key.to_string());
+        let temporary_path = self.path.join(format!(
+            ".{}.{}.{}",
+            key.to_string(),
+            process::id(),
+            TEMPORARY_FILES.fetch_add(1, Ordering::Relaxed)
+        ));
+
+        fs::write(&temporary_path, bytes)
+            .and_then(|()| fs::rename(&temporary_path, &path))
+            .inspect_err(|_| {
+                let _ = fs::remove_file(&temporary_path);
+            })
+    }
+}
+
+#[pymethods]
+impl Cache {
+    #[new]
+    fn new(path: PathBuf) -> PyResult<Self> {
+        Ok(Self {
+            inner: FileSystemCache::new(&path).map_err(to_py_err::<PyRuntimeError, _>)?,
+            path,
+        })
+    }
+
+    /// The path of the cache directory.
+    ///
+    /// ## Example
+    ///
+    /// ```py
+    /// from wasmer import Cache
+    /// import tempfile
+    ///
+    /// directory = tempfile.mkdtemp()
+    ///
+    /// assert Cache(directory).path == directory
+    /// ```
+    #[getter]
+    fn path(&self) -> String {
+        self.path.display().to_string()
+    }
+}
@@ -0,0 +1,262 @@
+use crate::{
+    errors::{self, to_py_err},
+    store,
+    wasmer_inner::{wasmer, wasmer_wasi::WasiError},
+};
+use pyo3::{exceptions::PyRuntimeError, ffi, prelude::*, AsPyPointer};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// The number of core dumps written by the process, to name them.
+static COREDUMPS: AtomicUsize = AtomicUsize::new(0);
+
+/// Converts `error` like `errors::runtime_error_to_py_err`. If it is
+/// a trap, and `store` has a `coredump_directory`, a core dump of the
+/// instance exporting `exports` is written first, and its path is
+/// the `coredump_path` attribute of the `Trap`.
+pub(crate) fn runtime_error_to_py_err(
+    error: wasmer::RuntimeError,
+    store: &wasmer::Store,
+    exports: Option<&wasmer::Exports>,
+) -> PyErr {
+    let directory = match store::coredump_directory(store) {
+        Some(directory) if !error.is::<PyErr>() && !error.is::<WasiError>() => directory,
+        _ => return errors::runtime_error_to_py_err(error),
+    };
+
+    let coredump = write(&directory, &error, exports);
+    let err = errors::runtime_error_to_py_err(error);
+
+    Python::with_gil(|py| match coredump {
+        Ok(path) => match err.instance(py).setattr("coredump_path", path) {
+            Ok(()) => err,
+            Err(err) => err,
+        },
+
+        // The trap is more important than the core dump.
+        Err(coredump_err) => {
+            coredump_err.restore(py);
+
+            // SAFETY: the GIL is held, and an error is set.
+            unsafe { ffi::PyErr_WriteUnraisable(err.instance(py).as_ptr()) };
+
+            err
+        }
+    })
+}
+
+fn write(
+    directory: &Path,
+    error: &wasmer::RuntimeError,
+    exports: Option<&wasmer::Exports>,
+) -> PyResult<PathBuf> {
+    let path = directory.join(format!(
+        "wasm-{}-{}.coredump",
+        process::id(),
+        COREDUMPS.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    fs::write(&path, coredump(error, exports)).map_err(|error| {
+        to_py_err::<PyRuntimeError, _>(format!(
+            "Failed to write the core dump `{}`: {}",
+            path.display(),
+            error
+        ))
+    })?;
+
+    Ok(path)
+}
+
+/// Encodes a core dump, following the WebAssembly tool conventions:
+/// a module whose custom sections describe the process and the stack
+/// of the trap, and whose memories and globals hold the state of the
+/// instance. The locals and the operand stacks of the frames are not
+/// known.
+fn coredump(error: &wasmer::RuntimeError, exports: Option<&wasmer::Exports>) -> Vec<u8> {
+    let frames = error.trace();
+    let module_name = frames
+        .first()
+        .map(|frame| frame.module_name())
+        .unwrap_or("<module>");
+    let (memories, globals) = match exports {
+        Some(exports) => (
+            exports
+                .iter()
+                .memories()
+                .map(|(_, memory)| memory)
+                .collect(),
+            exports.iter().globals().map(|(_, global)| global).collect(),
+        ),
+        None => (Vec::new(), Vec::new()),
+    };
+
+    let mut bytes = b"\0asm\x01\0\0\0".to_vec();
+
+    let mut process_info = vec![0];
+    name(&mut process_info, module_name);
+    section(&mut bytes, 0, &custom_payload("core", &process_info));
+
+    let mut modules = Vec::new();
+    leb128(&mut modules, 1);
+    modules.push(0);
+    name(&mut modules, module_name);
+    section(&mut bytes, 0, &custom_payload("coremodules", &modules));
+
+    let mut instances = Vec::new();
+    leb128(&mut instances, 1);
+    instances.push(0);
+    leb128(&mut instances, 0);
+    vector(&mut instances, 0..memories.len() as u32);
+    vector(&mut instances, 0..globals.len() as u32);
+    section(&mut bytes, 0, &custom_payload("coreinstances", &instances));
+
+    let mut stack = vec![0];
+    name(&mut stack, "main");
+    leb128(&mut stack, frames.len() as u64);
+
+    for frame in frames {
+        stack.push(0);
+        leb128(&mut stack, 0);
+        leb128(&mut stack, frame.func_index() as u64);
+        leb128(&mut stack, frame.func_offset() as u64);
+        // No locals, and no operand stack.
+        leb128(&mut stack, 0);
+        leb128(&mut stack, 0);
+    }
+
+    section(&mut bytes, 0, &custom_payload("corestack", &stack));
+
+    let mut memory_section = Vec::new();
+    leb128(&mut memory_section, memories.len() as u64);
+
+    for memory in &memories {
+        memory_section.push(0);
+        leb128(&mut memory_section, memory.size().0 as u64);
+    }
+
+    section(&mut bytes, 5, &memory_section);
+
+    let mut global_section = Vec::new();
+    leb128(&mut global_section, globals.len() as u64);
+
+    for global in &globals {
+        let value = global.get();
+
+        global_section.push(match value.ty() {
+            wasmer::Type::I32 => 0x7f,
+            wasmer::Type::I64 => 0x7e,
+            wasmer::Type::F32 => 0x7d,
+            wasmer::Type::F64 => 0x7c,
+            wasmer::Type::V128 => 0x7b,
+            wasmer::Type::FuncRef => 0x70,
+            wasmer::Type::ExternRef => 0x6f,
+        });
+        global_section.push(1);
+
+        match value {
+            wasmer::Value::I32(value) => {
+                global_section.push(0x41);
+                sleb128(&mut global_section, value as i64);
+            }
+            wasmer::Value::I64(value) => {
+                global_section.push(0x42);
+                sleb128(&mut global_section, value);
+            }
+            wasmer::Value::F32(value) => {
+                global_section.push(0x43);
+                global_section.extend_from_slice(&value.to_le_bytes());
+            }
+            wasmer::Value::F64(value) => {
+                global_section.push(0x44);
+                global_section.extend_from_slice(&value.to_le_bytes());
+            }
+            wasmer::Value::V128(value) => {
+                global_section.extend_from_slice(&[0xfd, 0x0c]);
+                global_section.extend_from_slice(&value.to_le_bytes());
+            }
+            // The references cannot be dumped.
+            wasmer::Value::FuncRef(_) => global_section.extend_from_slice(&[0xd0, 0x70]),
+            wasmer::Value::ExternRef(_) => global_section.extend_from_slice(&[0xd0, 0x6f]),
+        }
+
+        global_section.push(0x0b);
+    }
+
+    section(&mut bytes, 6, &global_section);
+
+    let mut data_section = Vec::new();
+    leb128(&mut data_section, memories.len() as u64);
+
+    for (index, memory) in memories.iter().enumerate() {
+        data_section.push(2);
+        leb128(&mut data_section, index as u64);
+        data_section.extend_from_slice(&[0x41, 0, 0x0b]);
+        // SAFETY: no WebAssembly code runs while the memory is read.
+        let data = unsafe { memory.data_unchecked() };
+        leb128(&mut data_section, data.len() as u64);
+        data_section.extend_from_slice(data);
+    }
+
+    section(&mut bytes, 11, &data_section);
+
+    bytes
+}
+
+fn leb128(bytes: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+
+        if value == 0 {
+            bytes.push(byte);
+            return;
+        }
+
+        bytes.push(byte | 0x80);
+    }
+}
+
+fn sleb128(bytes: &mut Vec<u8>, mut value: i64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+
+        if (value == 0 && byte & 0x40 == 0) || (value == -1 && byte & 0x40 != 0) {
+            bytes.push(byte);
+            return;
+        }
+
+        bytes.push(byte | 0x80);
+    }
+}
+
+fn name(bytes: &mut Vec<u8>, name: &str) {
+    leb128(bytes, name.len() as u64);
+    bytes.extend_from_slice(name.as_bytes());
+}
+
+fn vector(bytes: &mut Vec<u8>, indices: impl ExactSizeIterator<Item = u32>) {
+    leb128(bytes, indices.len() as u64);
+
+    for index in indices {
+        leb128(bytes, index as u64);
+    }
+}
+
+fn custom_payload(section_name: &str, payload: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    name(&mut bytes, section_name);
+    bytes.extend_from_slice(payload);
+
+    bytes
+}
+
+fn section(bytes: &mut Vec<u8>, id: u8, payload: &[u8]) {
+    bytes.push(id);
+    leb128(bytes, payload.len() as u64);
+    bytes.extend_from_slice(payload);
+}
@@ -0,0 +1,200 @@
+use crate::{
+    errors::{emscripten_exit_error, runtime_error_to_py_err, to_py_err},
+    externals::Memory,
+    import_object::ImportObject,
+    instance::Instance,
+    module::Module,
+    store::Store,
+    wasmer_inner::{wasmer, wasmer_emscripten},
+};
+use pyo3::{
+    exceptions::{PyRuntimeError, PyValueError},
+    prelude::*,
+};
+use std::{collections::HashMap, path::PathBuf};
+
+/// The environment provided to the Emscripten imports: the memory
+/// and the table imported by the module, and the state of the
+/// Emscripten runtime, e.g. its stack or its mapped directories.
+///
+/// `map_directories` maps guest paths to host directories, like
+/// `wasmer.wasi.StateBuilder.map_directories`.
+///
+/// ## Example
+///
+/// See the `wasmer.emscripten` module.
+#[pyclass(unsendable)]
+#[pyo3(text_signature = "(store, module, /, *, map_directories={})")]
+pub struct Environment {
+    inner: wasmer_emscripten::EmEnv,
+    globals: wasmer_emscripten::EmscriptenGlobals,
+}
+
+/// Gives the Emscripten exit functions access to the memory, to read
+/// the variadic arguments of `___syscall1`.
+#[derive(Clone)]
+struct ExitEnv {
+    memory: wasmer::Memory,
+}
+
+impl wasmer::WasmerEnv for ExitEnv {}
+
+impl Environment {
+    fn import_object(&mut self, store: &wasmer::Store) -> ImportObject {
+        let mut import_object = ImportObject::raw_new(wasmer_emscripten::generate_emscripten_env(
+            store,
+            &mut self.globals,
+            &self.inner,
+        ));
+
+        // The Emscripten implementations of `exit` and `abort` end
+        // the process, i.e. the Python interpreter: the program
+        // raises an exception instead.
+        let exit = wasmer::Function::new_native(store, |exit_code: i32| -> Result<(), PyErr> {
+            Err(emscripten_exit_error(exit_code))
+        });
+        let syscall_exit = wasmer::Function::new_native_with_env(
+            store,
+            ExitEnv {
+                memory: self.globals.memory.clone(),
+            },
+            |env: &ExitEnv, _which: i32, arguments: u32| -> Result<(), PyErr> {
+                let exit_code = arguments
+                    .checked_div(4)
+                    .and_then(|index| {
+                        env.memory
+                            .view::<i32>()
+                            .get(index as usize)
+                            .map(|cell| cell.get())
+                    })
+                    .ok_or_else(|| {
+                        to_py_err::<PyRuntimeError, _>("The arguments of `exit` are out of bounds")
+                    })?;
+
+                Err(emscripten_exit_error(exit_code))
+            },
+        );
+        let abort = wasmer::Function::new_native(store, |_: i32| -> Result<(), PyErr> {
+            Err(to_py_err::<PyRuntimeError, _>(
+                "The Emscripten program has aborted",
+            ))
+        });
+        let abort_without_value = wasmer::Function::new_native(store, || -> Result<(), PyErr> {
+            Err(to_py_err::<PyRuntimeError, _>(
+                "The Emscripten program has aborted",
+            ))
+        });
+
+        for (name, function) in [
+            ("exit", &exit),
+            ("_exit", &exit),
+            ("__exit", &exit),
+            ("__syscall1", &syscall_exit),
+            ("___syscall1", &syscall_exit),
+            ("abort", &abort),
+            ("_abort", &abort_without_value),
+        ] {
+            import_object.insert("env".to_string(), name.to_string(), function.clone().into());
+        }
+
+        import_object
+    }
+}
+
+#[pymethods]
+impl Environment {
+    #[new]
+    #[args("*", map_directories = "None")]
+    fn new(
+        store: &Store,
+        module: &Module,
+        map_directories: Option<HashMap<String, PathBuf>>,
+    ) -> PyResult<Self> {
+        let globals = wasmer_emscripten::EmscriptenGlobals::new(store.inner()?, module.inner())
+            .map_err(to_py_err::<PyValueError, _>)?;
+        let mut inner =
+            wasmer_emscripten::EmEnv::new(&globals.data, map_directories.unwrap_or_default());
+        inner.set_memory(globals.memory.clone());
+
+        Ok(Self { inner, globals })
+    }
+
+    /// Create a `wasmer.ImportObject` with the Emscripten imports,
+    /// in the `env`, `global`, `global.Math` and `asm2wasm`
+    /// namespaces.
+    ///
+    /// ## Example
+    ///
+    /// See the `wasmer.emscripten` module.
+    #[pyo3(text_signature = "($self, store)")]
+    fn generate_import_object(&mut self, store: &Store) -> PyResult<ImportObject> {
+        Ok(self.import_object(store.inner()?))
+    }
+
+    /// Create a dictionary of the Emscripten imports, see
+    /// `generate_import_object`.
+    #[pyo3(text_signature = "($self, store)")]
+    fn generate_imports(&mut self, store: &Store) -> PyResult<PyObject> {
+        self.import_object(store.inner()?).to_dict()
+    }
+
+    /// The memory imported by the module, as a `wasmer.Memory`.
+    #[getter]
+    fn memory(&self) -> Memory {
+        Memory::raw_new(self.globals.memory.clone())
+    }
+
+    /// Runs the program of `instance`, instantiated with the imports
+    /// of this environment: calls its global constructors, and its
+    /// `main` function with `program_name` and `args` as arguments.
+    ///
+    /// When the program calls `exit`, the call raises an
+    /// `EmscriptenExitError`, whose `exit_code` attribute holds the
+    /// exit code.
+    ///
+    /// ## Example
+    ///
+    /// See the `wasmer.emscripten` module.
+    #[args(args = "Vec::new()")]
+    #[pyo3(text_signature = "($self, instance, program_name, /, args=[])")]
+    fn run(
+        &mut self,
+        py: Python,
+        instance: &Instance,
+        program_name: &str,
+        args: Vec<String>,
+    ) -> PyResult<()> {
+        let mut instance = instance.inner()?.clone();
+        let (inner, globals) = (&mut self.inner, &mut self.globals);
+
+        // The arguments are allocated on the stack of the program.
+        let takes_arguments = ["_main", "main"]
+            .iter()
+            .find_map(|name| instance.exports.get_function(name).ok())
+            .is_some_and(|main| !main.ty().params().is_empty());
+
+        if takes_arguments && instance.exports.get_function("stackAlloc").is_err() {
+            return Err(to_py_err::<PyRuntimeError, _>(
+                "The Emscripten program must export `stackAlloc` to receive its arguments",
+            ));
+        }
+
+        py.allow_threads(|| {
+            wasmer_emscripten::run_emscripten_instance(
+                &mut instance,
+                inner,
+                globals,
+                program_name,
+                args.iter().map(String::as_str).collect(),
+                None,
+            )
+        })
+        .map_err(runtime_error_to_py_err)
+    }
+}
+
+/// Checks whether `module` has been produced by Emscripten, from its
+/// imports.
+pub fn is_emscripten_module(module: &Module) -> bool {
+    wasmer_emscripten::is_emscripten_module(module.inner())
+}
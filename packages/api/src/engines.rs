@@ -1,3 +1,3 @@
-pub use crate::wasmer_inner::wasmer_engines::{Dylib, Universal};
+pub use crate::wasmer_inner::wasmer_engines::{Dylib, Features, Staticlib, Universal};
 // Deprecated engines.
 pub use crate::wasmer_inner::wasmer_engines::{Native, JIT};
@@ -1,7 +1,116 @@
-use pyo3::{exceptions::PyRuntimeError, prelude::*, type_object::PyTypeObject};
-use std::string::ToString;
+use crate::wasmer_inner::{wasmer_vm, wasmer_wasi::WasiError};
+use pyo3::{
+    class::basic::PyObjectProtocol, create_exception, exceptions::PyRuntimeError, prelude::*,
+    type_object::PyTypeObject,
+};
+use std::{fmt, slice, string::ToString};
 use wasmer::RuntimeError;
 
+// The base class of the errors raised by Wasmer. It is a
+// `RuntimeError`, as all the errors used to be.
+create_exception!(wasmer, WasmerError, PyRuntimeError);
+
+// Raised when a module fails to compile.
+create_exception!(wasmer, CompileError, WasmerError);
+
+// Raised when a module fails to be instantiated, except for the
+// linking errors and the traps of its start function.
+create_exception!(wasmer, InstantiationError, WasmerError);
+
+// Raised when the imports of a module don't match its import types.
+create_exception!(wasmer, LinkError, WasmerError);
+
+// Raised when WebAssembly code traps, with its `code`, a `TrapCode`,
+// or `None` when the trap is raised by the host, its `frames`, a list
+// of `Frame`, and its `coredump_path`, see `Store`.
+create_exception!(wasmer, Trap, WasmerError);
+
+// Raised when a call is interrupted, see `Interruptible`.
+create_exception!(wasmer, ExecutionTimeout, WasmerError);
+
+// Raised when a WASI program calls `proc_exit`, with its `exit_code`.
+create_exception!(wasi, WasiExitError, WasmerError);
+
+// Raised when an Emscripten program calls `exit`, with its
+// `exit_code`.
+create_exception!(emscripten, EmscriptenExitError, WasmerError);
+
+#[derive(Debug, Copy, Clone)]
+#[repr(u8)]
+pub enum TrapCode {
+    StackOverflow = 1,
+    HeapAccessOutOfBounds = 2,
+    HeapMisaligned = 3,
+    TableAccessOutOfBounds = 4,
+    OutOfBounds = 5,
+    IndirectCallToNull = 6,
+    BadSignature = 7,
+    IntegerOverflow = 8,
+    IntegerDivisionByZero = 9,
+    BadConversionToInteger = 10,
+    UnreachableCodeReached = 11,
+    UnalignedAtomic = 12,
+}
+
+impl TrapCode {
+    pub fn iter() -> slice::Iter<'static, TrapCode> {
+        static VARIANTS: [TrapCode; 12] = [
+            TrapCode::StackOverflow,
+            TrapCode::HeapAccessOutOfBounds,
+            TrapCode::HeapMisaligned,
+            TrapCode::TableAccessOutOfBounds,
+            TrapCode::OutOfBounds,
+            TrapCode::IndirectCallToNull,
+            TrapCode::BadSignature,
+            TrapCode::IntegerOverflow,
+            TrapCode::IntegerDivisionByZero,
+            TrapCode::BadConversionToInteger,
+            TrapCode::UnreachableCodeReached,
+            TrapCode::UnalignedAtomic,
+        ];
+
+        VARIANTS.iter()
+    }
+}
+
+impl From<&TrapCode> for &'static str {
+    fn from(value: &TrapCode) -> Self {
+        match value {
+            TrapCode::StackOverflow => "STACK_OVERFLOW",
+            TrapCode::HeapAccessOutOfBounds => "HEAP_ACCESS_OUT_OF_BOUNDS",
+            TrapCode::HeapMisaligned => "HEAP_MISALIGNED",
+            TrapCode::TableAccessOutOfBounds => "TABLE_ACCESS_OUT_OF_BOUNDS",
+            TrapCode::OutOfBounds => "OUT_OF_BOUNDS",
+            TrapCode::IndirectCallToNull => "INDIRECT_CALL_TO_NULL",
+            TrapCode::BadSignature => "BAD_SIGNATURE",
+            TrapCode::IntegerOverflow => "INTEGER_OVERFLOW",
+            TrapCode::IntegerDivisionByZero => "INTEGER_DIVISION_BY_ZERO",
+            TrapCode::BadConversionToInteger => "BAD_CONVERSION_TO_INTEGER",
+            TrapCode::UnreachableCodeReached => "UNREACHABLE_CODE_REACHED",
+            TrapCode::UnalignedAtomic => "UNALIGNED_ATOMIC",
+        }
+    }
+}
+
+impl From<wasmer_vm::TrapCode> for TrapCode {
+    fn from(value: wasmer_vm::TrapCode) -> Self {
+        match value {
+            wasmer_vm::TrapCode::StackOverflow => Self::StackOverflow,
+            wasmer_vm::TrapCode::HeapAccessOutOfBounds => Self::HeapAccessOutOfBounds,
+            wasmer_vm::TrapCode::HeapMisaligned => Self::HeapMisaligned,
+            wasmer_vm::TrapCode::TableAccessOutOfBounds => Self::TableAccessOutOfBounds,
+            wasmer_vm::TrapCode::OutOfBounds => Self::OutOfBounds,
+            wasmer_vm::TrapCode::IndirectCallToNull => Self::IndirectCallToNull,
+            wasmer_vm::TrapCode::BadSignature => Self::BadSignature,
+            wasmer_vm::TrapCode::IntegerOverflow => Self::IntegerOverflow,
+            wasmer_vm::TrapCode::IntegerDivisionByZero => Self::IntegerDivisionByZero,
+            wasmer_vm::TrapCode::BadConversionToInteger => Self::BadConversionToInteger,
+            wasmer_vm::TrapCode::UnreachableCodeReached => Self::UnreachableCodeReached,
+            wasmer_vm::TrapCode::UnalignedAtomic => Self::UnalignedAtomic,
+        }
+    }
+}
+
 pub fn to_py_err<PyError, Error>(error: Error) -> PyErr
 where
     PyError: PyTypeObject,
@@ -11,8 +120,174 @@ where
 }
 
 pub fn runtime_error_to_py_err(error: RuntimeError) -> PyErr {
-    match error.downcast::<PyErr>() {
-        Ok(err) => err,
-        Err(err) => to_py_err::<PyRuntimeError, _>(err),
+    let error = match error.downcast::<PyErr>() {
+        Ok(err) => return err,
+        Err(err) => err,
+    };
+
+    match error.downcast::<WasiError>() {
+        Ok(WasiError::Exit(exit_code)) => wasi_exit_error(exit_code),
+        Ok(err) => trap(err.to_string(), None, Vec::new()),
+        Err(err) => {
+            let message = err.message();
+            let frames = err.trace().iter().map(Frame::from).collect();
+
+            trap(message, err.to_trap().map(Into::into), frames)
+        }
     }
 }
+
+/// A frame of the WebAssembly backtrace of a `Trap`, see its
+/// `frames` attribute. The innermost call comes first.
+///
+/// The backtrace is also rendered in the message of the trap, one
+/// line per frame, with the function name, the module name, the
+/// function index, and the offset in the module. Only the first 64
+/// frames are rendered, e.g. for a stack overflow.
+///
+/// The compilers don't translate the DWARF debugging information of
+/// a module for the native code, hence `gdb` or `lldb` attached to
+/// the Python process can't show the source lines of the WebAssembly
+/// frames. The `module_offset` of a frame can be resolved offline
+/// with the DWARF of the module instead, e.g. with `llvm-dwarfdump
+/// --lookup`, after subtracting the offset of the code section, the
+/// DWARF addresses being relative to it.
+///
+/// ## Example
+///
+/// ```py
+/// from wasmer import Store, Module, Instance, Trap
+///
+/// module = Module(
+///     Store(),
+///     """
+///     (module $app
+///       (func $crash
+///         unreachable)
+///       (func (export "run")
+///         call $crash))
+///     """
+/// )
+///
+/// try:
+///     Instance(module).exports.run()
+/// except Trap as trap:
+///     crash, run = trap.frames
+///
+///     assert crash.module_name == 'app'
+///     assert crash.function_name == 'crash'
+///     assert crash.function_index == 0
+///     assert run.function_index == 1
+///     assert '    at crash (app[0]:0x' in str(trap)
+/// ```
+#[pyclass]
+#[derive(Clone)]
+pub struct Frame {
+    /// The name of the module, or `<module>` if it has none.
+    #[pyo3(get)]
+    module_name: String,
+
+    /// The index of the function in the module.
+    #[pyo3(get)]
+    function_index: u32,
+
+    /// The name of the function, from the name section, if any.
+    #[pyo3(get)]
+    function_name: Option<String>,
+
+    /// The offset of the instruction in the module.
+    #[pyo3(get)]
+    module_offset: usize,
+
+    /// The offset of the instruction in the function.
+    #[pyo3(get)]
+    function_offset: usize,
+}
+
+impl From<&wasmer::FrameInfo> for Frame {
+    fn from(value: &wasmer::FrameInfo) -> Self {
+        Self {
+            module_name: value.module_name().to_string(),
+            function_index: value.func_index(),
+            function_name: value.function_name().map(ToString::to_string),
+            module_offset: value.module_offset(),
+            function_offset: value.func_offset(),
+        }
+    }
+}
+
+impl fmt::Display for Frame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} ({}[{}]:0x{:x})",
+            self.function_name.as_deref().unwrap_or("<unnamed>"),
+            self.module_name,
+            self.function_index,
+            self.module_offset
+        )
+    }
+}
+
+#[pyproto]
+impl PyObjectProtocol for Frame {
+    fn __repr__(&self) -> String {
+        format!("Frame({})", self)
+    }
+}
+
+const MAXIMUM_RENDERED_FRAMES: usize = 64;
+
+fn trap(message: String, code: Option<TrapCode>, frames: Vec<Frame>) -> PyErr {
+    let mut message = message;
+
+    // A stack overflow has a backtrace of hundreds of thousands of
+    // frames, which are all in `frames`, but not all in the message.
+    for frame in frames.iter().take(MAXIMUM_RENDERED_FRAMES) {
+        message.push_str(&format!("\n    at {}", frame));
+    }
+
+    if frames.len() > MAXIMUM_RENDERED_FRAMES {
+        message.push_str(&format!(
+            "\n    ... {} more frames",
+            frames.len() - MAXIMUM_RENDERED_FRAMES
+        ));
+    }
+
+    let err = Trap::new_err(message);
+
+    Python::with_gil(|py| {
+        let trap = err.instance(py);
+
+        match trap
+            .setattr("code", code.map(|code| code as u8))
+            .and_then(|()| trap.setattr("frames", frames.into_py(py)))
+            .and_then(|()| trap.setattr("coredump_path", py.None()))
+        {
+            Ok(()) => err,
+            Err(err) => err,
+        }
+    })
+}
+
+fn wasi_exit_error(exit_code: u32) -> PyErr {
+    let err = WasiExitError::new_err(format!("WASI exited with code {}", exit_code));
+
+    Python::with_gil(
+        |py| match err.instance(py).setattr("exit_code", exit_code) {
+            Ok(()) => err,
+            Err(err) => err,
+        },
+    )
+}
+
+pub(crate) fn emscripten_exit_error(exit_code: i32) -> PyErr {
+    let err = EmscriptenExitError::new_err(format!("Emscripten exited with code {}", exit_code));
+
+    Python::with_gil(
+        |py| match err.instance(py).setattr("exit_code", exit_code) {
+            Ok(()) => err,
+            Err(err) => err,
+        },
+    )
+}
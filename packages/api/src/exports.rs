@@ -1,13 +1,19 @@
 use crate::{
     errors::to_py_err,
     externals::{Function, Global, Memory, Table},
+    middlewares,
+    statistics::{CallStatistics, Statistics},
     wasmer_inner::wasmer,
 };
 use pyo3::{
-    class::{basic::PyObjectProtocol, iter::PyIterProtocol, sequence::PySequenceProtocol},
-    exceptions::PyLookupError,
+    class::{
+        basic::PyObjectProtocol, iter::PyIterProtocol, mapping::PyMappingProtocol,
+        sequence::PySequenceProtocol,
+    },
+    exceptions::{PyKeyError, PyLookupError},
     prelude::*,
 };
+use std::sync::Arc;
 
 /// Represents all the exports of an instance. It is built by
 /// `Instance.exports`.
@@ -18,6 +24,15 @@ use pyo3::{
 /// Protocol](https://docs.python.org/3/c-api/iter.html). Please see
 /// the `ExportsIterator` class.
 ///
+/// The exports can also be looked up by name, like in a dictionary:
+/// with `in`, `exports[name]`, `exports.get(name, default)`, and
+/// `keys`, `values` and `items`. Their names are listed by `dir`,
+/// e.g. for completion. Note that iterating over the exports yields
+/// `(name, export)` pairs, like `items`; iterate over `keys` to get
+/// the names only. An export named like one of those methods, e.g.
+/// `get`, takes precedence over it. The exports added by the
+/// middlewares, named `wasmer_*`, are internal and are left out.
+///
 /// ## Example
 ///
 /// ```py
@@ -41,16 +56,153 @@ use pyo3::{
 /// assert isinstance(exports.glob, Global)
 /// assert isinstance(exports.tab, Table)
 /// assert isinstance(exports.mem, Memory)
+///
+/// assert "func" in exports
+/// assert isinstance(exports["glob"], Global)
+/// assert exports.get("missing") == None
+/// assert exports.get("missing", 42) == 42
+/// assert exports.keys() == ["func", "glob", "tab", "mem"]
+/// assert "mem" in dir(exports)
 /// ```
 #[pyclass(unsendable)]
 #[derive(Clone)]
 pub struct Exports {
     inner: wasmer::Exports,
+
+    /// The statistics of the exported functions, if collected.
+    statistics: Option<Arc<Statistics>>,
 }
 
 impl Exports {
     pub fn new(inner: wasmer::Exports) -> Self {
-        Self { inner }
+        Self {
+            inner,
+            statistics: None,
+        }
+    }
+
+    pub(crate) fn with_statistics(mut self, statistics: Option<Arc<Statistics>>) -> Self {
+        self.statistics = statistics;
+        self
+    }
+
+    /// Iterates over the exports, except the internal ones.
+    fn iter(&self) -> impl Iterator<Item = (&String, &wasmer::Extern)> {
+        self.inner
+            .iter()
+            .filter(|(name, _)| !middlewares::is_internal_export(name))
+    }
+
+    /// Gets the export named `name` as a Python object, if any.
+    fn get_export(&self, py: Python, name: &str) -> PyResult<Option<PyObject>> {
+        self.inner
+            .get_extern(name)
+            .filter(|_| !middlewares::is_internal_export(name))
+            .map(|export| to_py_export(py, &self.inner, &self.statistics, name, export))
+            .transpose()
+    }
+}
+
+fn call_statistics(
+    statistics: &Option<Arc<Statistics>>,
+    name: &str,
+) -> Option<Arc<CallStatistics>> {
+    statistics.as_ref()?.get(name).cloned()
+}
+
+/// Converts the export named `name` of `exports` to its Python
+/// object: a `Function`, a `Global`, a `Memory`, or a `Table`.
+fn to_py_export(
+    py: Python,
+    exports: &wasmer::Exports,
+    statistics: &Option<Arc<Statistics>>,
+    name: &str,
+    export: &wasmer::Extern,
+) -> PyResult<PyObject> {
+    Ok(match export {
+        wasmer::Extern::Function(function) => Py::new(
+            py,
+            Function::raw_new(function.clone())
+                .with_instance_exports(exports)
+                .with_statistics(call_statistics(statistics, name)),
+        )?
+        .to_object(py),
+        wasmer::Extern::Global(global) => {
+            Py::new(py, Global::raw_new(global.clone()))?.to_object(py)
+        }
+        wasmer::Extern::Memory(memory) => {
+            Py::new(py, Memory::raw_new(memory.clone()))?.to_object(py)
+        }
+        wasmer::Extern::Table(table) => Py::new(py, Table::raw_new(table.clone()))?.to_object(py),
+    })
+}
+
+/// The methods to look the exports up like in a dictionary. They are
+/// registered with a leading underscore, and reached by `__getattr__`
+/// when there is no export of the same name, so that they never hide
+/// an export.
+const MAPPING_METHODS: [&str; 4] = ["get", "items", "keys", "values"];
+
+#[pymethods]
+impl Exports {
+    /// Returns the export named `name`, or `default` if there is no
+    /// such export.
+    #[pyo3(name = "_get", text_signature = "($self, name, default=None)")]
+    #[args(default = "None")]
+    fn get(&self, py: Python, name: &str, default: Option<PyObject>) -> PyResult<PyObject> {
+        Ok(self
+            .get_export(py, name)?
+            .unwrap_or_else(|| default.unwrap_or_else(|| py.None())))
+    }
+
+    /// Returns the names of the exports, in the order of the module.
+    #[pyo3(name = "_keys", text_signature = "($self)")]
+    fn keys(&self) -> Vec<String> {
+        self.iter().map(|(name, _)| name.clone()).collect()
+    }
+
+    /// Returns the exports, in the order of the module.
+    #[pyo3(name = "_values", text_signature = "($self)")]
+    fn values(&self, py: Python) -> PyResult<Vec<PyObject>> {
+        self.iter()
+            .map(|(name, export)| to_py_export(py, &self.inner, &self.statistics, name, export))
+            .collect()
+    }
+
+    /// Returns the `(name, export)` pairs of the exports, in the
+    /// order of the module.
+    #[pyo3(name = "_items", text_signature = "($self)")]
+    fn items(&self, py: Python) -> PyResult<Vec<(String, PyObject)>> {
+        self.iter()
+            .map(|(name, export)| {
+                Ok((
+                    name.clone(),
+                    to_py_export(py, &self.inner, &self.statistics, name, export)?,
+                ))
+            })
+            .collect()
+    }
+
+    /// Lists the attributes of the exports, including their names.
+    fn __dir__(&self, py: Python) -> PyResult<Vec<String>> {
+        let mut attributes = py
+            .get_type::<Self>()
+            .dir()
+            .extract::<Vec<String>>()?
+            .into_iter()
+            .filter(|attribute| {
+                !MAPPING_METHODS
+                    .iter()
+                    .any(|method| attribute.strip_prefix('_') == Some(*method))
+            })
+            .chain(MAPPING_METHODS.iter().map(|method| method.to_string()))
+            .chain(self.keys())
+            .collect::<Vec<_>>();
+
+        attributes.sort();
+        attributes.dedup();
+
+        Ok(attributes)
     }
 }
 
@@ -60,33 +212,40 @@ impl PyObjectProtocol for Exports {
         let gil_guard = Python::acquire_gil();
         let py = gil_guard.python();
 
-        Ok(match self.inner.get_extern(key) {
-            Some(wasmer::Extern::Function(function)) => {
-                Py::new(py, Function::raw_new(function.clone()))?.to_object(py)
-            }
-            Some(wasmer::Extern::Global(global)) => {
-                Py::new(py, Global::raw_new(global.clone()))?.to_object(py)
-            }
-            Some(wasmer::Extern::Memory(memory)) => {
-                Py::new(py, Memory::raw_new(memory.clone()))?.to_object(py)
-            }
-            Some(wasmer::Extern::Table(table)) => {
-                Py::new(py, Table::raw_new(table.clone()))?.to_object(py)
-            }
-            _ => {
-                return Err(to_py_err::<PyLookupError, _>(format!(
-                    "Export `{}` does not exist.",
-                    key
-                )))
-            }
-        })
+        if let Some(export) = self.get_export(py, key)? {
+            return Ok(export);
+        }
+
+        if MAPPING_METHODS.contains(&key) {
+            return Py::new(py, self.clone())?.getattr(py, format!("_{}", key).as_str());
+        }
+
+        Err(to_py_err::<PyLookupError, _>(format!(
+            "Export `{}` does not exist.",
+            key
+        )))
     }
 }
 
 #[pyproto]
-impl PySequenceProtocol for Exports {
+impl PyMappingProtocol for Exports {
     fn __len__(&self) -> usize {
-        self.inner.len()
+        self.iter().count()
+    }
+
+    fn __getitem__(&self, key: &str) -> PyResult<PyObject> {
+        let gil_guard = Python::acquire_gil();
+        let py = gil_guard.python();
+
+        self.get_export(py, key)?
+            .ok_or_else(|| to_py_err::<PyKeyError, _>(format!("Export `{}` does not exist.", key)))
+    }
+}
+
+#[pyproto]
+impl PySequenceProtocol for Exports {
+    fn __contains__(&self, key: &str) -> bool {
+        !middlewares::is_internal_export(key) && self.inner.contains(key)
     }
 }
 
@@ -94,8 +253,9 @@ impl PySequenceProtocol for Exports {
 impl PyIterProtocol for Exports {
     fn __iter__(slf: PyRef<Self>) -> ExportsIterator {
         ExportsIterator {
+            exports: slf.inner.clone(),
+            statistics: slf.statistics.clone(),
             vector: slf
-                .inner
                 .iter()
                 .map(|(name, export)| (name.clone(), export.clone()))
                 .collect(),
@@ -127,6 +287,8 @@ impl PyIterProtocol for Exports {
 /// ```
 #[pyclass]
 pub struct ExportsIterator {
+    exports: wasmer::Exports,
+    statistics: Option<Arc<Statistics>>,
     vector: Vec<(String, wasmer::Extern)>,
     index: usize,
 }
@@ -145,20 +307,7 @@ impl PyIterProtocol for ExportsIterator {
             Some((name, export)) => (
                 Ok(Some((
                     name.clone(),
-                    match export {
-                        wasmer::Extern::Function(function) => {
-                            Py::new(py, Function::raw_new(function.clone()))?.to_object(py)
-                        }
-                        wasmer::Extern::Global(global) => {
-                            Py::new(py, Global::raw_new(global.clone()))?.to_object(py)
-                        }
-                        wasmer::Extern::Memory(memory) => {
-                            Py::new(py, Memory::raw_new(memory.clone()))?.to_object(py)
-                        }
-                        wasmer::Extern::Table(table) => {
-                            Py::new(py, Table::raw_new(table.clone()))?.to_object(py)
-                        }
-                    },
+                    to_py_export(py, &slf.exports, &slf.statistics, name, export)?,
                 ))),
                 slf.index + 1,
             ),
@@ -1,16 +1,41 @@
 use crate::{
-    errors::{runtime_error_to_py_err, to_py_err},
-    store::Store,
+    coredump,
+    errors::{to_py_err, ExecutionTimeout},
+    exports::Exports,
+    middlewares::INTERRUPTED_GLOBAL,
+    stack,
+    statistics::{self, CallStatistics},
+    store::{self, Store},
+    timer::{self, Timeout},
     types::FunctionType,
     values::{to_py_object, to_wasm_value},
-    wasmer_inner::wasmer,
+    wasmer_inner::{
+        wasmer::{self, Exportable},
+        wasmer_vm::VMFunctionKind,
+    },
 };
 use pyo3::{
-    exceptions::{PyRuntimeError, PyValueError},
+    exceptions::{PyRuntimeError, PyTypeError, PyValueError},
     prelude::*,
-    types::{PyDict, PyTuple},
+    types::{PyDict, PyLong, PyTuple},
+};
+use std::{cell::RefCell, sync::Arc, time::Duration};
+#[cfg(unix)]
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{self, Read, Write},
+    mem::ManuallyDrop,
+    os::unix::{
+        io::{FromRawFd, IntoRawFd},
+        net::UnixStream,
+    },
+    sync::{
+        atomic::{AtomicI32, AtomicU64, Ordering},
+        Mutex, MutexGuard, OnceLock,
+    },
+    thread,
 };
-use std::sync::Arc;
 
 /// Represents a WebAssembly function instance.
 ///
@@ -59,6 +84,9 @@ use std::sync::Arc;
 /// | `'i64'`, `'I64'` | `Type.I64` |
 /// | `float`, `'f32'`, `'F32'` | `Type.F32` |
 /// | `'f64'`, `'F64'` | `Type.F64` |
+/// | `'v128'`, `'V128'` | `Type.V128` |
+/// | `object`, `'externref'` | `Type.EXTERN_REF` |
+/// | `'funcref'` | `Type.FUNC_REF` |
 /// | `None` | none (only in `return` position) |
 ///
 /// It is possible for a host function to return a tuple of the types above (except `None`), like:
@@ -77,6 +105,35 @@ use std::sync::Arc;
 /// assert function_type.results == [Type.I64, Type.I32]
 /// ```
 ///
+/// Any Python object can be passed to WebAssembly as an `externref`,
+/// and comes back as the same object. `None` is the null reference.
+///
+/// ```py
+/// from wasmer import Store, Module, Instance, Function
+///
+/// def describe(x: object) -> 'externref':
+///     return {'value': x}
+///
+/// store = Store()
+/// module = Module(
+///     store,
+///     """
+///     (module
+///       (import "env" "describe" (func $describe (param externref) (result externref)))
+///       (func (export "run") (param externref) (result externref)
+///         local.get 0
+///         call $describe))
+///     """
+/// )
+/// instance = Instance(module, {"env": {"describe": Function(store, describe)}})
+///
+/// assert instance.exports.run([1, 2]) == {'value': [1, 2]}
+/// ```
+///
+/// Note that the objects given to WebAssembly are kept alive by their
+/// store: they are released once the store, and everything created
+/// from it, e.g. the instances, have been dropped.
+///
 /// ## With `FunctionType`
 ///
 /// Second, the same code but without annotations and a `FunctionType`:
@@ -90,44 +147,515 @@ use std::sync::Arc;
 /// store = Store()
 /// function = Function(store, sum, FunctionType([Type.I32, Type.I32], [Type.I32]))
 /// ```
+///
+/// ## With an environment
+///
+/// A host function can receive an environment, i.e. any Python
+/// object given with the `env` argument, as its first argument. The
+/// first parameter is not part of the function type.
+///
+/// If the environment has an `init_with_exports` method, it is called
+/// with the `Exports` of the instance importing the function, right
+/// after instantiation. It allows the host function to read the
+/// memory of the instance, e.g. to read a string given by pointer.
+/// If the method raises an exception, the instantiation fails with
+/// it.
+///
+/// The exports given to the environment don't keep the instance
+/// alive.
+///
+/// ```py
+/// from wasmer import Store, Module, Instance, Function
+///
+/// class Env:
+///     def __init__(self):
+///         self.memory = None
+///         self.logs = []
+///
+///     def init_with_exports(self, exports):
+///         self.memory = exports.memory
+///
+/// def log(env, pointer: int, length: int):
+///     env.logs.append(bytes(env.memory.uint8_view(pointer)[0:length]).decode())
+///
+/// store = Store()
+/// env = Env()
+/// module = Module(
+///     store,
+///     """
+///     (module
+///       (import "env" "log" (func $log (param i32 i32)))
+///       (memory (export "memory") 1)
+///       (data (i32.const 0) "Hello")
+///       (func (export "run")
+///         i32.const 0
+///         i32.const 5
+///         call $log))
+///     """
+/// )
+/// instance = Instance(module, {"env": {"log": Function(store, log, env=env)}})
+/// instance.exports.run()
+///
+/// assert env.logs == ["Hello"]
+/// ```
 #[pyclass(unsendable)]
-#[pyo3(text_signature = "(store, function, function_type)")]
+#[pyo3(text_signature = "(store, function, function_type, env)")]
+#[derive(Clone)]
 pub struct Function {
     inner: wasmer::Function,
+
+    /// The `INTERRUPTED_GLOBAL` global of the instance exporting the
+    /// function, when it has been compiled with an `Interruptible`
+    /// middleware.
+    interrupted: Option<wasmer::Global>,
+
+    /// The exports of the instance exporting the function, for its
+    /// core dumps.
+    exports: Option<wasmer::Exports>,
+
+    /// The statistics of the calls to the function, if the store of
+    /// the instance exporting it collects them.
+    statistics: Option<Arc<CallStatistics>>,
+}
+
+thread_local! {
+    /// The exception raised by `init_with_exports` while an instance
+    /// is created, to be raised again by `Instance`.
+    static ENVIRONMENT_ERROR: RefCell<Option<PyErr>> = const { RefCell::new(None) };
+}
+
+/// Takes the exception raised by the `init_with_exports` method of an
+/// environment, if any.
+pub(crate) fn take_environment_error() -> Option<PyErr> {
+    ENVIRONMENT_ERROR.with(|error| error.borrow_mut().take())
+}
+
+/// Wasmer values are not `Send` because of the reference types, but
+/// the references built from Python objects hold `Function`s or
+/// `PyObject`s, which are `Send`, and `Python::allow_threads` runs
+/// its closure on the current thread. `stack::with_stack_size` runs
+/// its closure on another thread, but the current one waits for it.
+struct AssumeSend<T>(T);
+
+unsafe impl<T> Send for AssumeSend<T> {}
+
+/// The calls watching `SIGINT`, see `SigintWatcher`, by identifier,
+/// with whether `SIGINT` has been received during each of them.
+#[cfg(unix)]
+static WATCHED_CALLS: Mutex<BTreeMap<u64, (wasmer::Global, bool)>> = Mutex::new(BTreeMap::new());
+
+#[cfg(unix)]
+static NEXT_WATCHED_CALL: AtomicU64 = AtomicU64::new(0);
+
+/// The wakeup fd set before the outermost watched call, to which the
+/// signals are forwarded, e.g. the one of an `asyncio` event loop.
+#[cfg(unix)]
+static CHAINED_WAKEUP_FD: AtomicI32 = AtomicI32::new(-1);
+
+/// The wakeup fd of the process-wide watcher thread, started by the
+/// first watched call, or `None` if it could not be started.
+#[cfg(unix)]
+static WATCHER_WAKEUP_FD: OnceLock<Option<i32>> = OnceLock::new();
+
+#[cfg(unix)]
+fn lock_watched_calls() -> MutexGuard<'static, BTreeMap<u64, (wasmer::Global, bool)>> {
+    WATCHED_CALLS
+        .lock()
+        .unwrap_or_else(|error| error.into_inner())
+}
+
+/// Watches `SIGINT`, i.e. Ctrl-C, during a call to a function of a
+/// store created with `keyboard_interrupt=True`, to interrupt the
+/// function. The Python signal handlers only run once the call has
+/// returned, but the signals are written to the wakeup fd as soon as
+/// they are received, see `signal.set_wakeup_fd`.
+///
+/// One thread reads the wakeup fd of all the calls, for the lifetime
+/// of the process. The wakeup fd is set for the duration of the
+/// outermost call only, and the previous one is restored after it,
+/// unless another one has been set meanwhile, e.g. by a host
+/// function.
+#[cfg(unix)]
+struct SigintWatcher {
+    id: u64,
+
+    /// The wakeup fd to restore, if the call is the outermost one.
+    previous_wakeup_fd: Option<i32>,
+}
+
+#[cfg(unix)]
+impl SigintWatcher {
+    const SIGINT: u8 = 2;
+
+    /// Starts the watcher thread, and returns its wakeup fd.
+    fn watcher_wakeup_fd() -> Option<i32> {
+        *WATCHER_WAKEUP_FD.get_or_init(|| {
+            let (mut reader, writer) = UnixStream::pair().ok()?;
+            writer.set_nonblocking(true).ok()?;
+
+            thread::Builder::new()
+                .name("wasmer-sigint".to_string())
+                .spawn(move || {
+                    let mut signals = [0; 64];
+
+                    // The signal itself may interrupt the read.
+                    loop {
+                        let read = match reader.read(&mut signals) {
+                            Ok(0) => break,
+                            Ok(read) => read,
+                            Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+                            Err(_) => break,
+                        };
+
+                        if signals[..read].contains(&Self::SIGINT) {
+                            for (interrupted, has_sigint) in lock_watched_calls().values_mut() {
+                                *has_sigint = true;
+                                let _ = interrupted.set(wasmer::Value::I32(1));
+                            }
+                        }
+
+                        let chained_wakeup_fd = CHAINED_WAKEUP_FD.load(Ordering::SeqCst);
+
+                        if chained_wakeup_fd >= 0 {
+                            let mut chained = ManuallyDrop::new(unsafe {
+                                fs::File::from_raw_fd(chained_wakeup_fd)
+                            });
+                            let _ = chained.write(&signals[..read]);
+                        }
+                    }
+                })
+                .ok()?;
+
+            // The writer is never closed, so that the reader never
+            // reaches the end.
+            Some(writer.into_raw_fd())
+        })
+    }
+
+    /// Starts watching, unless the wakeup fd cannot be set, e.g. on
+    /// another thread than the main thread.
+    fn start(py: Python, interrupted: wasmer::Global) -> Option<Self> {
+        let wakeup_fd = Self::watcher_wakeup_fd()?;
+
+        let previous_wakeup_fd = py
+            .import("signal")
+            .and_then(|signal| signal.call_method1("set_wakeup_fd", (wakeup_fd,)))
+            .and_then(|fd| fd.extract::<i32>())
+            .ok()?;
+
+        // A nested call, e.g. from a host function, leaves the wakeup
+        // fd to the outermost one.
+        let previous_wakeup_fd = if previous_wakeup_fd == wakeup_fd {
+            None
+        } else {
+            CHAINED_WAKEUP_FD.store(previous_wakeup_fd, Ordering::SeqCst);
+
+            Some(previous_wakeup_fd)
+        };
+
+        let id = NEXT_WATCHED_CALL.fetch_add(1, Ordering::SeqCst);
+        lock_watched_calls().insert(id, (interrupted, false));
+
+        Some(Self {
+            id,
+            previous_wakeup_fd,
+        })
+    }
+
+    /// Stops watching, and returns whether `SIGINT` has been
+    /// received.
+    fn stop(self, py: Python) -> bool {
+        let has_sigint = lock_watched_calls()
+            .remove(&self.id)
+            .is_some_and(|(_, has_sigint)| has_sigint);
+
+        if let Some(previous_wakeup_fd) = self.previous_wakeup_fd {
+            let _ = py.import("signal").and_then(|signal| {
+                let wakeup_fd = signal
+                    .call_method1("set_wakeup_fd", (previous_wakeup_fd,))?
+                    .extract::<i32>()?;
+
+                // The wakeup fd set during the call is left in place.
+                if Some(wakeup_fd) != Self::watcher_wakeup_fd() {
+                    signal.call_method1("set_wakeup_fd", (wakeup_fd,))?;
+                }
+
+                Ok(())
+            });
+
+            CHAINED_WAKEUP_FD.store(-1, Ordering::SeqCst);
+        }
+
+        has_sigint
+    }
+}
+
+#[cfg(not(unix))]
+struct SigintWatcher;
+
+#[cfg(not(unix))]
+impl SigintWatcher {
+    fn start(_py: Python, _interrupted: wasmer::Global) -> Option<Self> {
+        None
+    }
+
+    fn stop(self, _py: Python) -> bool {
+        false
+    }
+}
+
+/// An argument of a `PendingCall`. Unlike `wasmer::Value`, it holds
+/// no reference, so it can be sent to another thread.
+#[derive(Clone, Copy)]
+enum NumericValue {
+    I32(i32),
+    I64(i64),
+    F32(f32),
+    F64(f64),
+    V128(u128),
+}
+
+impl NumericValue {
+    fn new(value: wasmer::Value) -> Option<Self> {
+        Some(match value {
+            wasmer::Value::I32(value) => Self::I32(value),
+            wasmer::Value::I64(value) => Self::I64(value),
+            wasmer::Value::F32(value) => Self::F32(value),
+            wasmer::Value::F64(value) => Self::F64(value),
+            wasmer::Value::V128(value) => Self::V128(value),
+            wasmer::Value::ExternRef(_) | wasmer::Value::FuncRef(_) => return None,
+        })
+    }
+
+    fn to_value(self) -> wasmer::Value {
+        match self {
+            Self::I32(value) => wasmer::Value::I32(value),
+            Self::I64(value) => wasmer::Value::I64(value),
+            Self::F32(value) => wasmer::Value::F32(value),
+            Self::F64(value) => wasmer::Value::F64(value),
+            Self::V128(value) => wasmer::Value::V128(value),
+        }
+    }
+}
+
+/// A call to a function, run by an executor of the `asyncio` event
+/// loop. See `Function.call_async`.
+#[pyclass]
+struct PendingCall {
+    function: Function,
+    arguments: Vec<NumericValue>,
+    timeout: Option<f64>,
+}
+
+#[pymethods]
+impl PendingCall {
+    #[call]
+    fn __call__(&self, py: Python) -> PyResult<PyObject> {
+        let arguments = self
+            .arguments
+            .iter()
+            .map(|argument| argument.to_value())
+            .collect();
+
+        self.function.call(py, arguments, self.timeout)
+    }
+}
+
+#[derive(Clone)]
+struct Environment {
+    /// The store of the function, which owns the `externref`s it
+    /// returns.
+    store: wasmer::Store,
+    py_function: Arc<PyObject>,
+    env: Option<Arc<PyObject>>,
+    result_types: Vec<wasmer::Type>,
+}
+
+impl wasmer::WasmerEnv for Environment {
+    fn init_with_instance(
+        &mut self,
+        instance: &wasmer::Instance,
+    ) -> Result<(), wasmer::HostEnvInitError> {
+        let env = match &self.env {
+            Some(env) => env,
+            None => return Ok(()),
+        };
+
+        // The exports are weak, to not create a cycle between the
+        // instance and the environment.
+        let mut exports = wasmer::Exports::new();
+
+        for (name, _) in instance.exports.iter() {
+            exports.insert(
+                name.clone(),
+                instance
+                    .exports
+                    .get_with_generics_weak::<wasmer::Extern, (), ()>(name)?,
+            );
+        }
+
+        Python::with_gil(|py| {
+            let env = PyObject::as_ref(env, py);
+
+            if !env.hasattr("init_with_exports")? {
+                return Ok(());
+            }
+
+            env.call_method1("init_with_exports", (Py::new(py, Exports::new(exports))?,))?;
+
+            Ok(())
+        })
+        .map_err(|error: PyErr| {
+            let message = error.to_string();
+
+            ENVIRONMENT_ERROR.with(|environment_error| {
+                *environment_error.borrow_mut() = Some(error);
+            });
+
+            wasmer::ExportError::Missing(message).into()
+        })
+    }
 }
 
 impl Function {
     pub fn raw_new(inner: wasmer::Function) -> Self {
-        Self { inner }
+        Self {
+            inner,
+            interrupted: None,
+            exports: None,
+            statistics: None,
+        }
+    }
+
+    /// Attaches the function to the instance exporting `exports`: it
+    /// becomes interruptible, with the `INTERRUPTED_GLOBAL` global of
+    /// the instance, if any, and its core dumps hold the state of the
+    /// instance.
+    pub(crate) fn with_instance_exports(mut self, exports: &wasmer::Exports) -> Self {
+        self.interrupted = exports.get_global(INTERRUPTED_GLOBAL).ok().cloned();
+        self.exports = Some(exports.clone());
+        self
+    }
+
+    /// Records the calls to the function in `statistics`, if any.
+    pub(crate) fn with_statistics(mut self, statistics: Option<Arc<CallStatistics>>) -> Self {
+        self.statistics = statistics;
+        self
+    }
+
+    /// Converts an error raised by the function, see
+    /// `coredump::runtime_error_to_py_err`.
+    fn runtime_error_to_py_err(&self, error: wasmer::RuntimeError) -> PyErr {
+        coredump::runtime_error_to_py_err(error, self.inner.store(), self.exports.as_ref())
+    }
+
+    /// Interrupts the function when the timeout expires, unless the
+    /// returned `Timeout` is cancelled before.
+    fn start_timer(&self, timeout: f64) -> PyResult<Timeout> {
+        let interrupted = self.interrupted.clone().ok_or_else(|| {
+            to_py_err::<PyRuntimeError, _>(
+                "The function has not been compiled with an `Interruptible` middleware",
+            )
+        })?;
+
+        if !timeout.is_finite() || timeout < 0.0 {
+            return Err(to_py_err::<PyValueError, _>(
+                "The timeout must be a positive number of seconds",
+            ));
+        }
+
+        // A timeout too long to be represented never expires.
+        let timeout = Duration::try_from_secs_f64(timeout).unwrap_or(Duration::MAX);
+
+        timer::start(timeout, interrupted).map_err(to_py_err::<PyRuntimeError, _>)
+    }
+
+    /// Starts a `SigintWatcher`, if the function is interruptible, and
+    /// its store has been created with `keyboard_interrupt=True`.
+    fn start_sigint_watcher(&self, py: Python) -> Option<SigintWatcher> {
+        self.interrupted
+            .clone()
+            .filter(|_| store::keyboard_interrupt(self.inner.store()))
+            .and_then(|interrupted| SigintWatcher::start(py, interrupted))
+    }
+
+    fn to_wasm_arguments(&self, arguments: &PyTuple) -> PyResult<Vec<wasmer::Value>> {
+        arguments
+            .iter()
+            .zip(self.inner.ty().params())
+            .map(|(value, ty)| to_wasm_value(self.inner.store(), (value, *ty)))
+            .collect()
+    }
+
+    /// Calls the function with the GIL released, and converts its
+    /// results to Python.
+    pub(crate) fn call(
+        &self,
+        py: Python,
+        arguments: Vec<wasmer::Value>,
+        timeout: Option<f64>,
+    ) -> PyResult<PyObject> {
+        check_callable(&self.inner)?;
+
+        let timer = timeout
+            .map(|timeout| self.start_timer(timeout))
+            .transpose()?;
+        let sigint_watcher = self.start_sigint_watcher(py);
+
+        // The GIL is released while the function runs, so that other
+        // Python threads can run too. Host functions acquire it back.
+        let function = &self.inner;
+        let statistics = self.statistics.as_deref();
+        let stack_size = store::stack_size(function.store());
+        let arguments = AssumeSend(arguments);
+        let results = py
+            .allow_threads(move || {
+                AssumeSend(stack::with_stack_size(stack_size, || {
+                    statistics::timed(statistics, || function.call(&arguments.0))
+                }))
+            })
+            .0;
+
+        let has_timed_out = timer.is_some_and(Timeout::cancel);
+        let has_sigint = sigint_watcher.is_some_and(|watcher| watcher.stop(py));
+        let results = results.map_err(to_py_err::<PyRuntimeError, _>)?;
+        let expired_timeout = timeout.filter(|_| has_timed_out);
+        let results = end_call(
+            py,
+            self.interrupted.as_ref(),
+            results,
+            expired_timeout,
+            has_sigint,
+        )?
+        .map_err(|error| self.runtime_error_to_py_err(error))?;
+
+        Ok(to_py_results(py, &results))
     }
 
     pub(crate) fn inner(&self) -> &wasmer::Function {
         &self.inner
     }
-}
 
-#[pymethods]
-impl Function {
-    #[new]
-    fn new(
+    /// Wraps a Python callable as a host function. When
+    /// `function_type` is `None`, the type is read from the type
+    /// annotations of the callable. When `env` is given, it is passed
+    /// as the first argument of the callable.
+    pub(crate) fn from_callable(
         py: Python,
-        store: &Store,
+        store: &wasmer::Store,
         py_function: &PyAny,
-        function_type: Option<&FunctionType>,
+        function_type: Option<wasmer::FunctionType>,
+        env: Option<&PyAny>,
     ) -> PyResult<Self> {
         if !py_function.is_callable() {
             return Err(to_py_err::<PyValueError, _>("Function must be a callable"));
         }
 
         let (argument_types, result_types) = match function_type {
-            Some(function_type) => {
-                let function_type: wasmer::FunctionType = function_type.into();
-
-                (
-                    function_type.params().to_vec(),
-                    function_type.results().to_vec(),
-                )
-            }
+            Some(function_type) => (
+                function_type.params().to_vec(),
+                function_type.results().to_vec(),
+            ),
 
             None => {
                 if !py_function.hasattr("__annotations__")? {
@@ -141,10 +669,28 @@ impl Function {
                     .downcast::<PyDict>()
                     .map_err(PyErr::from)?;
 
+                // The first parameter receives the environment, it
+                // has no WebAssembly type.
+                let env_parameter = match env {
+                    Some(_) => py
+                        .import("inspect")?
+                        .call_method1("signature", (py_function,))?
+                        .getattr("parameters")?
+                        .iter()?
+                        .next()
+                        .transpose()?
+                        .map(ToString::to_string),
+                    None => None,
+                };
+
                 let mut argument_types = Vec::new();
                 let mut result_types = Vec::new();
 
                 for (annotation_name, annotation_value) in annotations {
+                    if Some(annotation_name.to_string()) == env_parameter {
+                        continue;
+                    }
+
                     let maybe_ty = to_wasm_type(annotation_value)?;
 
                     match (annotation_name.to_string().as_str(), maybe_ty) {
@@ -172,19 +718,15 @@ impl Function {
             }
         };
 
-        #[derive(wasmer::WasmerEnv, Clone)]
-        struct Environment {
-            py_function: Arc<PyObject>,
-            result_types: Vec<wasmer::Type>,
-        }
-
         let environment = Environment {
+            store: store.clone(),
             py_function: Arc::new(py_function.to_object(py)),
+            env: env.map(|env| Arc::new(env.to_object(py))),
             result_types: result_types.clone(),
         };
 
         let host_function = wasmer::Function::new_with_env(
-            store.inner(),
+            store,
             &wasmer::FunctionType::new(argument_types, result_types),
             environment,
             |environment,
@@ -194,7 +736,12 @@ impl Function {
                 let py = gil.python();
 
                 let to_py_object = to_py_object(py);
-                let arguments: Vec<PyObject> = arguments.iter().map(to_py_object).collect();
+                let arguments: Vec<PyObject> = environment
+                    .env
+                    .iter()
+                    .map(|env| env.clone_ref(py))
+                    .chain(arguments.iter().map(to_py_object))
+                    .collect();
 
                 let results = environment
                     .py_function
@@ -205,20 +752,33 @@ impl Function {
                 let has_result_types = !result_types.is_empty();
 
                 Ok(if let Ok(results) = results.cast_as::<PyTuple>(py) {
+                    if results.len() != result_types.len() {
+                        let error = to_py_err::<PyRuntimeError, _>(format!(
+                            "The host function has returned {} values, expected {}",
+                            results.len(),
+                            result_types.len()
+                        ));
+
+                        return Err(wasmer::RuntimeError::user(Box::new(error)));
+                    }
+
                     results
                         .iter()
                         .zip(result_types)
-                        .map(|(value, ty)| to_wasm_value((value, *ty)))
+                        .map(|(value, ty)| to_wasm_value(&environment.store, (value, *ty)))
                         .collect::<PyResult<_>>()
                         .map_err(|error| wasmer::RuntimeError::user(Box::new(error)))?
                 } else if !results.is_none(py) && has_result_types {
-                    vec![to_wasm_value((
-                        results
-                            .cast_as::<PyAny>(py)
-                            .map_err(PyErr::from)
-                            .map_err(|error| wasmer::RuntimeError::user(Box::new(error)))?,
-                        result_types[0],
-                    ))
+                    vec![to_wasm_value(
+                        &environment.store,
+                        (
+                            results
+                                .cast_as::<PyAny>(py)
+                                .map_err(PyErr::from)
+                                .map_err(|error| wasmer::RuntimeError::user(Box::new(error)))?,
+                            result_types[0],
+                        ),
+                    )
                     .map_err(|error| wasmer::RuntimeError::user(Box::new(error)))?]
                 } else {
                     Vec::new()
@@ -228,34 +788,182 @@ impl Function {
 
         Ok(Self::raw_new(host_function))
     }
+}
+
+#[pymethods]
+impl Function {
+    #[new]
+    fn new(
+        py: Python,
+        store: &Store,
+        py_function: &PyAny,
+        function_type: Option<&FunctionType>,
+        env: Option<&PyAny>,
+    ) -> PyResult<Self> {
+        Self::from_callable(
+            py,
+            store.inner()?,
+            py_function,
+            function_type.map(Into::into),
+            env,
+        )
+    }
 
     /// Calls the function as a regular Python function.
+    ///
+    /// If the function has been compiled with an `Interruptible`
+    /// middleware, the `timeout` keyword argument bounds the duration
+    /// of the call, in seconds. When the call exceeds it, an
+    /// `ExecutionTimeout` exception is raised.
     #[call]
-    #[args(arguments = "*")]
-    fn __call__<'p>(&self, py: Python<'p>, arguments: &PyTuple) -> PyResult<PyObject> {
-        let arguments: Vec<wasmer::Value> = arguments
+    #[args(arguments = "*", timeout = "None")]
+    fn __call__<'p>(
+        &self,
+        py: Python<'p>,
+        arguments: &PyTuple,
+        timeout: Option<f64>,
+    ) -> PyResult<PyObject> {
+        let arguments = self.to_wasm_arguments(arguments)?;
+
+        self.call(py, arguments, timeout)
+    }
+
+    /// Calls the function asynchronously, on a worker thread of the
+    /// running `asyncio` event loop, with the GIL released. It
+    /// returns an awaitable resolving to the results of the call.
+    ///
+    /// The `timeout` keyword argument is the same as when calling the
+    /// function directly.
+    ///
+    /// Only the values crossing threads safely are accepted: a
+    /// function whose parameters or results are references raises a
+    /// `TypeError`. The host functions it calls run on the worker
+    /// thread too, so they must not use the objects bound to the
+    /// thread of the event loop, e.g. the `Memory` of an instance:
+    /// using them raises an exception.
+    ///
+    /// ## Example
+    ///
+    /// ```py
+    /// from wasmer import Store, Module, Instance
+    /// import asyncio
+    ///
+    /// module = Module(
+    ///     Store(),
+    ///     """
+    ///     (module
+    ///       (func (export "sum") (param i32 i32) (result i32)
+    ///         local.get 0
+    ///         local.get 1
+    ///         i32.add))
+    ///     """
+    /// )
+    /// instance = Instance(module)
+    ///
+    /// class Main:
+    ///     async def run(self, sum):
+    ///         return await sum.call_async(1, 2)
+    ///
+    /// assert asyncio.run(Main().run(instance.exports.sum)) == 3
+    /// ```
+    #[args(arguments = "*", timeout = "None")]
+    #[pyo3(text_signature = "($self, *arguments, timeout)")]
+    fn call_async<'p>(
+        &self,
+        py: Python<'p>,
+        arguments: &PyTuple,
+        timeout: Option<f64>,
+    ) -> PyResult<&'p PyAny> {
+        let function_type = self.inner.ty();
+
+        if function_type
+            .params()
             .iter()
-            .zip(self.inner.ty().params())
-            .map(|(value, ty)| to_wasm_value((value, *ty)))
-            .collect::<PyResult<_>>()?;
+            .chain(function_type.results())
+            .any(|ty| ty.is_ref())
+        {
+            return Err(to_py_err::<PyTypeError, _>(
+                "The function passes references, which cannot be sent to another thread",
+            ));
+        }
 
-        let results = self
-            .inner
-            .call(&arguments)
-            .map(<[_]>::into_vec)
-            .map_err(runtime_error_to_py_err)?;
+        let call = PendingCall {
+            function: self.clone(),
+            arguments: self
+                .to_wasm_arguments(arguments)?
+                .into_iter()
+                .filter_map(NumericValue::new)
+                .collect(),
+            timeout,
+        };
 
-        let to_py_object = to_py_object(py);
+        py.import("asyncio")?
+            .call_method0("get_running_loop")?
+            .call_method1("run_in_executor", (py.None(), Py::new(py, call)?))
+    }
 
-        Ok(match results.len() {
-            0 => py.None(),
-            1 => to_py_object(&results[0]),
-            _ => PyTuple::new(
-                py,
-                results.iter().map(to_py_object).collect::<Vec<PyObject>>(),
-            )
-            .to_object(py),
-        })
+    /// Calls the function once per tuple of arguments of an iterable,
+    /// and returns the list of the results.
+    ///
+    /// All the calls run in a single native loop with the GIL
+    /// released, which avoids a round trip through Python per call.
+    /// The first failing call stops the batch and raises its error.
+    ///
+    /// ## Example
+    ///
+    /// ```py
+    /// from wasmer import Store, Module, Instance
+    ///
+    /// module = Module(
+    ///     Store(),
+    ///     """
+    ///     (module
+    ///       (func (export "square") (param i32) (result i32)
+    ///         local.get 0
+    ///         local.get 0
+    ///         i32.mul))
+    ///     """
+    /// )
+    /// instance = Instance(module)
+    ///
+    /// assert instance.exports.square.call_batch((x,) for x in range(4)) == [0, 1, 4, 9]
+    /// ```
+    #[pyo3(text_signature = "($self, arguments)")]
+    fn call_batch(&self, py: Python, arguments: &PyAny) -> PyResult<Vec<PyObject>> {
+        let arguments = arguments
+            .iter()?
+            .map(|arguments| self.to_wasm_arguments(arguments?.downcast::<PyTuple>()?))
+            .collect::<PyResult<Vec<_>>>()?;
+
+        check_callable(&self.inner)?;
+
+        let sigint_watcher = self.start_sigint_watcher(py);
+
+        let function = &self.inner;
+        let statistics = self.statistics.as_deref();
+        let stack_size = store::stack_size(function.store());
+        let arguments = AssumeSend(arguments);
+        let results = py
+            .allow_threads(move || {
+                AssumeSend(stack::with_stack_size(stack_size, || {
+                    arguments
+                        .0
+                        .iter()
+                        .map(|arguments| statistics::timed(statistics, || function.call(arguments)))
+                        .collect::<Result<Vec<_>, _>>()
+                }))
+            })
+            .0;
+
+        let has_sigint = sigint_watcher.is_some_and(|watcher| watcher.stop(py));
+        let results = results.map_err(to_py_err::<PyRuntimeError, _>)?;
+        let results = end_call(py, self.interrupted.as_ref(), results, None, has_sigint)?
+            .map_err(|error| self.runtime_error_to_py_err(error))?;
+
+        Ok(results
+            .iter()
+            .map(|results| to_py_results(py, results))
+            .collect())
     }
 
     /// Returns the type of the function as a `FunctionType` object.
@@ -289,6 +997,517 @@ impl Function {
     fn ty(&self) -> FunctionType {
         self.inner.ty().into()
     }
+
+    /// Returns a `TypedFunction`, a handle to call the function with
+    /// a lower overhead, e.g. in tight loops.
+    ///
+    /// The signature is written `(params)->results`, with one letter
+    /// per type: `i` for `i32`, `I` for `i64`, `f` for `f32`, `F` for
+    /// `f64` and `v` for `v128`. It must be the signature of the
+    /// function, otherwise a `TypeError` is raised.
+    ///
+    /// The letters `u` and `U` are `i32` and `i64` too, but read as
+    /// unsigned integers: results are positive, and arguments must
+    /// be positive. By default, an integer argument out of the range
+    /// of its type raises an `OverflowError`; with `wrap=True`, it is
+    /// wrapped around instead, e.g. `-1` becomes `0xffffffff` for `u`.
+    ///
+    /// ## Example
+    ///
+    /// ```py
+    /// from wasmer import Store, Module, Instance
+    ///
+    /// module = Module(
+    ///     Store(),
+    ///     """
+    ///     (module
+    ///       (func (export "sum") (param i32 i64) (result i64)
+    ///         local.get 0
+    ///         i64.extend_i32_s
+    ///         local.get 1
+    ///         i64.add))
+    ///     """
+    /// )
+    /// instance = Instance(module)
+    /// sum = instance.exports.sum.typed("(iI)->I")
+    ///
+    /// assert sum(1, 2) == 3
+    ///
+    /// unsigned_sum = instance.exports.sum.typed("(iU)->U")
+    ///
+    /// assert unsigned_sum(1, 2 ** 64 - 2) == 2 ** 64 - 1
+    /// assert sum(1, 2 ** 64 - 2) == -1
+    ///
+    /// wrapping_sum = instance.exports.sum.typed("(iI)->I", wrap=True)
+    ///
+    /// assert wrapping_sum(0, 2 ** 64 + 3) == 3
+    /// ```
+    #[pyo3(text_signature = "($self, signature, /, wrap=False)")]
+    #[args(wrap = "false")]
+    fn typed(&self, signature: &str, wrap: bool) -> PyResult<TypedFunction> {
+        let function_type = self.inner.ty();
+
+        match parse_signature(signature) {
+            Some((params, results))
+                if &signature_to_function_type(&params, &results) == function_type =>
+            {
+                Ok(TypedFunction {
+                    inner: self.inner.clone(),
+                    native: NativeFunction::new(&self.inner),
+                    interrupted: self.interrupted.clone(),
+                    exports: self.exports.clone(),
+                    statistics: self.statistics.clone(),
+                    params,
+                    results,
+                    wrap,
+                })
+            }
+
+            _ => Err(to_py_err::<PyTypeError, _>(format!(
+                "The function has the signature `{}`, not `{}`",
+                to_signature(function_type),
+                signature
+            ))),
+        }
+    }
+}
+
+/// An argument of a `NativeFunction`.
+trait NativeArgument {
+    /// Reads the argument, whose type has been checked by
+    /// `TypedFunction.__call__`.
+    fn from_value(value: Option<&wasmer::Value>) -> Self;
+}
+
+/// The results of a `NativeFunction`.
+trait NativeResults {
+    fn into_values(self) -> Vec<wasmer::Value>;
+}
+
+impl NativeResults for () {
+    fn into_values(self) -> Vec<wasmer::Value> {
+        Vec::new()
+    }
+}
+
+macro_rules! native_value {
+    ($($ty:ty: $variant:ident),* $(,)?) => {
+        $(
+            impl NativeArgument for $ty {
+                fn from_value(value: Option<&wasmer::Value>) -> Self {
+                    match value {
+                        Some(wasmer::Value::$variant(value)) => *value,
+                        _ => Self::default(),
+                    }
+                }
+            }
+
+            impl NativeResults for $ty {
+                fn into_values(self) -> Vec<wasmer::Value> {
+                    vec![wasmer::Value::$variant(self)]
+                }
+            }
+        )*
+    };
+}
+
+native_value!(i32: I32, i64: I64, f32: F32, f64: F64);
+
+macro_rules! native_function {
+    ($($variant:ident($($param:ty),*) -> $result:ty;)*) => {
+        /// A `TypedFunction` resolved to a `wasmer::NativeFunc`, whose
+        /// calls skip the checks and the conversions of the
+        /// arguments and results of `wasmer::Function::call`. It
+        /// covers the signatures with up to 3 parameters of the same
+        /// numeric type, returning nothing or a value of this type.
+        #[allow(unused_parens)]
+        enum NativeFunction {
+            $($variant(wasmer::NativeFunc<($($param),*), $result>),)*
+        }
+
+        impl NativeFunction {
+            /// Resolves `function`, if its signature is covered.
+            fn new(function: &wasmer::Function) -> Option<Self> {
+                $(
+                    if let Ok(function) = function.native() {
+                        return Some(Self::$variant(function));
+                    }
+                )*
+
+                None
+            }
+
+            /// Calls the function with `arguments`, which have the
+            /// types of its parameters.
+            #[allow(unused_mut, unused_variables)]
+            fn call(
+                &self,
+                arguments: &[wasmer::Value],
+            ) -> Result<Vec<wasmer::Value>, wasmer::RuntimeError> {
+                let mut arguments = arguments.iter();
+
+                match self {
+                    $(
+                        Self::$variant(function) => function
+                            .call($(<$param>::from_value(arguments.next())),*)
+                            .map(NativeResults::into_values),
+                    )*
+                }
+            }
+        }
+    };
+}
+
+native_function! {
+    Void() -> ();
+    ToI32() -> i32;
+    I32ToVoid(i32) -> ();
+    I32ToI32(i32) -> i32;
+    I32x2ToVoid(i32, i32) -> ();
+    I32x2ToI32(i32, i32) -> i32;
+    I32x3ToVoid(i32, i32, i32) -> ();
+    I32x3ToI32(i32, i32, i32) -> i32;
+    ToI64() -> i64;
+    I64ToVoid(i64) -> ();
+    I64ToI64(i64) -> i64;
+    I64x2ToVoid(i64, i64) -> ();
+    I64x2ToI64(i64, i64) -> i64;
+    I64x3ToVoid(i64, i64, i64) -> ();
+    I64x3ToI64(i64, i64, i64) -> i64;
+    ToF32() -> f32;
+    F32ToVoid(f32) -> ();
+    F32ToF32(f32) -> f32;
+    F32x2ToVoid(f32, f32) -> ();
+    F32x2ToF32(f32, f32) -> f32;
+    F32x3ToVoid(f32, f32, f32) -> ();
+    F32x3ToF32(f32, f32, f32) -> f32;
+    ToF64() -> f64;
+    F64ToVoid(f64) -> ();
+    F64ToF64(f64) -> f64;
+    F64x2ToVoid(f64, f64) -> ();
+    F64x2ToF64(f64, f64) -> f64;
+    F64x3ToVoid(f64, f64, f64) -> ();
+    F64x3ToF64(f64, f64, f64) -> f64;
+}
+
+/// A handle to call a function with a lower overhead, built by
+/// `Function.typed`.
+///
+/// The types of the arguments are resolved once, and the number of
+/// arguments is checked. The functions with up to 3 parameters of
+/// the same type, returning nothing or a value of this type, are
+/// resolved to a native function too, called without the dynamic
+/// checks of `Function`. Unlike `Function`, the GIL is not released
+/// during the call, which is faster for short calls.
+///
+/// ## Example
+///
+/// See `Function.typed`.
+#[pyclass(unsendable)]
+pub struct TypedFunction {
+    inner: wasmer::Function,
+
+    /// The `NativeFunc` of `inner`, if its signature is covered.
+    native: Option<NativeFunction>,
+    interrupted: Option<wasmer::Global>,
+    exports: Option<wasmer::Exports>,
+    statistics: Option<Arc<CallStatistics>>,
+    params: Vec<SignatureType>,
+    results: Vec<SignatureType>,
+    wrap: bool,
+}
+
+impl TypedFunction {
+    fn call_inner(
+        &self,
+        arguments: &[wasmer::Value],
+    ) -> Result<Vec<wasmer::Value>, wasmer::RuntimeError> {
+        statistics::timed(self.statistics.as_deref(), || match &self.native {
+            Some(native) => native.call(arguments),
+            None => self.inner.call(arguments).map(<[_]>::into_vec),
+        })
+    }
+
+    fn to_wasm_argument(&self, value: &PyAny, ty: SignatureType) -> PyResult<wasmer::Value> {
+        if value.downcast::<PyLong>().is_err() {
+            return to_wasm_value(self.inner.store(), (value, ty.ty));
+        }
+
+        let value = match (ty.ty, self.wrap) {
+            (wasmer::Type::I32, true) => value.call_method1("__and__", (u32::MAX,))?,
+            (wasmer::Type::I64, true) => value.call_method1("__and__", (u64::MAX,))?,
+            _ => value,
+        };
+
+        Ok(match (ty.ty, ty.unsigned) {
+            (wasmer::Type::I32, true) => wasmer::Value::I32(value.extract::<u32>()? as i32),
+            (wasmer::Type::I64, true) => wasmer::Value::I64(value.extract::<u64>()? as i64),
+            _ => to_wasm_value(self.inner.store(), (value, ty.ty))?,
+        })
+    }
+}
+
+#[pymethods]
+impl TypedFunction {
+    /// Calls the function.
+    #[call]
+    #[args(arguments = "*")]
+    fn __call__(&self, py: Python, arguments: &PyTuple) -> PyResult<PyObject> {
+        if arguments.len() != self.params.len() {
+            return Err(to_py_err::<PyTypeError, _>(format!(
+                "The function expects {} arguments, got {}",
+                self.params.len(),
+                arguments.len()
+            )));
+        }
+
+        let arguments = arguments
+            .iter()
+            .zip(&self.params)
+            .map(|(value, ty)| self.to_wasm_argument(value, *ty))
+            .collect::<PyResult<Vec<_>>>()?;
+
+        check_callable(&self.inner)?;
+
+        let results = match store::stack_size(self.inner.store()) {
+            // The GIL is released for the host functions, which
+            // acquire it back from the thread of the call.
+            Some(stack_size) => {
+                let call = AssumeSend((self, arguments));
+
+                py.allow_threads(move || {
+                    let (function, arguments) = &call.0;
+
+                    AssumeSend(stack::with_stack_size(Some(stack_size), || {
+                        function.call_inner(arguments)
+                    }))
+                })
+                .0
+                .map_err(to_py_err::<PyRuntimeError, _>)?
+            }
+            None => self.call_inner(&arguments),
+        };
+        let results =
+            end_call(py, self.interrupted.as_ref(), results, None, false)?.map_err(|error| {
+                coredump::runtime_error_to_py_err(error, self.inner.store(), self.exports.as_ref())
+            })?;
+
+        if !self.results.iter().any(|ty| ty.unsigned) {
+            return Ok(to_py_results(py, &results));
+        }
+
+        let results = results
+            .iter()
+            .zip(&self.results)
+            .map(|(value, ty)| match (value, ty.unsigned) {
+                (wasmer::Value::I32(value), true) => (*value as u32).to_object(py),
+                (wasmer::Value::I64(value), true) => (*value as u64).to_object(py),
+                (value, _) => to_py_object(py)(value),
+            })
+            .collect::<Vec<_>>();
+
+        Ok(match results.len() {
+            1 => results[0].clone_ref(py),
+            _ => PyTuple::new(py, results).to_object(py),
+        })
+    }
+
+    /// Returns the type of the function as a `FunctionType` object,
+    /// like `Function.type`. The signedness of the `typed` signature
+    /// doesn't change the type.
+    ///
+    /// ## Example
+    ///
+    /// ```py
+    /// from wasmer import Store, Module, Instance, FunctionType, Type
+    ///
+    /// module = Module(Store(), '(module (func (export "id") (param i64) (result i64) local.get 0))')
+    /// id = Instance(module).exports.id
+    ///
+    /// assert id.typed("(U)->U").type == id.type == FunctionType([Type.I64], [Type.I64])
+    /// ```
+    #[getter(type)]
+    fn ty(&self) -> FunctionType {
+        self.inner.ty().into()
+    }
+}
+
+/// Host functions defined in Python are dynamic functions, which
+/// Wasmer cannot store in a table, or pass as a `funcref`, as is.
+/// They must be referenced by a module first, e.g. by importing them
+/// and putting them in a table with an element segment.
+pub(crate) fn check_storable(function: &wasmer::Function) -> PyResult<()> {
+    match function.to_export() {
+        wasmer::Export::Function(export) if export.vm_function.kind == VMFunctionKind::Dynamic => {
+            Err(to_py_err::<PyTypeError, _>(
+                "A host function cannot be used as a `funcref` directly; import it in a module, and reference it from an element segment instead",
+            ))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// The functions read from a table, or received as a `funcref`, have
+/// no trampoline to be called from the host.
+fn check_callable(function: &wasmer::Function) -> PyResult<()> {
+    match function.to_export() {
+        wasmer::Export::Function(export)
+            if export.vm_function.kind == VMFunctionKind::Static
+                && export.vm_function.call_trampoline.is_none() =>
+        {
+            Err(to_py_err::<PyRuntimeError, _>(
+                "A function read from a table cannot be called from the host, only from WebAssembly",
+            ))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Resets the `INTERRUPTED_GLOBAL` global `interrupted`, if any, and
+/// returns whether it was set.
+fn reset_interrupted(interrupted: Option<&wasmer::Global>) -> bool {
+    match interrupted {
+        Some(interrupted) => {
+            let was_interrupted = !matches!(interrupted.get(), wasmer::Value::I32(0));
+
+            if was_interrupted {
+                let _ = interrupted.set(wasmer::Value::I32(0));
+            }
+
+            was_interrupted
+        }
+
+        None => false,
+    }
+}
+
+/// Ends a call to a function whose `INTERRUPTED_GLOBAL` global is
+/// `interrupted`, if any: the global is reset, and if the call has
+/// failed because it was set, an `ExecutionTimeout` is raised.
+/// `expired_timeout` is the timeout of the call, if it has expired,
+/// and `has_sigint` whether `SIGINT` has been received during the
+/// call.
+fn end_call<T>(
+    py: Python,
+    interrupted: Option<&wasmer::Global>,
+    results: Result<T, wasmer::RuntimeError>,
+    expired_timeout: Option<f64>,
+    has_sigint: bool,
+) -> PyResult<Result<T, wasmer::RuntimeError>> {
+    match results {
+        Err(_) if reset_interrupted(interrupted) => {
+            // Runs the Python handler of `SIGINT`, which raises
+            // `KeyboardInterrupt` by default.
+            if has_sigint {
+                py.check_signals()?;
+            }
+
+            Err(match expired_timeout {
+                Some(timeout) => ExecutionTimeout::new_err(format!(
+                    "The execution has exceeded its timeout of {} seconds",
+                    timeout
+                )),
+                None => ExecutionTimeout::new_err("The execution has been interrupted"),
+            })
+        }
+
+        results => {
+            reset_interrupted(interrupted);
+
+            Ok(results)
+        }
+    }
+}
+
+/// Converts the results of a call to Python: `None`, a value, or a
+/// tuple of values.
+fn to_py_results(py: Python, results: &[wasmer::Value]) -> PyObject {
+    let to_py_object = to_py_object(py);
+
+    match results.len() {
+        0 => py.None(),
+        1 => to_py_object(&results[0]),
+        _ => PyTuple::new(
+            py,
+            results.iter().map(to_py_object).collect::<Vec<PyObject>>(),
+        )
+        .to_object(py),
+    }
+}
+
+/// The letter of a type in a signature, see `Function.typed`.
+fn type_to_letter(ty: &wasmer::Type) -> Option<char> {
+    Some(match ty {
+        wasmer::Type::I32 => 'i',
+        wasmer::Type::I64 => 'I',
+        wasmer::Type::F32 => 'f',
+        wasmer::Type::F64 => 'F',
+        wasmer::Type::V128 => 'v',
+        _ => return None,
+    })
+}
+
+fn to_signature(function_type: &wasmer::FunctionType) -> String {
+    let letters = |types: &[wasmer::Type]| -> String {
+        types
+            .iter()
+            .map(|ty| type_to_letter(ty).unwrap_or('?'))
+            .collect()
+    };
+
+    format!(
+        "({})->{}",
+        letters(function_type.params()),
+        letters(function_type.results())
+    )
+}
+
+/// A type in a signature, see `Function.typed`.
+#[derive(Clone, Copy)]
+struct SignatureType {
+    ty: wasmer::Type,
+
+    /// Whether an integer is read as unsigned.
+    unsigned: bool,
+}
+
+fn parse_signature(signature: &str) -> Option<(Vec<SignatureType>, Vec<SignatureType>)> {
+    let letter_to_type = |letter| {
+        let (ty, unsigned) = match letter {
+            'i' => (wasmer::Type::I32, false),
+            'u' => (wasmer::Type::I32, true),
+            'I' => (wasmer::Type::I64, false),
+            'U' => (wasmer::Type::I64, true),
+            'f' => (wasmer::Type::F32, false),
+            'F' => (wasmer::Type::F64, false),
+            'v' => (wasmer::Type::V128, false),
+            _ => return None,
+        };
+
+        Some(SignatureType { ty, unsigned })
+    };
+
+    let (params, results) = signature.strip_prefix('(')?.split_once(")->")?;
+
+    Some((
+        params
+            .chars()
+            .map(letter_to_type)
+            .collect::<Option<Vec<_>>>()?,
+        results
+            .chars()
+            .map(letter_to_type)
+            .collect::<Option<Vec<_>>>()?,
+    ))
+}
+
+fn signature_to_function_type(
+    params: &[SignatureType],
+    results: &[SignatureType],
+) -> wasmer::FunctionType {
+    let types = |types: &[SignatureType]| types.iter().map(|ty| ty.ty).collect::<Vec<_>>();
+
+    wasmer::FunctionType::new(types(params), types(results))
 }
 
 enum MappedType {
@@ -314,6 +1533,12 @@ fn to_wasm_type(value: &PyAny) -> PyResult<MappedType> {
                 (_, "str", "f32" | "F32") => MappedType::One(wasmer::Type::F32),
                 (_, "str", "f64" | "F64") => MappedType::One(wasmer::Type::F64),
 
+                (_, "str", "v128" | "V128") => MappedType::One(wasmer::Type::V128),
+
+                (_, "type", "<class 'object'>") => MappedType::One(wasmer::Type::ExternRef),
+                (_, "str", "externref") => MappedType::One(wasmer::Type::ExternRef),
+                (_, "str", "funcref") => MappedType::One(wasmer::Type::FuncRef),
+
                 (Level::Top, "tuple", _) => {
                     let tuple = value.cast_as::<PyTuple>()?;
                     let mut types = Vec::with_capacity(tuple.len());
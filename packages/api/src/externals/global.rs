@@ -1,5 +1,6 @@
 use crate::{
     errors::to_py_err,
+    externref,
     store::Store,
     types::GlobalType,
     values::{to_py_object, to_wasm_value, Value},
@@ -57,16 +58,16 @@ impl Global {
 #[pymethods]
 impl Global {
     #[new]
-    fn new(store: &Store, value: &Value, mutable: Option<bool>) -> Self {
-        let store = store.inner();
-        let value = value.inner().clone();
+    fn new(py: Python, store: &Store, value: &Value, mutable: Option<bool>) -> PyResult<Self> {
+        let store = store.inner()?;
+        let value = externref::to_store(py, store, value.inner().clone());
 
-        Self {
+        Ok(Self {
             inner: match mutable {
                 Some(true) => wasmer::Global::new_mut(store, value),
                 _ => wasmer::Global::new(store, value),
             },
-        }
+        })
     }
 
     /// Checks whether the global is mutable.
@@ -120,7 +121,7 @@ impl Global {
         }
 
         self.inner
-            .set(to_wasm_value((value, ty.ty))?)
+            .set(to_wasm_value(self.inner.store(), (value, ty.ty))?)
             .map_err(to_py_err::<PyValueError, _>)?;
 
         Ok(())
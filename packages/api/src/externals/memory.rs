@@ -1,14 +1,82 @@
 use crate::{
     errors::to_py_err,
     memory::{
-        Buffer, Float32Array, Float64Array, Int16Array, Int32Array, Int64Array, Int8Array,
-        Uint16Array, Uint32Array, Uint64Array, Uint8Array,
+        fill_buffer, Buffer, Float32Array, Float64Array, Int16Array, Int32Array, Int64Array,
+        Int8Array, Uint16Array, Uint32Array, Uint64Array, Uint8Array,
     },
     store::Store,
     types::MemoryType,
-    wasmer_inner::wasmer,
+    wasmer_inner::wasmer::{self, Exportable},
 };
-use pyo3::{exceptions::PyRuntimeError, prelude::*};
+use pyo3::{
+    buffer::PyBuffer,
+    class::{basic::CompareOp, buffer::PyBufferProtocol},
+    exceptions::{PyIndexError, PyRuntimeError, PyValueError},
+    ffi,
+    ffi::Py_buffer,
+    prelude::*,
+    types::{PyBytes, PyString},
+    AsPyPointer,
+};
+use std::{collections::BTreeMap, ops::Range, os::raw::c_int, ptr, sync::Mutex};
+
+/// Copies smaller than this number of bytes are done without
+/// releasing the GIL, since releasing it costs more than the copy
+/// itself.
+const GIL_RELEASE_THRESHOLD: usize = 64 * 1024;
+
+/// The callbacks notified of the growth of the memories, by the
+/// address of their definition, see `Memory.add_grow_callback`. The
+/// callbacks of a memory are dropped with it, see
+/// `forget_grow_callbacks`.
+static GROW_CALLBACKS: Mutex<BTreeMap<usize, Vec<PyObject>>> = Mutex::new(BTreeMap::new());
+
+/// The address of the definition of `memory`, shared by all the
+/// handles of the memory.
+fn definition_address(memory: &wasmer::Memory) -> usize {
+    match memory.to_export() {
+        wasmer::Export::Memory(memory) => memory.from.vmmemory().as_ptr() as usize,
+        _ => unreachable!("a memory is exported as a memory"),
+    }
+}
+
+/// Calls the grow callbacks of the memory defined at `definition`,
+/// which has grown from `old_size` to `new_size` pages. A callback
+/// raising an exception is reported by `sys.unraisablehook`.
+pub(crate) fn notify_growth(definition: usize, old_size: u32, new_size: u32) {
+    if GROW_CALLBACKS.lock().unwrap().is_empty() {
+        return;
+    }
+
+    Python::with_gil(|py| {
+        let callbacks = match GROW_CALLBACKS.lock().unwrap().get(&definition) {
+            Some(callbacks) => callbacks
+                .iter()
+                .map(|callback| callback.clone_ref(py))
+                .collect::<Vec<_>>(),
+            None => return,
+        };
+
+        for callback in callbacks {
+            if let Err(error) = callback.call1(py, (old_size, new_size)) {
+                error.restore(py);
+
+                // SAFETY: the GIL is held, and an error is set.
+                unsafe { ffi::PyErr_WriteUnraisable(callback.as_ptr()) };
+            }
+        }
+    })
+}
+
+/// Forgets the grow callbacks of the memory defined at `definition`,
+/// when it is dropped.
+pub(crate) fn forget_grow_callbacks(definition: usize) {
+    let mut callbacks = GROW_CALLBACKS.lock().unwrap();
+
+    if !callbacks.is_empty() {
+        callbacks.remove(&definition);
+    }
+}
 
 /// A WebAssembly memory instance.
 ///
@@ -52,19 +120,89 @@ use pyo3::{exceptions::PyRuntimeError, prelude::*};
 ///
 /// assert isinstance(memory, Memory)
 /// ```
+///
+/// Like `Buffer`, a memory implements the [Python buffer
+/// protocol][buffer-protocol], so its data can be read and written
+/// without any copy with [`memoryview`][memoryview]. The view must
+/// not be used after the memory has grown.
+///
+/// ```py
+/// from wasmer import Store, Memory, MemoryType
+///
+/// memory = Memory(Store(), MemoryType(minimum=1))
+/// view = memoryview(memory)
+/// view[0:3] = b'abc'
+///
+/// assert len(view) == memory.data_size
+/// assert bytes(memory.uint8_view()[0:3]) == b'abc'
+/// ```
+///
+/// The same goes for NumPy: `numpy.frombuffer(memory,
+/// dtype=numpy.float32, offset=offset, count=count)` returns a
+/// writable array directly over the memory data.
+///
+/// [buffer-protocol]: https://docs.python.org/3/c-api/buffer.html
+/// [memoryview]: https://docs.python.org/3/library/stdtypes.html?#memoryview
 #[pyclass(unsendable)]
 #[pyo3(text_signature = "(store, memory_type)")]
 pub struct Memory {
-    inner: wasmer::Memory,
+    /// The memory, until it is closed.
+    inner: Option<wasmer::Memory>,
 }
 
 impl Memory {
     pub fn raw_new(inner: wasmer::Memory) -> Self {
-        Self { inner }
+        Self { inner: Some(inner) }
+    }
+
+    pub(crate) fn inner(&self) -> PyResult<&wasmer::Memory> {
+        self.inner
+            .as_ref()
+            .ok_or_else(|| to_py_err::<PyValueError, _>("The memory is closed"))
+    }
+
+    /// Check that `length` bytes starting at `offset` are inside the
+    /// memory, and return the corresponding range.
+    fn byte_range(&self, offset: u64, length: usize) -> PyResult<Range<usize>> {
+        let data_size = self.inner()?.data_size();
+
+        match offset.checked_add(length as u64) {
+            Some(end) if end <= data_size => Ok(offset as usize..end as usize),
+            _ => Err(to_py_err::<PyIndexError, _>(format!(
+                "Out of bound: Reading or writing {} bytes at offset {} exceeds the memory size of {} bytes",
+                length, offset, data_size,
+            ))),
+        }
     }
 
-    pub(crate) fn inner(&self) -> &wasmer::Memory {
-        &self.inner
+    /// Copy the bytes of `buffer` into the memory, starting at
+    /// `offset`.
+    fn write_buffer(&self, py: Python, offset: u64, buffer: &PyBuffer<u8>) -> PyResult<()> {
+        let length = buffer.len_bytes();
+        let range = self.byte_range(offset, length)?;
+        let target = unsafe { self.inner()?.data_unchecked_mut() }[range].as_mut_ptr();
+
+        if !buffer.is_c_contiguous() {
+            let source = buffer.to_vec(py)?;
+
+            unsafe { ptr::copy_nonoverlapping(source.as_ptr(), target, length) };
+
+            return Ok(());
+        }
+
+        // The source may be a view over this very memory, hence
+        // `ptr::copy` which allows overlapping regions. Pointers are
+        // passed as integers to cross `allow_threads`.
+        let (source, target) = (buffer.buf_ptr() as usize, target as usize);
+        let copy = move || unsafe { ptr::copy(source as *const u8, target as *mut u8, length) };
+
+        if length < GIL_RELEASE_THRESHOLD {
+            copy();
+        } else {
+            py.allow_threads(copy);
+        }
+
+        Ok(())
     }
 }
 
@@ -73,7 +211,7 @@ impl Memory {
     #[new]
     fn new(store: &Store, memory_type: &MemoryType) -> PyResult<Self> {
         Ok(Self::raw_new(
-            wasmer::Memory::new(store.inner(), memory_type.into())
+            wasmer::Memory::new(store.inner()?, memory_type.into())
                 .map_err(to_py_err::<PyRuntimeError, _>)?,
         ))
     }
@@ -92,8 +230,8 @@ impl Memory {
     /// assert memory.size == 3
     /// ```
     #[getter]
-    fn size(&self) -> u32 {
-        self.inner.size().0
+    fn size(&self) -> PyResult<u32> {
+        Ok(self.inner()?.size().0)
     }
 
     /// Returns the size (in bytes) of the `Memory`.
@@ -110,8 +248,36 @@ impl Memory {
     /// assert memory.data_size == 196608
     /// ```
     #[getter]
-    fn data_size(&self) -> u64 {
-        self.inner.data_size()
+    fn data_size(&self) -> PyResult<u64> {
+        Ok(self.inner()?.data_size())
+    }
+
+    /// Returns the address of the memory data, e.g. for
+    /// `ctypes.Structure.from_address` to overlay C structures on
+    /// the memory data without any copy.
+    ///
+    /// The address is only valid until the memory grows: growing
+    /// may move the data elsewhere, and so invalidates this
+    /// address, and every object created from it. The same goes
+    /// for the objects created with `ctypes.Structure.from_buffer`
+    /// over the memory. Read `data_ptr` again after a growth.
+    ///
+    /// ## Example
+    ///
+    /// ```py
+    /// from wasmer import Store, Memory, MemoryType
+    /// import ctypes
+    ///
+    /// memory = Memory(Store(), MemoryType(minimum=1))
+    /// point = (ctypes.c_int32 * 2).from_address(memory.data_ptr() + 8)
+    /// point[0] = 1
+    /// point[1] = -2
+    ///
+    /// assert memory.int32_view(offset=2)[0:2] == [1, -2]
+    /// ```
+    #[pyo3(text_signature = "($self)")]
+    fn data_ptr(&self) -> PyResult<usize> {
+        Ok(self.inner()?.data_ptr() as usize)
     }
 
     /// Grow memory by the specified amount of WebAssembly pages.
@@ -133,12 +299,325 @@ impl Memory {
     /// ```
     #[pyo3(text_signature = "($self, number_of_pages)")]
     fn grow(&self, number_of_pages: u32) -> PyResult<u32> {
-        self.inner
+        self.inner()?
             .grow(number_of_pages)
             .map(|pages| pages.0)
             .map_err(to_py_err::<PyRuntimeError, _>)
     }
 
+    /// Registers `callback` to be called after the memory grows,
+    /// with its old size and its new size, in pages, e.g. to refresh
+    /// the views and the `ctypes` overlays of the memory, whose
+    /// address may have changed, see `data_ptr`.
+    ///
+    /// The callback is called after every growth, whether it is
+    /// requested by `grow` or by WebAssembly with `memory.grow`; it
+    /// is registered on the memory itself, i.e. on every `Memory`
+    /// object referring to it, e.g. `instance.exports.memory`. A
+    /// callback raising an exception is reported by
+    /// `sys.unraisablehook`, and the growth still succeeds.
+    ///
+    /// The callbacks are held until the memory is dropped, or until
+    /// they are removed with `remove_grow_callback`.
+    ///
+    /// ## Example
+    ///
+    /// ```py
+    /// from wasmer import Store, Module, Instance
+    ///
+    /// class Growths:
+    ///     def __init__(self):
+    ///         self.sizes = []
+    ///
+    ///     def grown(self, old_size, new_size):
+    ///         self.sizes.append((old_size, new_size))
+    ///
+    /// module = Module(
+    ///     Store(),
+    ///     """
+    ///     (module
+    ///       (memory (export "memory") 1)
+    ///       (func (export "grow") (param i32) (result i32)
+    ///         local.get 0
+    ///         memory.grow))
+    ///     """
+    /// )
+    /// instance = Instance(module)
+    /// growths = Growths()
+    /// instance.exports.memory.add_grow_callback(growths.grown)
+    ///
+    /// instance.exports.grow(2)
+    /// instance.exports.memory.grow(1)
+    ///
+    /// assert growths.sizes == [(1, 3), (3, 4)]
+    /// ```
+    #[pyo3(text_signature = "($self, callback)")]
+    fn add_grow_callback(&self, callback: PyObject) -> PyResult<()> {
+        let definition = definition_address(self.inner()?);
+
+        GROW_CALLBACKS
+            .lock()
+            .unwrap()
+            .entry(definition)
+            .or_default()
+            .push(callback);
+
+        Ok(())
+    }
+
+    /// Removes a callback registered with `add_grow_callback`. It
+    /// raises a `ValueError` if the callback is not registered.
+    #[pyo3(text_signature = "($self, callback)")]
+    fn remove_grow_callback(&self, py: Python, callback: PyObject) -> PyResult<()> {
+        let definition = definition_address(self.inner()?);
+
+        // The callbacks are compared without holding the lock, since
+        // comparing them runs Python code.
+        let registered = GROW_CALLBACKS
+            .lock()
+            .unwrap()
+            .get(&definition)
+            .map(|callbacks| {
+                callbacks
+                    .iter()
+                    .map(|callback| callback.clone_ref(py))
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        let found = registered
+            .iter()
+            .find(|registered| {
+                registered
+                    .as_ref(py)
+                    .rich_compare(callback.as_ref(py), CompareOp::Eq)
+                    .and_then(PyAny::is_true)
+                    .unwrap_or(false)
+            })
+            .ok_or_else(|| {
+                to_py_err::<PyValueError, _>("The callback is not registered on the memory")
+            })?;
+
+        let mut all_callbacks = GROW_CALLBACKS.lock().unwrap();
+
+        if let Some(callbacks) = all_callbacks.get_mut(&definition) {
+            if let Some(position) = callbacks
+                .iter()
+                .position(|callback| callback.as_ptr() == found.as_ptr())
+            {
+                callbacks.remove(position);
+            }
+
+            if callbacks.is_empty() {
+                all_callbacks.remove(&definition);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reads `length` bytes of the memory, starting at `offset`, in a
+    /// single copy. The GIL is released during large copies.
+    ///
+    /// ## Example
+    ///
+    /// ```py
+    /// from wasmer import Store, Memory, MemoryType
+    ///
+    /// memory = Memory(Store(), MemoryType(minimum=1))
+    /// memory.write(7, b'hello')
+    ///
+    /// assert memory.read(7, 5) == b'hello'
+    /// ```
+    #[pyo3(text_signature = "($self, offset, length)")]
+    fn read<'p>(&self, py: Python<'p>, offset: u64, length: usize) -> PyResult<&'p PyBytes> {
+        let range = self.byte_range(offset, length)?;
+        let source = &unsafe { self.inner()?.data_unchecked() }[range];
+
+        PyBytes::new_with(py, length, |bytes| {
+            if length < GIL_RELEASE_THRESHOLD {
+                bytes.copy_from_slice(source);
+            } else {
+                py.allow_threads(|| bytes.copy_from_slice(source));
+            }
+
+            Ok(())
+        })
+    }
+
+    /// Writes the bytes of `buffer` into the memory, starting at
+    /// `offset`, in a single copy. `buffer` can be any object
+    /// implementing the buffer protocol, like `bytes`, `bytearray`
+    /// or `memoryview`. The GIL is released during large copies.
+    ///
+    /// ## Example
+    ///
+    /// ```py
+    /// from wasmer import Store, Memory, MemoryType
+    ///
+    /// memory = Memory(Store(), MemoryType(minimum=1))
+    /// memory.write(0, bytearray(b'abc'))
+    ///
+    /// assert bytes(memory.uint8_view()[0:3]) == b'abc'
+    /// ```
+    #[pyo3(text_signature = "($self, offset, buffer)")]
+    fn write(&self, py: Python, offset: u64, buffer: &PyAny) -> PyResult<()> {
+        self.write_buffer(py, offset, &PyBuffer::<u8>::get(buffer)?)
+    }
+
+    /// Reads a string of `length` bytes, starting at `offset`, and
+    /// decodes it with `encoding`. Without a `length`, the string
+    /// stops at the first NUL byte, like a C string.
+    ///
+    /// ## Example
+    ///
+    /// ```py
+    /// from wasmer import Store, Memory, MemoryType
+    ///
+    /// memory = Memory(Store(), MemoryType(minimum=1))
+    /// memory.write(0, b'Hello, World!\0')
+    ///
+    /// assert memory.read_string(0, 5) == 'Hello'
+    /// assert memory.read_string(7) == 'World!'
+    /// ```
+    #[pyo3(text_signature = "($self, offset, /, length=None, encoding=\"utf-8\")")]
+    #[args(length = "None", encoding = "\"utf-8\"")]
+    fn read_string<'p>(
+        &self,
+        py: Python<'p>,
+        offset: u64,
+        length: Option<usize>,
+        encoding: &str,
+    ) -> PyResult<&'p PyAny> {
+        let length = match length {
+            Some(length) => length,
+            None => {
+                let start = self.byte_range(offset, 0)?.start;
+                let data = &unsafe { self.inner()?.data_unchecked() }[start..];
+
+                data.iter().position(|byte| *byte == 0).ok_or_else(|| {
+                    to_py_err::<PyIndexError, _>(format!(
+                        "Out of bound: No NUL byte found after offset {}",
+                        offset
+                    ))
+                })?
+            }
+        };
+
+        self.read(py, offset, length)?
+            .call_method1("decode", (encoding,))
+    }
+
+    /// Encodes `string` with `encoding` and writes it into the
+    /// memory, starting at `offset`. No NUL byte is added. Returns
+    /// the number of written bytes.
+    ///
+    /// ## Example
+    ///
+    /// ```py
+    /// from wasmer import Store, Memory, MemoryType
+    ///
+    /// memory = Memory(Store(), MemoryType(minimum=1))
+    ///
+    /// assert memory.write_string(0, 'émoji 🎉') == 11
+    /// assert memory.read_string(0, 11) == 'émoji 🎉'
+    /// ```
+    #[pyo3(text_signature = "($self, offset, string, /, encoding=\"utf-8\")")]
+    #[args(encoding = "\"utf-8\"")]
+    fn write_string(
+        &self,
+        py: Python,
+        offset: u64,
+        string: &PyString,
+        encoding: &str,
+    ) -> PyResult<usize> {
+        let bytes = string.call_method1("encode", (encoding,))?;
+        let buffer = PyBuffer::<u8>::get(bytes)?;
+
+        self.write_buffer(py, offset, &buffer)?;
+
+        Ok(buffer.len_bytes())
+    }
+
+    /// Takes a snapshot of the whole memory data, to be given to
+    /// `Memory.restore` later.
+    ///
+    /// ## Example
+    ///
+    /// ```py
+    /// from wasmer import Store, Memory, MemoryType
+    ///
+    /// memory = Memory(Store(), MemoryType(minimum=1))
+    /// memory.write(0, b'abc')
+    ///
+    /// snapshot = memory.snapshot()
+    ///
+    /// assert len(snapshot) == memory.data_size
+    /// assert snapshot[0:3] == b'abc'
+    /// ```
+    #[pyo3(text_signature = "($self)")]
+    fn snapshot<'p>(&self, py: Python<'p>) -> PyResult<&'p PyBytes> {
+        self.read(py, 0, self.inner()?.data_size() as usize)
+    }
+
+    /// Restores the memory data from a snapshot taken by
+    /// `Memory.snapshot`.
+    ///
+    /// The memory grows if it is smaller than the snapshot. A memory
+    /// cannot shrink though: if it has grown since the snapshot, the
+    /// extra pages are kept but zeroed, like freshly grown pages.
+    ///
+    /// ## Example
+    ///
+    /// ```py
+    /// from wasmer import Store, Memory, MemoryType
+    ///
+    /// memory = Memory(Store(), MemoryType(minimum=1))
+    /// memory.write(0, b'abc')
+    ///
+    /// snapshot = memory.snapshot()
+    ///
+    /// memory.write(0, b'xyz')
+    /// memory.grow(1)
+    /// memory.write(65536, b'xyz')
+    ///
+    /// memory.restore(snapshot)
+    ///
+    /// assert memory.read(0, 3) == b'abc'
+    /// assert memory.read(65536, 3) == bytes(3)
+    /// ```
+    #[pyo3(text_signature = "($self, snapshot)")]
+    fn restore(&self, py: Python, snapshot: &PyAny) -> PyResult<()> {
+        let snapshot = PyBuffer::<u8>::get(snapshot)?;
+        let length = snapshot.len_bytes();
+
+        if length % wasmer::WASM_PAGE_SIZE != 0 {
+            return Err(to_py_err::<PyValueError, _>(format!(
+                "The snapshot size must be a multiple of the page size ({} bytes), got {} bytes",
+                wasmer::WASM_PAGE_SIZE,
+                length,
+            )));
+        }
+
+        let data_size = self.inner()?.data_size() as usize;
+
+        if length > data_size {
+            self.grow(((length - data_size) / wasmer::WASM_PAGE_SIZE) as u32)?;
+        }
+
+        self.write_buffer(py, 0, &snapshot)?;
+
+        let data_size = self.inner()?.data_size() as usize;
+        let rest = &mut unsafe { self.inner()?.data_unchecked_mut() }[length..data_size];
+
+        if rest.len() < GIL_RELEASE_THRESHOLD {
+            rest.fill(0);
+        } else {
+            py.allow_threads(|| rest.fill(0));
+        }
+
+        Ok(())
+    }
+
     /// Creates a Python buffer to read and write the memory data. See
     /// the `Buffer` class to learn more.
     ///
@@ -154,8 +633,8 @@ impl Memory {
     /// assert isinstance(memory.buffer, Buffer)
     /// ```
     #[getter]
-    fn buffer(&self) -> Buffer {
-        Buffer::new(self.inner.clone())
+    fn buffer(&self) -> PyResult<Buffer> {
+        Ok(Buffer::new(self.inner()?.clone()))
     }
 
     /// Creates a read-and-write view over the memory data where
@@ -175,11 +654,11 @@ impl Memory {
     /// ```
     #[pyo3(text_signature = "($self, /, offset=0)")]
     #[args(offset = 0)]
-    fn uint8_view(&self, offset: usize) -> Uint8Array {
-        Uint8Array {
-            memory: self.inner.clone(),
+    fn uint8_view(&self, offset: usize) -> PyResult<Uint8Array> {
+        Ok(Uint8Array {
+            memory: self.inner()?.clone(),
             offset,
-        }
+        })
     }
 
     /// Creates a read-and-write over the memory data where elements
@@ -187,11 +666,11 @@ impl Memory {
     /// and the `Memory.uint8_view` method to see an example.
     #[pyo3(text_signature = "($self, /, offset=0)")]
     #[args(offset = 0)]
-    fn int8_view(&self, offset: usize) -> Int8Array {
-        Int8Array {
-            memory: self.inner.clone(),
+    fn int8_view(&self, offset: usize) -> PyResult<Int8Array> {
+        Ok(Int8Array {
+            memory: self.inner()?.clone(),
             offset,
-        }
+        })
     }
 
     /// Creates a read-and-write over the memory data where elements
@@ -199,11 +678,11 @@ impl Memory {
     /// more, and the `Memory.uint8_view` method to see an example.
     #[pyo3(text_signature = "($self, /, offset=0)")]
     #[args(offset = 0)]
-    fn uint16_view(&self, offset: usize) -> Uint16Array {
-        Uint16Array {
-            memory: self.inner.clone(),
+    fn uint16_view(&self, offset: usize) -> PyResult<Uint16Array> {
+        Ok(Uint16Array {
+            memory: self.inner()?.clone(),
             offset,
-        }
+        })
     }
 
     /// Creates a read-and-write over the memory data where elements
@@ -211,11 +690,11 @@ impl Memory {
     /// and the `Memory.uint8_view` method to see an example.
     #[pyo3(text_signature = "($self, /, offset=0)")]
     #[args(offset = 0)]
-    fn int16_view(&self, offset: usize) -> Int16Array {
-        Int16Array {
-            memory: self.inner.clone(),
+    fn int16_view(&self, offset: usize) -> PyResult<Int16Array> {
+        Ok(Int16Array {
+            memory: self.inner()?.clone(),
             offset,
-        }
+        })
     }
 
     /// Creates a read-and-write over the memory data where elements
@@ -223,11 +702,11 @@ impl Memory {
     /// more, and the `Memory.uint8_view` method to see an example.
     #[pyo3(text_signature = "($self, /, offset=0)")]
     #[args(offset = 0)]
-    fn uint32_view(&self, offset: usize) -> Uint32Array {
-        Uint32Array {
-            memory: self.inner.clone(),
+    fn uint32_view(&self, offset: usize) -> PyResult<Uint32Array> {
+        Ok(Uint32Array {
+            memory: self.inner()?.clone(),
             offset,
-        }
+        })
     }
 
     /// Creates a read-and-write over the memory data where elements
@@ -235,11 +714,11 @@ impl Memory {
     /// and the `Memory.uint8_view` method to see an example.
     #[pyo3(text_signature = "($self, /, offset=0)")]
     #[args(offset = 0)]
-    fn int32_view(&self, offset: usize) -> Int32Array {
-        Int32Array {
-            memory: self.inner.clone(),
+    fn int32_view(&self, offset: usize) -> PyResult<Int32Array> {
+        Ok(Int32Array {
+            memory: self.inner()?.clone(),
             offset,
-        }
+        })
     }
 
     /// Creates a read-and-write over the memory data where elements
@@ -247,11 +726,11 @@ impl Memory {
     /// more, and the `Memory.uint8_view` method to see an example.
     #[pyo3(text_signature = "($self, /, offset=0)")]
     #[args(offset = 0)]
-    fn uint64_view(&self, offset: usize) -> Uint64Array {
-        Uint64Array {
-            memory: self.inner.clone(),
+    fn uint64_view(&self, offset: usize) -> PyResult<Uint64Array> {
+        Ok(Uint64Array {
+            memory: self.inner()?.clone(),
             offset,
-        }
+        })
     }
 
     /// Creates a read-and-write over the memory data where elements
@@ -259,11 +738,11 @@ impl Memory {
     /// and the `Memory.uint8_view` method to see an example.
     #[pyo3(text_signature = "($self, /, offset=0)")]
     #[args(offset = 0)]
-    fn int64_view(&self, offset: usize) -> Int64Array {
-        Int64Array {
-            memory: self.inner.clone(),
+    fn int64_view(&self, offset: usize) -> PyResult<Int64Array> {
+        Ok(Int64Array {
+            memory: self.inner()?.clone(),
             offset,
-        }
+        })
     }
 
     /// Creates a read-and-write over the memory data where elements
@@ -271,11 +750,11 @@ impl Memory {
     /// and the `Memory.uint8_view` method to see an example.
     #[pyo3(text_signature = "($self, /, offset=0)")]
     #[args(offset = 0)]
-    fn float32_view(&self, offset: usize) -> Float32Array {
-        Float32Array {
-            memory: self.inner.clone(),
+    fn float32_view(&self, offset: usize) -> PyResult<Float32Array> {
+        Ok(Float32Array {
+            memory: self.inner()?.clone(),
             offset,
-        }
+        })
     }
 
     /// Creates a read-and-write over the memory data where elements
@@ -283,19 +762,23 @@ impl Memory {
     /// and the `Memory.uint8_view` method to see an example.
     #[pyo3(text_signature = "($self, /, offset=0)")]
     #[args(offset = 0)]
-    fn float64_view(&self, offset: usize) -> Float64Array {
-        Float64Array {
-            memory: self.inner.clone(),
+    fn float64_view(&self, offset: usize) -> PyResult<Float64Array> {
+        Ok(Float64Array {
+            memory: self.inner()?.clone(),
             offset,
-        }
+        })
     }
 
     /// Gets the memory type, of kind `MemoryType`.
     ///
+    /// The `minimum` follows the current size of the memory, in
+    /// pages, and the `maximum` bounds its growth: a memory without a
+    /// `maximum` can grow up to 65536 pages, i.e. 4GiB.
+    ///
     /// ## Example
     ///
     /// ```py
-    /// from wasmer import Store, Memory, Module, Instance
+    /// from wasmer import Store, Memory, MemoryType, Module, Instance
     ///
     /// module = Module(Store(), open('tests/tests.wasm', 'rb').read())
     /// instance = Instance(module)
@@ -305,9 +788,66 @@ impl Memory {
     /// assert memory_type.minimum == 17
     /// assert memory_type.maximum == None
     /// assert memory_type.shared == False
+    ///
+    /// # Check whether the memory can grow before growing it.
+    /// memory = Memory(Store(), MemoryType(minimum=1, maximum=3))
+    /// memory.grow(1)
+    ///
+    /// assert memory.type.minimum == 2
+    /// assert memory.type.maximum - memory.size == 1
     /// ```
     #[getter(type)]
-    fn ty(&self) -> MemoryType {
-        self.inner.ty().into()
+    fn ty(&self) -> PyResult<MemoryType> {
+        Ok(self.inner()?.ty().into())
+    }
+
+    /// Closes the memory: it releases its reference to the memory
+    /// data, and using it afterwards raises a `ValueError`. The data
+    /// is freed once nothing refers to it anymore, e.g. the instance
+    /// exporting the memory, or the views and the buffers created
+    /// from it. Closing a closed memory does nothing.
+    ///
+    /// A memory is also a context manager, closed at the exit of the
+    /// `with` block.
+    ///
+    /// ## Example
+    ///
+    /// ```py
+    /// from wasmer import Store, Memory, MemoryType
+    ///
+    /// with Memory(Store(), MemoryType(minimum=100)) as memory:
+    ///     memory.uint8_view()[0] = 42
+    ///
+    /// try:
+    ///     memory.size
+    /// except ValueError:
+    ///     pass
+    /// else:
+    ///     assert False
+    /// ```
+    #[pyo3(text_signature = "($self)")]
+    fn close(&mut self) {
+        self.inner = None;
+    }
+
+    fn __enter__(slf: PyRef<Self>) -> PyRef<Self> {
+        slf
+    }
+
+    fn __exit__(&mut self, _exc_type: &PyAny, _exc_value: &PyAny, _traceback: &PyAny) -> bool {
+        self.close();
+
+        false
+    }
+}
+
+#[pyproto]
+impl PyBufferProtocol for Memory {
+    fn bf_getbuffer(slf: PyRefMut<Self>, view: *mut Py_buffer, flags: c_int) -> PyResult<()> {
+        fill_buffer(slf.inner()?, slf.as_ptr(), view, flags)
+    }
+
+    fn bf_releasebuffer(_slf: PyRefMut<Self>, _view: *mut Py_buffer) -> PyResult<()> {
+        Ok(())
     }
 }
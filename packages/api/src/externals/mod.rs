@@ -3,7 +3,9 @@ mod global;
 mod memory;
 mod table;
 
-pub use function::Function;
+pub(crate) use function::{check_storable, take_environment_error};
+pub use function::{Function, TypedFunction};
 pub use global::Global;
 pub use memory::Memory;
+pub(crate) use memory::{forget_grow_callbacks, notify_growth};
 pub use table::Table;
@@ -1,7 +1,14 @@
 use crate::{
-    errors::to_py_err, store::Store, types::TableType, values::Value, wasmer_inner::wasmer,
+    errors::to_py_err,
+    store::Store,
+    types::TableType,
+    values::{to_py_object, to_wasm_value},
+    wasmer_inner::wasmer,
+};
+use pyo3::{
+    exceptions::{PyIndexError, PyRuntimeError},
+    prelude::*,
 };
-use pyo3::{exceptions::PyRuntimeError, prelude::*};
 
 /// A WebAssembly table instance.
 ///
@@ -12,8 +19,22 @@ use pyo3::{exceptions::PyRuntimeError, prelude::*};
 /// accessible and mutable from both host and WebAssembly.
 ///
 /// Specification: https://webassembly.github.io/spec/core/exec/runtime.html#table-instances
+///
+/// ## Example
+///
+/// Creates a `Table` from scratch, filled with `None`, i.e. null
+/// references:
+///
+/// ```py
+/// from wasmer import Store, Table, TableType, Type
+///
+/// table = Table(Store(), TableType(Type.FUNC_REF, minimum=3))
+///
+/// assert table.size == 3
+/// assert table.get(0) == None
+/// ```
 #[pyclass(unsendable)]
-#[pyo3(text_signature = "(store, table_type, initial_value)")]
+#[pyo3(text_signature = "(store, table_type, /, initial_value=None)")]
 pub struct Table {
     inner: wasmer::Table,
 }
@@ -26,19 +47,49 @@ impl Table {
     pub(crate) fn inner(&self) -> &wasmer::Table {
         &self.inner
     }
+
+    /// Convert a Python value to an element of this table.
+    fn to_element(&self, value: &PyAny) -> PyResult<wasmer::Value> {
+        to_wasm_value(self.inner.store(), (value, self.inner.ty().ty))
+    }
+
+    fn check_index(&self, index: u32) -> PyResult<()> {
+        let size = self.inner.size();
+
+        if index >= size {
+            return Err(to_py_err::<PyIndexError, _>(format!(
+                "Out of bound: The index {} is outside the table of {} elements",
+                index, size,
+            )));
+        }
+
+        Ok(())
+    }
 }
 
 #[pymethods]
 impl Table {
     #[new]
-    fn new(store: &Store, table_type: &TableType, initial_value: &Value) -> PyResult<Self> {
+    #[args(initial_value = "None")]
+    fn new(
+        py: Python,
+        store: &Store,
+        table_type: &TableType,
+        initial_value: Option<&PyAny>,
+    ) -> PyResult<Self> {
+        let table_type: wasmer::TableType = table_type.into();
+        let store = store.inner()?;
+        let initial_value = to_wasm_value(
+            store,
+            (
+                initial_value.unwrap_or_else(|| py.None().into_ref(py)),
+                table_type.ty,
+            ),
+        )?;
+
         Ok(Self {
-            inner: wasmer::Table::new(
-                store.inner(),
-                table_type.into(),
-                initial_value.inner().clone(),
-            )
-            .map_err(to_py_err::<PyRuntimeError, _>)?,
+            inner: wasmer::Table::new(store, table_type, initial_value)
+                .map_err(to_py_err::<PyRuntimeError, _>)?,
         })
     }
 
@@ -80,4 +131,108 @@ impl Table {
     fn ty(&self) -> TableType {
         self.inner.ty().into()
     }
+
+    /// Gets the element at `index`: a `Function`, or `None` for a
+    /// null reference.
+    ///
+    /// The function can be stored in a table again, or passed to
+    /// WebAssembly as a `funcref`, but it cannot be called from the
+    /// host.
+    ///
+    /// ## Example
+    ///
+    /// ```py
+    /// from wasmer import Store, Module, Instance, Function
+    ///
+    /// module = Module(
+    ///     Store(),
+    ///     """
+    ///     (module
+    ///       (table (export "table") 2 funcref)
+    ///       (elem (i32.const 0) $answer)
+    ///       (func $answer (result i32)
+    ///         i32.const 42))
+    ///     """
+    /// )
+    /// table = Instance(module).exports.table
+    ///
+    /// assert isinstance(table.get(0), Function)
+    /// assert table.get(1) == None
+    /// ```
+    #[pyo3(text_signature = "($self, index)")]
+    fn get(&self, py: Python, index: u32) -> PyResult<PyObject> {
+        self.check_index(index)?;
+
+        let element = self
+            .inner
+            .get(index)
+            .ok_or_else(|| to_py_err::<PyRuntimeError, _>("Failed to read the table element"))?;
+
+        Ok(to_py_object(py)(&element))
+    }
+
+    /// Sets the element at `index` to `value`: a `Function`, or
+    /// `None` for a null reference. WebAssembly code calling via
+    /// `call_indirect` at this index then calls the new function.
+    ///
+    /// The function must come from a module, e.g. an exported
+    /// function, or a function read from a table. A host function
+    /// must first be imported by a module and put in a table by an
+    /// element segment.
+    ///
+    /// ## Example
+    ///
+    /// ```py
+    /// from wasmer import Store, Module, Instance
+    ///
+    /// module = Module(
+    ///     Store(),
+    ///     """
+    ///     (module
+    ///       (type $i32 (func (result i32)))
+    ///       (table (export "table") 1 funcref)
+    ///       (func (export "answer") (result i32)
+    ///         i32.const 42)
+    ///       (func (export "dispatch") (result i32)
+    ///         i32.const 0
+    ///         call_indirect (type $i32)))
+    ///     """
+    /// )
+    /// instance = Instance(module)
+    /// instance.exports.table.set(0, instance.exports.answer)
+    ///
+    /// assert instance.exports.dispatch() == 42
+    /// ```
+    #[pyo3(text_signature = "($self, index, value)")]
+    fn set(&self, index: u32, value: &PyAny) -> PyResult<()> {
+        self.check_index(index)?;
+
+        self.inner
+            .set(index, self.to_element(value)?)
+            .map_err(to_py_err::<PyRuntimeError, _>)
+    }
+
+    /// Grows the table by `delta` elements, set to `initial_value`,
+    /// `None` by default. Returns the previous size.
+    ///
+    /// ## Example
+    ///
+    /// ```py
+    /// from wasmer import Store, Table, TableType, Type
+    ///
+    /// table = Table(Store(), TableType(Type.FUNC_REF, minimum=1))
+    ///
+    /// assert table.grow(2) == 1
+    /// assert table.size == 3
+    /// ```
+    #[pyo3(text_signature = "($self, delta, /, initial_value=None)")]
+    #[args(initial_value = "None")]
+    fn grow(&self, py: Python, delta: u32, initial_value: Option<&PyAny>) -> PyResult<u32> {
+        let initial_value =
+            self.to_element(initial_value.unwrap_or_else(|| py.None().into_ref(py)))?;
+
+        self.inner
+            .grow(delta, initial_value)
+            .map_err(to_py_err::<PyRuntimeError, _>)
+    }
 }
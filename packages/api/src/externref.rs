@@ -0,0 +1,90 @@
+use crate::{store, wasmer_inner::wasmer};
+use pyo3::{prelude::*, AsPyPointer};
+use std::{
+    collections::BTreeMap,
+    mem,
+    sync::{Mutex, MutexGuard},
+};
+
+/// The data of an `externref` built from a Python object.
+///
+/// Wasmer doesn't count the references held by WebAssembly reliably,
+/// e.g. for the arguments and the results of the calls, so the
+/// `externref`s given to a store are owned by the store instead, see
+/// `to_store`: their object is released with the store, whatever their
+/// count.
+struct ExternObject(Mutex<Option<PyObject>>);
+
+/// An `externref` owned by a store. `wasmer::ExternRef` isn't `Send`,
+/// but its count is atomic, and its data, an `ExternObject`, is `Send`
+/// and `Sync`.
+struct Owned(wasmer::ExternRef);
+
+unsafe impl Send for Owned {}
+
+/// The `externref`s owned by the stores, by the address of the
+/// tunables of the store, see `store::SETTINGS`, then by the address
+/// of their object, which they keep alive.
+static OWNED: Mutex<BTreeMap<usize, BTreeMap<usize, Owned>>> = Mutex::new(BTreeMap::new());
+
+fn lock_owned() -> MutexGuard<'static, BTreeMap<usize, BTreeMap<usize, Owned>>> {
+    // The references are consistent even if a thread has panicked.
+    OWNED.lock().unwrap_or_else(|error| error.into_inner())
+}
+
+/// Builds an `externref` holding `object`, owned by no store yet: it
+/// is released with its last reference, e.g. a `Value`.
+pub(crate) fn new(object: PyObject) -> wasmer::ExternRef {
+    wasmer::ExternRef::new(ExternObject(Mutex::new(Some(object))))
+}
+
+/// Gives `value` to `store`: an `externref` is replaced by the one
+/// owned by `store` holding the same object, created the first time
+/// the object is given to the store.
+pub(crate) fn to_store(py: Python, store: &wasmer::Store, value: wasmer::Value) -> wasmer::Value {
+    let extern_ref = match &value {
+        wasmer::Value::ExternRef(extern_ref) if !extern_ref.is_null() => extern_ref,
+        _ => return value,
+    };
+    let object = match to_object(py, extern_ref) {
+        Some(object) => object,
+        None => return wasmer::Value::null(),
+    };
+
+    let address = object.as_ptr() as usize;
+    let mut owned = lock_owned();
+    let extern_ref = &owned
+        .entry(store::tunables_address(store.tunables()))
+        .or_default()
+        .entry(address)
+        .or_insert_with(|| Owned(new(object)))
+        .0;
+
+    wasmer::Value::ExternRef(extern_ref.clone())
+}
+
+/// Gets the object of `extern_ref`, or `None` if it is null, or if its
+/// store has been dropped.
+pub(crate) fn to_object(py: Python, extern_ref: &wasmer::ExternRef) -> Option<PyObject> {
+    let object = extern_ref.downcast::<ExternObject>()?;
+    let object = object.0.lock().unwrap_or_else(|error| error.into_inner());
+
+    object.as_ref().map(|object| object.clone_ref(py))
+}
+
+/// Releases the objects owned by the store of `tunables`, when they
+/// are dropped with the last clone of the store.
+pub(crate) fn release(tunables: &dyn wasmer::Tunables) {
+    let owned = lock_owned().remove(&store::tunables_address(tunables));
+
+    for Owned(extern_ref) in owned.into_iter().flat_map(BTreeMap::into_values) {
+        if let Some(object) = extern_ref.downcast::<ExternObject>() {
+            // The object is dropped out of its lock, since releasing
+            // it may run Python code.
+            let object =
+                mem::take(&mut *object.0.lock().unwrap_or_else(|error| error.into_inner()));
+
+            drop(object);
+        }
+    }
+}
@@ -4,9 +4,14 @@ use crate::{
     wasmer_inner::wasmer,
 };
 use pyo3::{
-    exceptions::PyTypeError,
+    class::{
+        basic::PyObjectProtocol, iter::PyIterProtocol, number::PyNumberProtocol,
+        sequence::PySequenceProtocol,
+    },
+    exceptions::{PyLookupError, PyRuntimeError, PyTypeError},
     prelude::*,
-    types::{PyDict, PyString},
+    types::{PyDict, PyIterator, PyList, PyString},
+    PyNativeType,
 };
 
 /// An `ImportObject` represents all of the import data used when
@@ -147,6 +152,34 @@ use pyo3::{
 /// ```
 ///
 /// etc.
+///
+/// Import objects can be combined, with `extend` or the `|`
+/// operator, and inspected: iterating over an import object yields
+/// its namespace names, and `names` lists the names registered in a
+/// namespace.
+///
+/// ```py
+/// from wasmer import Store, ImportObject, Function
+///
+/// store = Store()
+///
+/// def log(x: int):
+///     pass
+///
+/// def sum(x: int, y: int) -> int:
+///     return x + y
+///
+/// logging = ImportObject()
+/// logging.register("env", {"log": Function(store, log)})
+///
+/// math = ImportObject()
+/// math.register("math", {"sum": Function(store, sum)})
+///
+/// import_object = logging | math
+///
+/// assert list(import_object) == ["env", "math"]
+/// assert import_object.names("math") == ["sum"]
+/// ```
 #[pyclass(unsendable)]
 #[pyo3(text_signature = "()")]
 pub struct ImportObject {
@@ -164,16 +197,149 @@ impl ImportObject {
 }
 
 impl ImportObject {
-    /// Gets an `ImportObject` from a Python dictionary.
-    pub(crate) fn from_pydict(dict: &PyDict) -> Result<Self, PyErr> {
+    /// Gets an `ImportObject` from a Python dictionary, to
+    /// instantiate `module`.
+    ///
+    /// Plain Python callables are wrapped as host functions, with the
+    /// function type expected by the module's import.
+    pub(crate) fn from_pydict(dict: &PyDict, module: &wasmer::Module) -> Result<Self, PyErr> {
         let mut io = ImportObject::new();
         for (namespace_name, namespace_dict) in dict.into_iter() {
             let namespace_name = namespace_name.to_string();
             let namespace_dict = namespace_dict.downcast::<PyDict>()?;
-            io.register(&namespace_name, namespace_dict)?;
+            io.register_for_module(&namespace_name, namespace_dict, Some(module))?;
         }
         Ok(io)
     }
+
+    fn register_for_module(
+        &mut self,
+        namespace_name: &str,
+        namespace: &PyDict,
+        module: Option<&wasmer::Module>,
+    ) -> PyResult<()> {
+        let mut wasmer_namespace = wasmer::Exports::new();
+
+        for (name, item) in namespace.into_iter() {
+            let name = String::from(name.downcast::<PyString>().map_err(PyErr::from)?.to_str()?);
+
+            if let Some(export) = to_extern(item)? {
+                wasmer_namespace.insert(name, export);
+            } else if let (Some(module), true) = (module, item.is_callable()) {
+                let function_type = module.imports().functions().find_map(|import| {
+                    if import.module() == namespace_name && import.name() == name {
+                        Some(import.ty().clone())
+                    } else {
+                        None
+                    }
+                });
+
+                let function =
+                    Function::from_callable(item.py(), module.store(), item, function_type, None)?;
+
+                wasmer_namespace.insert(name, function.inner().clone());
+            } else {
+                return Err(to_py_err::<PyTypeError, _>(format!(
+                    "`ImportObject` cannot register the given type `{:?}`",
+                    item.get_type().name()
+                )));
+            }
+        }
+
+        self.inner.register(namespace_name, wasmer_namespace);
+
+        Ok(())
+    }
+
+    /// Adds all the externs of `other` to this import object. An
+    /// extern already registered under the same namespace and name
+    /// is replaced.
+    ///
+    /// The externs are copied one by one: `wasmer::ImportObject`
+    /// shares its namespaces between its clones.
+    pub(crate) fn merge(&mut self, other: &wasmer::ImportObject) {
+        for (namespace_name, name, export) in other.externs_vec() {
+            self.insert(namespace_name, name, export);
+        }
+    }
+
+    pub(crate) fn insert(&mut self, namespace_name: String, name: String, export: wasmer::Extern) {
+        let mut namespace = self
+            .inner
+            .get_namespace_exports(&namespace_name)
+            .unwrap_or_default();
+
+        namespace.insert(name, export);
+        self.inner.register(namespace_name, namespace);
+    }
+
+    /// Gets a copy of this import object, where the function imports
+    /// of `module` not satisfied by this import object are filled by
+    /// stubs, which trap with the name of the import when called.
+    pub(crate) fn with_missing_imports_stubbed(&self, module: &wasmer::Module) -> Self {
+        let mut import_object = ImportObject::new();
+        import_object.merge(&self.inner);
+
+        for import in module.imports().functions() {
+            if self
+                .inner
+                .get_export(import.module(), import.name())
+                .is_some()
+            {
+                continue;
+            }
+
+            let message = format!(
+                "Missing import `{}.{}` has been called",
+                import.module(),
+                import.name()
+            );
+            let stub = wasmer::Function::new(module.store(), import.ty(), move |_| {
+                let error = to_py_err::<PyRuntimeError, _>(&message);
+
+                Err(wasmer::RuntimeError::user(Box::new(error)))
+            });
+
+            import_object.insert(
+                import.module().to_string(),
+                import.name().to_string(),
+                stub.into(),
+            );
+        }
+
+        import_object
+    }
+
+    /// Gets the sorted namespace names.
+    fn namespace_names(&self) -> Vec<String> {
+        let mut namespace_names = self
+            .inner
+            .externs_vec()
+            .into_iter()
+            .map(|(namespace_name, _, _)| namespace_name)
+            .collect::<Vec<_>>();
+
+        namespace_names.sort();
+        namespace_names.dedup();
+
+        namespace_names
+    }
+}
+
+/// Gets the extern held by a `Function`, `Memory`, `Global` or
+/// `Table`. A closed `Memory` raises a `ValueError`.
+pub(crate) fn to_extern(item: &PyAny) -> PyResult<Option<wasmer::Extern>> {
+    Ok(if let Ok(function) = item.downcast::<PyCell<Function>>() {
+        Some(function.borrow().inner().clone().into())
+    } else if let Ok(memory) = item.downcast::<PyCell<Memory>>() {
+        Some(memory.borrow().inner()?.clone().into())
+    } else if let Ok(global) = item.downcast::<PyCell<Global>>() {
+        Some(global.borrow().inner().clone().into())
+    } else if let Ok(table) = item.downcast::<PyCell<Table>>() {
+        Some(table.borrow().inner().clone().into())
+    } else {
+        None
+    })
 }
 
 #[pymethods]
@@ -258,37 +424,136 @@ impl ImportObject {
     /// ```
     #[pyo3(text_signature = "($self, namespace_name, namespace)")]
     pub(crate) fn register(&mut self, namespace_name: &str, namespace: &PyDict) -> PyResult<()> {
-        let mut wasmer_namespace = wasmer::Exports::new();
+        self.register_for_module(namespace_name, namespace, None)
+    }
 
-        for (name, item) in namespace.into_iter() {
-            let name = String::from(name.downcast::<PyString>().map_err(PyErr::from)?.to_str()?);
+    /// Adds all the externs of `other`, an `ImportObject` or a
+    /// dictionary as given to `register`, to this import object.
+    /// Namespaces are merged: an extern already registered under
+    /// the same namespace and name is replaced.
+    ///
+    /// ## Example
+    ///
+    /// ```py
+    /// from wasmer import Store, ImportObject, Function
+    ///
+    /// store = Store()
+    ///
+    /// def log(x: int):
+    ///     pass
+    ///
+    /// def sum(x: int, y: int) -> int:
+    ///     return x + y
+    ///
+    /// import_object = ImportObject()
+    /// import_object.register("env", {"log": Function(store, log)})
+    /// import_object.extend({"env": {"sum": Function(store, sum)}})
+    ///
+    /// assert import_object.names("env") == ["log", "sum"]
+    /// ```
+    #[pyo3(text_signature = "($self, other)")]
+    fn extend(&mut self, other: &PyAny) -> PyResult<()> {
+        if let Ok(other) = other.downcast::<PyCell<ImportObject>>() {
+            let other = other.try_borrow()?.inner.clone();
+            self.merge(&other);
+        } else if let Ok(other) = other.downcast::<PyDict>() {
+            let mut import_object = ImportObject::new();
 
-            if let Ok(function) = item.downcast::<PyCell<Function>>() {
-                let function = function.borrow();
+            for (namespace_name, namespace) in other.into_iter() {
+                let namespace_name = namespace_name.downcast::<PyString>()?.to_str()?;
+                import_object.register(namespace_name, namespace.downcast::<PyDict>()?)?;
+            }
 
-                wasmer_namespace.insert(name, function.inner().clone());
-            } else if let Ok(memory) = item.downcast::<PyCell<Memory>>() {
-                let memory = memory.borrow();
+            self.merge(&import_object.inner);
+        } else {
+            return Err(to_py_err::<PyTypeError, _>(format!(
+                "`ImportObject` cannot be extended with the given type `{}`",
+                other.get_type().name()?
+            )));
+        }
 
-                wasmer_namespace.insert(name, memory.inner().clone());
-            } else if let Ok(global) = item.downcast::<PyCell<Global>>() {
-                let global = global.borrow();
+        Ok(())
+    }
 
-                wasmer_namespace.insert(name, global.inner().clone());
-            } else if let Ok(table) = item.downcast::<PyCell<Table>>() {
-                let table = table.borrow();
+    /// Gets the sorted list of the names registered in a namespace.
+    ///
+    /// ## Example
+    ///
+    /// ```py
+    /// from wasmer import Store, ImportObject, Memory, MemoryType
+    ///
+    /// store = Store()
+    ///
+    /// import_object = ImportObject()
+    /// import_object.register("env", {"memory": Memory(store, MemoryType(minimum=1))})
+    ///
+    /// assert import_object.names("env") == ["memory"]
+    /// ```
+    #[pyo3(text_signature = "($self, namespace_name)")]
+    fn names(&self, namespace_name: &str) -> PyResult<Vec<String>> {
+        let namespace = self
+            .inner
+            .get_namespace_exports(namespace_name)
+            .ok_or_else(|| {
+                to_py_err::<PyLookupError, _>(format!(
+                    "Namespace `{}` does not exist.",
+                    namespace_name
+                ))
+            })?;
 
-                wasmer_namespace.insert(name, table.inner().clone());
-            } else {
-                return Err(to_py_err::<PyTypeError, _>(format!(
-                    "`ImportObject` cannot register the given type `{:?}`",
-                    item.get_type().name()
-                )));
-            }
-        }
+        let mut names = namespace
+            .iter()
+            .map(|(name, _)| name.clone())
+            .collect::<Vec<_>>();
+        names.sort();
 
-        self.inner.register(namespace_name, wasmer_namespace);
+        Ok(names)
+    }
+}
 
-        Ok(())
+#[pyproto]
+impl PyNumberProtocol for ImportObject {
+    fn __or__(lhs: PyRef<ImportObject>, rhs: PyRef<ImportObject>) -> ImportObject {
+        let mut import_object = ImportObject::new();
+        import_object.merge(&lhs.inner);
+        import_object.merge(&rhs.inner);
+
+        import_object
+    }
+}
+
+#[pyproto]
+impl PySequenceProtocol for ImportObject {
+    fn __len__(&self) -> usize {
+        self.namespace_names().len()
+    }
+
+    fn __contains__(&self, namespace_name: &str) -> bool {
+        self.inner.contains_namespace(namespace_name)
+    }
+}
+
+#[pyproto]
+impl PyIterProtocol for ImportObject {
+    fn __iter__(slf: PyRef<Self>) -> PyResult<PyObject> {
+        let py = slf.py();
+        let namespace_names = PyList::new(py, slf.namespace_names());
+
+        Ok(PyIterator::from_object(py, namespace_names)?.to_object(py))
+    }
+}
+
+#[pyproto]
+impl PyObjectProtocol for ImportObject {
+    fn __repr__(&self) -> PyResult<String> {
+        let gil_guard = Python::acquire_gil();
+        let py = gil_guard.python();
+
+        let namespaces = PyDict::new(py);
+        for namespace_name in self.namespace_names() {
+            namespaces.set_item(&namespace_name, self.names(&namespace_name)?)?;
+        }
+
+        Ok(format!("ImportObject({})", namespaces.repr()?))
     }
 }
@@ -1,11 +1,19 @@
-use crate::errors::runtime_error_to_py_err;
+use crate::errors::{runtime_error_to_py_err, to_py_err, InstantiationError, LinkError};
 use crate::{
-    errors::to_py_err, exports::Exports, import_object::ImportObject, module::Module,
+    exports::Exports,
+    externals,
+    import_object::ImportObject,
+    middlewares,
+    module::Module,
+    snapshot,
+    statistics::{self, Statistics},
+    store,
     wasmer_inner::wasmer,
 };
-use pyo3::types::PyDict;
-use pyo3::{exceptions::PyRuntimeError, prelude::*};
-use std::borrow::Borrow;
+use pyo3::exceptions::{PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyDict};
+use std::{cell::Cell, rc::Rc, sync::Arc};
 
 /// A WebAssembly instance is a stateful, executable instance of a
 /// WebAssembly `Module`.
@@ -81,11 +89,107 @@ use std::borrow::Borrow;
 /// # Let's test it!
 /// assert instance.exports.add_one(41) == 42
 /// ```
+///
+/// The import object can also be a nested dictionary. A plain Python
+/// callable is wrapped as a `Function` whose type is the one expected
+/// by the module.
+///
+/// ```py
+/// from wasmer import Store, Module, Instance
+///
+/// module = Module(
+///     Store(),
+///     """
+///     (module
+///       (import "math" "sum" (func $sum (param i32 i32) (result i32)))
+///       (func (export "add_one") (param i32) (result i32)
+///         local.get 0
+///         i32.const 1
+///         call $sum))
+///     """
+/// )
+/// instance = Instance(module, {"math": {"sum": lambda x, y: x + y}})
+///
+/// assert instance.exports.add_one(41) == 42
+/// ```
+///
+/// Instantiating a compiled module is much cheaper than compiling it:
+/// the memories are reserved lazily, and their pages are allocated
+/// when first touched. Compiling is what is expensive,
+/// so a fresh instance per request, e.g. as a sandbox, should come
+/// from a `Module` compiled once, or loaded from a `Cache`.
+///
+/// With `allow_missing_imports=True`, the function imports missing
+/// from the import object are filled with stubs, which raise a
+/// `RuntimeError` naming the import when called. It helps to load a
+/// module where only some imports are supported, and to discover
+/// which ones are actually called.
+///
+/// ```py
+/// from wasmer import Store, Module, Instance
+///
+/// module = Module(
+///     Store(),
+///     """
+///     (module
+///       (import "env" "unsupported" (func $unsupported))
+///       (func (export "answer") (result i32)
+///         i32.const 42)
+///       (func (export "call_unsupported")
+///         call $unsupported))
+///     """
+/// )
+/// instance = Instance(module, allow_missing_imports=True)
+///
+/// assert instance.exports.answer() == 42
+///
+/// try:
+///     instance.exports.call_unsupported()
+/// except RuntimeError as error:
+///     assert str(error) == "Missing import `env.unsupported` has been called"
+/// else:
+///     assert False
+/// ```
+///
+/// With `run_start=False`, the start function of the module doesn't
+/// run when the instance is created, but when `run_start` is called,
+/// see the `DeferredStart` middleware.
+///
+/// For a module compiled with an `Interruptible` middleware, the
+/// start function is bounded in time by `timeout`, in seconds, like
+/// an exported function: an `ExecutionTimeout` is raised when it is
+/// exceeded, e.g. by an infinite loop.
+///
+/// ```py
+/// from wasmer import engine, Store, Module, Instance, Interruptible, ExecutionTimeout
+/// from wasmer_compiler_cranelift import Compiler
+///
+/// store = Store(engine.Universal(Compiler(middlewares=[Interruptible()])))
+/// module = Module(
+///     store,
+///     """
+///     (module
+///       (func $start
+///         (loop
+///           br 0))
+///       (start $start))
+///     """
+/// )
+///
+/// try:
+///     Instance(module, timeout=0.01)
+/// except ExecutionTimeout:
+///     pass
+/// else:
+///     assert False
+/// ```
 #[pyclass(unsendable)]
-#[pyo3(text_signature = "(module, import_object)")]
+#[pyo3(
+    text_signature = "(module, import_object, /, *, allow_missing_imports=False, run_start=True, timeout=None)"
+)]
 pub struct Instance {
-    #[allow(unused)]
-    inner: wasmer::Instance,
+    /// The instance, until it is closed.
+    inner: Option<wasmer::Instance>,
 
     /// The exports of the instance, as an object of kind `Exports`.
     ///
@@ -94,62 +198,927 @@ pub struct Instance {
     /// See the `Exports` class.
     #[pyo3(get)]
     exports: Py<Exports>,
+
+    /// The imports of the instance, to clone it, until it is closed.
+    imports: Option<Rc<ResolvedImports>>,
+
+    /// The statistics of the exported functions, if the store
+    /// collects them.
+    statistics: Option<Arc<Statistics>>,
+
+    /// The start function deferred by the `DeferredStart`
+    /// middleware, until it runs.
+    pending_start: Cell<Option<wasmer::Function>>,
+
+    /// The hook of the `Tracing` middleware, set by `set_trace`,
+    /// which the table of the instance doesn't own.
+    tracing_hook: Cell<Option<wasmer::Function>>,
 }
 
 pub enum InstanceError {
-    InstantiationError(wasmer::InstantiationError),
+    InstantiationError(Box<wasmer::InstantiationError>),
     PyErr(PyErr),
 }
 
+impl InstanceError {
+    pub(crate) fn into_py_err(self, module: &wasmer::Module) -> PyErr {
+        match self {
+            InstanceError::InstantiationError(error) => {
+                if let wasmer::InstantiationError::Start(error) = *error {
+                    runtime_error_to_py_err(error)
+                } else if let Some(error) = externals::take_environment_error() {
+                    error
+                } else {
+                    // Named modules are mentioned in the error, to
+                    // ease debugging applications with many modules.
+                    let message = match module.name() {
+                        Some(name) => format!("Failed to instantiate module `{}`: {}", name, error),
+                        None => error.to_string(),
+                    };
+
+                    match *error {
+                        wasmer::InstantiationError::Link(_) => LinkError::new_err(message),
+                        _ => InstantiationError::new_err(message),
+                    }
+                }
+            }
+            InstanceError::PyErr(error) => error,
+        }
+    }
+}
+
 impl Instance {
+    pub(crate) fn inner(&self) -> PyResult<&wasmer::Instance> {
+        self.inner.as_ref().ok_or_else(closed_error)
+    }
+
+    /// Keeps the hook of the `Tracing` middleware alive while it is
+    /// in the table of the instance.
+    pub(crate) fn set_tracing_hook(&self, hook: Option<wasmer::Function>) {
+        self.tracing_hook.set(hook);
+    }
+
+    fn imports(&self) -> PyResult<&Rc<ResolvedImports>> {
+        self.imports.as_ref().ok_or_else(closed_error)
+    }
+
     pub fn raw_new(
         py: Python,
         module: &Module,
         import_object: Option<&PyAny>,
+        allow_missing_imports: bool,
+        run_start: bool,
+        timeout: Option<f64>,
     ) -> Result<Self, InstanceError> {
         let module = module.inner();
+        let import_object =
+            to_import_object(module, import_object).map_err(InstanceError::PyErr)?;
 
-        let instance = match import_object {
-            Some(import_object) => match import_object.downcast::<PyCell<ImportObject>>() {
-                Ok(io) => wasmer::Instance::new(&module, io.borrow().inner()),
-                Err(_e) => match import_object.downcast::<PyDict>() {
-                    Ok(dict) => {
-                        let io = ImportObject::from_pydict(dict)
-                            .map_err(|e| InstanceError::PyErr(e.into()))?;
-                        wasmer::Instance::new(&module, io.borrow().inner())
-                    }
-                    Err(e) => {
-                        return Err(InstanceError::PyErr(e.into()));
-                    }
-                },
-            },
-            None => wasmer::Instance::new(&module, &wasmer::imports! {}),
+        Instance::from_import_object(
+            py,
+            module,
+            &import_object,
+            allow_missing_imports,
+            run_start,
+            timeout,
+        )
+    }
+
+    pub(crate) fn from_import_object(
+        py: Python,
+        module: &wasmer::Module,
+        import_object: &ImportObject,
+        allow_missing_imports: bool,
+        run_start: bool,
+        timeout: Option<f64>,
+    ) -> Result<Self, InstanceError> {
+        check_stores(module, import_object).map_err(InstanceError::PyErr)?;
+
+        let imports = if allow_missing_imports {
+            ResolvedImports::new(module, import_object.with_missing_imports_stubbed(module))
+        } else {
+            ResolvedImports::new(module, ImportObject::raw_new(import_object.inner().clone()))
         };
-        let instance = instance.map_err(InstanceError::InstantiationError)?;
 
-        let exports =
-            Py::new(py, Exports::new(instance.exports.clone())).map_err(InstanceError::PyErr)?;
+        Instance::from_resolved_imports(py, module, Rc::new(imports), run_start, timeout)
+    }
 
-        Ok(Instance {
-            inner: instance,
+    fn from_resolved_imports(
+        py: Python,
+        module: &wasmer::Module,
+        imports: Rc<ResolvedImports>,
+        run_start: bool,
+        timeout: Option<f64>,
+    ) -> Result<Self, InstanceError> {
+        if !run_start && module.info().start_function.is_some() {
+            return Err(InstanceError::PyErr(to_py_err::<PyValueError, _>(
+                "The start function of the module can't be deferred, \
+                 the module must be compiled with a `DeferredStart` middleware",
+            )));
+        }
+
+        if timeout.is_some()
+            && module
+                .exports()
+                .all(|export| export.name() != middlewares::INTERRUPTED_GLOBAL)
+        {
+            return Err(InstanceError::PyErr(to_py_err::<PyValueError, _>(
+                "The instantiation can't time out, \
+                 the module must be compiled with an `Interruptible` middleware",
+            )));
+        }
+
+        let instance = wasmer::Instance::new(module, imports.as_ref())
+            .map_err(|error| InstanceError::InstantiationError(Box::new(error)))?;
+
+        let statistics = statistics::new(module.store(), &instance.exports);
+        let exports = Py::new(
+            py,
+            Exports::new(instance.exports.clone()).with_statistics(statistics.clone()),
+        )
+        .map_err(InstanceError::PyErr)?;
+
+        let instance = Instance {
+            pending_start: Cell::new(
+                instance
+                    .exports
+                    .get_function(middlewares::START_EXPORT)
+                    .ok()
+                    .cloned(),
+            ),
+            inner: Some(instance),
             exports,
-        })
+            imports: Some(imports),
+            statistics,
+            tracing_hook: Cell::new(None),
+        };
+
+        if run_start {
+            instance.start(py, timeout).map_err(InstanceError::PyErr)?;
+        }
+
+        Ok(instance)
+    }
+
+    /// Runs the deferred start function, if it hasn't run yet, and
+    /// tells whether it has run. It is interruptible, like the
+    /// exported functions.
+    fn start(&self, py: Python, timeout: Option<f64>) -> PyResult<bool> {
+        match self.pending_start.take() {
+            Some(start) => {
+                externals::Function::raw_new(start)
+                    .with_instance_exports(&self.inner()?.exports)
+                    .call(py, Vec::new(), timeout)?;
+
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+}
+
+/// Gets the `ImportObject` given to instantiate `module`: an
+/// `ImportObject`, a dictionary, or nothing.
+fn to_import_object(
+    module: &wasmer::Module,
+    import_object: Option<&PyAny>,
+) -> PyResult<ImportObject> {
+    Ok(match import_object {
+        Some(import_object) => match import_object.downcast::<PyCell<ImportObject>>() {
+            Ok(io) => ImportObject::raw_new(io.borrow().inner().clone()),
+            Err(_e) => ImportObject::from_pydict(import_object.downcast::<PyDict>()?, module)?,
+        },
+        None => ImportObject::new(),
+    })
+}
+
+/// Checks that the imports of `module` found in `import_object` come
+/// from a store with the same engine as the module. Different engines
+/// don't share their function signatures, and their externs can't be
+/// mixed.
+fn check_stores(module: &wasmer::Module, import_object: &ImportObject) -> PyResult<()> {
+    for (namespace_name, name, export) in import_object.inner().externs_vec() {
+        let store = match &export {
+            wasmer::Extern::Function(function) => function.store(),
+            wasmer::Extern::Global(global) => global.store(),
+            wasmer::Extern::Memory(memory) => memory.store(),
+            wasmer::Extern::Table(table) => table.store(),
+        };
+
+        let engine_id = store::engine_id(store);
+
+        if engine_id.is_some() && engine_id == store::engine_id(module.store()) {
+            continue;
+        }
+
+        if module
+            .imports()
+            .any(|import| import.module() == namespace_name && import.name() == name)
+        {
+            return Err(LinkError::new_err(format!(
+                "Import `{}.{}` comes from a store with another engine than the module",
+                namespace_name, name
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// The error raised when a closed instance is used.
+fn closed_error() -> PyErr {
+    to_py_err::<PyValueError, _>("The instance is closed")
+}
+
+impl Drop for Instance {
+    fn drop(&mut self) {
+        if let Some(inner) = &self.inner {
+            middlewares::forget_trace(inner);
+        }
     }
 }
 
 #[pymethods]
 impl Instance {
     #[new]
-    fn new(py: Python, module: &Module, import_object: Option<&PyAny>) -> PyResult<Self> {
-        Instance::raw_new(py, &module, import_object).map_err(|error| match error {
-            InstanceError::InstantiationError(error) => {
-                if let wasmer::InstantiationError::Start(error) = error {
-                    runtime_error_to_py_err(error)
-                } else {
-                    to_py_err::<PyRuntimeError, _>(error)
+    #[args(
+        import_object = "None",
+        "*",
+        allow_missing_imports = "false",
+        run_start = "true",
+        timeout = "None"
+    )]
+    fn new(
+        py: Python,
+        module: &Module,
+        import_object: Option<&PyAny>,
+        allow_missing_imports: bool,
+        run_start: bool,
+        timeout: Option<f64>,
+    ) -> PyResult<Self> {
+        Instance::raw_new(
+            py,
+            &module,
+            import_object,
+            allow_missing_imports,
+            run_start,
+            timeout,
+        )
+        .map_err(|error| error.into_py_err(module.inner()))
+    }
+
+    /// Runs the start function of an instance created with
+    /// `run_start=False`. The start function runs once: a
+    /// `RuntimeError` is raised when it has already run. Nothing
+    /// happens when the module has no start function. Note that
+    /// Wasmer never calls the `_initialize` function of a WASI
+    /// reactor, it is called like any export.
+    ///
+    /// `timeout` has the same meaning as for `Instance`.
+    ///
+    /// ## Example
+    ///
+    /// See the `DeferredStart` class.
+    #[args("*", timeout = "None")]
+    #[pyo3(text_signature = "($self, /, *, timeout=None)")]
+    fn run_start(&self, py: Python, timeout: Option<f64>) -> PyResult<()> {
+        let has_start_function = self.inner()?.module().info().start_function.is_some()
+            || self
+                .inner()?
+                .exports
+                .get_function(middlewares::START_EXPORT)
+                .is_ok();
+
+        if !self.start(py, timeout)? && has_start_function {
+            return Err(to_py_err::<PyRuntimeError, _>(
+                "The start function of the instance has already run",
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Creates a new instance of the same module, with the same
+    /// imports, and a copy of the memories and the mutable globals of
+    /// this instance. It is cheaper to clone an instance after an
+    /// expensive initialisation than to replay it.
+    ///
+    /// The memories are copied, not shared copy-on-write. They must be
+    /// exported, as well as the mutable globals, otherwise a
+    /// `RuntimeError` is raised. The tables are initialised by the
+    /// module, like for a new instance, and the start function, if
+    /// any, runs again. The state of the middlewares, e.g. the
+    /// remaining points of `Metering` or the function set by
+    /// `set_trace`, is not copied.
+    ///
+    /// ## Example
+    ///
+    /// ```py
+    /// from wasmer import Store, Module, Instance
+    ///
+    /// module = Module(
+    ///     Store(),
+    ///     """
+    ///     (module
+    ///       (memory (export "memory") 1)
+    ///       (global $counter (export "counter") (mut i32) (i32.const 0))
+    ///       (func (export "increment") (result i32)
+    ///         global.get $counter
+    ///         i32.const 1
+    ///         i32.add
+    ///         global.set $counter
+    ///         global.get $counter))
+    ///     """
+    /// )
+    /// instance = Instance(module)
+    /// instance.exports.increment()
+    /// instance.exports.memory.uint8_view()[0] = 42
+    ///
+    /// clone = instance.clone()
+    ///
+    /// assert clone.exports.increment() == 2
+    /// assert clone.exports.memory.uint8_view()[0] == 42
+    ///
+    /// # The clone doesn't share its state with the instance.
+    /// assert instance.exports.increment() == 2
+    /// ```
+    #[pyo3(text_signature = "($self)")]
+    fn clone(&self, py: Python) -> PyResult<Self> {
+        let module = self.inner()?.module();
+        let (memories, globals) = (
+            snapshot::exported_memories(module)?,
+            snapshot::exported_mutable_globals(module)?,
+        );
+
+        let clone =
+            Instance::from_resolved_imports(py, module, self.imports()?.clone(), true, None)
+                .map_err(|error| error.into_py_err(module))?;
+
+        for name in &memories {
+            let (source, destination) = (
+                self.inner()?
+                    .exports
+                    .get_memory(name)
+                    .map_err(to_py_err::<PyRuntimeError, _>)?,
+                clone
+                    .inner()?
+                    .exports
+                    .get_memory(name)
+                    .map_err(to_py_err::<PyRuntimeError, _>)?,
+            );
+
+            // SAFETY: the memories are distinct, and no WebAssembly
+            // code runs while they are copied. The start function may
+            // have grown the memory of the clone already.
+            snapshot::write_memory(destination, source.size(), unsafe {
+                source.data_unchecked()
+            })?;
+        }
+
+        for name in &globals {
+            let (source, destination) = (
+                self.inner()?
+                    .exports
+                    .get_global(name)
+                    .map_err(to_py_err::<PyRuntimeError, _>)?,
+                clone
+                    .inner()?
+                    .exports
+                    .get_global(name)
+                    .map_err(to_py_err::<PyRuntimeError, _>)?,
+            );
+
+            destination
+                .set(source.get())
+                .map_err(to_py_err::<PyRuntimeError, _>)?;
+        }
+
+        Ok(clone)
+    }
+
+    /// Instantiates `module`, typically a new version of the module
+    /// of this instance, and carries the exported memories and
+    /// mutable globals of this instance over to the new instance: an
+    /// export of the new instance with the same name and the same
+    /// kind receives a copy of the content of the memory, or the
+    /// value of the global. It allows reloading some code without
+    /// losing its state. This instance is left untouched.
+    ///
+    /// The arguments `import_object` and `allow_missing_imports` have
+    /// the same meaning as for `Instance`. The start function of the
+    /// new module runs before the state is carried over. The exports
+    /// that don't exist in the new instance are dropped, the new
+    /// exports keep their initial value, and a global of another type
+    /// raises a `ValueError`. The state of the middlewares is not
+    /// carried over, like for `clone`.
+    ///
+    /// ## Example
+    ///
+    /// ```py
+    /// from wasmer import Store, Module, Instance
+    ///
+    /// store = Store()
+    /// instance = Instance(
+    ///     Module(
+    ///         store,
+    ///         """
+    ///         (module
+    ///           (global $counter (export "counter") (mut i32) (i32.const 0))
+    ///           (func (export "increment") (result i32)
+    ///             global.get $counter
+    ///             i32.const 1
+    ///             i32.add
+    ///             global.set $counter
+    ///             global.get $counter))
+    ///         """
+    ///     )
+    /// )
+    /// assert instance.exports.increment() == 1
+    ///
+    /// # A new version of the module counts by steps of 10.
+    /// instance = instance.migrate(
+    ///     Module(
+    ///         store,
+    ///         """
+    ///         (module
+    ///           (global $counter (export "counter") (mut i32) (i32.const 0))
+    ///           (func (export "increment") (result i32)
+    ///             global.get $counter
+    ///             i32.const 10
+    ///             i32.add
+    ///             global.set $counter
+    ///             global.get $counter))
+    ///         """
+    ///     )
+    /// )
+    /// assert instance.exports.increment() == 11
+    /// ```
+    ///
+    /// ## Tiered compilation
+    ///
+    /// Wasmer compiles a module entirely before it is instantiated,
+    /// it can't compile a function lazily on its first call, nor
+    /// replace the code of a function of a running instance. The
+    /// same effect is obtained for a whole module by starting with
+    /// the fast Singlepass compiler, compiling the module again with
+    /// the optimizing Cranelift compiler in a thread, since the
+    /// compilation doesn't hold the GIL, and migrating the instance
+    /// once it is ready.
+    ///
+    /// ```py
+    /// from wasmer import engine, Store, Module, Instance
+    /// from wasmer_compiler_cranelift import Compiler as Cranelift
+    /// from wasmer_compiler_singlepass import Compiler as Singlepass
+    /// from concurrent.futures import ThreadPoolExecutor
+    ///
+    /// wat = """
+    /// (module
+    ///   (global $counter (export "counter") (mut i32) (i32.const 0))
+    ///   (func (export "increment") (result i32)
+    ///     global.get $counter
+    ///     i32.const 1
+    ///     i32.add
+    ///     global.set $counter
+    ///     global.get $counter))
+    /// """
+    ///
+    /// instance = Instance(Module(Store(engine.Universal(Singlepass)), wat))
+    ///
+    /// with ThreadPoolExecutor() as executor:
+    ///     optimized = executor.submit(Module, Store(engine.Universal(Cranelift)), wat)
+    ///
+    ///     assert instance.exports.increment() == 1
+    ///
+    ///     instance = instance.migrate(optimized.result())
+    ///
+    /// assert instance.exports.increment() == 2
+    /// ```
+    #[args(import_object = "None", "*", allow_missing_imports = "false")]
+    #[pyo3(text_signature = "($self, module, import_object, /, *, allow_missing_imports=False)")]
+    fn migrate(
+        &self,
+        py: Python,
+        module: &Module,
+        import_object: Option<&PyAny>,
+        allow_missing_imports: bool,
+    ) -> PyResult<Self> {
+        let migrated =
+            Instance::raw_new(py, module, import_object, allow_missing_imports, true, None)
+                .map_err(|error| error.into_py_err(module.inner()))?;
+
+        // Check the globals before writing anything.
+        let mut globals = Vec::new();
+
+        for (name, export) in self.inner()?.exports.iter() {
+            let source = match export {
+                wasmer::Extern::Global(global)
+                    if global.ty().mutability.is_mutable()
+                        && !middlewares::is_internal_export(name) =>
+                {
+                    global
                 }
+                _ => continue,
+            };
+
+            let destination = match migrated.inner()?.exports.get_global(name) {
+                Ok(global) if global.ty().mutability.is_mutable() => global,
+                _ => continue,
+            };
+
+            if source.ty().ty != destination.ty().ty {
+                return Err(to_py_err::<PyValueError, _>(format!(
+                    "The global `{}` has the type `{}`, but it has the type `{}` in the new module",
+                    name,
+                    source.ty().ty,
+                    destination.ty().ty
+                )));
             }
-            InstanceError::PyErr(error) => error,
+
+            globals.push((source, destination));
+        }
+
+        for (name, export) in self.inner()?.exports.iter() {
+            let source = match export {
+                wasmer::Extern::Memory(memory) => memory,
+                _ => continue,
+            };
+
+            let destination = match migrated.inner()?.exports.get_memory(name) {
+                Ok(memory) => memory,
+                _ => continue,
+            };
+
+            // The new instance may import the memory of this instance.
+            if source.same(destination) {
+                continue;
+            }
+
+            // SAFETY: the memories are distinct, and no WebAssembly
+            // code runs while they are copied.
+            snapshot::write_memory(destination, source.size(), unsafe {
+                source.data_unchecked()
+            })?;
+        }
+
+        for (source, destination) in globals {
+            destination
+                .set(source.get())
+                .map_err(to_py_err::<PyRuntimeError, _>)?;
+        }
+
+        Ok(migrated)
+    }
+
+    /// Takes a snapshot of the state of the instance, i.e. its
+    /// memories, its mutable globals and its tables, as `bytes`. The
+    /// snapshot can be restored with `restore`, on this instance or
+    /// on any instance of the same module, with the same imports.
+    ///
+    /// The memories, the mutable globals and the tables defined by
+    /// the module must be exported, otherwise a `RuntimeError` is
+    /// raised. The functions referenced by the tables and the globals
+    /// are recorded by index, hence they must be exported by the
+    /// instance, or imported by the module; an imported function must
+    /// also be exported, or be in a table, of the instance the
+    /// snapshot is restored on. An
+    /// `externref` cannot be snapshotted. The state of the
+    /// middlewares is not recorded, like for `clone`.
+    ///
+    /// ## Example
+    ///
+    /// ```py
+    /// from wasmer import Store, Module, Instance
+    ///
+    /// module = Module(
+    ///     Store(),
+    ///     """
+    ///     (module
+    ///       (memory (export "memory") 1)
+    ///       (global $counter (export "counter") (mut i32) (i32.const 0))
+    ///       (func (export "increment") (result i32)
+    ///         global.get $counter
+    ///         i32.const 1
+    ///         i32.add
+    ///         global.set $counter
+    ///         global.get $counter))
+    ///     """
+    /// )
+    /// instance = Instance(module)
+    /// instance.exports.increment()
+    /// instance.exports.memory.uint8_view()[0] = 42
+    ///
+    /// snapshot = instance.snapshot()
+    /// assert isinstance(snapshot, bytes)
+    ///
+    /// other = Instance(module)
+    /// other.restore(snapshot)
+    ///
+    /// assert other.exports.increment() == 2
+    /// assert other.exports.memory.uint8_view()[0] == 42
+    /// ```
+    #[pyo3(text_signature = "($self)")]
+    fn snapshot<'p>(&self, py: Python<'p>) -> PyResult<&'p PyBytes> {
+        let snapshot = snapshot::snapshot(self.inner()?, self.imports()?)?;
+
+        Ok(PyBytes::new(py, &snapshot))
+    }
+
+    /// Restores a snapshot taken by `snapshot`. A `ValueError` is
+    /// raised if the snapshot is invalid, or if it has been taken
+    /// from an instance of another module; the instance is left
+    /// untouched then.
+    ///
+    /// ## Example
+    ///
+    /// ```py
+    /// from wasmer import Store, Module, Instance
+    ///
+    /// module = Module(
+    ///     Store(),
+    ///     """
+    ///     (module
+    ///       (global $counter (export "counter") (mut i32) (i32.const 0))
+    ///       (func (export "increment") (result i32)
+    ///         global.get $counter
+    ///         i32.const 1
+    ///         i32.add
+    ///         global.set $counter
+    ///         global.get $counter))
+    ///     """
+    /// )
+    /// instance = Instance(module)
+    /// snapshot = instance.snapshot()
+    ///
+    /// assert instance.exports.increment() == 1
+    /// assert instance.exports.increment() == 2
+    ///
+    /// # Rewind the instance.
+    /// instance.restore(snapshot)
+    ///
+    /// assert instance.exports.increment() == 1
+    /// ```
+    #[pyo3(text_signature = "($self, snapshot)")]
+    fn restore(&self, snapshot: &[u8]) -> PyResult<()> {
+        snapshot::restore(self.inner()?, self.imports()?, snapshot)
+    }
+
+    /// Returns the statistics of the calls to the exported functions,
+    /// collected since the instance has been created, if its store
+    /// has been created with `statistics=True`, otherwise a
+    /// `RuntimeError` is raised.
+    ///
+    /// It is a dictionary, by export name, of dictionaries with the
+    /// number of `calls`, including the failed ones, and the `time`
+    /// spent running them, in seconds. The time is measured around
+    /// the WebAssembly code, and includes the host functions it
+    /// calls, but not the conversion of the arguments and the
+    /// results. A function exported under several names is counted
+    /// under the name it has been called by.
+    ///
+    /// ## Example
+    ///
+    /// ```py
+    /// from wasmer import Store, Module, Instance
+    ///
+    /// module = Module(
+    ///     Store(statistics=True),
+    ///     """
+    ///     (module
+    ///       (func (export "square") (param i32) (result i32)
+    ///         local.get 0
+    ///         local.get 0
+    ///         i32.mul)
+    ///       (func (export "unused")))
+    ///     """
+    /// )
+    /// instance = Instance(module)
+    ///
+    /// instance.exports.square(2)
+    /// instance.exports.square.call_batch([(3,), (4,)])
+    ///
+    /// stats = instance.stats()
+    ///
+    /// assert stats["square"]["calls"] == 3
+    /// assert stats["square"]["time"] > 0
+    /// assert stats["unused"] == {"calls": 0, "time": 0.0}
+    /// ```
+    #[pyo3(text_signature = "($self)")]
+    fn stats<'p>(&self, py: Python<'p>) -> PyResult<&'p PyDict> {
+        let statistics = self.statistics.as_ref().ok_or_else(|| {
+            to_py_err::<PyRuntimeError, _>(
+                "The store of the instance doesn't collect statistics, see `Store(statistics=True)`",
+            )
+        })?;
+
+        statistics::to_py_dict(py, statistics)
+    }
+
+    /// Closes the instance: it releases its references to the
+    /// instance, to its imports, and to its exports, whose `Exports`
+    /// object is emptied. Using it afterwards raises a `ValueError`.
+    /// Closing a closed instance does nothing.
+    ///
+    /// The native resources of the instance, e.g. its memories, are
+    /// freed once nothing refers to them anymore: the `Function`,
+    /// `Memory`, `Global` or `Table` objects obtained from its
+    /// exports, or the views over its memories, keep them alive.
+    ///
+    /// An instance is also a context manager, closed at the exit of
+    /// the `with` block.
+    ///
+    /// ## Example
+    ///
+    /// ```py
+    /// from wasmer import Store, Module, Instance
+    ///
+    /// module = Module(Store(), '(module (memory (export "memory") 100))')
+    ///
+    /// with Instance(module) as instance:
+    ///     assert instance.exports.memory.size == 100
+    ///
+    /// try:
+    ///     instance.snapshot()
+    /// except ValueError:
+    ///     pass
+    /// else:
+    ///     assert False
+    /// ```
+    #[pyo3(text_signature = "($self)")]
+    fn close(&mut self, py: Python) -> PyResult<()> {
+        if let Some(inner) = self.inner.take() {
+            middlewares::forget_trace(&inner);
+        }
+
+        self.imports = None;
+        self.statistics = None;
+        self.pending_start.take();
+        self.tracing_hook.take();
+        *self.exports.try_borrow_mut(py)? = Exports::new(wasmer::Exports::new());
+
+        Ok(())
+    }
+
+    fn __enter__(slf: PyRef<Self>) -> PyRef<Self> {
+        slf
+    }
+
+    fn __exit__(
+        &mut self,
+        py: Python,
+        _exc_type: &PyAny,
+        _exc_value: &PyAny,
+        _traceback: &PyAny,
+    ) -> PyResult<bool> {
+        self.close(py)?;
+
+        Ok(false)
+    }
+}
+
+/// An `InstancePre` holds a module whose imports have been resolved
+/// once and for all, to instantiate it repeatedly: `instantiate` skips
+/// looking up the imports in the import object, and wrapping the
+/// Python callables as `Function`s, on each call.
+///
+/// The arguments have the same meaning as for `Instance`. A missing
+/// import raises a `LinkError` when the `InstancePre` is created,
+/// unless `allow_missing_imports` is true.
+///
+/// ## Example
+///
+/// ```py
+/// from wasmer import Store, Module, InstancePre
+///
+/// module = Module(
+///     Store(),
+///     """
+///     (module
+///       (import "math" "sum" (func $sum (param i32 i32) (result i32)))
+///       (global $counter (mut i32) (i32.const 0))
+///       (func (export "add_one") (param i32) (result i32)
+///         local.get 0
+///         i32.const 1
+///         call $sum)
+///       (func (export "count") (result i32)
+///         global.get $counter
+///         i32.const 1
+///         i32.add
+///         global.set $counter
+///         global.get $counter))
+///     """
+/// )
+/// instance_pre = InstancePre(module, {"math": {"sum": lambda x, y: x + y}})
+///
+/// first = instance_pre.instantiate()
+/// second = instance_pre.instantiate()
+///
+/// assert first.exports.add_one(41) == 42
+/// assert first.exports.count() == 1
+/// assert first.exports.count() == 2
+///
+/// # Each instance has its own state.
+/// assert second.exports.count() == 1
+/// ```
+#[pyclass(unsendable)]
+#[pyo3(text_signature = "(module, import_object, /, *, allow_missing_imports=False)")]
+pub struct InstancePre {
+    module: Py<Module>,
+    imports: Rc<ResolvedImports>,
+}
+
+/// The imports of a module, in the order of the module's imports.
+/// A missing import is reported by `wasmer::Instance::new`.
+pub(crate) struct ResolvedImports {
+    exports: Vec<Option<wasmer::Export>>,
+
+    /// The import object owning the externs of `exports`.
+    _import_object: ImportObject,
+}
+
+impl ResolvedImports {
+    fn new(module: &wasmer::Module, import_object: ImportObject) -> Self {
+        Self {
+            exports: module
+                .imports()
+                .map(|import| {
+                    import_object
+                        .inner()
+                        .get_export(import.module(), import.name())
+                })
+                .collect(),
+            _import_object: import_object,
+        }
+    }
+
+    pub(crate) fn exports(&self) -> &[Option<wasmer::Export>] {
+        &self.exports
+    }
+}
+
+impl wasmer::Resolver for ResolvedImports {
+    fn resolve(&self, index: u32, _module: &str, _field: &str) -> Option<wasmer::Export> {
+        self.exports.get(index as usize).cloned().flatten()
+    }
+}
+
+#[pymethods]
+impl InstancePre {
+    #[new]
+    #[args(import_object = "None", "*", allow_missing_imports = "false")]
+    fn new(
+        py: Python,
+        module: Py<Module>,
+        import_object: Option<&PyAny>,
+        allow_missing_imports: bool,
+    ) -> PyResult<Self> {
+        let inner = module.borrow(py).inner().clone();
+
+        let mut import_object = to_import_object(&inner, import_object)?;
+        check_stores(&inner, &import_object)?;
+
+        if allow_missing_imports {
+            import_object = import_object.with_missing_imports_stubbed(&inner);
+        }
+
+        let imports = ResolvedImports::new(&inner, import_object);
+
+        if let Some((import, _)) = inner
+            .imports()
+            .zip(&imports.exports)
+            .find(|(_, export)| export.is_none())
+        {
+            return Err(LinkError::new_err(format!(
+                "Import `{}.{}` is missing",
+                import.module(),
+                import.name()
+            )));
+        }
+
+        Ok(Self {
+            module,
+            imports: Rc::new(imports),
         })
     }
+
+    /// Creates a new `Instance` of the module, with the resolved
+    /// imports. `run_start` and `timeout` have the same meaning as
+    /// for `Instance`.
+    ///
+    /// ## Example
+    ///
+    /// See the `InstancePre` class.
+    #[args("*", run_start = "true", timeout = "None")]
+    #[pyo3(text_signature = "($self, /, *, run_start=True, timeout=None)")]
+    fn instantiate(&self, py: Python, run_start: bool, timeout: Option<f64>) -> PyResult<Instance> {
+        let module = self.module.borrow(py);
+
+        Instance::from_resolved_imports(
+            py,
+            module.inner(),
+            self.imports.clone(),
+            run_start,
+            timeout,
+        )
+        .map_err(|error| error.into_py_err(module.inner()))
+    }
 }
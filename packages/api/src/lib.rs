@@ -1,26 +1,41 @@
 use pyo3::{
     prelude::*,
-    types::{PyBytes, PyTuple},
+    types::{PyBytes, PyDict, PyTuple},
     wrap_pymodule,
 };
 
 pub(crate) mod wasmer_inner {
     pub use wasmer;
+    pub use wasmer_emscripten;
     pub use wasmer_engines;
     pub use wasmer_types;
+    pub use wasmer_vfs;
+    pub use wasmer_vm;
     pub use wasmer_wasi;
 }
 
+mod cache;
+mod coredump;
+mod emscripten;
 mod engines;
 mod errors;
 mod exports;
 mod externals;
+mod externref;
 mod import_object;
 mod instance;
+mod linker;
 mod memory;
+mod middlewares;
 mod module;
+mod perf;
+mod snapshot;
+mod stack;
+mod statistics;
 mod store;
 mod target;
+mod timer;
+mod tunables;
 mod types;
 mod values;
 mod wasi;
@@ -80,6 +95,19 @@ mod wat;
 ///   or `Table`? Well, there is the Python `dict` for that!
 /// * It is possible to read and write `Memory` data with the Python
 ///   buffer protocol with `Buffer`.
+/// * Errors are `WasmerError`s: `CompileError`, `InstantiationError`,
+///   `LinkError`, or `Trap`, whose `code` is a `TrapCode`.
+///
+/// ```py
+/// from wasmer import Store, Module, Instance, Trap, TrapCode
+///
+/// module = Module(Store(), '(module (func (export "crash") unreachable))')
+///
+/// try:
+///     Instance(module).exports.crash()
+/// except Trap as trap:
+///     assert trap.code == TrapCode.UNREACHABLE_CODE_REACHED
+/// ```
 ///
 /// Have fun!
 #[pymodule]
@@ -122,16 +150,127 @@ fn wasmer(py: Python, module: &PyModule) -> PyResult<()> {
         wat::wasm2wat(bytes)
     }
 
+    /// Unpickle a `Module`, see `Module.__reduce__`.
+    #[pyfn(module)]
+    #[pyo3(name = "_unpickle_module")]
+    fn unpickle_module(py: Python, bytes: &PyAny) -> PyResult<module::Module> {
+        module::unpickle(py, bytes)
+    }
+
+    /// Detect the CPU features of the current host, as a
+    /// `target.CpuFeatures` object.
+    ///
+    /// ## Example
+    ///
+    /// ```py
+    /// from wasmer import host_cpu_features, target
+    ///
+    /// cpu_features = host_cpu_features()
+    ///
+    /// assert isinstance(cpu_features, target.CpuFeatures)
+    /// ```
+    #[pyfn(module)]
+    #[pyo3(text_signature = "()")]
+    fn host_cpu_features() -> target::CpuFeatures {
+        target::CpuFeatures::host()
+    }
+
+    /// Returns the remaining points of an instance compiled with a
+    /// `Metering` middleware. It returns 0 when all the points have
+    /// been consumed.
+    ///
+    /// ## Example
+    ///
+    /// See the `Metering` class.
+    #[pyfn(module)]
+    #[pyo3(text_signature = "(instance)")]
+    fn get_remaining_points(instance: &instance::Instance) -> PyResult<u64> {
+        middlewares::get_remaining_points(instance)
+    }
+
+    /// Sets the remaining points of an instance compiled with a
+    /// `Metering` middleware.
+    ///
+    /// ## Example
+    ///
+    /// ```py
+    /// from wasmer import engine, get_remaining_points, set_remaining_points, Store, Module, Instance, Metering
+    /// from wasmer_compiler_cranelift import Compiler
+    ///
+    /// store = Store(engine.Universal(Compiler(middlewares=[Metering(10)])))
+    /// instance = Instance(Module(store, '(module)'))
+    ///
+    /// set_remaining_points(instance, 42)
+    ///
+    /// assert get_remaining_points(instance) == 42
+    /// ```
+    #[pyfn(module)]
+    #[pyo3(text_signature = "(instance, points)")]
+    fn set_remaining_points(instance: &instance::Instance, points: u64) -> PyResult<()> {
+        middlewares::set_remaining_points(instance, points)
+    }
+
+    /// Returns an `InterruptHandle` to interrupt the calls to the
+    /// functions of an instance compiled with an `Interruptible`
+    /// middleware.
+    ///
+    /// ## Example
+    ///
+    /// See the `InterruptHandle` class.
+    #[pyfn(module)]
+    #[pyo3(text_signature = "(instance)")]
+    fn interrupt_handle(instance: &instance::Instance) -> PyResult<middlewares::InterruptHandle> {
+        middlewares::interrupt_handle(instance)
+    }
+
+    /// Sets the Python function called on the entry and the exit of
+    /// the functions of an instance compiled with a `Tracing`
+    /// middleware, or disables tracing with `None`.
+    ///
+    /// ## Example
+    ///
+    /// See the `Tracing` class.
+    #[pyfn(module)]
+    #[pyo3(text_signature = "(instance, callback)")]
+    fn set_trace(instance: &instance::Instance, callback: Option<PyObject>) -> PyResult<()> {
+        middlewares::set_trace(instance, callback)
+    }
+
+    // Exceptions.
+    module.add("WasmerError", py.get_type::<errors::WasmerError>())?;
+    module.add("CompileError", py.get_type::<errors::CompileError>())?;
+    module.add(
+        "InstantiationError",
+        py.get_type::<errors::InstantiationError>(),
+    )?;
+    module.add("LinkError", py.get_type::<errors::LinkError>())?;
+    module.add("Trap", py.get_type::<errors::Trap>())?;
+    module.add(
+        "ExecutionTimeout",
+        py.get_type::<errors::ExecutionTimeout>(),
+    )?;
+
     // Classes.
+    module.add_class::<cache::Cache>()?;
+    module.add_class::<errors::Frame>()?;
     module.add_class::<exports::Exports>()?;
     module.add_class::<exports::ExportsIterator>()?;
     module.add_class::<externals::Function>()?;
     module.add_class::<externals::Global>()?;
     module.add_class::<externals::Memory>()?;
     module.add_class::<externals::Table>()?;
+    module.add_class::<externals::TypedFunction>()?;
     module.add_class::<import_object::ImportObject>()?;
     module.add_class::<instance::Instance>()?;
+    module.add_class::<instance::InstancePre>()?;
+    module.add_class::<linker::Linker>()?;
     module.add_class::<memory::Buffer>()?;
+    module.add_class::<middlewares::DeferredStart>()?;
+    module.add_class::<middlewares::InterruptHandle>()?;
+    module.add_class::<middlewares::Interruptible>()?;
+    module.add_class::<middlewares::Metering>()?;
+    module.add_class::<middlewares::Middleware>()?;
+    module.add_class::<middlewares::Tracing>()?;
     module.add_class::<memory::Float32Array>()?;
     module.add_class::<memory::Float64Array>()?;
     module.add_class::<memory::Int16Array>()?;
@@ -142,8 +281,11 @@ fn wasmer(py: Python, module: &PyModule) -> PyResult<()> {
     module.add_class::<memory::Uint32Array>()?;
     module.add_class::<memory::Uint64Array>()?;
     module.add_class::<memory::Uint8Array>()?;
+    module.add_class::<module::CompileReport>()?;
     module.add_class::<module::Module>()?;
+    module.add_class::<module::ValidationResult>()?;
     module.add_class::<store::Store>()?;
+    module.add_class::<tunables::Tunables>()?;
     module.add_class::<types::ExportType>()?;
     module.add_class::<types::FunctionType>()?;
     module.add_class::<types::GlobalType>()?;
@@ -168,7 +310,23 @@ fn wasmer(py: Python, module: &PyModule) -> PyResult<()> {
         ))?,
     )?;
 
+    module.add(
+        "TrapCode",
+        enum_module.getattr("IntEnum")?.call1(PyTuple::new(
+            py,
+            [
+                "TrapCode",
+                errors::TrapCode::iter()
+                    .map(Into::into)
+                    .collect::<Vec<&'static str>>()
+                    .join(" ")
+                    .as_str(),
+            ],
+        ))?,
+    )?;
+
     // Modules.
+    module.add_wrapped(wrap_pymodule!(emscripten))?;
     module.add_wrapped(wrap_pymodule!(engine))?;
     module.add_wrapped(wrap_pymodule!(target))?;
     module.add_wrapped(wrap_pymodule!(wasi))?;
@@ -185,12 +343,13 @@ fn wasmer(py: Python, module: &PyModule) -> PyResult<()> {
 /// 2. **Load** an atifact so it can be used by the user (normally,
 ///    pushing the code into executable memory and so on).
 ///
-/// It currently has two implementations:
+/// It currently has three implementations:
 ///
 /// 1. Universal with `engine.Universal`,
-/// 2. Dylib with `engine.Dylib`.
+/// 2. Dylib with `engine.Dylib`,
+/// 3. Staticlib with `engine.Staticlib`.
 ///
-/// Both engines receive an optional compiler. If absent, engines will
+/// All engines receive an optional compiler. If absent, engines will
 /// run in headless mode, i.e. they won't be able to compile (create)
 /// an artifact), they will only be able to run (load) an artifact.
 ///
@@ -219,12 +378,25 @@ fn wasmer(py: Python, module: &PyModule) -> PyResult<()> {
 /// engine = engine.Universal(Compiler)
 /// ```
 ///
+/// Create a Universal engine with the Cranelift compiler and the SIMD
+/// proposal disabled:
+///
+/// ```py
+/// from wasmer import engine
+/// from wasmer_compiler_cranelift import Compiler
+///
+/// features = engine.Features(simd=False)
+/// engine = engine.Universal(Compiler, features=features)
+/// ```
+///
 /// Engines are stored inside the `wasmer.Store`.
 #[pymodule]
 fn engine(_py: Python, module: &PyModule) -> PyResult<()> {
     // Classes.
     module.add_class::<engines::Universal>()?;
     module.add_class::<engines::Dylib>()?;
+    module.add_class::<engines::Staticlib>()?;
+    module.add_class::<engines::Features>()?;
 
     // Deprecated classes.
     module.add_class::<engines::JIT>()?; // Alias to `Universal`.
@@ -328,6 +500,37 @@ fn target(_py: Python, module: &PyModule) -> PyResult<()> {
 /// # Here we go, let's start the program.
 /// instance.exports._start()
 /// ```
+///
+/// When the program calls `proc_exit`, the call raises a
+/// `WasiExitError`, a subclass of `RuntimeError`, whose `exit_code`
+/// attribute holds the exit code:
+///
+/// ```py
+/// from wasmer import wasi, Store, Module, Instance
+///
+/// store = Store()
+/// module = Module(
+///     store,
+///     """
+///     (module
+///       (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+///       (memory (export "memory") 1)
+///       (func (export "_start")
+///         i32.const 3
+///         call $proc_exit))
+///     """
+/// )
+///
+/// wasi_env = wasi.StateBuilder('test-program').finalize()
+/// instance = Instance(module, wasi_env.generate_import_object(store, module))
+///
+/// try:
+///     instance.exports._start()
+/// except wasi.WasiExitError as error:
+///     assert error.exit_code == 3
+/// else:
+///     assert False
+/// ```
 #[pymodule]
 fn wasi(py: Python, module: &PyModule) -> PyResult<()> {
     let enum_module = py.import("enum")?;
@@ -340,15 +543,84 @@ fn wasi(py: Python, module: &PyModule) -> PyResult<()> {
     /// A strict detection expects that all imports live in a single WASI
     /// namespace. A non-strict detection expects that at least one WASI
     /// namespace exits to detect the version. Note that the strict
-    /// detection is faster than the non-strict one.
-    #[pyfn(module)]
-    #[pyo3(text_signature = "(module, strict)")]
+    /// detection is faster than the non-strict one, which is the
+    /// default.
+    ///
+    /// Returns `None` if the module does not import any WASI
+    /// namespace.
+    ///
+    /// ## Example
+    ///
+    /// ```py
+    /// from wasmer import wasi, Store, Module
+    ///
+    /// module = Module(Store(), open('tests/wasi.wasm', 'rb').read())
+    ///
+    /// assert wasi.get_version(module) == wasi.Version.SNAPSHOT1
+    /// assert wasi.get_version(Module(Store(), '(module)')) == None
+    /// ```
+    #[pyfn(module, strict = "false")]
+    #[pyo3(text_signature = "(module, /, strict=False)")]
     fn get_version(module: &module::Module, strict: bool) -> Option<wasi::Version> {
         wasi::get_version(module, strict)
     }
 
+    /// Runs a WASI program once, like `subprocess.run`, and returns a
+    /// `CompletedProcess` with its `returncode`, and its captured
+    /// `stdout` and `stderr`.
+    ///
+    /// `module_or_path` is a `wasmer.Module`, or the path of a
+    /// WebAssembly file, compiled with the default `wasmer.Store`.
+    /// The program is given the arguments `args`, the environment
+    /// variables `env`, and reads its standard input from `stdin`,
+    /// which accepts the same values as `StateBuilder.stdin`.
+    ///
+    /// `timeout`, in seconds, interrupts the program by raising a
+    /// `wasmer.ExecutionTimeout` exception. When a path is given, the
+    /// module is compiled with the `wasmer.Interruptible` middleware
+    /// for that purpose; a `wasmer.Module` must have been compiled
+    /// with it.
+    ///
+    /// ## Example
+    ///
+    /// ```py
+    /// from wasmer import wasi
+    ///
+    /// result = wasi.run('tests/wasi.wasm', args=['--foo'], env={'ABC': 'DEF'})
+    ///
+    /// assert result.returncode == 0
+    /// assert result.args == ['wasi.wasm', '--foo']
+    /// assert b'Found program name: `wasi.wasm`' in result.stdout
+    /// assert result.stderr == b''
+    /// ```
+    #[pyfn(
+        module,
+        args = "Vec::new()",
+        env = "None",
+        stdin = "None",
+        timeout = "None"
+    )]
+    #[pyo3(text_signature = "(module_or_path, /, args=[], env={}, stdin=b\"\", timeout=None)")]
+    fn run(
+        py: Python,
+        module_or_path: &PyAny,
+        args: Vec<String>,
+        env: Option<&PyDict>,
+        stdin: Option<&PyAny>,
+        timeout: Option<f64>,
+    ) -> PyResult<wasi::CompletedProcess> {
+        let stdin = stdin.unwrap_or_else(|| PyBytes::new(py, &[]));
+
+        wasi::run(py, module_or_path, args, env, stdin, timeout)
+    }
+
+    // Exceptions.
+    module.add("WasiExitError", py.get_type::<errors::WasiExitError>())?;
+
     // Classes.
+    module.add_class::<wasi::CompletedProcess>()?;
     module.add_class::<wasi::Environment>()?;
+    module.add_class::<wasi::MemoryFileSystem>()?;
     module.add_class::<wasi::StateBuilder>()?;
 
     // Enums.
@@ -369,3 +641,86 @@ fn wasi(py: Python, module: &PyModule) -> PyResult<()> {
 
     Ok(())
 }
+
+/// Wasmer's [Emscripten](https://emscripten.org/) implementation, to
+/// run the modules produced by Emscripten, which expect the
+/// Emscripten runtime in their imports rather than WASI.
+///
+/// Use `is_emscripten_module` to detect such a module, and build an
+/// `Environment` for it. This environment holds the memory and the
+/// table the module imports, and generates a `wasmer.ImportObject`
+/// to instantiate the module. `Environment.run` then runs the
+/// program.
+///
+/// ## Example
+///
+/// ```py
+/// from wasmer import emscripten, Store, Module, Instance
+///
+/// store = Store()
+/// module = Module(
+///     store,
+///     """
+///     (module
+///       (import "env" "memory" (memory 256 256))
+///       (import "env" "table" (table 0 funcref))
+///       (import "env" "_emscripten_memcpy_big" (func $memcpy_big (param i32 i32 i32) (result i32)))
+///       (global $stack_top (mut i32) (i32.const 65536))
+///       (func (export "stackAlloc") (param $size i32) (result i32)
+///         global.get $stack_top
+///         local.get $size
+///         i32.sub
+///         global.set $stack_top
+///         global.get $stack_top)
+///       ;; Copies the 3 first bytes of `argv[1]` at the address 0.
+///       (func (export "_main") (param $argc i32) (param $argv i32) (result i32)
+///         i32.const 0
+///         local.get $argv
+///         i32.load offset=4
+///         i32.const 3
+///         call $memcpy_big
+///         drop
+///         i32.const 0))
+///     """
+/// )
+///
+/// assert emscripten.is_emscripten_module(module)
+///
+/// emscripten_env = emscripten.Environment(store, module)
+/// instance = Instance(module, emscripten_env.generate_import_object(store))
+///
+/// emscripten_env.run(instance, 'test-program', ['foo'])
+///
+/// assert bytes(emscripten_env.memory.uint8_view()[0:3]) == b'foo'
+/// ```
+#[pymodule]
+fn emscripten(py: Python, module: &PyModule) -> PyResult<()> {
+    // Functions.
+
+    /// Checks whether a `wasmer.Module` has been produced by
+    /// Emscripten, from the Emscripten imports it expects.
+    ///
+    /// ## Example
+    ///
+    /// ```py
+    /// from wasmer import emscripten, Store, Module
+    ///
+    /// assert not emscripten.is_emscripten_module(Module(Store(), '(module)'))
+    /// ```
+    #[pyfn(module)]
+    #[pyo3(text_signature = "(module)")]
+    fn is_emscripten_module(module: &module::Module) -> bool {
+        emscripten::is_emscripten_module(module)
+    }
+
+    // Exceptions.
+    module.add(
+        "EmscriptenExitError",
+        py.get_type::<errors::EmscriptenExitError>(),
+    )?;
+
+    // Classes.
+    module.add_class::<emscripten::Environment>()?;
+
+    Ok(())
+}
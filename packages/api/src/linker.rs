@@ -0,0 +1,182 @@
+use crate::{
+    errors::to_py_err,
+    import_object::{to_extern, ImportObject},
+    instance::Instance,
+    middlewares,
+    module::Module,
+};
+use pyo3::{exceptions::PyTypeError, prelude::*};
+
+/// A `Linker` links several modules together: the exports of an
+/// instance registered under a namespace are used to resolve the
+/// imports of this namespace, for the modules instantiated next.
+///
+/// Host externs can also be defined, to be imported by any module
+/// instantiated by the linker. A definition replaces any previous
+/// one with the same namespace and name.
+///
+/// ## Example
+///
+/// ```py
+/// from wasmer import Store, Module, Linker, Function
+///
+/// store = Store()
+///
+/// def log(x: int):
+///     print(x)
+///
+/// utils = Module(
+///     store,
+///     """
+///     (module
+///       (func (export "double") (param i32) (result i32)
+///         local.get 0
+///         i32.const 2
+///         i32.mul))
+///     """
+/// )
+/// application = Module(
+///     store,
+///     """
+///     (module
+///       (import "env" "log" (func $log (param i32)))
+///       (import "utils" "double" (func $double (param i32) (result i32)))
+///       (func (export "quadruple") (param i32) (result i32)
+///         local.get 0
+///         call $double
+///         call $double))
+///     """
+/// )
+///
+/// linker = Linker()
+/// linker.define("env", "log", Function(store, log))
+/// linker.instantiate(utils, "utils")
+///
+/// instance = linker.instantiate(application)
+///
+/// assert instance.exports.quadruple(3) == 12
+/// ```
+#[pyclass(unsendable)]
+#[pyo3(text_signature = "()")]
+pub struct Linker {
+    import_object: ImportObject,
+}
+
+#[pymethods]
+impl Linker {
+    #[new]
+    fn new() -> Self {
+        Self {
+            import_object: ImportObject::new(),
+        }
+    }
+
+    /// Defines a `Function`, `Memory`, `Global` or `Table`, under
+    /// `namespace_name` and `name`.
+    ///
+    /// ## Example
+    ///
+    /// ```py
+    /// from wasmer import Store, Linker, Memory, MemoryType
+    ///
+    /// store = Store()
+    /// linker = Linker()
+    /// linker.define("env", "memory", Memory(store, MemoryType(minimum=1)))
+    ///
+    /// assert linker.import_object.names("env") == ["memory"]
+    /// ```
+    #[pyo3(text_signature = "($self, namespace_name, name, item)")]
+    fn define(&mut self, namespace_name: String, name: String, item: &PyAny) -> PyResult<()> {
+        match to_extern(item)? {
+            Some(export) => {
+                self.import_object.insert(namespace_name, name, export);
+
+                Ok(())
+            }
+            None => Err(to_py_err::<PyTypeError, _>(format!(
+                "`Linker` cannot define the given type `{}`",
+                item.get_type().name()?
+            ))),
+        }
+    }
+
+    /// Defines all the exports of `instance` under `namespace_name`.
+    ///
+    /// ## Example
+    ///
+    /// ```py
+    /// from wasmer import Store, Module, Instance, Linker
+    ///
+    /// module = Module(Store(), '(module (memory (export "memory") 1))')
+    ///
+    /// linker = Linker()
+    /// linker.define_instance("env", Instance(module))
+    ///
+    /// assert linker.import_object.names("env") == ["memory"]
+    /// ```
+    #[pyo3(text_signature = "($self, namespace_name, instance)")]
+    fn define_instance(&mut self, namespace_name: String, instance: &Instance) -> PyResult<()> {
+        for (name, export) in instance.inner()?.exports.iter() {
+            if middlewares::is_internal_export(name) {
+                continue;
+            }
+
+            self.import_object
+                .insert(namespace_name.clone(), name.clone(), export.clone());
+        }
+
+        Ok(())
+    }
+
+    /// Instantiates `module`, whose imports are resolved by the
+    /// linker. If `namespace_name` is given, the exports of the new
+    /// instance are defined under it, see `define_instance`.
+    ///
+    /// `allow_missing_imports` has the same meaning as for
+    /// `Instance`.
+    ///
+    /// ## Example
+    ///
+    /// See the `Linker` class.
+    #[pyo3(
+        text_signature = "($self, module, /, namespace_name=None, *, allow_missing_imports=False)"
+    )]
+    #[args(namespace_name = "None", "*", allow_missing_imports = "false")]
+    fn instantiate(
+        &mut self,
+        py: Python,
+        module: &Module,
+        namespace_name: Option<String>,
+        allow_missing_imports: bool,
+    ) -> PyResult<Instance> {
+        let instance = Instance::from_import_object(
+            py,
+            module.inner(),
+            &self.import_object,
+            allow_missing_imports,
+            true,
+            None,
+        )
+        .map_err(|error| error.into_py_err(module.inner()))?;
+
+        if let Some(namespace_name) = namespace_name {
+            self.define_instance(namespace_name, &instance)?;
+        }
+
+        Ok(instance)
+    }
+
+    /// Gets a copy of the definitions of the linker, as an
+    /// `ImportObject`.
+    ///
+    /// ## Example
+    ///
+    /// See the `define` method.
+    #[getter]
+    fn import_object(&self) -> ImportObject {
+        let mut import_object = ImportObject::new();
+        import_object.merge(self.import_object.inner());
+
+        import_object
+    }
+}
@@ -1,6 +1,7 @@
 mod buffer;
 mod views;
 
+pub(crate) use buffer::fill_buffer;
 pub use buffer::Buffer;
 pub use views::{
     Float32Array, Float64Array, Int16Array, Int32Array, Int64Array, Int8Array, Uint16Array,
@@ -0,0 +1,1183 @@
+use crate::{
+    errors::to_py_err,
+    instance::Instance,
+    wasmer_inner::{
+        wasmer,
+        wasmer_engines::{OpaqueMiddleware, PerModuleMiddleware},
+        wasmer_types::{FunctionIndex, GlobalIndex, ModuleInfo, SignatureIndex, TableIndex},
+    },
+};
+use loupe::{MemoryUsage, MemoryUsageTracker};
+use pyo3::{exceptions::PyRuntimeError, prelude::*};
+use std::{collections::HashMap, fmt, mem, sync::Arc};
+use wasmer::{
+    wasmparser::{Operator, Type as WpType, TypeOrFuncType as WpTypeOrFuncType},
+    ExportIndex, FunctionMiddleware, FunctionType, GlobalInit, GlobalType, LocalFunctionIndex,
+    MiddlewareError, MiddlewareReaderState, ModuleMiddleware, Mutability, TableType, Type,
+};
+use wasmer_middlewares::metering::{self, MeteringPoints};
+
+/// The name of the global exported by the modules compiled with an
+/// `Interruptible` middleware. It is not zero when the execution must
+/// be interrupted.
+pub(crate) const INTERRUPTED_GLOBAL: &str = "wasmer_interrupted";
+
+/// The names of the globals exported by the modules compiled with a
+/// `Metering` middleware, the same as with
+/// `wasmer_middlewares::Metering`, so that its `get_remaining_points`
+/// and `set_remaining_points` work.
+const REMAINING_POINTS_GLOBAL: &str = "wasmer_metering_remaining_points";
+const POINTS_EXHAUSTED_GLOBAL: &str = "wasmer_metering_points_exhausted";
+
+/// The names of all the exports added by the middlewares. They hold
+/// the state of the middlewares, not of the instance, hence they are
+/// not copied by `Instance.clone`, `Instance.migrate` and
+/// `Instance.snapshot`.
+const INTERNAL_EXPORTS: [&str; 6] = [
+    INTERRUPTED_GLOBAL,
+    REMAINING_POINTS_GLOBAL,
+    POINTS_EXHAUSTED_GLOBAL,
+    START_EXPORT,
+    TRACING_HOOK_TABLE,
+    TRACING_GLOBAL,
+];
+
+/// Checks whether `name` is the name of an export added by a
+/// middleware.
+pub(crate) fn is_internal_export(name: &str) -> bool {
+    INTERNAL_EXPORTS.contains(&name)
+}
+
+type CostFunction = Box<dyn Fn(&Operator) -> PyResult<u64> + Send + Sync>;
+
+/// The name of an operator, i.e. the name of its variant, without
+/// its immediates, e.g. `I32Add` or `Call`.
+fn operator_name(operator: &Operator) -> String {
+    let operator = format!("{:?}", operator);
+
+    operator
+        .split(|c: char| !c.is_alphanumeric())
+        .next()
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// A middleware that meters the execution of WebAssembly code, to
+/// bound it deterministically.
+///
+/// Every operator has a cost, and each instance of the compiled
+/// module starts with `limit` points. When all the points are
+/// consumed, the execution traps with a `RuntimeError`.
+///
+/// By default, every operator costs 1 point. The optional
+/// `cost_function` receives the name of an operator, e.g. `"I32Add"`
+/// or `"Call"`, and returns its cost. It is called when the module is
+/// compiled, not when it is executed. If it raises an exception, the
+/// compilation fails with a `CompileError`.
+///
+/// A `Metering` middleware is given to a compiler with the
+/// `middlewares` argument.
+///
+/// Use `get_remaining_points` and `set_remaining_points` to read and
+/// change the remaining points of an instance.
+///
+/// ## Example
+///
+/// ```py
+/// from wasmer import engine, get_remaining_points, Store, Module, Instance, Metering
+/// from wasmer_compiler_cranelift import Compiler
+///
+/// def cost_function(operator):
+///     if operator == 'I32Add':
+///         return 2
+///
+///     return 0
+///
+/// store = Store(engine.Universal(Compiler(middlewares=[Metering(10, cost_function)])))
+/// module = Module(
+///     store,
+///     """
+///     (module
+///       (func (export "sum") (param i32 i32) (result i32)
+///         local.get 0
+///         local.get 1
+///         i32.add))
+///     """
+/// )
+/// instance = Instance(module)
+///
+/// assert instance.exports.sum(1, 2) == 3
+/// assert get_remaining_points(instance) == 8
+/// ```
+#[pyclass]
+#[pyo3(text_signature = "(limit, /, cost_function)")]
+pub struct Metering {
+    inner: Arc<MeteringMiddleware>,
+    fingerprint: Option<String>,
+}
+
+#[pymethods]
+impl Metering {
+    #[new]
+    fn new(limit: u64, cost_function: Option<PyObject>) -> Self {
+        // A Python cost function cannot be described.
+        let fingerprint = match cost_function {
+            Some(_) => None,
+            None => Some(format!("metering:{}", limit)),
+        };
+        let cost_function: CostFunction = match cost_function {
+            Some(cost_function) => Box::new(move |operator| {
+                Python::with_gil(|py| {
+                    cost_function
+                        .call1(py, (operator_name(operator),))
+                        .and_then(|cost| cost.extract::<u64>(py))
+                })
+            }),
+            None => Box::new(|_| Ok(1)),
+        };
+
+        Self {
+            inner: Arc::new(MeteringMiddleware {
+                limit,
+                cost_function: Arc::new(cost_function),
+            }),
+            fingerprint,
+        }
+    }
+
+    /// Please don't use it. Internal use only.
+    #[allow(clippy::wrong_self_convention)]
+    fn into_opaque_middleware(&self) -> OpaqueMiddleware {
+        OpaqueMiddleware::raw_per_module(self.inner.clone(), self.fingerprint.clone())
+    }
+}
+
+struct MeteringMiddleware {
+    limit: u64,
+    cost_function: Arc<CostFunction>,
+}
+
+impl fmt::Debug for MeteringMiddleware {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter
+            .debug_struct("MeteringMiddleware")
+            .field("limit", &self.limit)
+            .finish()
+    }
+}
+
+/// The index of the global exported as `name` by the module described
+/// by `module_info`, once it has been transformed by `middleware`.
+fn exported_global(
+    module_info: &ModuleInfo,
+    middleware: &str,
+    name: &str,
+) -> Result<GlobalIndex, MiddlewareError> {
+    match module_info.exports.get(name) {
+        Some(ExportIndex::Global(global_index)) => Ok(*global_index),
+        _ => Err(MiddlewareError::new(
+            middleware,
+            format!("The module does not export the `{}` global", name),
+        )),
+    }
+}
+
+impl PerModuleMiddleware for MeteringMiddleware {
+    fn transform_module_info(&self, module_info: &mut ModuleInfo) {
+        let remaining_points = module_info
+            .globals
+            .push(GlobalType::new(Type::I64, Mutability::Var));
+
+        module_info
+            .global_initializers
+            .push(GlobalInit::I64Const(self.limit as i64));
+
+        module_info.exports.insert(
+            REMAINING_POINTS_GLOBAL.to_string(),
+            ExportIndex::Global(remaining_points),
+        );
+
+        let points_exhausted = module_info
+            .globals
+            .push(GlobalType::new(Type::I32, Mutability::Var));
+
+        module_info
+            .global_initializers
+            .push(GlobalInit::I32Const(0));
+
+        module_info.exports.insert(
+            POINTS_EXHAUSTED_GLOBAL.to_string(),
+            ExportIndex::Global(points_exhausted),
+        );
+    }
+
+    fn generate_function_middleware(
+        &self,
+        module_info: &ModuleInfo,
+        _: LocalFunctionIndex,
+    ) -> Result<Box<dyn FunctionMiddleware>, MiddlewareError> {
+        Ok(Box::new(MeteringFunctionMiddleware {
+            cost_function: self.cost_function.clone(),
+            remaining_points: exported_global(module_info, "metering", REMAINING_POINTS_GLOBAL)?,
+            points_exhausted: exported_global(module_info, "metering", POINTS_EXHAUSTED_GLOBAL)?,
+            accumulated_cost: 0,
+        }))
+    }
+}
+
+struct MeteringFunctionMiddleware {
+    cost_function: Arc<CostFunction>,
+    remaining_points: GlobalIndex,
+    points_exhausted: GlobalIndex,
+
+    /// The cost of the operators fed since the last check.
+    accumulated_cost: u64,
+}
+
+impl fmt::Debug for MeteringFunctionMiddleware {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter
+            .debug_struct("MeteringFunctionMiddleware")
+            .field("remaining_points", &self.remaining_points)
+            .field("points_exhausted", &self.points_exhausted)
+            .field("accumulated_cost", &self.accumulated_cost)
+            .finish()
+    }
+}
+
+impl FunctionMiddleware for MeteringFunctionMiddleware {
+    fn feed<'a>(
+        &mut self,
+        operator: Operator<'a>,
+        state: &mut MiddlewareReaderState<'a>,
+    ) -> Result<(), MiddlewareError> {
+        // The cost is added before the check, so that e.g. a `Call`
+        // is paid before the call.
+        let cost = (self.cost_function)(&operator)
+            .map_err(|error| MiddlewareError::new("metering", error.to_string()))?;
+
+        self.accumulated_cost = self.accumulated_cost.saturating_add(cost);
+
+        // The operators ending a basic block: the points of the block
+        // are checked and consumed.
+        let ends_block = matches!(
+            operator,
+            Operator::Loop { .. }
+                | Operator::End
+                | Operator::Else
+                | Operator::Br { .. }
+                | Operator::BrTable { .. }
+                | Operator::BrIf { .. }
+                | Operator::Call { .. }
+                | Operator::CallIndirect { .. }
+                | Operator::Return
+        );
+
+        if ends_block && self.accumulated_cost > 0 {
+            let remaining_points = self.remaining_points.as_u32();
+            let cost = self.accumulated_cost as i64;
+
+            state.extend(&[
+                // Trap if the remaining points are fewer than the cost.
+                Operator::GlobalGet {
+                    global_index: remaining_points,
+                },
+                Operator::I64Const { value: cost },
+                Operator::I64LtU,
+                Operator::If {
+                    ty: WpTypeOrFuncType::Type(WpType::EmptyBlockType),
+                },
+                Operator::I32Const { value: 1 },
+                Operator::GlobalSet {
+                    global_index: self.points_exhausted.as_u32(),
+                },
+                Operator::Unreachable,
+                Operator::End,
+                // Consume the points.
+                Operator::GlobalGet {
+                    global_index: remaining_points,
+                },
+                Operator::I64Const { value: cost },
+                Operator::I64Sub,
+                Operator::GlobalSet {
+                    global_index: remaining_points,
+                },
+            ]);
+
+            self.accumulated_cost = 0;
+        }
+
+        state.push_operator(operator);
+
+        Ok(())
+    }
+}
+
+/// A middleware that calls a Python function for every operator of
+/// every function of the compiled module, to inspect it.
+///
+/// The `feed` function receives the index of the function (local to
+/// the module, i.e. imported functions are not counted) and the name
+/// of the operator, e.g. `"I32Add"` or `"Call"`. It is called when
+/// the module is compiled, possibly from several threads. If it
+/// raises an exception, the compilation fails with a `RuntimeError`.
+///
+/// The operators are left unchanged.
+///
+/// A `Middleware` is given to a compiler with the `middlewares`
+/// argument.
+///
+/// ## Example
+///
+/// ```py
+/// from wasmer import engine, Store, Module, Middleware
+/// from wasmer_compiler_cranelift import Compiler
+///
+/// class Recorder:
+///     def __init__(self):
+///         self.operators = []
+///
+///     def feed(self, function_index, operator):
+///         self.operators.append((function_index, operator))
+///
+/// recorder = Recorder()
+/// store = Store(engine.Universal(Compiler(middlewares=[Middleware(recorder.feed)])))
+/// module = Module(
+///     store,
+///     """
+///     (module
+///       (func (param i32 i32) (result i32)
+///         local.get 0
+///         local.get 1
+///         i32.add))
+///     """
+/// )
+///
+/// assert recorder.operators == [(0, 'LocalGet'), (0, 'LocalGet'), (0, 'I32Add'), (0, 'End')]
+/// ```
+#[pyclass]
+#[pyo3(text_signature = "(feed)")]
+pub struct Middleware {
+    inner: Arc<PythonMiddleware>,
+}
+
+#[pymethods]
+impl Middleware {
+    #[new]
+    fn new(feed: PyObject) -> Self {
+        Self {
+            inner: Arc::new(PythonMiddleware { feed }),
+        }
+    }
+
+    /// Please don't use it. Internal use only.
+    #[allow(clippy::wrong_self_convention)]
+    fn into_opaque_middleware(&self) -> OpaqueMiddleware {
+        OpaqueMiddleware::raw_new(self.inner.clone(), None)
+    }
+}
+
+struct PythonMiddleware {
+    feed: PyObject,
+}
+
+impl fmt::Debug for PythonMiddleware {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.debug_struct("PythonMiddleware").finish()
+    }
+}
+
+impl MemoryUsage for PythonMiddleware {
+    fn size_of_val(&self, _: &mut dyn MemoryUsageTracker) -> usize {
+        mem::size_of_val(self)
+    }
+}
+
+impl ModuleMiddleware for PythonMiddleware {
+    fn generate_function_middleware(
+        &self,
+        local_function_index: LocalFunctionIndex,
+    ) -> Box<dyn FunctionMiddleware> {
+        Box::new(PythonFunctionMiddleware {
+            feed: self.feed.clone(),
+            local_function_index,
+        })
+    }
+}
+
+struct PythonFunctionMiddleware {
+    feed: PyObject,
+    local_function_index: LocalFunctionIndex,
+}
+
+impl fmt::Debug for PythonFunctionMiddleware {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter
+            .debug_struct("PythonFunctionMiddleware")
+            .field("local_function_index", &self.local_function_index)
+            .finish()
+    }
+}
+
+impl FunctionMiddleware for PythonFunctionMiddleware {
+    fn feed<'a>(
+        &mut self,
+        operator: Operator<'a>,
+        state: &mut MiddlewareReaderState<'a>,
+    ) -> Result<(), MiddlewareError> {
+        Python::with_gil(|py| {
+            self.feed.call1(
+                py,
+                (self.local_function_index.as_u32(), operator_name(&operator)),
+            )
+        })
+        .map_err(|error| MiddlewareError::new("python", error.to_string()))?;
+
+        state.push_operator(operator);
+
+        Ok(())
+    }
+}
+
+/// A middleware that makes the execution of WebAssembly code
+/// interruptible, to bound it in time.
+///
+/// The compiled code checks whether it must be interrupted when a
+/// function is entered and at every iteration of a loop. An
+/// interrupted call raises an `ExecutionTimeout` exception, which is
+/// a `RuntimeError`.
+///
+/// A call is interrupted either when it exceeds its `timeout`, given
+/// in seconds to an exported function, e.g.
+/// `instance.exports.run(timeout=0.1)`, or from another thread with
+/// an `InterruptHandle`, see `interrupt_handle`. A pending
+/// interruption belongs to the instance: it is neither copied by
+/// `Instance.clone` nor recorded by `Instance.snapshot`.
+///
+/// With a store created with `keyboard_interrupt=True`, see `Store`,
+/// a call made from the main thread is also interrupted by Ctrl-C,
+/// i.e. `SIGINT`, on Unix: the Python handler of `SIGINT` then runs,
+/// and raises `KeyboardInterrupt` by default.
+///
+/// The start function of the module is deferred like with a
+/// `DeferredStart` middleware, so that it is interruptible too: it
+/// is bounded in time by the `timeout` argument of `Instance`.
+///
+/// An `Interruptible` middleware is given to a compiler with the
+/// `middlewares` argument.
+///
+/// ## Example
+///
+/// ```py
+/// from wasmer import engine, Store, Module, Instance, Interruptible, ExecutionTimeout
+/// from wasmer_compiler_cranelift import Compiler
+///
+/// store = Store(engine.Universal(Compiler(middlewares=[Interruptible()])))
+/// module = Module(
+///     store,
+///     """
+///     (module
+///       (func (export "run")
+///         (loop
+///           br 0)))
+///     """
+/// )
+/// instance = Instance(module)
+///
+/// try:
+///     instance.exports.run(timeout=0.01)
+/// except ExecutionTimeout:
+///     pass
+/// else:
+///     assert False
+/// ```
+#[pyclass]
+#[pyo3(text_signature = "()")]
+pub struct Interruptible {
+    inner: Arc<InterruptibleMiddleware>,
+}
+
+#[pymethods]
+impl Interruptible {
+    #[new]
+    fn new() -> Self {
+        Self {
+            inner: Arc::new(InterruptibleMiddleware),
+        }
+    }
+
+    /// Please don't use it. Internal use only.
+    #[allow(clippy::wrong_self_convention)]
+    fn into_opaque_middleware(&self) -> OpaqueMiddleware {
+        OpaqueMiddleware::raw_per_module(self.inner.clone(), Some("interruptible".to_string()))
+    }
+}
+
+#[derive(Debug)]
+struct InterruptibleMiddleware;
+
+impl PerModuleMiddleware for InterruptibleMiddleware {
+    fn transform_module_info(&self, module_info: &mut ModuleInfo) {
+        let global_index = module_info
+            .globals
+            .push(GlobalType::new(Type::I32, Mutability::Var));
+
+        module_info
+            .global_initializers
+            .push(GlobalInit::I32Const(0));
+
+        module_info.exports.insert(
+            INTERRUPTED_GLOBAL.to_string(),
+            ExportIndex::Global(global_index),
+        );
+
+        defer_start_function(module_info);
+    }
+
+    fn generate_function_middleware(
+        &self,
+        module_info: &ModuleInfo,
+        _: LocalFunctionIndex,
+    ) -> Result<Box<dyn FunctionMiddleware>, MiddlewareError> {
+        Ok(Box::new(InterruptibleFunctionMiddleware {
+            global_index: exported_global(module_info, "interruptible", INTERRUPTED_GLOBAL)?,
+            entered: false,
+        }))
+    }
+}
+
+#[derive(Debug)]
+struct InterruptibleFunctionMiddleware {
+    global_index: GlobalIndex,
+    entered: bool,
+}
+
+impl InterruptibleFunctionMiddleware {
+    /// Traps if the `INTERRUPTED_GLOBAL` global is not zero.
+    fn check<'a>(&self) -> [Operator<'a>; 4] {
+        [
+            Operator::GlobalGet {
+                global_index: self.global_index.as_u32(),
+            },
+            Operator::If {
+                ty: WpTypeOrFuncType::Type(WpType::EmptyBlockType),
+            },
+            Operator::Unreachable,
+            Operator::End,
+        ]
+    }
+}
+
+impl FunctionMiddleware for InterruptibleFunctionMiddleware {
+    fn feed<'a>(
+        &mut self,
+        operator: Operator<'a>,
+        state: &mut MiddlewareReaderState<'a>,
+    ) -> Result<(), MiddlewareError> {
+        if !self.entered {
+            self.entered = true;
+            state.extend(&self.check());
+        }
+
+        let is_loop = matches!(operator, Operator::Loop { .. });
+
+        state.push_operator(operator);
+
+        if is_loop {
+            state.extend(&self.check());
+        }
+
+        Ok(())
+    }
+}
+
+/// A handle to interrupt the calls to the functions of an instance
+/// compiled with an `Interruptible` middleware, e.g. from another
+/// thread. It is built by `interrupt_handle`.
+///
+/// ## Example
+///
+/// ```py
+/// from wasmer import engine, interrupt_handle, Store, Module, Instance, Interruptible, ExecutionTimeout
+/// from wasmer_compiler_cranelift import Compiler
+/// import threading
+///
+/// store = Store(engine.Universal(Compiler(middlewares=[Interruptible()])))
+/// module = Module(
+///     store,
+///     """
+///     (module
+///       (func (export "run")
+///         (loop
+///           br 0)))
+///     """
+/// )
+/// instance = Instance(module)
+/// handle = interrupt_handle(instance)
+///
+/// threading.Timer(0.01, handle.interrupt).start()
+///
+/// try:
+///     instance.exports.run()
+/// except ExecutionTimeout:
+///     pass
+/// else:
+///     assert False
+/// ```
+#[pyclass]
+pub struct InterruptHandle {
+    interrupted: wasmer::Global,
+}
+
+#[pymethods]
+impl InterruptHandle {
+    /// Interrupts the running call. If no call is running, the next
+    /// call is interrupted.
+    #[pyo3(text_signature = "($self)")]
+    fn interrupt(&self) -> PyResult<()> {
+        self.interrupted
+            .set(wasmer::Value::I32(1))
+            .map_err(to_py_err::<PyRuntimeError, _>)
+    }
+}
+
+pub fn interrupt_handle(instance: &Instance) -> PyResult<InterruptHandle> {
+    Ok(InterruptHandle {
+        interrupted: instance
+            .inner()?
+            .exports
+            .get_global(INTERRUPTED_GLOBAL)
+            .map_err(|_| {
+                to_py_err::<PyRuntimeError, _>(
+                    "The instance has not been compiled with an `Interruptible` middleware",
+                )
+            })?
+            .clone(),
+    })
+}
+
+/// The name of the function exported by the modules compiled with a
+/// `DeferredStart` middleware, in place of their start function.
+pub(crate) const START_EXPORT: &str = "wasmer_start";
+
+/// A `DeferredStart` middleware removes the start function of a
+/// module, and exports it instead, so that its instances can be
+/// created with `Instance(module, run_start=False)`: the memories,
+/// tables and globals of the instance are initialised, but its start
+/// function only runs when `Instance.run_start` is called, e.g.
+/// after the imported memory has been populated. By default, the
+/// start function still runs when the instance is created. An
+/// `Interruptible` middleware defers the start function as well.
+///
+/// ## Example
+///
+/// ```py
+/// from wasmer import engine, Store, Module, Instance, DeferredStart
+/// from wasmer_compiler_cranelift import Compiler
+///
+/// store = Store(engine.Universal(Compiler(middlewares=[DeferredStart()])))
+/// module = Module(
+///     store,
+///     """
+///     (module
+///       (global $started (export "started") (mut i32) (i32.const 0))
+///       (func $start
+///         i32.const 1
+///         global.set $started)
+///       (start $start))
+///     """
+/// )
+///
+/// instance = Instance(module, run_start=False)
+/// assert instance.exports.started.value == 0
+///
+/// instance.run_start()
+/// assert instance.exports.started.value == 1
+/// ```
+#[pyclass]
+#[pyo3(text_signature = "()")]
+pub struct DeferredStart {
+    inner: Arc<DeferredStartMiddleware>,
+}
+
+#[pymethods]
+impl DeferredStart {
+    #[new]
+    fn new() -> Self {
+        Self {
+            inner: Arc::new(DeferredStartMiddleware),
+        }
+    }
+
+    /// Please don't use it. Internal use only.
+    #[allow(clippy::wrong_self_convention)]
+    fn into_opaque_middleware(&self) -> OpaqueMiddleware {
+        OpaqueMiddleware::raw_new(self.inner.clone(), Some("deferred-start".to_string()))
+    }
+}
+
+#[derive(Debug)]
+struct DeferredStartMiddleware;
+
+impl MemoryUsage for DeferredStartMiddleware {
+    fn size_of_val(&self, _: &mut dyn MemoryUsageTracker) -> usize {
+        mem::size_of_val(self)
+    }
+}
+
+impl ModuleMiddleware for DeferredStartMiddleware {
+    fn generate_function_middleware(&self, _: LocalFunctionIndex) -> Box<dyn FunctionMiddleware> {
+        Box::new(DeferredStartFunctionMiddleware)
+    }
+
+    fn transform_module_info(&self, module_info: &mut ModuleInfo) {
+        defer_start_function(module_info);
+    }
+}
+
+/// Replaces the start function of the module by the `START_EXPORT`
+/// export, if it has not been replaced by another middleware already.
+fn defer_start_function(module_info: &mut ModuleInfo) {
+    if let Some(start_function) = module_info.start_function.take() {
+        module_info.exports.insert(
+            START_EXPORT.to_string(),
+            ExportIndex::Function(start_function),
+        );
+    }
+}
+
+/// The functions are left untouched.
+#[derive(Debug)]
+struct DeferredStartFunctionMiddleware;
+
+impl FunctionMiddleware for DeferredStartFunctionMiddleware {}
+
+/// The names of the exports added to the modules compiled with a
+/// `Tracing` middleware: a table holding the hook called on the
+/// entry and the exit of the functions, and a global that is not zero
+/// when tracing is enabled. The Python function is owned by the hook,
+/// itself owned by the `Instance`, since a table doesn't own the
+/// environments of its functions.
+pub(crate) const TRACING_HOOK_TABLE: &str = "wasmer_tracing_hook";
+pub(crate) const TRACING_GLOBAL: &str = "wasmer_tracing";
+
+/// A `Tracing` middleware instruments the functions of a module to
+/// call a Python function, set on an instance with `set_trace`, on
+/// the entry and the exit of each of them. The Python function
+/// receives the event, i.e. `"enter"` or `"exit"`, the index of the
+/// WebAssembly function, including the imported functions, and its
+/// name from the name section, or `None`. An exception raised by the
+/// Python function aborts the call.
+///
+/// Tracing is disabled until a Python function is set, and can be
+/// disabled again with `set_trace(instance, None)`; a disabled hook
+/// costs a load and a branch per call. The exit is not traced when a
+/// function traps.
+///
+/// ## Example
+///
+/// ```py
+/// from wasmer import engine, set_trace, Store, Module, Instance, Tracing
+/// from wasmer_compiler_cranelift import Compiler
+///
+/// store = Store(engine.Universal(Compiler(middlewares=[Tracing()])))
+/// module = Module(
+///     store,
+///     """
+///     (module
+///       (func $double (param i32) (result i32)
+///         local.get 0
+///         i32.const 2
+///         i32.mul)
+///       (func $run (export "run") (result i32)
+///         i32.const 21
+///         call $double))
+///     """
+/// )
+/// instance = Instance(module)
+///
+/// class Recorder:
+///     def __init__(self):
+///         self.events = []
+///
+///     def trace(self, event, function_index, function_name):
+///         self.events.append((event, function_name))
+///
+/// recorder = Recorder()
+/// set_trace(instance, recorder.trace)
+///
+/// assert instance.exports.run() == 42
+/// assert recorder.events == [
+///     ("enter", "run"),
+///     ("enter", "double"),
+///     ("exit", "double"),
+///     ("exit", "run"),
+/// ]
+///
+/// set_trace(instance, None)
+/// instance.exports.run()
+///
+/// assert len(recorder.events) == 4
+/// ```
+#[pyclass]
+#[pyo3(text_signature = "()")]
+pub struct Tracing {
+    inner: Arc<TracingMiddleware>,
+}
+
+#[pymethods]
+impl Tracing {
+    #[new]
+    fn new() -> Self {
+        Self {
+            inner: Arc::new(TracingMiddleware),
+        }
+    }
+
+    /// Please don't use it. Internal use only.
+    #[allow(clippy::wrong_self_convention)]
+    fn into_opaque_middleware(&self) -> OpaqueMiddleware {
+        OpaqueMiddleware::raw_per_module(self.inner.clone(), Some("tracing".to_string()))
+    }
+}
+
+/// What the `Tracing` middleware adds to the module being compiled.
+#[derive(Debug, Clone)]
+struct TracedModule {
+    /// The signature of the hook: the event, and the function index.
+    hook_signature: SignatureIndex,
+    hook_table: TableIndex,
+    tracing_global: GlobalIndex,
+}
+
+impl TracedModule {
+    /// Finds what the `Tracing` middleware has added to the module
+    /// described by `module_info`.
+    fn new(module_info: &ModuleInfo) -> Result<Self, MiddlewareError> {
+        let hook_table = match module_info.exports.get(TRACING_HOOK_TABLE) {
+            Some(ExportIndex::Table(table_index)) => *table_index,
+            _ => {
+                return Err(MiddlewareError::new(
+                    "tracing",
+                    format!(
+                        "The module does not export the `{}` table",
+                        TRACING_HOOK_TABLE
+                    ),
+                ))
+            }
+        };
+
+        // The hook signature is the last one of its type, since the
+        // other middlewares don't add signatures.
+        let hook_type = hook_type();
+        let hook_signature = module_info
+            .signatures
+            .iter()
+            .rev()
+            .find(|(_, signature)| **signature == hook_type)
+            .map(|(index, _)| index)
+            .ok_or_else(|| MiddlewareError::new("tracing", "The module has no hook signature"))?;
+
+        Ok(Self {
+            hook_signature,
+            hook_table,
+            tracing_global: exported_global(module_info, "tracing", TRACING_GLOBAL)?,
+        })
+    }
+}
+
+/// The type of the hook, see `TracedModule::hook_signature`.
+fn hook_type() -> FunctionType {
+    FunctionType::new([Type::I32, Type::I32], [])
+}
+
+#[derive(Debug)]
+struct TracingMiddleware;
+
+fn wasmparser_type(ty: Type) -> WpType {
+    match ty {
+        Type::I32 => WpType::I32,
+        Type::I64 => WpType::I64,
+        Type::F32 => WpType::F32,
+        Type::F64 => WpType::F64,
+        Type::V128 => WpType::V128,
+        Type::FuncRef => WpType::FuncRef,
+        Type::ExternRef => WpType::ExternRef,
+    }
+}
+
+impl PerModuleMiddleware for TracingMiddleware {
+    fn transform_module_info(&self, module_info: &mut ModuleInfo) {
+        module_info.signatures.push(hook_type());
+
+        let hook_table = module_info
+            .tables
+            .push(TableType::new(Type::FuncRef, 1, Some(1)));
+
+        let tracing_global = module_info
+            .globals
+            .push(GlobalType::new(Type::I32, Mutability::Var));
+
+        module_info
+            .global_initializers
+            .push(GlobalInit::I32Const(0));
+
+        module_info.exports.insert(
+            TRACING_HOOK_TABLE.to_string(),
+            ExportIndex::Table(hook_table),
+        );
+        module_info.exports.insert(
+            TRACING_GLOBAL.to_string(),
+            ExportIndex::Global(tracing_global),
+        );
+    }
+
+    fn generate_function_middleware(
+        &self,
+        module_info: &ModuleInfo,
+        local_function_index: LocalFunctionIndex,
+    ) -> Result<Box<dyn FunctionMiddleware>, MiddlewareError> {
+        let module = TracedModule::new(module_info)?;
+        let function_index = module_info.func_index(local_function_index);
+        let results = module_info.signatures[module_info.functions[function_index]].results();
+
+        // The compilers only know the block types of the type
+        // section, hence a body with several results is wrapped only
+        // if the module has a type without parameters returning them.
+        let body_type = match results {
+            [] => Some(WpTypeOrFuncType::Type(WpType::EmptyBlockType)),
+            [result] => Some(WpTypeOrFuncType::Type(wasmparser_type(*result))),
+            _ => module_info
+                .signatures
+                .iter()
+                .take(module.hook_signature.as_u32() as usize)
+                .find(|(_, signature)| {
+                    signature.params().is_empty() && signature.results() == results
+                })
+                .map(|(index, _)| WpTypeOrFuncType::FuncType(index.as_u32())),
+        };
+
+        Ok(Box::new(TracingFunctionMiddleware {
+            function_index: function_index.as_u32(),
+            body_type,
+            module,
+            depth: None,
+        }))
+    }
+}
+
+#[derive(Debug)]
+struct TracingFunctionMiddleware {
+    module: TracedModule,
+    function_index: u32,
+    body_type: Option<WpTypeOrFuncType>,
+
+    /// The depth of the blocks in the body of the function, once it
+    /// has been entered.
+    depth: Option<usize>,
+}
+
+impl TracingFunctionMiddleware {
+    /// Calls the hook with `event`, i.e. 0 on entry, and 1 on exit,
+    /// if tracing is enabled.
+    fn call_hook<'a>(&self, event: i32) -> [Operator<'a>; 7] {
+        [
+            Operator::GlobalGet {
+                global_index: self.module.tracing_global.as_u32(),
+            },
+            Operator::If {
+                ty: WpTypeOrFuncType::Type(WpType::EmptyBlockType),
+            },
+            Operator::I32Const { value: event },
+            Operator::I32Const {
+                value: self.function_index as i32,
+            },
+            Operator::I32Const { value: 0 },
+            Operator::CallIndirect {
+                index: self.module.hook_signature.as_u32(),
+                table_index: self.module.hook_table.as_u32(),
+            },
+            Operator::End,
+        ]
+    }
+}
+
+impl FunctionMiddleware for TracingFunctionMiddleware {
+    fn feed<'a>(
+        &mut self,
+        operator: Operator<'a>,
+        state: &mut MiddlewareReaderState<'a>,
+    ) -> Result<(), MiddlewareError> {
+        // The body is wrapped in a block, so that the branches to the
+        // label of the function exit through the hook. Otherwise, only
+        // the unconditional branches to it are traced.
+        let depth = match self.depth {
+            Some(depth) => depth,
+            None => {
+                state.extend(&self.call_hook(0));
+
+                if let Some(ty) = self.body_type {
+                    state.push_operator(Operator::Block { ty });
+                }
+
+                0
+            }
+        };
+
+        self.depth = Some(match operator {
+            Operator::Block { .. }
+            | Operator::Loop { .. }
+            | Operator::If { .. }
+            | Operator::Try { .. } => depth + 1,
+            Operator::End if depth == 0 => {
+                if self.body_type.is_some() {
+                    state.push_operator(Operator::End);
+                }
+
+                state.extend(&self.call_hook(1));
+
+                0
+            }
+            Operator::End => depth - 1,
+            Operator::Return => {
+                state.extend(&self.call_hook(1));
+
+                depth
+            }
+            Operator::Br { relative_depth }
+                if self.body_type.is_none() && relative_depth as usize == depth =>
+            {
+                state.extend(&self.call_hook(1));
+
+                depth
+            }
+            _ => depth,
+        });
+
+        state.push_operator(operator);
+
+        Ok(())
+    }
+}
+
+/// A Python function set by `set_trace`, with the names of the
+/// functions of the module: the environment of the hook.
+#[derive(Clone)]
+struct Tracer {
+    callback: Arc<PyObject>,
+    function_names: Arc<HashMap<FunctionIndex, String>>,
+}
+
+impl wasmer::WasmerEnv for Tracer {}
+
+/// The hook of the `Tracing` middleware, calling the Python function.
+fn trace(tracer: &Tracer, event: i32, function_index: u32) -> Result<(), PyErr> {
+    let function_name = tracer
+        .function_names
+        .get(&FunctionIndex::from_u32(function_index));
+
+    Python::with_gil(|py| {
+        tracer.callback.call1(
+            py,
+            (
+                if event == 0 { "enter" } else { "exit" },
+                function_index,
+                function_name,
+            ),
+        )
+    })?;
+
+    Ok(())
+}
+
+fn tracing_exports(
+    instance: &wasmer::Instance,
+) -> Result<(&wasmer::Table, &wasmer::Global), wasmer::ExportError> {
+    Ok((
+        instance.exports.get_table(TRACING_HOOK_TABLE)?,
+        instance.exports.get_global(TRACING_GLOBAL)?,
+    ))
+}
+
+/// Sets the Python function called on the entry and the exit of the
+/// functions of `instance`, or disables tracing with `None`.
+pub fn set_trace(instance: &Instance, callback: Option<PyObject>) -> PyResult<()> {
+    let inner = instance.inner()?;
+    let (hook_table, tracing_global) = tracing_exports(inner).map_err(|_| {
+        to_py_err::<PyRuntimeError, _>(
+            "The instance has not been compiled with a `Tracing` middleware",
+        )
+    })?;
+
+    forget_trace(inner);
+
+    let hook = match callback {
+        Some(callback) => {
+            let hook = wasmer::Function::new_native_with_env(
+                hook_table.store(),
+                Tracer {
+                    callback: Arc::new(callback),
+                    function_names: Arc::new(inner.module().info().function_names.clone()),
+                },
+                trace,
+            );
+
+            hook_table
+                .set(0, wasmer::Value::FuncRef(Some(hook.clone())))
+                .map_err(to_py_err::<PyRuntimeError, _>)?;
+            tracing_global
+                .set(wasmer::Value::I32(1))
+                .map_err(to_py_err::<PyRuntimeError, _>)?;
+
+            Some(hook)
+        }
+        None => None,
+    };
+
+    // The previous hook, if any, is dropped once it is unreachable.
+    instance.set_tracing_hook(hook);
+
+    Ok(())
+}
+
+/// Disables tracing on `instance`, and removes the hook from its
+/// table, e.g. before the hook is dropped with the instance.
+pub(crate) fn forget_trace(instance: &wasmer::Instance) {
+    if let Ok((hook_table, tracing_global)) = tracing_exports(instance) {
+        let _ = tracing_global.set(wasmer::Value::I32(0));
+        let _ = hook_table.set(0, wasmer::Value::FuncRef(None));
+    }
+}
+
+/// Check that the instance has been compiled with a `Metering`
+/// middleware.
+fn check_metered(instance: &Instance) -> PyResult<()> {
+    let exports = &instance.inner()?.exports;
+
+    if exports.get_global(REMAINING_POINTS_GLOBAL).is_err()
+        || exports.get_global(POINTS_EXHAUSTED_GLOBAL).is_err()
+    {
+        return Err(to_py_err::<PyRuntimeError, _>(
+            "The instance has not been compiled with a `Metering` middleware",
+        ));
+    }
+
+    Ok(())
+}
+
+pub fn get_remaining_points(instance: &Instance) -> PyResult<u64> {
+    check_metered(instance)?;
+
+    Ok(match metering::get_remaining_points(instance.inner()?) {
+        MeteringPoints::Remaining(points) => points,
+        MeteringPoints::Exhausted => 0,
+    })
+}
+
+pub fn set_remaining_points(instance: &Instance, points: u64) -> PyResult<()> {
+    check_metered(instance)?;
+
+    metering::set_remaining_points(instance.inner()?, points);
+
+    Ok(())
+}
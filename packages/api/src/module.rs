@@ -1,10 +1,26 @@
-use crate::{errors::to_py_err, store::Store, types, wasmer_inner::wasmer};
+use crate::{
+    cache::Cache,
+    errors::{to_py_err, CompileError, WasmerError},
+    middlewares, perf,
+    store::Store,
+    types,
+    wasmer_inner::{wasmer, wasmer_engines::ProgressCallback},
+};
+use memmap2::Mmap;
 use pyo3::{
-    exceptions::{PyRuntimeError, PyTypeError},
+    buffer::PyBuffer,
+    class::basic::PyObjectProtocol,
+    exceptions::PyTypeError,
     prelude::*,
     types::{PyAny, PyBytes, PyList, PyString},
 };
-use std::convert::TryInto;
+use std::{
+    convert::TryInto,
+    fs::File,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
 
 /// A WebAssembly module contains stateless WebAssembly code that has
 /// already been compiled and can be instantiated multiple times.
@@ -34,16 +50,110 @@ use std::convert::TryInto;
 /// # Let's compile WebAssembly from WAT.
 /// module = Module(store, '(module)')
 /// ```
-#[pyclass(unsendable)]
-#[pyo3(text_signature = "(store, bytes)")]
+///
+/// The bytes can also be read from a file-like object, e.g. an open
+/// file or the body of an HTTP response, with a `read` method. Wasmer
+/// compiles a module as a whole though: the file is read until its
+/// end before the compilation starts, and the download can't overlap
+/// with the compilation.
+///
+/// ```py
+/// from wasmer import Store, Module
+///
+/// with open('tests/tests.wasm', 'rb') as file:
+///     module = Module(Store(), file)
+/// ```
+///
+/// An optional `Cache` can be given with the `cache` keyword
+/// argument, so that the compiled module is stored on disk and
+/// reused on subsequent constructions. See `Cache` to learn more.
+///
+/// An optional `progress` callable is called with the number of
+/// compiled functions, and the total number of functions, each time
+/// a function has been compiled, from the compiler threads. A
+/// function is counted once it has left the compiler middlewares,
+/// which may be before its machine code is generated, e.g. with
+/// LLVM. An exception raised by `progress` is raised once the
+/// compilation is over. The modules compiled by the same engine with
+/// a `progress` are compiled one at a time.
+///
+/// ```py
+/// from wasmer import Store, Module
+///
+/// class Recorder:
+///     def __init__(self):
+///         self.steps = []
+///
+///     def progress(self, compiled, total):
+///         self.steps.append((compiled, total))
+///
+/// recorder = Recorder()
+/// module = Module(Store(), '(module (func) (func) (func))', progress=recorder.progress)
+///
+/// assert recorder.steps == [(1, 3), (2, 3), (3, 3)]
+/// assert module.compile_report.functions == 3
+/// ```
+///
+/// A module can be pickled, e.g. to be sent to the workers of a
+/// `multiprocessing` pool, or cached with `joblib`, without being
+/// compiled again: it is pickled with `Module.serialize`, and
+/// unpickled with `Module.deserialize`, in a new store whose engine,
+/// `engine.Universal` or `engine.Dylib`, can load the artifact,
+/// without a compiler. The settings of the original store, e.g. its
+/// tunables or its middlewares, are not pickled. Like
+/// `Module.deserialize`, unpickling a module is **unsafe** with
+/// untrusted data, and the artifact must target the host.
+///
+/// ```py
+/// from wasmer import Store, Module, Instance
+/// import pickle
+///
+/// module = Module(Store(), '(module (func (export "answer") (result i32) i32.const 42))')
+/// module = pickle.loads(pickle.dumps(module))
+///
+/// assert Instance(module).exports.answer() == 42
+/// ```
+#[pyclass]
+#[pyo3(text_signature = "(store, bytes, cache, *, progress)")]
 pub struct Module {
     inner: wasmer::Module,
+    compile_report: Option<CompileReport>,
 }
 
 impl Module {
+    pub(crate) fn raw_new(inner: wasmer::Module) -> Self {
+        Self {
+            inner,
+            compile_report: None,
+        }
+    }
+
     pub(crate) fn inner(&self) -> &wasmer::Module {
         &self.inner
     }
+
+    /// Compiles `bytes`, or loads them from `cache`, see `Module`.
+    fn compile_with(
+        py: Python,
+        store: &Store,
+        bytes: &[u8],
+        cache: Option<&PyCell<Cache>>,
+        progress: Option<PyObject>,
+    ) -> PyResult<Self> {
+        let start = Instant::now();
+        let module = match cache {
+            Some(cache) => cache
+                .borrow_mut()
+                .load_or_compile(py, store, bytes, progress)?,
+            None => compile(py, store, bytes, progress)?,
+        };
+        let compile_report = CompileReport::new(&module, start.elapsed());
+
+        Ok(Module {
+            inner: module,
+            compile_report: Some(compile_report),
+        })
+    }
 }
 
 #[pymethods]
@@ -55,6 +165,10 @@ impl Module {
     /// WebAssembly features in the `Store` engine to assure deterministic
     /// validation of the `Module`.
     ///
+    /// It returns a `ValidationResult`, which is truthy if the bytes
+    /// are valid, and otherwise describes the error. See
+    /// `ValidationResult` to learn more.
+    ///
     /// ## Example
     ///
     /// ```py
@@ -62,33 +176,101 @@ impl Module {
     ///
     /// assert Module.validate(Store(), wasm_bytes)
     /// ```
-    #[pyo3(text_signature = "(bytes)")]
+    #[pyo3(text_signature = "(store, bytes)")]
     #[staticmethod]
-    fn validate(store: &Store, bytes: &PyAny) -> bool {
-        match bytes.downcast::<PyBytes>() {
-            Ok(bytes) => wasmer::Module::validate(store.inner(), bytes.as_bytes()).is_ok(),
-            _ => false,
-        }
+    fn validate(store: &Store, bytes: &PyAny) -> PyResult<ValidationResult> {
+        Ok(match bytes.downcast::<PyBytes>() {
+            Ok(bytes) => {
+                let bytes = bytes.as_bytes();
+
+                match wasmer::Module::validate(store.inner()?, bytes) {
+                    Ok(()) => ValidationResult::valid(),
+                    Err(error) => ValidationResult::invalid(error, bytes),
+                }
+            }
+            _ => ValidationResult {
+                valid: false,
+                message: Some("`Module.validate` accepts Wasm bytes".to_string()),
+                offset: None,
+                section: None,
+            },
+        })
     }
 
     #[new]
-    fn new(store: &Store, bytes: &PyAny) -> PyResult<Self> {
-        let store = store.inner();
-
-        // Read the bytes as if there were real bytes or a WAT string.
-        let module = if let Ok(bytes) = bytes.downcast::<PyBytes>() {
-            wasmer::Module::new(store, bytes.as_bytes())
+    #[args(cache = "None", "*", progress = "None")]
+    fn new(
+        py: Python,
+        store: &Store,
+        bytes: &PyAny,
+        cache: Option<&PyCell<Cache>>,
+        progress: Option<PyObject>,
+    ) -> PyResult<Self> {
+        // Read the bytes as if there were real bytes, a WAT string,
+        // or a file.
+        let read_bytes;
+        let bytes = if let Ok(bytes) = bytes.downcast::<PyBytes>() {
+            bytes.as_bytes()
         } else if let Ok(string) = bytes.downcast::<PyString>() {
-            wasmer::Module::new(store, string.to_str()?.as_bytes())
+            string.to_str()?.as_bytes()
+        } else if bytes.hasattr("read")? {
+            read_bytes = read_file(bytes)?;
+            read_bytes.as_slice()
         } else {
             return Err(to_py_err::<PyTypeError, _>(
-                "`Module` accepts Wasm bytes or a WAT string",
+                "`Module` accepts Wasm bytes, a WAT string, or a readable file-like object",
             ));
         };
 
-        Ok(Module {
-            inner: module.map_err(to_py_err::<PyRuntimeError, _>)?,
-        })
+        Module::compile_with(py, store, bytes, cache, progress)
+    }
+
+    /// Compiles a module from the file at `path`, holding WebAssembly
+    /// bytes or WAT. The arguments `cache` and `progress` have the
+    /// same meaning as for `Module`.
+    ///
+    /// Unlike `Module(store, open(path, 'rb').read())`, the file is
+    /// memory-mapped rather than read in Python bytes, so a large
+    /// module is not held twice in memory while it is compiled. The
+    /// file must not be modified in the meantime.
+    ///
+    /// ## Example
+    ///
+    /// ```py
+    /// from wasmer import Store, Module
+    ///
+    /// module = Module.from_file(Store(), 'tests/tests.wasm')
+    ///
+    /// assert module.compile_report.functions > 0
+    /// ```
+    #[pyo3(text_signature = "(store, path, /, cache, *, progress)")]
+    #[staticmethod]
+    #[args(cache = "None", "*", progress = "None")]
+    fn from_file(
+        py: Python,
+        store: &Store,
+        path: PathBuf,
+        cache: Option<&PyCell<Cache>>,
+        progress: Option<PyObject>,
+    ) -> PyResult<Self> {
+        let file = File::open(&path)?;
+
+        // SAFETY: the file is documented to not be modified while
+        // the module is compiled.
+        let bytes = unsafe { Mmap::map(&file)? };
+
+        Module::compile_with(py, store, &bytes, cache, progress)
+    }
+
+    /// The `CompileReport` of the module, if it has been built by
+    /// `Module`, `None` if it has been deserialized.
+    ///
+    /// ## Example
+    ///
+    /// See `CompileReport`.
+    #[getter]
+    fn compile_report(&self) -> Option<CompileReport> {
+        self.compile_report.clone()
     }
 
     /// Get or set the current name of the module.
@@ -98,6 +280,9 @@ impl Module {
     ///
     /// Not all modules have a name.
     ///
+    /// The name is used in the backtraces of the traps, and in the
+    /// instantiation errors.
+    ///
     /// ## Example
     ///
     /// ```py
@@ -132,14 +317,19 @@ impl Module {
     /// the exports of this module.
     ///
     /// The order of the exports is guaranteed to be the same as in
-    /// the WebAssembly bytecode.
+    /// the WebAssembly bytecode. The exports added by the
+    /// middlewares are internal and are left out.
     ///
     /// ## Example
     ///
     /// See the `ExportType` class to learn more.
     #[getter]
     fn exports(&self) -> PyResult<Vec<types::ExportType>> {
-        self.inner.exports().map(TryInto::try_into).collect()
+        self.inner
+            .exports()
+            .filter(|export| !middlewares::is_internal_export(export.name()))
+            .map(TryInto::try_into)
+            .collect()
     }
 
     /// Returns a list of `ImportType` objects, which represents all
@@ -189,6 +379,24 @@ impl Module {
         )
     }
 
+    /// Returns the names of all the custom sections of this module,
+    /// in the order they appear in the WebAssembly bytecode. Each
+    /// name is listed once, even if it has multiple custom sections.
+    ///
+    /// ## Example
+    ///
+    /// ```py
+    /// from wasmer import Store, Module
+    ///
+    /// module = Module(Store(), open('tests/custom_sections.wasm', 'rb').read())
+    ///
+    /// assert module.custom_section_names == ['hello', 'easter_egg']
+    /// ```
+    #[getter]
+    fn custom_section_names(&self) -> Vec<String> {
+        self.inner.info().custom_sections.keys().cloned().collect()
+    }
+
     /// Serializes a module into a binary representation that the
     /// `Engine` can later process via `Module.deserialize`.
     ///
@@ -209,11 +417,39 @@ impl Module {
             py,
             self.inner
                 .serialize()
-                .map_err(to_py_err::<PyRuntimeError, _>)?
+                .map_err(to_py_err::<WasmerError, _>)?
                 .as_slice(),
         ))
     }
 
+    /// Serializes a module into a file that the `Engine` can later
+    /// process via `Module.deserialize_from_file`.
+    ///
+    /// Contrary to `Module.serialize`, the artifact is written
+    /// directly to the file, without going through a Python `bytes`.
+    ///
+    /// ## Example
+    ///
+    /// ```py
+    /// from wasmer import Store, Module
+    /// import os
+    /// import tempfile
+    ///
+    /// module = Module(Store(), '(module)')
+    ///
+    /// with tempfile.TemporaryDirectory() as directory:
+    ///     path = os.path.join(directory, 'module.wasmu')
+    ///     module.serialize_to_file(path)
+    ///
+    ///     assert os.path.getsize(path) > 0
+    /// ```
+    #[pyo3(text_signature = "($self, path)")]
+    fn serialize_to_file(&self, path: PathBuf) -> PyResult<()> {
+        self.inner
+            .serialize_to_file(path)
+            .map_err(to_py_err::<WasmerError, _>)
+    }
+
     /// Deserializes a serialized module binary into a `Module`.
     ///
     /// **Note**: the module has to be serialized before with the
@@ -230,6 +466,9 @@ impl Module {
     ///
     /// And as such, the `deserialize` method is unsafe.
     ///
+    /// Any bytes-like object is accepted, e.g. `bytes`, `bytearray`
+    /// or `memoryview`.
+    ///
     /// ## Example
     ///
     /// ```py
@@ -255,10 +494,383 @@ impl Module {
     /// ```
     #[pyo3(text_signature = "($self, bytes)")]
     #[staticmethod]
-    fn deserialize(store: &Store, bytes: &PyBytes) -> PyResult<Self> {
-        let module = unsafe { wasmer::Module::deserialize(store.inner(), bytes.as_bytes()) }
-            .map_err(to_py_err::<PyRuntimeError, _>)?;
+    fn deserialize(py: Python, store: &Store, bytes: &PyAny) -> PyResult<Self> {
+        let module = if let Ok(bytes) = bytes.downcast::<PyBytes>() {
+            unsafe { wasmer::Module::deserialize(store.inner()?, bytes.as_bytes()) }
+        } else if let Ok(buffer) = PyBuffer::<u8>::get(bytes) {
+            unsafe { wasmer::Module::deserialize(store.inner()?, buffer.to_vec(py)?.as_slice()) }
+        } else {
+            return Err(to_py_err::<PyTypeError, _>(
+                "`Module.deserialize` accepts a bytes-like object",
+            ));
+        }
+        .map_err(to_py_err::<WasmerError, _>)?;
+        perf::register(store, &module)?;
 
-        Ok(Module { inner: module })
+        Ok(Module::raw_new(module))
     }
+
+    /// Deserializes a serialized module from a file into a `Module`.
+    ///
+    /// Contrary to `Module.deserialize`, the artifact doesn't need to
+    /// be read into a Python `bytes` first: the file is memory-mapped
+    /// instead. With the `engine.Dylib` engine, the shared object
+    /// file is directly loaded with `dlopen`, even with a headless
+    /// engine.
+    ///
+    /// **Note**: the module has to be serialized before with the
+    /// `serialize` or `serialize_to_file` methods.
+    ///
+    /// ## Safety
+    ///
+    /// This function is inherently **unsafe**, see
+    /// `Module.deserialize` to learn more.
+    ///
+    /// ## Example
+    ///
+    /// ```py
+    /// from wasmer import Store, Module
+    /// import os
+    /// import tempfile
+    ///
+    /// store = Store()
+    /// module = Module(store, '(module (func (export "function") (param i32 i64)))')
+    ///
+    /// with tempfile.TemporaryDirectory() as directory:
+    ///     path = os.path.join(directory, 'module.wasmu')
+    ///
+    ///     module.serialize_to_file(path)
+    ///     module = Module.deserialize_from_file(store, path)
+    ///
+    /// assert module.exports[0].name == 'function'
+    /// ```
+    #[pyo3(text_signature = "(store, path)")]
+    #[staticmethod]
+    fn deserialize_from_file(store: &Store, path: PathBuf) -> PyResult<Self> {
+        let module = unsafe { wasmer::Module::deserialize_from_file(store.inner()?, path) }
+            .map_err(to_py_err::<WasmerError, _>)?;
+        perf::register(store, &module)?;
+
+        Ok(Module::raw_new(module))
+    }
+
+    /// Pickles the module as its serialized artifact, see `Module`
+    /// and `unpickle`.
+    fn __reduce__(&self, py: Python) -> PyResult<(PyObject, (PyObject,))> {
+        Ok((
+            py.import("wasmer")?
+                .getattr("_unpickle_module")?
+                .to_object(py),
+            (self.serialize(py)?.to_object(py),),
+        ))
+    }
+}
+
+/// Unpickles a module pickled by `Module.__reduce__`, in a new store
+/// whose engine can load the artifact.
+pub(crate) fn unpickle(py: Python, bytes: &PyAny) -> PyResult<Module> {
+    let engine = py.import("wasmer")?.getattr("engine")?;
+    let engine = match bytes.downcast::<PyBytes>() {
+        Ok(bytes) if wasmer::DylibArtifact::is_deserializable(bytes.as_bytes()) => {
+            engine.getattr("Dylib")?.call0()?
+        }
+        _ => engine.getattr("Universal")?.call0()?,
+    };
+    let store = py
+        .get_type::<Store>()
+        .call1((engine,))?
+        .extract::<PyRef<Store>>()?;
+
+    Module::deserialize(py, &store, bytes)
+}
+
+/// The size of the chunks read from a file by `read_file`.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Reads a file-like object until its end, in binary or text mode.
+fn read_file(file: &PyAny) -> PyResult<Vec<u8>> {
+    let mut bytes = Vec::new();
+
+    loop {
+        let chunk = file.call_method1("read", (CHUNK_SIZE,))?;
+        let chunk = if let Ok(chunk) = chunk.downcast::<PyBytes>() {
+            chunk.as_bytes()
+        } else if let Ok(chunk) = chunk.downcast::<PyString>() {
+            chunk.to_str()?.as_bytes()
+        } else {
+            return Err(to_py_err::<PyTypeError, _>(
+                "The `read` method of the file must return bytes or a string",
+            ));
+        };
+
+        if chunk.is_empty() {
+            return Ok(bytes);
+        }
+
+        bytes.extend_from_slice(chunk);
+    }
+}
+
+/// Compile a module, and report its progress to `progress`, if any,
+/// see `Module`.
+///
+/// The GIL is released during the compilation, so that the compiler
+/// threads can call Python code, e.g. the cost function of the
+/// `Metering` middleware.
+pub(crate) fn compile(
+    py: Python,
+    store: &Store,
+    bytes: &[u8],
+    progress: Option<PyObject>,
+) -> PyResult<wasmer::Module> {
+    let progress_error = Arc::new(Mutex::new(None));
+    let callback = progress.map(|progress| {
+        let progress_error = progress_error.clone();
+
+        Box::new(move |compiled: usize, total: usize| {
+            let mut progress_error = progress_error.lock().unwrap();
+
+            if progress_error.is_none() {
+                *progress_error =
+                    Python::with_gil(|py| progress.call1(py, (compiled, total))).err();
+            }
+        }) as ProgressCallback
+    });
+
+    let inner = store.inner()?;
+    let module = py
+        .allow_threads(|| {
+            store
+                .progress()
+                .report(callback, || wasmer::Module::new(inner, bytes))
+        })
+        .map_err(to_py_err::<CompileError, _>)?;
+
+    if let Some(error) = progress_error.lock().unwrap().take() {
+        return Err(error);
+    }
+
+    perf::register(store, &module)?;
+
+    Ok(module)
+}
+
+/// A report on the compilation of a `Module`, built by
+/// `Module.compile_report`.
+///
+/// * `compile_time` is the time spent compiling the module, or
+///   loading it from the `Cache`, in seconds,
+/// * `code_size` is the size of the machine code of the functions,
+///   in bytes, trampolines excluded,
+/// * `functions` is the number of functions defined by the module.
+///
+/// ## Example
+///
+/// ```py
+/// from wasmer import Store, Module, CompileReport
+///
+/// module = Module(Store(), '(module (func (export "answer") (result i32) i32.const 42))')
+/// report = module.compile_report
+///
+/// assert isinstance(report, CompileReport)
+/// assert report.compile_time > 0
+/// assert report.code_size > 0
+/// assert report.functions == 1
+/// ```
+#[pyclass]
+#[derive(Clone)]
+pub struct CompileReport {
+    /// The time spent compiling the module, in seconds.
+    #[pyo3(get)]
+    compile_time: f64,
+
+    /// The size of the machine code of the functions, in bytes.
+    #[pyo3(get)]
+    code_size: usize,
+
+    /// The number of functions defined by the module.
+    #[pyo3(get)]
+    functions: usize,
+}
+
+impl CompileReport {
+    fn new(module: &wasmer::Module, compile_time: Duration) -> Self {
+        Self {
+            compile_time: compile_time.as_secs_f64(),
+            code_size: perf::function_spans(module)
+                .iter()
+                .map(|(_, _, size)| size)
+                .sum(),
+            functions: module.artifact().finished_functions().len(),
+        }
+    }
+}
+
+#[pyproto]
+impl PyObjectProtocol for CompileReport {
+    fn __str__(&self) -> String {
+        format!(
+            "CompileReport(compile_time: {}, code_size: {}, functions: {})",
+            self.compile_time, self.code_size, self.functions,
+        )
+    }
+}
+
+/// The result of `Module.validate`.
+///
+/// It is truthy if the WebAssembly bytes are valid, falsy
+/// otherwise. When the bytes are invalid, `message` describes the
+/// error, `offset` is the position in the bytes where the error
+/// occurred, if known, and `section` is the name of the WebAssembly
+/// section containing this offset, if any.
+///
+/// ## Example
+///
+/// ```py
+/// from wasmer import Store, Module
+///
+/// # Valid bytes.
+/// result = Module.validate(Store(), b'\x00asm\x01\x00\x00\x00')
+///
+/// assert result
+/// assert result.message == None
+///
+/// # Invalid bytes: the type section declares a function with an
+/// # unknown value type.
+/// result = Module.validate(
+///     Store(),
+///     b'\x00asm\x01\x00\x00\x00\x01\x05\x01\x60\x01\x42\x00'
+/// )
+///
+/// assert not result
+/// assert result.offset == 13
+/// assert result.section == 'type'
+/// assert result.message == 'Invalid type'
+/// ```
+#[pyclass]
+pub struct ValidationResult {
+    /// Whether the WebAssembly bytes are valid.
+    #[pyo3(get)]
+    valid: bool,
+
+    /// The error message, if the bytes are invalid.
+    #[pyo3(get)]
+    message: Option<String>,
+
+    /// The offset of the error in the bytes, if known.
+    #[pyo3(get)]
+    offset: Option<usize>,
+
+    /// The name of the section containing the error, if known.
+    #[pyo3(get)]
+    section: Option<String>,
+}
+
+impl ValidationResult {
+    fn valid() -> Self {
+        Self {
+            valid: true,
+            message: None,
+            offset: None,
+            section: None,
+        }
+    }
+
+    fn invalid(error: wasmer::CompileError, bytes: &[u8]) -> Self {
+        let message = match error {
+            wasmer::CompileError::Validate(message) => message,
+            wasmer::CompileError::Wasm(wasmer::WasmError::InvalidWebAssembly {
+                message,
+                offset,
+            }) => format!("{} (at offset {})", message, offset),
+            error => error.to_string(),
+        };
+
+        // Validation messages are of the form `<message> (at offset
+        // <offset>)`.
+        let (message, offset) = match message
+            .strip_suffix(')')
+            .and_then(|message| message.rsplit_once(" (at offset "))
+            .and_then(|(message, offset)| Some((message, offset.parse::<usize>().ok()?)))
+        {
+            Some((message, offset)) => (message.to_string(), Some(offset)),
+            None => (message, None),
+        };
+
+        Self {
+            valid: false,
+            message: Some(message),
+            offset,
+            section: offset.and_then(|offset| section_at(bytes, offset)),
+        }
+    }
+}
+
+#[pyproto]
+impl PyObjectProtocol for ValidationResult {
+    fn __bool__(&self) -> bool {
+        self.valid
+    }
+
+    fn __str__(&self) -> String {
+        format!(
+            "ValidationResult(valid: {}, message: {:?}, offset: {:?}, section: {:?})",
+            self.valid, self.message, self.offset, self.section,
+        )
+    }
+}
+
+/// Find the name of the section containing `offset` in the
+/// WebAssembly `bytes`.
+fn section_at(bytes: &[u8], offset: usize) -> Option<String> {
+    // Skip the magic number and the version.
+    let mut position = 8;
+
+    while position < bytes.len() && position <= offset {
+        let id = bytes[position];
+        position += 1;
+
+        // Read the section size, encoded as an unsigned LEB128.
+        let mut size: usize = 0;
+        let mut shift = 0;
+
+        loop {
+            let byte = *bytes.get(position)?;
+            position += 1;
+
+            size |= ((byte & 0x7f) as usize).checked_shl(shift)?;
+            shift += 7;
+
+            if byte & 0x80 == 0 {
+                break;
+            }
+        }
+
+        let end = position.saturating_add(size);
+
+        if offset < end {
+            let name = match id {
+                0 => "custom",
+                1 => "type",
+                2 => "import",
+                3 => "function",
+                4 => "table",
+                5 => "memory",
+                6 => "global",
+                7 => "export",
+                8 => "start",
+                9 => "element",
+                10 => "code",
+                11 => "data",
+                12 => "datacount",
+                _ => return None,
+            };
+
+            return Some(name.to_string());
+        }
+
+        position = end;
+    }
+
+    None
 }
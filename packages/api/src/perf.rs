@@ -0,0 +1,99 @@
+use crate::{
+    errors::to_py_err,
+    store::Store,
+    wasmer_inner::{wasmer, wasmer_types::FunctionIndex},
+};
+use pyo3::{exceptions::PyRuntimeError, prelude::*};
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    process,
+    sync::Mutex,
+};
+
+/// The perf map of the process, with the process ID it is opened for:
+/// a forked process writes to its own map.
+static PERF_MAP: Mutex<Option<(u32, File)>> = Mutex::new(None);
+
+/// Writes the compiled functions of `module` to the perf map of the
+/// process, i.e. `/tmp/perf-<pid>.map`, if the engine of `store` has
+/// been created with `perf_map=True`.
+pub(crate) fn register(store: &Store, module: &wasmer::Module) -> PyResult<()> {
+    if !store.perf_map() {
+        return Ok(());
+    }
+
+    write(module).map_err(to_py_err::<PyRuntimeError, _>)
+}
+
+/// Gets the address and the size of the compiled functions of
+/// `module`, by index.
+pub(crate) fn function_spans(module: &wasmer::Module) -> Vec<(FunctionIndex, usize, usize)> {
+    let artifact = module.artifact();
+    let info = module.info();
+
+    // The size of a function isn't exposed by the artifact: a
+    // function spans up to the next piece of code, i.e. another
+    // function or a trampoline, which are allocated after the
+    // functions.
+    let mut starts = artifact
+        .finished_functions()
+        .values()
+        .map(|body| body.0 as usize)
+        .chain(
+            artifact
+                .finished_function_call_trampolines()
+                .values()
+                .map(|trampoline| *trampoline as usize),
+        )
+        .chain(
+            artifact
+                .finished_dynamic_function_trampolines()
+                .values()
+                .map(|body| body.0 as usize),
+        )
+        .collect::<Vec<_>>();
+    starts.sort_unstable();
+
+    artifact
+        .finished_functions()
+        .iter()
+        .filter_map(|(local_index, body)| {
+            let start = body.0 as usize;
+            let end = starts.iter().find(|other| **other > start)?;
+
+            Some((info.func_index(local_index), start, end - start))
+        })
+        .collect()
+}
+
+fn write(module: &wasmer::Module) -> io::Result<()> {
+    let info = module.info();
+    let module_name = module.name().unwrap_or("<module>");
+    let mut lines = String::new();
+
+    for (index, start, size) in function_spans(module) {
+        lines.push_str(&format!("{:x} {:x} wasm::{}::", start, size, module_name));
+
+        match info.function_names.get(&index) {
+            Some(name) => lines.push_str(name),
+            None => lines.push_str(&format!("function{}", index.as_u32())),
+        }
+
+        lines.push('\n');
+    }
+
+    let mut perf_map = PERF_MAP.lock().unwrap();
+    let pid = process::id();
+
+    if perf_map.as_ref().map(|(other, _)| *other) != Some(pid) {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(format!("/tmp/perf-{}.map", pid))?;
+
+        *perf_map = Some((pid, file));
+    }
+
+    perf_map.as_mut().unwrap().1.write_all(lines.as_bytes())
+}
@@ -0,0 +1,700 @@
+use crate::{
+    errors::to_py_err,
+    instance::ResolvedImports,
+    middlewares,
+    wasmer_inner::{
+        wasmer::{self, Exportable},
+        wasmer_vm::VMFunctionKind,
+    },
+};
+use pyo3::{
+    exceptions::{PyRuntimeError, PyValueError},
+    prelude::*,
+};
+use std::{collections::HashMap, convert::TryInto};
+
+/// The first bytes of a snapshot, with the version of its format.
+const MAGIC: &[u8; 8] = b"\0wsnap\x01\0";
+
+/// Gets the names of the exports of `indices`, i.e. the `kind`s
+/// defined by `module`. Each of them must be exported. The ones added
+/// by the middlewares are left out.
+fn export_names(
+    module: &wasmer::Module,
+    kind: &str,
+    indices: impl Iterator<Item = (u32, wasmer::ExportIndex)>,
+) -> PyResult<Vec<String>> {
+    let exports = &module.info().exports;
+
+    indices
+        .filter(|(_, export_index)| {
+            !exports.iter().any(|(name, export)| {
+                *export == *export_index && middlewares::is_internal_export(name)
+            })
+        })
+        .map(|(index, export_index)| {
+            exports
+                .iter()
+                .find(|(_, export)| **export == export_index)
+                .map(|(name, _)| name.clone())
+                .ok_or_else(|| {
+                    to_py_err::<PyRuntimeError, _>(format!(
+                        "The {} {} is not exported, the state of the instance is not reachable",
+                        kind, index
+                    ))
+                })
+        })
+        .collect()
+}
+
+/// Gets the names of the memories defined by `module`.
+pub(crate) fn exported_memories(module: &wasmer::Module) -> PyResult<Vec<String>> {
+    let info = module.info();
+
+    export_names(
+        module,
+        "memory",
+        info.memories
+            .keys()
+            .skip(info.num_imported_memories)
+            .map(|index| (index.as_u32(), wasmer::ExportIndex::Memory(index))),
+    )
+}
+
+/// Gets the names of the mutable globals defined by `module`.
+pub(crate) fn exported_mutable_globals(module: &wasmer::Module) -> PyResult<Vec<String>> {
+    let info = module.info();
+
+    export_names(
+        module,
+        "mutable global",
+        info.globals
+            .iter()
+            .skip(info.num_imported_globals)
+            .filter(|(_, ty)| ty.mutability == wasmer::Mutability::Var)
+            .map(|(index, _)| (index.as_u32(), wasmer::ExportIndex::Global(index))),
+    )
+}
+
+/// Gets the names of the tables defined by `module`.
+fn exported_tables(module: &wasmer::Module) -> PyResult<Vec<String>> {
+    let info = module.info();
+
+    export_names(
+        module,
+        "table",
+        info.tables
+            .keys()
+            .skip(info.num_imported_tables)
+            .map(|index| (index.as_u32(), wasmer::ExportIndex::Table(index))),
+    )
+}
+
+/// Writes `data` at the beginning of `memory`, which is grown to
+/// `pages` if needed. The rest of the memory is zeroed.
+pub(crate) fn write_memory(
+    memory: &wasmer::Memory,
+    pages: wasmer::Pages,
+    data: &[u8],
+) -> PyResult<()> {
+    if pages > memory.size() {
+        memory
+            .grow(pages - memory.size())
+            .map_err(to_py_err::<PyRuntimeError, _>)?;
+    }
+
+    // SAFETY: no WebAssembly code runs while the memory is written.
+    let bytes = unsafe { memory.data_unchecked_mut() };
+
+    if data.len() > bytes.len() {
+        return Err(to_py_err::<PyValueError, _>(
+            "The snapshot of the memory is larger than the memory",
+        ));
+    }
+
+    let (written, rest) = bytes.split_at_mut(data.len());
+    written.copy_from_slice(data);
+    rest.fill(0);
+
+    Ok(())
+}
+
+/// The identity of a function: its body, and its environment, i.e.
+/// the instance of a WebAssembly function, or the closure of a host
+/// function.
+fn function_identity(function: &wasmer::Function) -> (usize, usize) {
+    match function.to_export() {
+        wasmer::Export::Function(function) => (
+            function.vm_function.address as usize,
+            // SAFETY: both members of the union are pointers.
+            unsafe { function.vm_function.vmctx.host_env } as usize,
+        ),
+        _ => unreachable!(),
+    }
+}
+
+/// How an instance sees one of its imported functions: host functions
+/// are called through a trampoline, and their environment is cloned
+/// for each instance, hence it cannot be known beforehand.
+struct ImportedFunction {
+    address: usize,
+    environment: Option<usize>,
+}
+
+impl ImportedFunction {
+    fn new(
+        module: &wasmer::Module,
+        function_index: usize,
+        export: &wasmer::Export,
+    ) -> Option<Self> {
+        let function = match export {
+            wasmer::Export::Function(function) => function,
+            _ => return None,
+        };
+
+        let address = match function.vm_function.kind {
+            VMFunctionKind::Dynamic => {
+                module
+                    .artifact()
+                    .finished_dynamic_function_trampolines()
+                    .values()
+                    .nth(function_index)?
+                    .0 as usize
+            }
+            VMFunctionKind::Static => function.vm_function.address as usize,
+        };
+
+        let environment = match function.metadata {
+            Some(_) => None,
+            // SAFETY: both members of the union are pointers.
+            None => Some(unsafe { function.vm_function.vmctx.host_env } as usize),
+        };
+
+        Some(Self {
+            address,
+            environment,
+        })
+    }
+
+    fn is(&self, (address, environment): (usize, usize)) -> bool {
+        self.address == address && self.environment.is_none_or(|other| other == environment)
+    }
+}
+
+/// The reference to a function in a snapshot: the index of a
+/// function defined by the module, or of an imported function.
+enum FunctionReference {
+    Defined(u32),
+    Imported(u32),
+}
+
+/// Encodes a snapshot.
+struct Writer {
+    bytes: Vec<u8>,
+}
+
+impl Writer {
+    fn u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn u64(&mut self, value: u64) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.u64(bytes.len() as u64);
+        self.bytes.extend_from_slice(bytes);
+    }
+}
+
+/// Decodes a snapshot.
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, length: usize) -> PyResult<&'a [u8]> {
+        if length > self.bytes.len() {
+            return Err(to_py_err::<PyValueError, _>("The snapshot is truncated"));
+        }
+
+        let (taken, rest) = self.bytes.split_at(length);
+        self.bytes = rest;
+
+        Ok(taken)
+    }
+
+    fn u8(&mut self) -> PyResult<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> PyResult<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> PyResult<u64> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn bytes(&mut self) -> PyResult<&'a [u8]> {
+        let length = self.u64()?;
+
+        self.take(length as usize)
+    }
+
+    fn string(&mut self) -> PyResult<&'a str> {
+        std::str::from_utf8(self.bytes()?)
+            .map_err(|_| to_py_err::<PyValueError, _>("The snapshot is invalid"))
+    }
+}
+
+/// The state of an instance, read from, or written to, a snapshot.
+struct State<'a> {
+    instance: &'a wasmer::Instance,
+    imported_functions: Vec<Option<ImportedFunction>>,
+    exported_functions: HashMap<u32, wasmer::Function>,
+    memories: Vec<String>,
+    globals: Vec<String>,
+    tables: Vec<String>,
+}
+
+impl<'a> State<'a> {
+    fn new(instance: &'a wasmer::Instance, imports: &'a ResolvedImports) -> PyResult<Self> {
+        let module = instance.module();
+        let mut function_index = 0;
+        let imported_functions = module
+            .imports()
+            .zip(imports.exports())
+            .map(|(import, export)| match import.ty() {
+                wasmer::ExternType::Function(_) => {
+                    function_index += 1;
+
+                    export.as_ref().and_then(|export| {
+                        ImportedFunction::new(module, function_index - 1, export)
+                    })
+                }
+                _ => None,
+            })
+            .collect();
+
+        let mut state = Self {
+            instance,
+            imported_functions,
+            exported_functions: HashMap::new(),
+            memories: exported_memories(module)?,
+            globals: exported_mutable_globals(module)?,
+            tables: exported_tables(module)?,
+        };
+        state.exported_functions = state.defined_functions();
+
+        Ok(state)
+    }
+
+    /// Gets the functions defined by the module that the instance
+    /// exports, by index. A snapshot can only refer to those, since
+    /// the other functions of the instance are not reachable.
+    fn defined_functions(&self) -> HashMap<u32, wasmer::Function> {
+        let artifact = self.instance.module().artifact();
+        let bodies = artifact.finished_functions();
+
+        self.instance
+            .exports
+            .iter()
+            .functions()
+            .filter(|(name, _)| !middlewares::is_internal_export(name))
+            .filter_map(|(_, function)| {
+                let identity = function_identity(function);
+
+                if self.imported(identity).is_some() {
+                    return None;
+                }
+
+                bodies
+                    .iter()
+                    .find(|(_, body)| body.0 as usize == identity.0)
+                    .map(|(index, _)| (index.as_u32(), function.clone()))
+            })
+            .collect()
+    }
+
+    fn memory(&self, name: &str) -> PyResult<&'a wasmer::Memory> {
+        self.instance
+            .exports
+            .get_memory(name)
+            .map_err(to_py_err::<PyRuntimeError, _>)
+    }
+
+    fn global(&self, name: &str) -> PyResult<&'a wasmer::Global> {
+        self.instance
+            .exports
+            .get_global(name)
+            .map_err(to_py_err::<PyRuntimeError, _>)
+    }
+
+    fn table(&self, name: &str) -> PyResult<&'a wasmer::Table> {
+        self.instance
+            .exports
+            .get_table(name)
+            .map_err(to_py_err::<PyRuntimeError, _>)
+    }
+
+    /// Gets how a snapshot refers to `function`.
+    fn reference(&self, function: &wasmer::Function) -> PyResult<FunctionReference> {
+        let identity = function_identity(function);
+
+        // An imported function may be defined by another instance of
+        // the same module, hence the imports are looked up first.
+        if let Some(index) = self.imported(identity) {
+            return Ok(FunctionReference::Imported(index as u32));
+        }
+
+        // A defined function is identified by its environment too,
+        // since it may come from another instance of the same module.
+        self.exported_functions
+            .iter()
+            .find(|(_, exported)| function_identity(exported) == identity)
+            .map(|(index, _)| FunctionReference::Defined(*index))
+            .ok_or_else(|| {
+                to_py_err::<PyRuntimeError, _>(
+                    "A function referenced by the instance is not exported by it, or comes from another instance",
+                )
+            })
+    }
+
+    /// Gets the index of the import of the function identified by
+    /// `identity`, if any.
+    fn imported(&self, identity: (usize, usize)) -> Option<usize> {
+        self.imported_functions.iter().position(|imported| {
+            imported
+                .as_ref()
+                .is_some_and(|imported| imported.is(identity))
+        })
+    }
+
+    /// Gets the functions that can be referenced by the instance: its
+    /// exported functions, and the elements of its tables.
+    fn functions(&self) -> HashMap<(usize, usize), wasmer::Function> {
+        let mut functions = HashMap::new();
+
+        for (name, export) in self.instance.exports.iter() {
+            match export {
+                _ if middlewares::is_internal_export(name) => (),
+
+                wasmer::Extern::Function(function) => {
+                    functions.insert(function_identity(function), function.clone());
+                }
+
+                wasmer::Extern::Table(table) => {
+                    for index in 0..table.size() {
+                        if let Some(wasmer::Value::FuncRef(Some(function))) = table.get(index) {
+                            functions.insert(function_identity(&function), function);
+                        }
+                    }
+                }
+
+                _ => (),
+            }
+        }
+
+        functions
+    }
+
+    /// Gets the function a snapshot refers to by `kind` and `index`,
+    /// among `functions`.
+    fn function(
+        &self,
+        functions: &HashMap<(usize, usize), wasmer::Function>,
+        kind: u8,
+        index: u32,
+    ) -> PyResult<wasmer::Function> {
+        match kind {
+            1 => self.exported_functions.get(&index).cloned().ok_or_else(|| {
+                to_py_err::<PyRuntimeError, _>(format!(
+                    "The function {} of the snapshot is not exported by the instance",
+                    index
+                ))
+            }),
+            2 => match self.imported_functions.get(index as usize) {
+                Some(Some(imported)) => functions
+                    .iter()
+                    .find(|(identity, _)| imported.is(**identity))
+                    .map(|(_, function)| function.clone())
+                    .ok_or_else(|| {
+                        to_py_err::<PyRuntimeError, _>(format!(
+                            "The imported function {} of the snapshot is neither exported, nor in a table of the instance",
+                            index
+                        ))
+                    }),
+                _ => Err(mismatch()),
+            },
+            _ => Err(to_py_err::<PyValueError, _>("The snapshot is invalid")),
+        }
+    }
+
+    fn write_function(
+        &self,
+        writer: &mut Writer,
+        function: &Option<wasmer::Function>,
+    ) -> PyResult<()> {
+        match function
+            .as_ref()
+            .map(|function| self.reference(function))
+            .transpose()?
+        {
+            None => writer.u8(0),
+            Some(FunctionReference::Defined(index)) => {
+                writer.u8(1);
+                writer.u32(index);
+            }
+            Some(FunctionReference::Imported(index)) => {
+                writer.u8(2);
+                writer.u32(index);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn read_function(
+        &self,
+        reader: &mut Reader,
+        functions: &HashMap<(usize, usize), wasmer::Function>,
+    ) -> PyResult<Option<wasmer::Function>> {
+        match reader.u8()? {
+            0 => Ok(None),
+            kind => self.function(functions, kind, reader.u32()?).map(Some),
+        }
+    }
+
+    fn write(&self) -> PyResult<Vec<u8>> {
+        let mut writer = Writer {
+            bytes: MAGIC.to_vec(),
+        };
+
+        writer.u32(self.memories.len() as u32);
+
+        for name in &self.memories {
+            let memory = self.memory(name)?;
+
+            writer.bytes(name.as_bytes());
+            writer.u32(memory.size().0);
+            // SAFETY: no WebAssembly code runs while the memory is read.
+            writer.bytes(unsafe { memory.data_unchecked() });
+        }
+
+        writer.u32(self.globals.len() as u32);
+
+        for name in &self.globals {
+            writer.bytes(name.as_bytes());
+
+            match self.global(name)?.get() {
+                wasmer::Value::I32(value) => {
+                    writer.u8(0);
+                    writer.u64(value as u32 as u64);
+                }
+                wasmer::Value::I64(value) => {
+                    writer.u8(1);
+                    writer.u64(value as u64);
+                }
+                wasmer::Value::F32(value) => {
+                    writer.u8(2);
+                    writer.u64(value.to_bits() as u64);
+                }
+                wasmer::Value::F64(value) => {
+                    writer.u8(3);
+                    writer.u64(value.to_bits());
+                }
+                wasmer::Value::V128(value) => {
+                    writer.u8(4);
+                    writer.bytes(&value.to_le_bytes());
+                }
+                wasmer::Value::FuncRef(function) => {
+                    writer.u8(5);
+                    self.write_function(&mut writer, &function)?;
+                }
+                wasmer::Value::ExternRef(_) => {
+                    return Err(to_py_err::<PyRuntimeError, _>(format!(
+                        "The global `{}` holds an `externref`, which cannot be snapshotted",
+                        name
+                    )))
+                }
+            }
+        }
+
+        writer.u32(self.tables.len() as u32);
+
+        for name in &self.tables {
+            let table = self.table(name)?;
+
+            writer.bytes(name.as_bytes());
+            writer.u32(table.size());
+
+            for index in 0..table.size() {
+                match table.get(index) {
+                    Some(wasmer::Value::FuncRef(function)) => {
+                        self.write_function(&mut writer, &function)?
+                    }
+                    _ => {
+                        return Err(to_py_err::<PyRuntimeError, _>(format!(
+                            "The table `{}` holds `externref`s, which cannot be snapshotted",
+                            name
+                        )))
+                    }
+                }
+            }
+        }
+
+        Ok(writer.bytes)
+    }
+
+    fn read_names(&self, reader: &mut Reader, names: &[String]) -> PyResult<()> {
+        if reader.u32()? as usize != names.len() {
+            return Err(mismatch());
+        }
+
+        Ok(())
+    }
+
+    fn read(&self, bytes: &[u8]) -> PyResult<()> {
+        let mut reader = Reader { bytes };
+
+        if reader.take(MAGIC.len()).ok() != Some(&MAGIC[..]) {
+            return Err(to_py_err::<PyValueError, _>(
+                "The bytes are not a snapshot of an instance",
+            ));
+        }
+
+        // The whole snapshot is checked before the instance is
+        // written.
+        self.read_names(&mut reader, &self.memories)?;
+        let mut memories = Vec::with_capacity(self.memories.len());
+
+        for name in &self.memories {
+            if reader.string()? != name {
+                return Err(mismatch());
+            }
+
+            let pages = wasmer::Pages(reader.u32()?);
+            let data = reader.bytes()?;
+
+            if pages.bytes().0 != data.len() {
+                return Err(to_py_err::<PyValueError, _>("The snapshot is invalid"));
+            }
+
+            memories.push((self.memory(name)?, pages, data));
+        }
+
+        self.read_names(&mut reader, &self.globals)?;
+        let functions = self.functions();
+        let mut globals = Vec::with_capacity(self.globals.len());
+
+        for name in &self.globals {
+            if reader.string()? != name {
+                return Err(mismatch());
+            }
+
+            let value = match reader.u8()? {
+                0 => wasmer::Value::I32(reader.u64()? as u32 as i32),
+                1 => wasmer::Value::I64(reader.u64()? as i64),
+                2 => wasmer::Value::F32(f32::from_bits(reader.u64()? as u32)),
+                3 => wasmer::Value::F64(f64::from_bits(reader.u64()?)),
+                4 => wasmer::Value::V128(u128::from_le_bytes(
+                    reader
+                        .bytes()?
+                        .try_into()
+                        .map_err(|_| to_py_err::<PyValueError, _>("The snapshot is invalid"))?,
+                )),
+                5 => wasmer::Value::FuncRef(self.read_function(&mut reader, &functions)?),
+                _ => return Err(to_py_err::<PyValueError, _>("The snapshot is invalid")),
+            };
+
+            let global = self.global(name)?;
+
+            if value.ty() != global.ty().ty {
+                return Err(mismatch());
+            }
+
+            globals.push((global, value));
+        }
+
+        self.read_names(&mut reader, &self.tables)?;
+        let mut tables = Vec::with_capacity(self.tables.len());
+
+        for name in &self.tables {
+            if reader.string()? != name {
+                return Err(mismatch());
+            }
+
+            let size = reader.u32()?;
+            let elements = (0..size)
+                .map(|_| self.read_function(&mut reader, &functions))
+                .collect::<PyResult<Vec<_>>>()?;
+
+            tables.push((self.table(name)?, elements));
+        }
+
+        if !reader.bytes.is_empty() {
+            return Err(to_py_err::<PyValueError, _>("The snapshot is invalid"));
+        }
+
+        for (memory, pages, data) in memories {
+            write_memory(memory, pages, data)?;
+        }
+
+        for (global, value) in globals {
+            global.set(value).map_err(to_py_err::<PyRuntimeError, _>)?;
+        }
+
+        for (table, elements) in tables {
+            let size = elements.len() as u32;
+
+            if size > table.size() {
+                table
+                    .grow(size - table.size(), wasmer::Value::FuncRef(None))
+                    .map_err(to_py_err::<PyRuntimeError, _>)?;
+            }
+
+            for (index, element) in elements.into_iter().enumerate() {
+                table
+                    .set(index as u32, wasmer::Value::FuncRef(element))
+                    .map_err(to_py_err::<PyRuntimeError, _>)?;
+            }
+
+            for index in size..table.size() {
+                table
+                    .set(index, wasmer::Value::FuncRef(None))
+                    .map_err(to_py_err::<PyRuntimeError, _>)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn mismatch() -> PyErr {
+    to_py_err::<PyValueError, _>("The snapshot does not match the module of the instance")
+}
+
+/// Takes a snapshot of the memories, the mutable globals, and the
+/// tables of `instance`.
+pub(crate) fn snapshot(
+    instance: &wasmer::Instance,
+    imports: &ResolvedImports,
+) -> PyResult<Vec<u8>> {
+    State::new(instance, imports)?.write()
+}
+
+/// Restores a snapshot taken by `snapshot`, from an instance of the
+/// same module.
+pub(crate) fn restore(
+    instance: &wasmer::Instance,
+    imports: &ResolvedImports,
+    bytes: &[u8],
+) -> PyResult<()> {
+    State::new(instance, imports)?.read(bytes)
+}
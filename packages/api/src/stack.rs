@@ -0,0 +1,37 @@
+use std::{io, panic, thread};
+
+/// Wasmer values and the closures calling the functions are not
+/// `Send`, but the current thread waits for the thread running them,
+/// so they are never used by two threads at a time.
+struct AssumeSend<T>(T);
+
+unsafe impl<T> Send for AssumeSend<T> {}
+
+/// Runs `f` on a thread with a stack of `stack_size` bytes, if any,
+/// otherwise on the current thread, see `Store`. The current thread
+/// waits for `f` to return either way. The GIL must be released,
+/// since the host functions acquire it back from the other thread,
+/// where they cannot use the unsendable objects, e.g. a `Memory`.
+pub(crate) fn with_stack_size<T>(
+    stack_size: Option<usize>,
+    f: impl FnOnce() -> T,
+) -> io::Result<T> {
+    let stack_size = match stack_size {
+        Some(stack_size) => stack_size,
+        None => return Ok(f()),
+    };
+
+    let f = AssumeSend(f);
+
+    thread::scope(|scope| {
+        let thread = thread::Builder::new()
+            .name("wasmer-stack".to_string())
+            .stack_size(stack_size)
+            .spawn_scoped(scope, move || AssumeSend((f.0)()))?;
+
+        match thread.join() {
+            Ok(result) => Ok(result.0),
+            Err(payload) => panic::resume_unwind(payload),
+        }
+    })
+}
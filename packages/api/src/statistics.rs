@@ -0,0 +1,80 @@
+use crate::{store, wasmer_inner::wasmer};
+use pyo3::{prelude::*, types::PyDict};
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+/// The number of calls to an exported function, and the time spent
+/// running them.
+#[derive(Default)]
+pub(crate) struct CallStatistics {
+    calls: AtomicU64,
+    nanoseconds: AtomicU64,
+}
+
+impl CallStatistics {
+    fn record(&self, duration: Duration) {
+        self.calls.fetch_add(1, Ordering::Relaxed);
+        self.nanoseconds
+            .fetch_add(duration.as_nanos() as u64, Ordering::Relaxed);
+    }
+}
+
+/// The statistics of the exported functions of an instance, by name.
+pub(crate) type Statistics = BTreeMap<String, Arc<CallStatistics>>;
+
+/// Creates the statistics of the exported functions of an instance,
+/// if `store` has been created with `statistics=True`.
+pub(crate) fn new(store: &wasmer::Store, exports: &wasmer::Exports) -> Option<Arc<Statistics>> {
+    if !store::statistics(store) {
+        return None;
+    }
+
+    Some(Arc::new(
+        exports
+            .iter()
+            .functions()
+            .map(|(name, _)| (name.clone(), Arc::default()))
+            .collect(),
+    ))
+}
+
+/// Runs `call`, and records it in `statistics`, if any.
+pub(crate) fn timed<T>(statistics: Option<&CallStatistics>, call: impl FnOnce() -> T) -> T {
+    match statistics {
+        Some(statistics) => {
+            let start = Instant::now();
+            let result = call();
+            statistics.record(start.elapsed());
+
+            result
+        }
+
+        None => call(),
+    }
+}
+
+/// Converts `statistics` to a dictionary of dictionaries, by export
+/// name, with the number of `calls`, and the `time` spent running
+/// them, in seconds.
+pub(crate) fn to_py_dict<'p>(py: Python<'p>, statistics: &Statistics) -> PyResult<&'p PyDict> {
+    let dict = PyDict::new(py);
+
+    for (name, call_statistics) in statistics {
+        let export = PyDict::new(py);
+        export.set_item("calls", call_statistics.calls.load(Ordering::Relaxed))?;
+        export.set_item(
+            "time",
+            Duration::from_nanos(call_statistics.nanoseconds.load(Ordering::Relaxed)).as_secs_f64(),
+        )?;
+
+        dict.set_item(name, export)?;
+    }
+
+    Ok(dict)
+}
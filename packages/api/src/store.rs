@@ -1,8 +1,13 @@
 use crate::{
     errors::to_py_err,
+    tunables::Tunables,
     wasmer_inner::{wasmer, wasmer_engines as engines},
 };
-use pyo3::{exceptions::PyTypeError, prelude::*};
+use pyo3::{
+    exceptions::{PyTypeError, PyValueError},
+    prelude::*,
+};
+use std::{collections::BTreeMap, path::PathBuf, sync::Mutex};
 
 /// The store represents all global state that can be manipulated by
 /// WebAssembly programs. It consists of the runtime representation of
@@ -43,41 +48,315 @@ use pyo3::{exceptions::PyTypeError, prelude::*};
 /// `compiler_compiler_cranelift`, `compiler_compiler_llvm`,
 /// `compiler_compiler_singlepass`, otherwise it will run in headless
 /// mode.
+///
+/// The limits of the memories and the tables, and how the memories
+/// are laid out, are configured with `tunables`, see `Tunables`.
+///
+/// WebAssembly runs on the stack of the calling thread, and overflowing
+/// it raises a `Trap` whose code is `TrapCode.STACK_OVERFLOW`. With a
+/// `stack_size`, in bytes, the functions of the store are called on a
+/// thread with a stack of this size instead, which the calling thread
+/// waits for: larger for deeply recursive code, or smaller to tighten
+/// the limit for untrusted code. The host functions run on this
+/// thread too, so they cannot use the objects bound to the calling
+/// thread, e.g. a `Memory` or an `Instance`.
+///
+/// ```py
+/// from wasmer import Store, Module, Instance
+///
+/// store = Store(stack_size=64 * 1024 * 1024)
+/// module = Module(
+///     store,
+///     """
+///     (module
+///       (func $depth (export "depth") (param i32) (result i32)
+///         local.get 0
+///         i32.eqz
+///         if (result i32)
+///           i32.const 0
+///         else
+///           local.get 0
+///           i32.const 1
+///           i32.sub
+///           call $depth
+///           i32.const 1
+///           i32.add
+///         end))
+///     """
+/// )
+///
+/// assert Instance(module).exports.depth(1_000_000) == 1_000_000
+/// ```
+///
+/// With a `coredump_directory`, each trap raised by a function of the
+/// store writes a core dump to this directory, following the
+/// [WebAssembly tool
+/// conventions](https://github.com/WebAssembly/tool-conventions/blob/main/Coredump.md),
+/// for post-mortem debugging. Its path is the `coredump_path`
+/// attribute of the `Trap`, `None` otherwise. The core dump holds the
+/// backtrace, and the exported memories and globals of the instance
+/// exporting the function; the locals of the frames are not
+/// recorded.
+///
+/// ```py
+/// from wasmer import Store, Module, Instance, Trap
+/// import os
+/// import tempfile
+///
+/// with tempfile.TemporaryDirectory() as directory:
+///     store = Store(coredump_directory=directory)
+///     instance = Instance(Module(store, '(module (func (export "crash") unreachable))'))
+///
+///     try:
+///         instance.exports.crash()
+///     except Trap as trap:
+///         assert os.path.dirname(trap.coredump_path) == directory
+///
+///         with open(trap.coredump_path, 'rb') as coredump:
+///             assert coredump.read(4) == b'\0asm'
+/// ```
+///
+/// With `keyboard_interrupt=True`, the calls made from the main thread
+/// to the functions compiled with an `Interruptible` middleware are
+/// interrupted by Ctrl-C, i.e. `SIGINT`, on Unix. For the duration of
+/// such a call, the Python wakeup fd, see `signal.set_wakeup_fd`, is
+/// replaced by the one of a thread interrupting the call, which
+/// forwards the signals to the previous wakeup fd. Without it, the
+/// signals are left to Python, and only handled once the call has
+/// returned.
+///
+/// With `statistics=True`, the instances of the store count the calls
+/// to their exported functions, and the time spent running them,
+/// including the host functions they call, see `Instance.stats`.
+/// Without it, the calls are not instrumented at all.
+///
+/// ```py
+/// from wasmer import Store, Module, Instance
+///
+/// instance = Instance(Module(Store(statistics=True), '(module (func (export "main")))'))
+/// instance.exports.main()
+///
+/// assert instance.stats()["main"]["calls"] == 1
+/// ```
+///
+/// Several stores can live side by side, e.g. a Singlepass store for
+/// untrusted code and an LLVM store for hot paths. A module is
+/// instantiated with externs from stores sharing its engine only,
+/// otherwise a `LinkError` is raised.
+///
+/// ```py
+/// from wasmer import engine, Store, Module, Instance, Function, LinkError
+/// from wasmer_compiler_cranelift import Compiler
+///
+/// universal = engine.Universal(Compiler)
+/// store = Store(universal)
+/// module = Module(store, '(module (import "env" "f" (func)))')
+///
+/// Instance(module, {"env": {"f": Function(Store(universal), lambda: None)}})
+///
+/// try:
+///     Instance(module, {"env": {"f": Function(Store(), lambda: None)}})
+/// except LinkError:
+///     pass
+/// else:
+///     assert False
+/// ```
 #[pyclass]
-#[pyo3(text_signature = "(engine)")]
+#[pyo3(
+    text_signature = "(engine, /, *, tunables, stack_size, coredump_directory, keyboard_interrupt, statistics)"
+)]
 pub struct Store {
-    inner: wasmer::Store,
+    /// The store, until it is closed.
+    inner: Option<wasmer::Store>,
     engine_name: String,
     compiler_name: Option<String>,
+    fingerprint: Option<String>,
+    progress: engines::Progress,
+    perf_map: bool,
 }
 
 impl Store {
-    pub fn inner(&self) -> &wasmer::Store {
-        &self.inner
+    pub fn inner(&self) -> PyResult<&wasmer::Store> {
+        self.inner
+            .as_ref()
+            .ok_or_else(|| to_py_err::<PyValueError, _>("The store is closed"))
+    }
+
+    /// Describes the configuration of the engine, see
+    /// `Cache::key`, or `None` if it cannot be described.
+    pub(crate) fn fingerprint(&self) -> Option<&String> {
+        self.fingerprint.as_ref()
+    }
+
+    /// Whether the compiled functions are written to the perf map of
+    /// the process, see `perf::register`.
+    pub(crate) fn perf_map(&self) -> bool {
+        self.perf_map
+    }
+
+    /// The middleware reporting the progress of the compilations of
+    /// the engine, see `module::compile`.
+    pub(crate) fn progress(&self) -> &engines::Progress {
+        &self.progress
     }
 }
 
+/// The settings of a store that are not held by Wasmer.
+#[derive(Default)]
+struct Settings {
+    /// The `Engine::id` of the engine the store has been created
+    /// with, see `engine_id`.
+    engine_id: String,
+    stack_size: Option<usize>,
+    coredump_directory: Option<PathBuf>,
+    keyboard_interrupt: bool,
+    statistics: bool,
+}
+
+/// The settings of the stores, by the address of their tunables. The
+/// tunables are shared by the clones of a store, and are dropped with
+/// the last one, see `forget_settings`.
+static SETTINGS: Mutex<BTreeMap<usize, Settings>> = Mutex::new(BTreeMap::new());
+
+pub(crate) fn tunables_address(tunables: &dyn wasmer::Tunables) -> usize {
+    tunables as *const dyn wasmer::Tunables as *const () as usize
+}
+
+/// Gets a setting of `store`, if any.
+fn setting<T>(store: &wasmer::Store, get: impl FnOnce(&Settings) -> Option<T>) -> Option<T> {
+    SETTINGS
+        .lock()
+        .unwrap()
+        .get(&tunables_address(store.tunables()))
+        .and_then(get)
+}
+
+/// Gets the identifier of the engine `store` has been created with.
+/// Unlike `wasmer::Store::same`, it is the same for all the stores
+/// created with an engine: each store has a clone of the engine,
+/// which gets a new `Engine::id`.
+pub(crate) fn engine_id(store: &wasmer::Store) -> Option<String> {
+    setting(store, |settings| Some(settings.engine_id.clone()))
+}
+
+/// Gets the stack size of the thread calling the functions of
+/// `store`, if any.
+pub(crate) fn stack_size(store: &wasmer::Store) -> Option<usize> {
+    setting(store, |settings| settings.stack_size)
+}
+
+/// Gets the directory where the core dumps of the traps of `store`
+/// are written, if any.
+pub(crate) fn coredump_directory(store: &wasmer::Store) -> Option<PathBuf> {
+    setting(store, |settings| settings.coredump_directory.clone())
+}
+
+/// Whether the calls to the functions of `store` are interrupted by
+/// `SIGINT`, see `SigintWatcher`.
+pub(crate) fn keyboard_interrupt(store: &wasmer::Store) -> bool {
+    setting(store, |settings| Some(settings.keyboard_interrupt)).unwrap_or(false)
+}
+
+/// Whether the instances of `store` collect statistics about the
+/// calls to their exported functions, see `statistics::new`.
+pub(crate) fn statistics(store: &wasmer::Store) -> bool {
+    setting(store, |settings| Some(settings.statistics)).unwrap_or(false)
+}
+
+/// Forgets the settings of the store owning `tunables`, when they are
+/// dropped.
+pub(crate) fn forget_settings(tunables: &dyn wasmer::Tunables) {
+    SETTINGS.lock().unwrap().remove(&tunables_address(tunables));
+}
+
+fn new_store<E: wasmer::Engine>(
+    engine: &E,
+    tunables: Option<&Tunables>,
+    settings: Settings,
+) -> wasmer::Store {
+    let tunables = tunables.cloned().unwrap_or_default();
+    let store = wasmer::Store::new_with_tunables(engine, tunables.inner(engine.target()));
+
+    SETTINGS.lock().unwrap().insert(
+        tunables_address(store.tunables()),
+        Settings {
+            engine_id: engine.id().id(),
+            ..settings
+        },
+    );
+
+    store
+}
+
 #[pymethods]
 impl Store {
     #[new]
-    fn new(py: Python, engine: Option<&PyAny>) -> PyResult<Self> {
-        let (inner, engine_name, compiler_name) = match engine {
+    #[args(
+        engine = "None",
+        "*",
+        tunables = "None",
+        stack_size = "None",
+        coredump_directory = "None",
+        keyboard_interrupt = "false",
+        statistics = "false"
+    )]
+    fn new(
+        py: Python,
+        engine: Option<&PyAny>,
+        tunables: Option<&Tunables>,
+        stack_size: Option<usize>,
+        coredump_directory: Option<PathBuf>,
+        keyboard_interrupt: bool,
+        statistics: bool,
+    ) -> PyResult<Self> {
+        if stack_size == Some(0) {
+            return Err(to_py_err::<PyValueError, _>(
+                "The stack size must be a positive number of bytes",
+            ));
+        }
+
+        let settings = Settings {
+            stack_size,
+            coredump_directory,
+            keyboard_interrupt,
+            statistics,
+            ..Default::default()
+        };
+
+        let (inner, engine_name, compiler_name, fingerprint, progress, perf_map) = match engine {
             Some(engine) => {
                 if let Ok(universal) = engine.downcast::<PyCell<engines::Universal>>() {
                     let universal = universal.borrow();
 
                     (
-                        wasmer::Store::new(universal.inner()),
+                        new_store(universal.inner(), tunables, settings),
                         engines::Universal::name(),
                         universal.compiler_name().cloned(),
+                        universal.fingerprint().cloned(),
+                        universal.progress().clone(),
+                        universal.perf_map(),
                     )
                 } else if let Ok(dylib) = engine.downcast::<PyCell<engines::Dylib>>() {
                     let dylib = dylib.borrow();
 
                     (
-                        wasmer::Store::new(dylib.inner()),
+                        new_store(dylib.inner(), tunables, settings),
                         engines::Dylib::name(),
                         dylib.compiler_name().cloned(),
+                        dylib.fingerprint().cloned(),
+                        dylib.progress().clone(),
+                        false,
+                    )
+                } else if let Ok(staticlib) = engine.downcast::<PyCell<engines::Staticlib>>() {
+                    let staticlib = staticlib.borrow();
+
+                    (
+                        new_store(staticlib.inner(), tunables, settings),
+                        engines::Staticlib::name(),
+                        staticlib.compiler_name().cloned(),
+                        staticlib.fingerprint().cloned(),
+                        staticlib.progress().clone(),
+                        false,
                     )
                 } else {
                     return Err(to_py_err::<PyTypeError, _>("Unknown engine"));
@@ -99,30 +378,81 @@ impl Store {
                     .ok();
 
                 let target = None;
-                let engine = engines::Universal::raw_new(compiler, target)?;
+                let features = None;
+                let engine = engines::Universal::raw_new(compiler, target, features)?;
 
                 (
-                    wasmer::Store::new(engine.inner()),
+                    new_store(engine.inner(), tunables, settings),
                     engines::Universal::name(),
                     engine.compiler_name().cloned(),
+                    engine.fingerprint().cloned(),
+                    engine.progress().clone(),
+                    false,
                 )
             }
         };
 
         Ok(Self {
-            inner,
+            inner: Some(inner),
             engine_name: engine_name.to_string(),
             compiler_name,
+            fingerprint,
+            progress,
+            perf_map,
         })
     }
 
     #[getter]
-    fn engine_name(&self) -> &String {
+    pub(crate) fn engine_name(&self) -> &String {
         &self.engine_name
     }
 
     #[getter]
-    fn compiler_name(&self) -> Option<&String> {
+    pub(crate) fn compiler_name(&self) -> Option<&String> {
         self.compiler_name.as_ref()
     }
+
+    /// Closes the store: using it afterwards, e.g. to compile a
+    /// module, raises a `ValueError`. Closing a closed store does
+    /// nothing.
+    ///
+    /// It doesn't release the native resources created from the
+    /// store: the modules, the instances and the externs keep working,
+    /// since they hold their own reference to the engine. The memories
+    /// and the tables of an instance are released by closing the
+    /// instance, see `Instance.close`.
+    ///
+    /// A store is also a context manager, closed at the exit of the
+    /// `with` block, e.g. to make sure that nothing is created from it
+    /// past the block.
+    ///
+    /// ## Example
+    ///
+    /// ```py
+    /// from wasmer import Store, Module
+    ///
+    /// with Store() as store:
+    ///     module = Module(store, "(module)")
+    ///
+    /// try:
+    ///     Module(store, "(module)")
+    /// except ValueError:
+    ///     pass
+    /// else:
+    ///     assert False
+    /// ```
+    #[pyo3(text_signature = "($self)")]
+    fn close(&mut self) {
+        self.inner = None;
+    }
+
+    fn __enter__(slf: PyRef<Self>) -> PyRef<Self> {
+        slf
+    }
+
+    fn __exit__(&mut self, _exc_type: &PyAny, _exc_value: &PyAny, _traceback: &PyAny) -> bool {
+        self.close();
+
+        false
+    }
 }
@@ -0,0 +1,141 @@
+use crate::wasmer_inner::wasmer;
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, BTreeSet, BinaryHeap},
+    io,
+    sync::{Condvar, Mutex, MutexGuard},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// The timeouts of the calls, see `Function.__call__`. They are all
+/// watched by one thread, started by the first timeout, which
+/// interrupts a call by setting its `INTERRUPTED_GLOBAL` global.
+struct Timers {
+    started: bool,
+    next_id: u64,
+
+    /// The deadlines of the pending timeouts, the earliest first. The
+    /// cancelled ones are skipped once they are reached.
+    deadlines: BinaryHeap<Reverse<(Instant, u64)>>,
+
+    /// The globals of the pending timeouts, by identifier.
+    pending: BTreeMap<u64, wasmer::Global>,
+
+    /// The timeouts that have expired, and have not been cancelled
+    /// yet.
+    expired: BTreeSet<u64>,
+}
+
+static TIMERS: Mutex<Timers> = Mutex::new(Timers {
+    started: false,
+    next_id: 0,
+    deadlines: BinaryHeap::new(),
+    pending: BTreeMap::new(),
+    expired: BTreeSet::new(),
+});
+
+/// Notified when a timeout is added.
+static TIMERS_CHANGED: Condvar = Condvar::new();
+
+fn lock_timers() -> MutexGuard<'static, Timers> {
+    // The timers are consistent even if a thread has panicked.
+    TIMERS.lock().unwrap_or_else(|error| error.into_inner())
+}
+
+/// A pending timeout, see `start`.
+pub(crate) struct Timeout {
+    /// `None` if the deadline is too far to be represented.
+    id: Option<u64>,
+}
+
+/// Sets `interrupted` once `timeout` has elapsed, unless the returned
+/// `Timeout` is cancelled before.
+pub(crate) fn start(timeout: Duration, interrupted: wasmer::Global) -> io::Result<Timeout> {
+    let deadline = match Instant::now().checked_add(timeout) {
+        Some(deadline) => deadline,
+        None => return Ok(Timeout { id: None }),
+    };
+
+    let mut timers = lock_timers();
+
+    if !timers.started {
+        thread::Builder::new()
+            .name("wasmer-timer".to_string())
+            .spawn(watch)?;
+
+        timers.started = true;
+    }
+
+    let id = timers.next_id;
+    timers.next_id += 1;
+    timers.deadlines.push(Reverse((deadline, id)));
+    timers.pending.insert(id, interrupted);
+
+    TIMERS_CHANGED.notify_one();
+
+    Ok(Timeout { id: Some(id) })
+}
+
+impl Timeout {
+    /// Cancels the timeout, and returns whether it has expired.
+    pub(crate) fn cancel(self) -> bool {
+        let id = match self.id {
+            Some(id) => id,
+            None => return false,
+        };
+
+        let mut timers = lock_timers();
+        let has_expired = timers.pending.remove(&id).is_none() && timers.expired.remove(&id);
+
+        // Drop the deadlines of the cancelled timeouts once they
+        // outnumber the pending ones, e.g. after many calls with a
+        // long timeout.
+        let Timers {
+            deadlines, pending, ..
+        } = &mut *timers;
+
+        if deadlines.len() > 2 * pending.len() + 64 {
+            deadlines.retain(|Reverse((_, id))| pending.contains_key(id));
+        }
+
+        has_expired
+    }
+}
+
+/// Interrupts the calls whose timeout has expired, until the end of
+/// the process.
+fn watch() {
+    let mut timers = lock_timers();
+
+    loop {
+        let now = Instant::now();
+
+        while let Some(&Reverse((deadline, id))) = timers.deadlines.peek() {
+            if deadline > now {
+                break;
+            }
+
+            timers.deadlines.pop();
+
+            if let Some(interrupted) = timers.pending.remove(&id) {
+                if interrupted.set(wasmer::Value::I32(1)).is_ok() {
+                    timers.expired.insert(id);
+                }
+            }
+        }
+
+        timers = match timers.deadlines.peek() {
+            Some(&Reverse((deadline, _))) => {
+                TIMERS_CHANGED
+                    .wait_timeout(timers, deadline - now)
+                    .unwrap_or_else(|error| error.into_inner())
+                    .0
+            }
+
+            None => TIMERS_CHANGED
+                .wait(timers)
+                .unwrap_or_else(|error| error.into_inner()),
+        };
+    }
+}
@@ -0,0 +1,522 @@
+use crate::{
+    externals, externref, store,
+    wasmer_inner::{
+        wasmer::{
+            self,
+            vm::{
+                self, MemoryError, MemoryStyle, TableStyle, VMMemoryDefinition, VMTableDefinition,
+            },
+            MemoryType, Pages, TableType,
+        },
+        wasmer_vm::{TableElement, Trap},
+    },
+};
+use loupe::MemoryUsage;
+use pyo3::{class::basic::PyObjectProtocol, ffi, prelude::*, AsPyPointer};
+use std::{ptr::NonNull, sync::Arc};
+
+/// Controls how a `Store` creates the memories and the tables: their
+/// limits, and how the memories are laid out.
+///
+/// `maximum_memory_pages` and `maximum_table_elements` cap every
+/// memory and table of the store, whatever the modules declare. A
+/// memory or a table cannot grow beyond its cap, and creating one
+/// whose minimum exceeds its cap fails, e.g. instantiating a module
+/// raises a `LinkError`.
+///
+/// A memory whose maximum is within `static_memory_bound`, in pages,
+/// is static: its address space is reserved upfront, and followed by
+/// a guard of `static_memory_offset_guard_size` bytes. Other memories
+/// are dynamic, followed by a guard of
+/// `dynamic_memory_offset_guard_size` bytes. Those settings default
+/// to the Wasmer defaults for the target of the engine.
+///
+/// A `limiter` approves or denies the creation and the growth of the
+/// memories and the tables of the store, to account for them, e.g.
+/// with a quota per tenant. It is an object with a
+/// `memory_growing(current, desired, maximum)` method, and a
+/// `table_growing(current, desired, maximum)` method, both optional,
+/// and returning whether the memory, or the table, can grow from
+/// `current` to `desired` pages, or elements. `maximum` is the
+/// maximum of the memory, or the table, if any. At creation, `current`
+/// is 0. A denied growth makes `memory.grow` and `table.grow` return
+/// -1 in WebAssembly, `Memory.grow` and `Table.grow` raise a
+/// `RuntimeError`, and a denied creation makes instantiating a module
+/// raise a `LinkError`. A limiter raising an exception denies the
+/// growth, and the exception is reported by `sys.unraisablehook`.
+///
+/// ## Example
+///
+/// Cap each memory to 64 MiB, i.e. 1024 pages:
+///
+/// ```py
+/// from wasmer import Store, Module, Instance, Tunables
+///
+/// store = Store(tunables=Tunables(maximum_memory_pages=1024))
+/// instance = Instance(Module(store, '(module (memory (export "memory") 1))'))
+/// memory = instance.exports.memory
+///
+/// assert memory.type.maximum == 1024
+///
+/// try:
+///     memory.grow(1024)
+/// except RuntimeError:
+///     pass
+/// else:
+///     assert False
+/// ```
+///
+/// Share a quota of 100 pages between all the memories:
+///
+/// ```py
+/// from wasmer import Store, Module, Instance, Memory, MemoryType, Tunables
+///
+/// class Quota:
+///     def __init__(self, pages):
+///         self.pages = pages
+///
+///     def memory_growing(self, current, desired, maximum):
+///         if desired - current > self.pages:
+///             return False
+///
+///         self.pages -= desired - current
+///
+///         return True
+///
+/// quota = Quota(100)
+/// store = Store(tunables=Tunables(limiter=quota))
+/// instance = Instance(Module(store, '(module (memory (export "memory") 10))'))
+///
+/// assert quota.pages == 90
+///
+/// instance.exports.memory.grow(50)
+///
+/// assert quota.pages == 40
+///
+/// try:
+///     Memory(store, MemoryType(minimum=50))
+/// except RuntimeError:
+///     pass
+/// else:
+///     assert False
+/// ```
+#[pyclass]
+#[pyo3(
+    text_signature = "(*, maximum_memory_pages, maximum_table_elements, static_memory_bound, static_memory_offset_guard_size, dynamic_memory_offset_guard_size, limiter)"
+)]
+#[derive(Clone, Default)]
+pub struct Tunables {
+    /// The maximum number of pages of a memory, if any.
+    #[pyo3(get, set)]
+    pub maximum_memory_pages: Option<u32>,
+
+    /// The maximum number of elements of a table, if any.
+    #[pyo3(get, set)]
+    pub maximum_table_elements: Option<u32>,
+
+    /// The size, in pages, of the address space reserved for a
+    /// static memory.
+    #[pyo3(get, set)]
+    pub static_memory_bound: Option<u32>,
+
+    /// The size, in bytes, of the guard following a static memory.
+    #[pyo3(get, set)]
+    pub static_memory_offset_guard_size: Option<u64>,
+
+    /// The size, in bytes, of the guard following a dynamic memory.
+    #[pyo3(get, set)]
+    pub dynamic_memory_offset_guard_size: Option<u64>,
+
+    /// The object approving the creation and the growth of the
+    /// memories and the tables, if any.
+    #[pyo3(get, set)]
+    pub limiter: Option<PyObject>,
+}
+
+impl Tunables {
+    /// Gets the Wasmer tunables for an engine targeting `target`.
+    pub(crate) fn inner(&self, target: &wasmer::Target) -> LimitingTunables {
+        let mut base = wasmer::BaseTunables::for_target(target);
+
+        if let Some(static_memory_bound) = self.static_memory_bound {
+            base.static_memory_bound = Pages(static_memory_bound);
+        }
+
+        if let Some(guard_size) = self.static_memory_offset_guard_size {
+            base.static_memory_offset_guard_size = guard_size;
+        }
+
+        if let Some(guard_size) = self.dynamic_memory_offset_guard_size {
+            base.dynamic_memory_offset_guard_size = guard_size;
+        }
+
+        LimitingTunables {
+            base,
+            maximum_memory_pages: self.maximum_memory_pages.map(Pages),
+            maximum_table_elements: self.maximum_table_elements,
+            limiter: self.limiter.clone(),
+        }
+    }
+}
+
+#[pymethods]
+impl Tunables {
+    #[new]
+    #[args(
+        "*",
+        maximum_memory_pages = "None",
+        maximum_table_elements = "None",
+        static_memory_bound = "None",
+        static_memory_offset_guard_size = "None",
+        dynamic_memory_offset_guard_size = "None",
+        limiter = "None"
+    )]
+    fn new(
+        maximum_memory_pages: Option<u32>,
+        maximum_table_elements: Option<u32>,
+        static_memory_bound: Option<u32>,
+        static_memory_offset_guard_size: Option<u64>,
+        dynamic_memory_offset_guard_size: Option<u64>,
+        limiter: Option<PyObject>,
+    ) -> Self {
+        Self {
+            maximum_memory_pages,
+            maximum_table_elements,
+            static_memory_bound,
+            static_memory_offset_guard_size,
+            dynamic_memory_offset_guard_size,
+            limiter,
+        }
+    }
+}
+
+#[pyproto]
+impl PyObjectProtocol for Tunables {
+    fn __repr__(&self) -> PyResult<String> {
+        fn or_none<T: ToString>(value: Option<T>) -> String {
+            value.map_or_else(|| "None".to_string(), |value| value.to_string())
+        }
+
+        let limiter = match &self.limiter {
+            Some(limiter) => Python::with_gil(|py| -> PyResult<String> {
+                Ok(limiter.as_ref(py).repr()?.to_string())
+            })?,
+            None => "None".to_string(),
+        };
+
+        Ok(format!(
+            "Tunables(maximum_memory_pages={}, maximum_table_elements={}, static_memory_bound={}, static_memory_offset_guard_size={}, dynamic_memory_offset_guard_size={}, limiter={})",
+            or_none(self.maximum_memory_pages),
+            or_none(self.maximum_table_elements),
+            or_none(self.static_memory_bound),
+            or_none(self.static_memory_offset_guard_size),
+            or_none(self.dynamic_memory_offset_guard_size),
+            limiter,
+        ))
+    }
+}
+
+/// The base tunables, where the maximum of the memories and the
+/// tables is capped, and whose memories and tables are approved by
+/// the limiter, if any.
+#[derive(MemoryUsage)]
+pub(crate) struct LimitingTunables {
+    base: wasmer::BaseTunables,
+    maximum_memory_pages: Option<Pages>,
+    maximum_table_elements: Option<u32>,
+    #[loupe(skip)]
+    limiter: Option<PyObject>,
+}
+
+impl Drop for LimitingTunables {
+    fn drop(&mut self) {
+        store::forget_settings(self);
+        externref::release(self);
+    }
+}
+
+impl LimitingTunables {
+    fn memory_type(&self, ty: &MemoryType) -> Result<MemoryType, MemoryError> {
+        let mut ty = *ty;
+
+        if let Some(maximum) = self.maximum_memory_pages {
+            if ty.minimum > maximum {
+                return Err(MemoryError::MinimumMemoryTooLarge {
+                    min_requested: ty.minimum,
+                    max_allowed: maximum,
+                });
+            }
+
+            ty.maximum = Some(ty.maximum.map_or(maximum, |declared| declared.min(maximum)));
+        }
+
+        Ok(ty)
+    }
+
+    fn table_type(&self, ty: &TableType) -> Result<TableType, String> {
+        let mut ty = *ty;
+
+        if let Some(maximum) = self.maximum_table_elements {
+            if ty.minimum > maximum {
+                return Err(format!(
+                    "The minimum requested ({} elements) table is greater than the maximum allowed table ({} elements)",
+                    ty.minimum, maximum
+                ));
+            }
+
+            ty.maximum = Some(ty.maximum.map_or(maximum, |declared| declared.min(maximum)));
+        }
+
+        Ok(ty)
+    }
+}
+
+impl LimitingTunables {
+    fn limit_memory(
+        &self,
+        ty: &MemoryType,
+        create: impl FnOnce(&MemoryType) -> Result<Arc<dyn vm::Memory>, MemoryError>,
+    ) -> Result<Arc<dyn vm::Memory>, MemoryError> {
+        let ty = self.memory_type(ty)?;
+
+        if let Some(limiter) = &self.limiter {
+            if !allows(
+                limiter,
+                "memory_growing",
+                0,
+                ty.minimum.0,
+                ty.maximum.map(|maximum| maximum.0),
+            ) {
+                return Err(MemoryError::Generic(
+                    "The creation of the memory has been denied by the limiter".to_string(),
+                ));
+            }
+        }
+
+        // Every memory is wrapped, to notify its grow callbacks, see
+        // `Memory.add_grow_callback`.
+        Ok(Arc::new(LimitedMemory {
+            inner: create(&ty)?,
+            limiter: self.limiter.clone(),
+        }))
+    }
+
+    fn limit_table(
+        &self,
+        ty: &TableType,
+        create: impl FnOnce(&TableType) -> Result<Arc<dyn vm::Table>, String>,
+    ) -> Result<Arc<dyn vm::Table>, String> {
+        let ty = self.table_type(ty)?;
+
+        let limiter = match &self.limiter {
+            Some(limiter) => limiter.clone(),
+            None => return create(&ty),
+        };
+
+        if !allows(&limiter, "table_growing", 0, ty.minimum, ty.maximum) {
+            return Err("The creation of the table has been denied by the limiter".to_string());
+        }
+
+        Ok(Arc::new(LimitedTable {
+            inner: create(&ty)?,
+            limiter,
+        }))
+    }
+}
+
+impl wasmer::Tunables for LimitingTunables {
+    fn memory_style(&self, memory: &MemoryType) -> MemoryStyle {
+        let memory = self.memory_type(memory).unwrap_or(*memory);
+
+        self.base.memory_style(&memory)
+    }
+
+    fn table_style(&self, table: &TableType) -> TableStyle {
+        self.base.table_style(table)
+    }
+
+    fn create_host_memory(
+        &self,
+        ty: &MemoryType,
+        style: &MemoryStyle,
+    ) -> Result<Arc<dyn vm::Memory>, MemoryError> {
+        self.limit_memory(ty, |ty| self.base.create_host_memory(ty, style))
+    }
+
+    unsafe fn create_vm_memory(
+        &self,
+        ty: &MemoryType,
+        style: &MemoryStyle,
+        vm_definition_location: NonNull<VMMemoryDefinition>,
+    ) -> Result<Arc<dyn vm::Memory>, MemoryError> {
+        self.limit_memory(ty, |ty| {
+            self.base
+                .create_vm_memory(ty, style, vm_definition_location)
+        })
+    }
+
+    fn create_host_table(
+        &self,
+        ty: &TableType,
+        style: &TableStyle,
+    ) -> Result<Arc<dyn vm::Table>, String> {
+        self.limit_table(ty, |ty| self.base.create_host_table(ty, style))
+    }
+
+    unsafe fn create_vm_table(
+        &self,
+        ty: &TableType,
+        style: &TableStyle,
+        vm_definition_location: NonNull<VMTableDefinition>,
+    ) -> Result<Arc<dyn vm::Table>, String> {
+        self.limit_table(ty, |ty| {
+            self.base.create_vm_table(ty, style, vm_definition_location)
+        })
+    }
+}
+
+/// Asks `limiter` whether a memory, or a table, can grow from
+/// `current` to `desired`, by calling its `method`. A missing method
+/// approves every growth.
+fn allows(
+    limiter: &PyObject,
+    method: &str,
+    current: u32,
+    desired: u32,
+    maximum: Option<u32>,
+) -> bool {
+    Python::with_gil(|py| {
+        let limiter = limiter.as_ref(py);
+
+        if !limiter.hasattr(method).unwrap_or(false) {
+            return true;
+        }
+
+        match limiter
+            .call_method1(method, (current, desired, maximum))
+            .and_then(|allowed| allowed.is_true())
+        {
+            Ok(allowed) => allowed,
+            Err(error) => {
+                error.restore(py);
+
+                // SAFETY: the GIL is held, and an error is set.
+                unsafe { ffi::PyErr_WriteUnraisable(limiter.as_ptr()) };
+
+                false
+            }
+        }
+    })
+}
+
+/// A memory whose growth is approved by a limiter, if any, and
+/// notified to its grow callbacks.
+#[derive(Debug, MemoryUsage)]
+struct LimitedMemory {
+    inner: Arc<dyn vm::Memory>,
+    #[loupe(skip)]
+    limiter: Option<PyObject>,
+}
+
+impl Drop for LimitedMemory {
+    fn drop(&mut self) {
+        externals::forget_grow_callbacks(self.inner.vmmemory().as_ptr() as usize);
+    }
+}
+
+impl vm::Memory for LimitedMemory {
+    fn ty(&self) -> MemoryType {
+        self.inner.ty()
+    }
+
+    fn style(&self) -> &MemoryStyle {
+        self.inner.style()
+    }
+
+    fn size(&self) -> Pages {
+        self.inner.size()
+    }
+
+    fn grow(&self, delta: Pages) -> Result<Pages, MemoryError> {
+        let current = self.inner.size();
+
+        // An overflowing growth is left to fail.
+        if let (Some(limiter), Some(desired)) = (&self.limiter, current.0.checked_add(delta.0)) {
+            let maximum = self.inner.ty().maximum.map(|maximum| maximum.0);
+
+            if !allows(limiter, "memory_growing", current.0, desired, maximum) {
+                return Err(MemoryError::Generic(
+                    "The growth of the memory has been denied by the limiter".to_string(),
+                ));
+            }
+        }
+
+        let previous = self.inner.grow(delta)?;
+
+        if delta.0 > 0 {
+            externals::notify_growth(
+                self.inner.vmmemory().as_ptr() as usize,
+                previous.0,
+                self.inner.size().0,
+            );
+        }
+
+        Ok(previous)
+    }
+
+    fn vmmemory(&self) -> NonNull<VMMemoryDefinition> {
+        self.inner.vmmemory()
+    }
+}
+
+/// A table whose growth is approved by a limiter.
+#[derive(Debug, MemoryUsage)]
+struct LimitedTable {
+    inner: Arc<dyn vm::Table>,
+    #[loupe(skip)]
+    limiter: PyObject,
+}
+
+impl vm::Table for LimitedTable {
+    fn style(&self) -> &TableStyle {
+        self.inner.style()
+    }
+
+    fn ty(&self) -> &TableType {
+        self.inner.ty()
+    }
+
+    fn size(&self) -> u32 {
+        self.inner.size()
+    }
+
+    fn grow(&self, delta: u32, init_value: TableElement) -> Option<u32> {
+        let current = self.inner.size();
+
+        if let Some(desired) = current.checked_add(delta) {
+            if !allows(
+                &self.limiter,
+                "table_growing",
+                current,
+                desired,
+                self.inner.ty().maximum,
+            ) {
+                return None;
+            }
+        }
+
+        self.inner.grow(delta, init_value)
+    }
+
+    fn get(&self, index: u32) -> Option<TableElement> {
+        self.inner.get(index)
+    }
+
+    fn set(&self, index: u32, reference: TableElement) -> Result<(), Trap> {
+        self.inner.set(index, reference)
+    }
+
+    fn vmtable(&self) -> NonNull<VMTableDefinition> {
+        self.inner.vmtable()
+    }
+}
@@ -1,13 +1,18 @@
 use crate::{errors::to_py_err, wasmer_inner::wasmer};
 use pyo3::{
-    class::basic::PyObjectProtocol,
+    class::basic::{CompareOp, PyObjectProtocol},
     conversion::{FromPyObject, IntoPy},
     exceptions::PyValueError,
     prelude::*,
 };
-use std::{convert::TryFrom, slice};
+use std::{
+    collections::hash_map::DefaultHasher,
+    convert::TryFrom,
+    hash::{Hash, Hasher},
+    slice,
+};
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 #[repr(u8)]
 pub enum Type {
     I32 = 1,
@@ -110,6 +115,24 @@ impl Into<wasmer::Type> for Type {
     }
 }
 
+/// Compares two types by value: only the equality and the inequality
+/// are supported.
+fn richcmp<T: PartialEq>(py: Python, left: &T, right: &T, op: CompareOp) -> PyObject {
+    match op {
+        CompareOp::Eq => (left == right).into_py(py),
+        CompareOp::Ne => (left != right).into_py(py),
+        _ => py.NotImplemented(),
+    }
+}
+
+/// Hashes a type by value, consistently with `richcmp`.
+fn hash<T: Hash>(value: &T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+
+    hasher.finish()
+}
+
 /// Represents the signature of a function that is either implemented
 /// in WebAssembly module or exposed to WebAssembly by the host.
 ///
@@ -125,8 +148,13 @@ impl Into<wasmer::Type> for Type {
 ///     params=[Type.I32, Type.I32],
 ///     results=[Type.I32]
 /// )
+///
+/// # Types are compared by value.
+/// assert function_type == FunctionType([Type.I32, Type.I32], [Type.I32])
+/// assert function_type != FunctionType([Type.I32], [])
 /// ```
 #[pyclass]
+#[derive(Clone, PartialEq, Eq, Hash)]
 #[pyo3(text_signature = "(params, results)")]
 pub struct FunctionType {
     /// Parameters, i.e. inputs, of the function.
@@ -180,6 +208,14 @@ impl PyObjectProtocol for FunctionType {
             self.params, self.results,
         )
     }
+
+    fn __richcmp__(&self, other: PyRef<Self>, op: CompareOp) -> PyObject {
+        richcmp(other.py(), self, &*other, op)
+    }
+
+    fn __hash__(&self) -> u64 {
+        hash(self)
+    }
 }
 
 /// A descriptor for a WebAssembly memory type.
@@ -196,8 +232,11 @@ impl PyObjectProtocol for FunctionType {
 ///     minimum=1,
 ///     shared=True
 /// )
+///
+/// assert memory_type == MemoryType(1, shared=True)
 /// ```
 #[pyclass]
+#[derive(Clone, PartialEq, Eq, Hash)]
 #[pyo3(text_signature = "(minimum, /, maximum, shared)")]
 pub struct MemoryType {
     /// The minimum number of pages in the memory.
@@ -256,6 +295,14 @@ impl PyObjectProtocol for MemoryType {
             self.minimum, self.maximum, self.shared,
         )
     }
+
+    fn __richcmp__(&self, other: PyRef<Self>, op: CompareOp) -> PyObject {
+        richcmp(other.py(), self, &*other, op)
+    }
+
+    fn __hash__(&self) -> u64 {
+        hash(self)
+    }
 }
 
 /// A descriptor for a WebAssembly global.
@@ -267,9 +314,12 @@ impl PyObjectProtocol for MemoryType {
 ///
 /// # Describes a global of kind `i32` which is immutable.
 /// global_type = GlobalType(Type.I32, mutable=False)
+///
+/// assert global_type == GlobalType(Type.I32)
 /// ```
 #[pyclass]
-#[pyo3(text_signature = "(type, mutable)")]
+#[derive(Clone, PartialEq, Eq, Hash)]
+#[pyo3(text_signature = "(type, /, mutable=False)")]
 pub struct GlobalType {
     /// The type of the value stored in the global.
     #[pyo3(get)]
@@ -283,6 +333,7 @@ pub struct GlobalType {
 #[pymethods]
 impl GlobalType {
     #[new]
+    #[args(mutable = "false")]
     fn new(r#type: Type, mutable: bool) -> Self {
         Self { r#type, mutable }
     }
@@ -305,6 +356,14 @@ impl PyObjectProtocol for GlobalType {
             self.r#type, self.mutable,
         )
     }
+
+    fn __richcmp__(&self, other: PyRef<Self>, op: CompareOp) -> PyObject {
+        richcmp(other.py(), self, &*other, op)
+    }
+
+    fn __hash__(&self) -> u64 {
+        hash(self)
+    }
 }
 
 /// A descriptor for a table in a WebAssembly module.
@@ -320,9 +379,13 @@ impl PyObjectProtocol for GlobalType {
 /// from wasmer import TableType, Type
 ///
 /// table_type = TableType(Type.I32, minimum=7, maximum=42)
+///
+/// assert table_type == TableType(Type.I32, 7, 42)
+/// assert table_type != TableType(Type.I32, 7)
 /// ```
 #[pyclass]
-#[pyo3(text_signature = "(type, minimum, maximum)")]
+#[derive(Clone, PartialEq, Eq, Hash)]
+#[pyo3(text_signature = "(type, minimum, /, maximum=None)")]
 pub struct TableType {
     /// The type of data stored in elements of the table.
     #[pyo3(get)]
@@ -340,6 +403,7 @@ pub struct TableType {
 #[pymethods]
 impl TableType {
     #[new]
+    #[args(maximum = "None")]
     fn new(r#type: Type, minimum: u32, maximum: Option<u32>) -> Self {
         Self {
             r#type,
@@ -373,6 +437,14 @@ impl PyObjectProtocol for TableType {
             self.r#type, self.minimum, self.maximum,
         )
     }
+
+    fn __richcmp__(&self, other: PyRef<Self>, op: CompareOp) -> PyObject {
+        richcmp(other.py(), self, &*other, op)
+    }
+
+    fn __hash__(&self) -> u64 {
+        hash(self)
+    }
 }
 
 /// Represents the type of a module's export (not to be confused with
@@ -442,6 +514,20 @@ impl ExportType {
     }
 }
 
+#[pyproto]
+impl PyObjectProtocol for ExportType {
+    fn __str__(&self) -> PyResult<String> {
+        let gil_guard = Python::acquire_gil();
+        let py = gil_guard.python();
+
+        Ok(format!(
+            "ExportType(name: {:?}, type: {})",
+            self.name,
+            self.r#type.as_ref(py).str()?,
+        ))
+    }
+}
+
 impl TryFrom<wasmer::ExportType> for ExportType {
     type Error = PyErr;
 
@@ -533,6 +619,21 @@ impl ImportType {
     }
 }
 
+#[pyproto]
+impl PyObjectProtocol for ImportType {
+    fn __str__(&self) -> PyResult<String> {
+        let gil_guard = Python::acquire_gil();
+        let py = gil_guard.python();
+
+        Ok(format!(
+            "ImportType(module: {:?}, name: {:?}, type: {})",
+            self.module,
+            self.name,
+            self.r#type.as_ref(py).str()?,
+        ))
+    }
+}
+
 impl TryFrom<wasmer::ImportType> for ImportType {
     type Error = PyErr;
 
@@ -1,9 +1,17 @@
-use crate::wasmer_inner::{wasmer, wasmer_types::NativeWasmType};
+use crate::{
+    externals::{check_storable, Function},
+    externref,
+    wasmer_inner::{wasmer, wasmer_types::NativeWasmType},
+};
 use pyo3::{
+    buffer::PyBuffer,
     class::basic::PyObjectProtocol,
+    exceptions::PyTypeError,
     prelude::*,
     types::{PyFloat, PyLong},
+    PyNativeType,
 };
+use std::convert::TryInto;
 
 pub trait NativeFromPyAny {
     type Native;
@@ -67,6 +75,14 @@ impl NativeFromPyAny for u128 {
     }
 }
 
+impl NativeFromPyAny for i128 {
+    type Native = Self;
+
+    fn from_pyany(any: &PyAny) -> PyResult<Self::Native> {
+        any.downcast::<PyLong>()?.extract::<Self::Native>()
+    }
+}
+
 pub trait TryFromPyAny {
     fn try_from<N>(&self) -> PyResult<N::Native>
     where
@@ -82,7 +98,45 @@ impl TryFromPyAny for PyAny {
     }
 }
 
-pub(crate) fn to_wasm_value((any, ty): (&PyAny, wasmer::Type)) -> PyResult<wasmer::Value> {
+/// Reads a `v128` value from a buffer of 16 bytes, in little-endian
+/// order like in a memory.
+fn v128_from_buffer(any: &PyAny) -> Option<u128> {
+    let bytes = PyBuffer::<u8>::get(any).ok()?.to_vec(any.py()).ok()?;
+
+    Some(u128::from_le_bytes(bytes.try_into().ok()?))
+}
+
+/// Converts a Python value to a value of type `ty` given to `store`,
+/// see `externref::to_store`.
+pub(crate) fn to_wasm_value(
+    store: &wasmer::Store,
+    (any, ty): (&PyAny, wasmer::Type),
+) -> PyResult<wasmer::Value> {
+    Ok(externref::to_store(
+        any.py(),
+        store,
+        to_unowned_value((any, ty))?,
+    ))
+}
+
+/// Converts a Python value to a value of type `ty`, given to no store
+/// yet.
+fn to_unowned_value((any, ty): (&PyAny, wasmer::Type)) -> PyResult<wasmer::Value> {
+    if let Ok(value) = any.downcast::<PyCell<Value>>() {
+        let value = value.borrow().inner().clone();
+
+        if value.ty() != ty {
+            return Err(PyTypeError::new_err(format!(
+                "The value `{:?}` has the type `{:?}`, expected `{:?}`",
+                value,
+                value.ty(),
+                ty
+            )));
+        }
+
+        return Ok(value);
+    }
+
     Ok(match ty {
         wasmer::Type::I32 => any
             .try_from::<i32>()
@@ -94,8 +148,28 @@ pub(crate) fn to_wasm_value((any, ty): (&PyAny, wasmer::Type)) -> PyResult<wasme
             .to_value(),
         wasmer::Type::F32 => any.try_from::<f32>()?.to_value(),
         wasmer::Type::F64 => any.try_from::<f64>()?.to_value(),
-        wasmer::Type::V128 => any.try_from::<u128>()?.to_value(),
-        _ => unimplemented!(),
+        wasmer::Type::V128 => any
+            .try_from::<u128>()
+            .or_else(|_| any.try_from::<i128>().map(|x| x as u128))
+            .or_else(|error| v128_from_buffer(any).ok_or(error))?
+            .to_value(),
+        wasmer::Type::FuncRef if any.is_none() => wasmer::Value::FuncRef(None),
+        wasmer::Type::FuncRef => match any.downcast::<PyCell<Function>>() {
+            Ok(function) => {
+                let function = function.borrow().inner().clone();
+                check_storable(&function)?;
+
+                wasmer::Value::FuncRef(Some(function))
+            }
+            Err(_) => {
+                return Err(PyTypeError::new_err(format!(
+                    "A `funcref` value must be a `Function` or `None`, got `{}`",
+                    any.get_type().name()?
+                )))
+            }
+        },
+        wasmer::Type::ExternRef if any.is_none() => wasmer::Value::null(),
+        wasmer::Type::ExternRef => wasmer::Value::ExternRef(externref::new(PyObject::from(any))),
     })
 }
 
@@ -107,7 +181,13 @@ pub(crate) fn to_py_object<'p>(py: Python<'p>) -> impl Fn(&wasmer::Value) -> PyO
             wasmer::Value::F32(value) => value.to_object(py),
             wasmer::Value::F64(value) => value.to_object(py),
             wasmer::Value::V128(value) => value.to_object(py),
-            _ => unimplemented!(),
+            wasmer::Value::FuncRef(Some(function)) => {
+                Function::raw_new(function.clone()).into_py(py)
+            }
+            wasmer::Value::FuncRef(None) => py.None(),
+            wasmer::Value::ExternRef(value) => {
+                externref::to_object(py, value).unwrap_or_else(|| py.None())
+            }
         }
     }
 }
@@ -117,12 +197,28 @@ pub(crate) fn to_py_object<'p>(py: Python<'p>) -> impl Fn(&wasmer::Value) -> PyO
 /// Most of the time, the types for WebAssembly values will be
 /// inferred. When it's not possible, the `Value` class is necessary.
 ///
+/// A `Value` is accepted wherever a WebAssembly value is expected,
+/// e.g. as an argument of a function, or as the value of a global,
+/// as long as it has the expected type.
+///
 /// ## Example
 ///
 /// ```py
-/// from wasmer import Value
+/// from wasmer import Store, Module, Instance, Value
 ///
 /// value = Value.i32(42)
+///
+/// module = Module(
+///     Store(),
+///     """
+///     (module
+///       (func (export "wide") (param i64) (result i64)
+///         local.get 0))
+///     """
+/// )
+/// instance = Instance(module)
+///
+/// assert instance.exports.wide(Value.i64(7)) == 7
 /// ```
 #[pyclass(unsendable)]
 pub struct Value {
@@ -205,7 +301,10 @@ impl Value {
         }
     }
 
-    /// Build a WebAssembly `v128` value.
+    /// Build a WebAssembly `v128` value, from an integer or from 16
+    /// bytes in little-endian order, like in a memory. Anywhere a
+    /// `v128` value is expected, an integer or 16 bytes can be given
+    /// too, and `v128` results are integers.
     ///
     /// ## Example
     ///
@@ -213,13 +312,33 @@ impl Value {
     /// from wasmer import Value
     ///
     /// value = Value.v128(42)
+    ///
+    /// assert repr(Value.v128(bytes([42] + [0] * 15))) == repr(value)
     /// ```
     #[staticmethod]
     #[pyo3(text_signature = "(value)")]
-    fn v128(value: u128) -> Self {
-        Self {
-            inner: wasmer::Value::V128(value),
-        }
+    fn v128(value: &PyAny) -> PyResult<Self> {
+        Ok(Self {
+            inner: to_unowned_value((value, wasmer::Type::V128))?,
+        })
+    }
+
+    /// Build a WebAssembly `externref` value, holding any Python
+    /// object. `None` is the null reference.
+    ///
+    /// ## Example
+    ///
+    /// ```py
+    /// from wasmer import Value
+    ///
+    /// value = Value.externref([1, 2, 3])
+    /// ```
+    #[staticmethod]
+    #[pyo3(text_signature = "(value)")]
+    fn externref(value: &PyAny) -> PyResult<Self> {
+        Ok(Self {
+            inner: to_unowned_value((value, wasmer::Type::ExternRef))?,
+        })
     }
 }
 
@@ -0,0 +1,188 @@
+use crate::{
+    import_object::ImportObject,
+    wasmer_inner::{wasmer, wasmer_wasi},
+};
+use std::sync::{Arc, Mutex};
+use wasmer_wasi::types::{
+    __wasi_clockid_t, __wasi_errno_t, __wasi_timestamp_t, __WASI_CLOCK_THREAD_CPUTIME_ID,
+    __WASI_EFAULT, __WASI_EINVAL, __WASI_ESUCCESS,
+};
+
+/// The deterministic sources configured on a `StateBuilder`.
+#[derive(Clone, Copy, Default)]
+pub(crate) struct Sources {
+    pub(crate) clock: Option<Clock>,
+    pub(crate) random_seed: Option<u64>,
+}
+
+/// A clock starting at `time`, and advancing by `step` after each
+/// reading, in nanoseconds.
+#[derive(Clone, Copy)]
+pub(crate) struct Clock {
+    pub(crate) time: u64,
+    pub(crate) step: u64,
+}
+
+/// The `splitmix64` generator: small, fast, and producing the same
+/// bytes on every platform for a given seed.
+struct Random {
+    state: u64,
+}
+
+impl Random {
+    fn next(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+
+        z ^ (z >> 31)
+    }
+
+    fn fill(&mut self, bytes: &mut [u8]) {
+        for chunk in bytes.chunks_mut(8) {
+            let random = self.next().to_le_bytes();
+            chunk.copy_from_slice(&random[..chunk.len()]);
+        }
+    }
+}
+
+/// The state of the sources of an `Environment`, shared by all the
+/// import objects it generates.
+#[derive(Clone)]
+pub(crate) struct State {
+    clock: Option<Arc<Mutex<Clock>>>,
+    random: Option<Arc<Mutex<Random>>>,
+}
+
+impl State {
+    pub(crate) fn new(sources: Sources) -> Self {
+        Self {
+            clock: sources.clock.map(|clock| Arc::new(Mutex::new(clock))),
+            random: sources
+                .random_seed
+                .map(|seed| Arc::new(Mutex::new(Random { state: seed }))),
+        }
+    }
+
+    /// Replaces the clock and random imports of `import_object`, in
+    /// the `namespace_name` WASI namespace, by the deterministic
+    /// ones.
+    pub(crate) fn override_imports(
+        &self,
+        store: &wasmer::Store,
+        wasi_env: &wasmer_wasi::WasiEnv,
+        namespace_name: &str,
+        import_object: &mut ImportObject,
+    ) {
+        let env = Env {
+            wasi: wasi_env.clone(),
+            state: self.clone(),
+        };
+        let mut define = |name: &str, function| {
+            import_object.insert(
+                namespace_name.to_string(),
+                name.to_string(),
+                wasmer::Extern::Function(function),
+            )
+        };
+
+        if self.clock.is_some() {
+            define(
+                "clock_res_get",
+                wasmer::Function::new_native_with_env(store, env.clone(), clock_res_get),
+            );
+            define(
+                "clock_time_get",
+                wasmer::Function::new_native_with_env(store, env.clone(), clock_time_get),
+            );
+        }
+
+        if self.random.is_some() {
+            define(
+                "random_get",
+                wasmer::Function::new_native_with_env(store, env, random_get),
+            );
+        }
+    }
+}
+
+#[derive(Clone)]
+struct Env {
+    wasi: wasmer_wasi::WasiEnv,
+    state: State,
+}
+
+impl wasmer::WasmerEnv for Env {
+    fn init_with_instance(
+        &mut self,
+        instance: &wasmer::Instance,
+    ) -> Result<(), wasmer::HostEnvInitError> {
+        self.wasi.init_with_instance(instance)
+    }
+}
+
+fn clock_res_get(
+    env: &Env,
+    clock_id: __wasi_clockid_t,
+    resolution: wasmer::WasmPtr<__wasi_timestamp_t>,
+) -> __wasi_errno_t {
+    if clock_id > __WASI_CLOCK_THREAD_CPUTIME_ID {
+        return __WASI_EINVAL;
+    }
+
+    match resolution.deref(env.wasi.memory()) {
+        Some(resolution) => {
+            resolution.set(1);
+
+            __WASI_ESUCCESS
+        }
+        None => __WASI_EFAULT,
+    }
+}
+
+fn clock_time_get(
+    env: &Env,
+    clock_id: __wasi_clockid_t,
+    _precision: __wasi_timestamp_t,
+    time: wasmer::WasmPtr<__wasi_timestamp_t>,
+) -> __wasi_errno_t {
+    if clock_id > __WASI_CLOCK_THREAD_CPUTIME_ID {
+        return __WASI_EINVAL;
+    }
+
+    let time = match time.deref(env.wasi.memory()) {
+        Some(time) => time,
+        None => return __WASI_EFAULT,
+    };
+
+    // All the clocks share the same readings.
+    if let Some(clock) = &env.state.clock {
+        let mut clock = clock.lock().unwrap();
+        time.set(clock.time);
+        clock.time = clock.time.wrapping_add(clock.step);
+    }
+
+    __WASI_ESUCCESS
+}
+
+fn random_get(env: &Env, buffer: u32, buffer_length: u32) -> __wasi_errno_t {
+    let view = env.wasi.memory().view::<u8>();
+    let cells = match view.get(buffer as usize..buffer as usize + buffer_length as usize) {
+        Some(cells) => cells,
+        None => return __WASI_EFAULT,
+    };
+
+    let mut bytes = vec![0; cells.len()];
+
+    if let Some(random) = &env.state.random {
+        random.lock().unwrap().fill(&mut bytes);
+    }
+
+    for (cell, byte) in cells.iter().zip(bytes) {
+        cell.set(byte);
+    }
+
+    __WASI_ESUCCESS
+}
@@ -0,0 +1,906 @@
+use crate::{
+    errors::to_py_err,
+    wasmer_inner::{wasmer_vfs, wasmer_wasi},
+};
+use pyo3::{
+    buffer::PyBuffer,
+    exceptions::{
+        PyFileExistsError, PyFileNotFoundError, PyIsADirectoryError, PyNotADirectoryError,
+        PyOSError, PyTypeError,
+    },
+    prelude::*,
+    types::{PyBytes, PyDict, PyString},
+    PyNativeType,
+};
+use std::{
+    collections::{BTreeMap, HashMap},
+    convert::TryInto,
+    fs,
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::{Component, Path, PathBuf},
+    sync::{Arc, Mutex, MutexGuard},
+};
+use wasmer_vfs::{FileOpener, FileType, Metadata, OpenOptions, OpenOptionsConfig, ReadDir};
+use wasmer_wasi::{FsError, VirtualFile};
+
+/// Memory file systems are mounted by the WASI file system under
+/// this prefix, followed by their index. The NUL byte guarantees
+/// that the path does not exist on the host, which matters because
+/// some WASI calls check the existence of paths on the host
+/// directly.
+const MOUNT_PREFIX: &str = "\0memory-file-system-";
+
+/// The default maximum size of the files written by a WASI module
+/// in a memory file system, see `MemoryFileSystem`.
+const DEFAULT_MAX_FILE_SIZE: u64 = 1 << 30;
+
+#[derive(Debug, Clone)]
+enum Node {
+    Directory,
+    File(Arc<Mutex<Vec<u8>>>),
+}
+
+impl Node {
+    fn metadata(&self) -> Metadata {
+        let (ft, len) = match self {
+            Node::Directory => (
+                FileType {
+                    dir: true,
+                    ..Default::default()
+                },
+                0,
+            ),
+            Node::File(data) => (
+                FileType {
+                    file: true,
+                    ..Default::default()
+                },
+                data.lock().map(|data| data.len() as u64).unwrap_or(0),
+            ),
+        };
+
+        Metadata {
+            ft,
+            len,
+            ..Default::default()
+        }
+    }
+}
+
+type Nodes = BTreeMap<String, Node>;
+
+/// Normalizes a path of a memory file system to an absolute path
+/// with `/` as separator, used as the key of its node.
+fn normalize(path: &Path) -> Result<String, FsError> {
+    let mut components = Vec::new();
+
+    for component in path.components() {
+        match component {
+            Component::Normal(name) => {
+                components.push(name.to_str().ok_or(FsError::InvalidInput)?);
+            }
+            Component::ParentDir => {
+                components.pop().ok_or(FsError::InvalidInput)?;
+            }
+            Component::RootDir | Component::CurDir | Component::Prefix(_) => {}
+        }
+    }
+
+    Ok(format!("/{}", components.join("/")))
+}
+
+fn parent_key(key: &str) -> Option<&str> {
+    match key.rfind('/') {
+        _ if key == "/" => None,
+        Some(0) => Some("/"),
+        Some(index) => Some(&key[..index]),
+        None => None,
+    }
+}
+
+fn child_key(key: &str, name: &str) -> String {
+    if key == "/" {
+        format!("/{}", name)
+    } else {
+        format!("{}/{}", key, name)
+    }
+}
+
+/// Gets the keys of the direct children of the directory `key`.
+fn children<'a>(nodes: &'a Nodes, key: &'a str) -> impl Iterator<Item = &'a String> + 'a {
+    let prefix = child_key(key, "");
+
+    nodes
+        .range(prefix.clone()..)
+        .take_while(move |(child, _)| child.starts_with(&prefix))
+        .map(|(child, _)| child)
+        .filter(move |child| parent_key(child) == Some(key))
+}
+
+fn check_parent(nodes: &Nodes, key: &str) -> Result<(), FsError> {
+    match parent_key(key).map(|parent| nodes.get(parent)) {
+        Some(Some(Node::Directory)) => Ok(()),
+        Some(Some(Node::File(_))) => Err(FsError::BaseNotDirectory),
+        Some(None) => Err(FsError::EntityNotFound),
+        None => Err(FsError::PermissionDenied),
+    }
+}
+
+fn fs_error_to_py_err(error: FsError, path: &Path) -> PyErr {
+    let message = format!("{}: `{}`", error, path.display());
+
+    match error {
+        FsError::EntityNotFound => to_py_err::<PyFileNotFoundError, _>(message),
+        FsError::AlreadyExists => to_py_err::<PyFileExistsError, _>(message),
+        FsError::NotAFile => to_py_err::<PyIsADirectoryError, _>(message),
+        FsError::BaseNotDirectory => to_py_err::<PyNotADirectoryError, _>(message),
+        _ => to_py_err::<PyOSError, _>(message),
+    }
+}
+
+/// An in-memory file system, to be mapped as a directory of a WASI
+/// module with `StateBuilder.map_directory`. The WASI module has no
+/// access to the host file system through it.
+///
+/// The file system is built from a dictionary, where keys are file
+/// or directory names, and values are the file contents, as
+/// `bytes`, or the directory entries, as a dictionary. It can be
+/// read and modified from Python before, during and after the
+/// execution of the WASI module.
+///
+/// ## Example
+///
+/// ```py
+/// from wasmer import wasi
+///
+/// file_system = wasi.MemoryFileSystem({
+///     "input.txt": b"hello",
+///     "data": {
+///         "numbers.bin": bytes([1, 2, 3]),
+///     },
+/// })
+///
+/// wasi_env = \
+///     wasi.StateBuilder('test-program'). \
+///         map_directory('sandbox', file_system). \
+///         finalize()
+///
+/// assert file_system.list_directory("/") == ["data", "input.txt"]
+/// assert file_system.read_file("/data/numbers.bin") == bytes([1, 2, 3])
+/// ```
+///
+/// The WASI module cannot make a file larger than `max_file_size`
+/// bytes, 1GiB by default: such a write, or truncation, fails, and
+/// the file is left unchanged. It bounds the memory a WASI module
+/// can claim per file on the host.
+///
+/// ```py
+/// from wasmer import wasi
+///
+/// file_system = wasi.MemoryFileSystem(max_file_size=64 * 1024)
+/// ```
+#[pyclass]
+#[pyo3(text_signature = "(files={}, *, max_file_size=1073741824)")]
+#[derive(Clone, Debug)]
+pub struct MemoryFileSystem {
+    nodes: Arc<Mutex<Nodes>>,
+    max_file_size: u64,
+}
+
+impl MemoryFileSystem {
+    fn lock(&self) -> Result<MutexGuard<'_, Nodes>, FsError> {
+        self.nodes.lock().map_err(|_| FsError::Lock)
+    }
+
+    fn populate(&self, key: &str, files: &PyDict) -> PyResult<()> {
+        for (name, value) in files.iter() {
+            let name = name.downcast::<PyString>()?.to_str()?;
+            let key = child_key(key, name);
+
+            if let Ok(directory) = value.downcast::<PyDict>() {
+                self.create_dir(&key)
+                    .map_err(|e| fs_error_to_py_err(e, Path::new(&key)))?;
+                self.populate(&key, directory)?;
+            } else {
+                self.write_file(PathBuf::from(key), value)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn read_dir(&self, path: &Path, key: &str) -> Result<ReadDir, FsError> {
+        let nodes = self.lock()?;
+
+        match nodes.get(key) {
+            Some(Node::Directory) => Ok(ReadDir::new(
+                children(&nodes, key)
+                    .map(|child| wasmer_vfs::DirEntry {
+                        path: path.join(&child[child.rfind('/').unwrap_or(0) + 1..]),
+                        metadata: Ok(nodes[child].metadata()),
+                    })
+                    .collect(),
+            )),
+            Some(Node::File(_)) => Err(FsError::BaseNotDirectory),
+            None => Err(FsError::EntityNotFound),
+        }
+    }
+
+    fn create_dir(&self, key: &str) -> Result<(), FsError> {
+        let mut nodes = self.lock()?;
+
+        if nodes.contains_key(key) {
+            return Err(FsError::AlreadyExists);
+        }
+
+        check_parent(&nodes, key)?;
+        nodes.insert(key.to_string(), Node::Directory);
+
+        Ok(())
+    }
+
+    fn remove_dir(&self, key: &str) -> Result<(), FsError> {
+        let mut nodes = self.lock()?;
+
+        match nodes.get(key) {
+            Some(Node::Directory) if key == "/" => Err(FsError::PermissionDenied),
+            Some(Node::Directory) if children(&nodes, key).next().is_some() => {
+                Err(FsError::DirectoryNotEmpty)
+            }
+            Some(Node::Directory) => {
+                nodes.remove(key);
+
+                Ok(())
+            }
+            Some(Node::File(_)) => Err(FsError::BaseNotDirectory),
+            None => Err(FsError::EntityNotFound),
+        }
+    }
+
+    fn rename(&self, from: &str, to: &str) -> Result<(), FsError> {
+        let mut nodes = self.lock()?;
+
+        if !nodes.contains_key(from) {
+            return Err(FsError::EntityNotFound);
+        }
+
+        if from == "/" || to.starts_with(&child_key(from, "")) {
+            return Err(FsError::InvalidInput);
+        }
+
+        if from == to {
+            return Ok(());
+        }
+
+        check_parent(&nodes, to)?;
+
+        match (&nodes[from], nodes.get(to)) {
+            (_, Some(Node::Directory)) if children(&nodes, to).next().is_some() => {
+                return Err(FsError::DirectoryNotEmpty)
+            }
+            (Node::Directory, Some(Node::File(_))) => return Err(FsError::BaseNotDirectory),
+            (Node::File(_), Some(Node::Directory)) => return Err(FsError::NotAFile),
+            _ => {}
+        }
+
+        // Move the node, and all its descendants.
+        let descendants = child_key(from, "");
+        let keys = nodes
+            .keys()
+            .filter(|key| *key == from || key.starts_with(&descendants))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        for key in keys {
+            let node = nodes.remove(&key).unwrap();
+            nodes.insert(format!("{}{}", to, &key[from.len()..]), node);
+        }
+
+        Ok(())
+    }
+
+    fn metadata(&self, key: &str) -> Result<Metadata, FsError> {
+        self.lock()?
+            .get(key)
+            .map(Node::metadata)
+            .ok_or(FsError::EntityNotFound)
+    }
+
+    fn remove_file(&self, key: &str) -> Result<(), FsError> {
+        let mut nodes = self.lock()?;
+
+        match nodes.get(key) {
+            Some(Node::File(_)) => {
+                nodes.remove(key);
+
+                Ok(())
+            }
+            Some(Node::Directory) => Err(FsError::NotAFile),
+            None => Err(FsError::EntityNotFound),
+        }
+    }
+
+    fn open(&self, key: &str, conf: &OpenOptionsConfig) -> Result<Box<dyn VirtualFile>, FsError> {
+        let mut nodes = self.lock()?;
+
+        let data = match nodes.get(key) {
+            Some(Node::File(_)) if conf.create_new() => return Err(FsError::AlreadyExists),
+            Some(Node::File(data)) => data.clone(),
+            Some(Node::Directory) => return Err(FsError::NotAFile),
+            None if conf.create() || conf.create_new() => {
+                check_parent(&nodes, key)?;
+
+                let data = Arc::new(Mutex::new(Vec::new()));
+                nodes.insert(key.to_string(), Node::File(data.clone()));
+
+                data
+            }
+            None => return Err(FsError::EntityNotFound),
+        };
+
+        if conf.truncate() {
+            data.lock().map_err(|_| FsError::Lock)?.clear();
+        }
+
+        Ok(Box::new(MemoryFile {
+            data,
+            cursor: 0,
+            append: conf.append(),
+            max_file_size: self.max_file_size,
+        }))
+    }
+}
+
+#[pymethods]
+impl MemoryFileSystem {
+    #[new]
+    #[args(files = "None", "*", max_file_size = "DEFAULT_MAX_FILE_SIZE")]
+    fn new(files: Option<&PyDict>, max_file_size: u64) -> PyResult<Self> {
+        let file_system = Self {
+            nodes: Arc::new(Mutex::new(
+                vec![("/".to_string(), Node::Directory)]
+                    .into_iter()
+                    .collect(),
+            )),
+            max_file_size,
+        };
+
+        if let Some(files) = files {
+            file_system.populate("/", files)?;
+        }
+
+        Ok(file_system)
+    }
+
+    /// Reads the content of the file at `path`.
+    ///
+    /// ## Example
+    ///
+    /// ```py
+    /// from wasmer import wasi
+    ///
+    /// file_system = wasi.MemoryFileSystem({"input.txt": b"hello"})
+    ///
+    /// assert file_system.read_file("/input.txt") == b"hello"
+    /// ```
+    #[pyo3(text_signature = "($self, path)")]
+    fn read_file<'py>(&self, py: Python<'py>, path: PathBuf) -> PyResult<&'py PyBytes> {
+        let key = normalize(&path).map_err(|e| fs_error_to_py_err(e, &path))?;
+        let nodes = self.lock().map_err(|e| fs_error_to_py_err(e, &path))?;
+
+        match nodes.get(&key) {
+            Some(Node::File(data)) => {
+                let data = data
+                    .lock()
+                    .map_err(|_| fs_error_to_py_err(FsError::Lock, &path))?;
+
+                Ok(PyBytes::new(py, &data))
+            }
+            Some(Node::Directory) => Err(fs_error_to_py_err(FsError::NotAFile, &path)),
+            None => Err(fs_error_to_py_err(FsError::EntityNotFound, &path)),
+        }
+    }
+
+    /// Writes `data`, `bytes` or any bytes-like object, to the file
+    /// at `path`. The file is created if it does not exist, or
+    /// replaced otherwise.
+    ///
+    /// ## Example
+    ///
+    /// ```py
+    /// from wasmer import wasi
+    ///
+    /// file_system = wasi.MemoryFileSystem()
+    /// file_system.write_file("/input.txt", b"hello")
+    ///
+    /// assert file_system.read_file("/input.txt") == b"hello"
+    /// ```
+    #[pyo3(text_signature = "($self, path, data)")]
+    fn write_file(&self, path: PathBuf, data: &PyAny) -> PyResult<()> {
+        let data = match PyBuffer::<u8>::get(data) {
+            Ok(buffer) => buffer.to_vec(data.py())?,
+            Err(_) => {
+                return Err(to_py_err::<PyTypeError, _>(format!(
+                    "`MemoryFileSystem` cannot store the given type `{}`",
+                    data.get_type().name()?
+                )))
+            }
+        };
+
+        let key = normalize(&path).map_err(|e| fs_error_to_py_err(e, &path))?;
+        let mut nodes = self.lock().map_err(|e| fs_error_to_py_err(e, &path))?;
+
+        match nodes.get(&key) {
+            Some(Node::Directory) => Err(fs_error_to_py_err(FsError::NotAFile, &path)),
+            _ => {
+                check_parent(&nodes, &key).map_err(|e| fs_error_to_py_err(e, &path))?;
+                nodes.insert(key, Node::File(Arc::new(Mutex::new(data))));
+
+                Ok(())
+            }
+        }
+    }
+
+    /// Creates a directory at `path`. Its parent directory must
+    /// exist.
+    ///
+    /// ## Example
+    ///
+    /// ```py
+    /// from wasmer import wasi
+    ///
+    /// file_system = wasi.MemoryFileSystem()
+    /// file_system.create_directory("/data")
+    ///
+    /// assert file_system.list_directory("/") == ["data"]
+    /// ```
+    #[pyo3(text_signature = "($self, path)")]
+    fn create_directory(&self, path: PathBuf) -> PyResult<()> {
+        normalize(&path)
+            .and_then(|key| self.create_dir(&key))
+            .map_err(|e| fs_error_to_py_err(e, &path))
+    }
+
+    /// Lists the sorted names of the entries of the directory at
+    /// `path`, `/` by default.
+    ///
+    /// ## Example
+    ///
+    /// See the `MemoryFileSystem` class.
+    #[pyo3(text_signature = "($self, /, path=\"/\")")]
+    #[args(path = "None")]
+    fn list_directory(&self, path: Option<PathBuf>) -> PyResult<Vec<String>> {
+        let path = path.unwrap_or_else(|| PathBuf::from("/"));
+
+        Ok(normalize(&path)
+            .and_then(|key| self.read_dir(&path, &key))
+            .map_err(|e| fs_error_to_py_err(e, &path))?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .collect())
+    }
+
+    /// Gets the whole file system as a dictionary, in the same form
+    /// as given to the constructor.
+    ///
+    /// ## Example
+    ///
+    /// ```py
+    /// from wasmer import wasi
+    ///
+    /// files = {"input.txt": b"hello", "data": {}}
+    /// file_system = wasi.MemoryFileSystem(files)
+    ///
+    /// assert file_system.to_dict() == files
+    /// ```
+    #[pyo3(text_signature = "($self)")]
+    fn to_dict(&self, py: Python) -> PyResult<PyObject> {
+        let nodes = self
+            .lock()
+            .map_err(|e| fs_error_to_py_err(e, Path::new("/")))?;
+
+        let root = PyDict::new(py);
+        let mut directories = HashMap::new();
+        directories.insert("/", root);
+
+        // Parents come before their children in the sorted keys.
+        for (key, node) in nodes.iter() {
+            let (parent, name) = match (parent_key(key), key.rfind('/')) {
+                (Some(parent), Some(index)) => (parent, &key[index + 1..]),
+                _ => continue,
+            };
+            let parent = directories[parent];
+
+            match node {
+                Node::Directory => {
+                    let directory = PyDict::new(py);
+                    parent.set_item(name, directory)?;
+                    directories.insert(key.as_str(), directory);
+                }
+                Node::File(data) => {
+                    let data = data
+                        .lock()
+                        .map_err(|_| fs_error_to_py_err(FsError::Lock, Path::new(key)))?;
+                    parent.set_item(name, PyBytes::new(py, &data))?;
+                }
+            }
+        }
+
+        Ok(root.to_object(py))
+    }
+}
+
+/// A file opened in a `MemoryFileSystem`.
+#[derive(Debug)]
+struct MemoryFile {
+    data: Arc<Mutex<Vec<u8>>>,
+    cursor: u64,
+    append: bool,
+    max_file_size: u64,
+}
+
+fn lock_data(data: &Mutex<Vec<u8>>) -> io::Result<MutexGuard<'_, Vec<u8>>> {
+    data.lock()
+        .map_err(|_| io::Error::other("The file is poisoned"))
+}
+
+impl MemoryFile {
+    /// Checks that the file can be `size` bytes long, and returns
+    /// that size as an index of its data.
+    fn check_size(&self, size: Option<u64>) -> Option<usize> {
+        size.filter(|size| *size <= self.max_file_size)
+            .and_then(|size| size.try_into().ok())
+    }
+
+    /// The cursor as an index of `data`, clamped to its length.
+    fn position(&self, data: &[u8]) -> usize {
+        self.cursor.try_into().unwrap_or(usize::MAX).min(data.len())
+    }
+}
+
+impl Read for MemoryFile {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        let data = lock_data(&self.data)?;
+        let start = self.position(&data);
+        let length = buffer.len().min(data.len() - start);
+
+        buffer[..length].copy_from_slice(&data[start..start + length]);
+        drop(data);
+        self.cursor += length as u64;
+
+        Ok(length)
+    }
+}
+
+impl Write for MemoryFile {
+    fn write(&mut self, buffer: &[u8]) -> io::Result<usize> {
+        let mut data = lock_data(&self.data)?;
+
+        if self.append {
+            self.cursor = data.len() as u64;
+        }
+
+        let end = self
+            .check_size(self.cursor.checked_add(buffer.len() as u64))
+            .ok_or_else(|| io::Error::other("The file would exceed its maximum size"))?;
+        let start = end - buffer.len();
+
+        if end > data.len() {
+            data.resize(end, 0);
+        }
+
+        data[start..end].copy_from_slice(buffer);
+        drop(data);
+        self.cursor = end as u64;
+
+        Ok(buffer.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for MemoryFile {
+    fn seek(&mut self, position: SeekFrom) -> io::Result<u64> {
+        let cursor = match position {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => {
+                (lock_data(&self.data)?.len() as u64).checked_add_signed(offset)
+            }
+            SeekFrom::Current(offset) => self.cursor.checked_add_signed(offset),
+        };
+
+        self.cursor = cursor.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Invalid seek to a negative position",
+            )
+        })?;
+
+        Ok(self.cursor)
+    }
+}
+
+impl VirtualFile for MemoryFile {
+    fn last_accessed(&self) -> u64 {
+        0
+    }
+
+    fn last_modified(&self) -> u64 {
+        0
+    }
+
+    fn created_time(&self) -> u64 {
+        0
+    }
+
+    fn size(&self) -> u64 {
+        lock_data(&self.data)
+            .map(|data| data.len() as u64)
+            .unwrap_or(0)
+    }
+
+    fn set_len(&mut self, new_size: u64) -> Result<(), FsError> {
+        let new_size = self
+            .check_size(Some(new_size))
+            .ok_or(FsError::InvalidInput)?;
+
+        lock_data(&self.data)
+            .map_err(|_| FsError::Lock)?
+            .resize(new_size, 0);
+
+        Ok(())
+    }
+
+    fn unlink(&mut self) -> Result<(), FsError> {
+        Ok(())
+    }
+
+    fn bytes_available(&self) -> Result<usize, FsError> {
+        let data = lock_data(&self.data).map_err(|_| FsError::Lock)?;
+
+        Ok(data.len() - self.position(&data))
+    }
+}
+
+/// What a WASI module is allowed to do in a mapped directory: the
+/// module can always read, and can write, create new entries, or
+/// follow symbolic links if allowed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Capabilities {
+    pub(crate) write: bool,
+    pub(crate) create: bool,
+    pub(crate) follow_symlinks: bool,
+}
+
+impl Default for Capabilities {
+    fn default() -> Self {
+        Self {
+            write: true,
+            create: true,
+            follow_symlinks: true,
+        }
+    }
+}
+
+impl Capabilities {
+    fn intersect(self, other: Self) -> Self {
+        Self {
+            write: self.write && other.write,
+            create: self.create && other.create,
+            follow_symlinks: self.follow_symlinks && other.follow_symlinks,
+        }
+    }
+
+    fn require(allowed: bool) -> Result<(), FsError> {
+        if allowed {
+            Ok(())
+        } else {
+            Err(FsError::PermissionDenied)
+        }
+    }
+}
+
+/// Fails if a component of `path`, below `root`, is a symbolic link.
+fn check_symlinks(root: &Path, path: &Path) -> Result<(), FsError> {
+    let mut current = root.to_path_buf();
+
+    for component in path.strip_prefix(root).unwrap_or(path).components() {
+        current.push(component);
+
+        match fs::symlink_metadata(&current) {
+            Ok(metadata) if metadata.file_type().is_symlink() => {
+                return Err(FsError::PermissionDenied)
+            }
+            Ok(_) => {}
+            Err(_) => break,
+        }
+    }
+
+    Ok(())
+}
+
+/// The file system of a WASI module: the host file system, where
+/// memory file systems are mounted, and where host directories can
+/// be restricted.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct WasiFileSystem {
+    host: wasmer_vfs::host_fs::FileSystem,
+    mounts: Vec<(MemoryFileSystem, Capabilities)>,
+    restrictions: Vec<(PathBuf, Capabilities)>,
+}
+
+enum Target<'a> {
+    Host,
+    Memory(&'a MemoryFileSystem, String),
+}
+
+impl WasiFileSystem {
+    /// Mounts a memory file system, and returns the path to map.
+    pub(crate) fn mount(
+        &mut self,
+        file_system: MemoryFileSystem,
+        capabilities: Capabilities,
+    ) -> PathBuf {
+        self.mounts.push((file_system, capabilities));
+
+        PathBuf::from(format!("{}{}", MOUNT_PREFIX, self.mounts.len() - 1))
+    }
+
+    /// Restricts what can be done in the host `directory`, and all
+    /// its descendants.
+    pub(crate) fn restrict(&mut self, directory: PathBuf, capabilities: Capabilities) {
+        if capabilities != Capabilities::default() {
+            self.restrictions.push((directory, capabilities));
+        }
+    }
+
+    pub(crate) fn has_mounts(&self) -> bool {
+        !self.mounts.is_empty() || !self.restrictions.is_empty()
+    }
+
+    fn route(&self, path: &Path) -> Result<(Target<'_>, Capabilities), FsError> {
+        let mut components = path.components();
+
+        let mount = match components.next() {
+            Some(Component::Normal(name)) => name
+                .to_str()
+                .and_then(|name| name.strip_prefix(MOUNT_PREFIX))
+                .and_then(|index| index.parse::<usize>().ok())
+                .and_then(|index| self.mounts.get(index)),
+            _ => None,
+        };
+
+        if let Some((mount, capabilities)) = mount {
+            return Ok((
+                Target::Memory(mount, normalize(components.as_path())?),
+                *capabilities,
+            ));
+        }
+
+        let mut capabilities = Capabilities::default();
+
+        for (directory, restriction) in &self.restrictions {
+            if path.starts_with(directory) {
+                if !restriction.follow_symlinks {
+                    check_symlinks(directory, path)?;
+                }
+
+                capabilities = capabilities.intersect(*restriction);
+            }
+        }
+
+        Ok((Target::Host, capabilities))
+    }
+}
+
+impl wasmer_vfs::FileSystem for WasiFileSystem {
+    fn read_dir(&self, path: &Path) -> Result<ReadDir, FsError> {
+        match self.route(path)?.0 {
+            Target::Host => self.host.read_dir(path),
+            Target::Memory(mount, key) => mount.read_dir(path, &key),
+        }
+    }
+
+    fn create_dir(&self, path: &Path) -> Result<(), FsError> {
+        let (target, capabilities) = self.route(path)?;
+        Capabilities::require(capabilities.write && capabilities.create)?;
+
+        match target {
+            Target::Host => self.host.create_dir(path),
+            Target::Memory(mount, key) => mount.create_dir(&key),
+        }
+    }
+
+    fn remove_dir(&self, path: &Path) -> Result<(), FsError> {
+        let (target, capabilities) = self.route(path)?;
+        Capabilities::require(capabilities.write)?;
+
+        match target {
+            Target::Host => self.host.remove_dir(path),
+            Target::Memory(mount, key) => mount.remove_dir(&key),
+        }
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<(), FsError> {
+        let (from_target, from_capabilities) = self.route(from)?;
+        let (to_target, to_capabilities) = self.route(to)?;
+        Capabilities::require(
+            from_capabilities.write && to_capabilities.write && to_capabilities.create,
+        )?;
+
+        match (from_target, to_target) {
+            (Target::Host, Target::Host) => self.host.rename(from, to),
+            (Target::Memory(from_mount, from), Target::Memory(to_mount, to))
+                if Arc::ptr_eq(&from_mount.nodes, &to_mount.nodes) =>
+            {
+                from_mount.rename(&from, &to)
+            }
+            // Renaming across file systems is not supported.
+            _ => Err(FsError::InvalidInput),
+        }
+    }
+
+    fn metadata(&self, path: &Path) -> Result<Metadata, FsError> {
+        match self.route(path)?.0 {
+            Target::Host => self.host.metadata(path),
+            Target::Memory(mount, key) => mount.metadata(&key),
+        }
+    }
+
+    fn symlink_metadata(&self, path: &Path) -> Result<Metadata, FsError> {
+        match self.route(path)?.0 {
+            Target::Host => self.host.symlink_metadata(path),
+            Target::Memory(mount, key) => mount.metadata(&key),
+        }
+    }
+
+    fn remove_file(&self, path: &Path) -> Result<(), FsError> {
+        let (target, capabilities) = self.route(path)?;
+        Capabilities::require(capabilities.write)?;
+
+        match target {
+            Target::Host => self.host.remove_file(path),
+            Target::Memory(mount, key) => mount.remove_file(&key),
+        }
+    }
+
+    fn new_open_options(&self) -> OpenOptions {
+        OpenOptions::new(Box::new(self.clone()))
+    }
+}
+
+impl FileOpener for WasiFileSystem {
+    fn open(
+        &mut self,
+        path: &Path,
+        conf: &OpenOptionsConfig,
+    ) -> Result<Box<dyn VirtualFile>, FsError> {
+        let (target, capabilities) = self.route(path)?;
+
+        if conf.write() || conf.append() || conf.truncate() {
+            Capabilities::require(capabilities.write)?;
+        }
+
+        // `create` needs the capability only if the file does not
+        // exist yet.
+        if conf.create_new()
+            || (conf.create() && wasmer_vfs::FileSystem::metadata(self, path).is_err())
+        {
+            Capabilities::require(capabilities.create)?;
+        }
+
+        match target {
+            Target::Host => wasmer_vfs::FileSystem::new_open_options(&self.host)
+                .read(conf.read())
+                .write(conf.write())
+                .create_new(conf.create_new())
+                .create(conf.create())
+                .append(conf.append())
+                .truncate(conf.truncate())
+                .open(path),
+            Target::Memory(mount, key) => mount.open(&key, conf),
+        }
+    }
+}
@@ -0,0 +1,1283 @@
+use crate::{
+    engines,
+    errors::{to_py_err, WasiExitError},
+    import_object::ImportObject,
+    instance::Instance,
+    middlewares::Interruptible,
+    module::{compile, Module},
+    store::Store,
+    wasmer_inner::{wasmer, wasmer_vfs, wasmer_wasi},
+};
+use pyo3::{
+    buffer::PyBuffer,
+    class::basic::PyObjectProtocol,
+    exceptions::{PyFileNotFoundError, PyRuntimeError, PyValueError},
+    prelude::*,
+    types::{IntoPyDict, PyBytes, PyDict, PyList},
+    PyNativeType,
+};
+use std::{
+    fs,
+    io::{self, Read, Seek, Write},
+    net::IpAddr,
+    path::PathBuf,
+    slice,
+};
+
+mod deterministic;
+mod filesystem;
+mod sockets;
+
+use filesystem::Capabilities;
+pub use filesystem::MemoryFileSystem;
+
+#[derive(Copy, Clone)]
+#[repr(u8)]
+pub enum Version {
+    Latest = 1,
+    Snapshot0 = 2,
+    Snapshot1 = 3,
+}
+
+impl Version {
+    pub fn iter() -> slice::Iter<'static, Version> {
+        static VARIANTS: [Version; 3] = [Version::Latest, Version::Snapshot0, Version::Snapshot1];
+
+        VARIANTS.iter()
+    }
+}
+
+impl From<&Version> for &'static str {
+    fn from(value: &Version) -> Self {
+        match value {
+            Version::Latest => "LATEST",
+            Version::Snapshot0 => "SNAPSHOT0",
+            Version::Snapshot1 => "SNAPSHOT1",
+        }
+    }
+}
+
+impl ToPyObject for Version {
+    fn to_object(&self, py: Python) -> PyObject {
+        (*self as u8).into_py(py)
+    }
+}
+
+impl IntoPy<PyObject> for Version {
+    fn into_py(self, py: Python) -> PyObject {
+        self.to_object(py)
+    }
+}
+
+impl<'source> FromPyObject<'source> for Version {
+    fn extract(obj: &'source PyAny) -> PyResult<Self> {
+        let variant = u8::extract(obj)?;
+
+        Ok(match variant {
+            1 => Self::Latest,
+            2 => Self::Snapshot0,
+            3 => Self::Snapshot1,
+            _ => {
+                return Err(to_py_err::<PyValueError, _>(
+                    "Failed to extract `Version` from `PyAny`",
+                ))
+            }
+        })
+    }
+}
+
+impl From<wasmer_wasi::WasiVersion> for Version {
+    fn from(value: wasmer_wasi::WasiVersion) -> Self {
+        match value {
+            wasmer_wasi::WasiVersion::Latest => Self::Latest,
+            wasmer_wasi::WasiVersion::Snapshot0 => Self::Snapshot0,
+            wasmer_wasi::WasiVersion::Snapshot1 => Self::Snapshot1,
+        }
+    }
+}
+
+impl Into<wasmer_wasi::WasiVersion> for Version {
+    fn into(self) -> wasmer_wasi::WasiVersion {
+        match self {
+            Self::Latest => wasmer_wasi::WasiVersion::Latest,
+            Self::Snapshot0 => wasmer_wasi::WasiVersion::Snapshot0,
+            Self::Snapshot1 => wasmer_wasi::WasiVersion::Snapshot1,
+        }
+    }
+}
+
+/// A standard device of a WASI module, backed by a Python file-like
+/// object. Reads and writes are forwarded to the object as they
+/// happen, with its `read`, `write` and `flush` methods.
+#[derive(Debug)]
+struct PyFile {
+    file: PyObject,
+}
+
+impl PyFile {
+    fn call<T, F>(&self, f: F) -> io::Result<T>
+    where
+        F: FnOnce(Python, &PyAny) -> PyResult<T>,
+    {
+        Python::with_gil(|py| f(py, self.file.as_ref(py)))
+            .map_err(|error| io::Error::other(error.to_string()))
+    }
+}
+
+impl Read for PyFile {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        self.call(|py, file| {
+            let data = file.call_method1("read", (buffer.len(),))?;
+            let data = PyBuffer::<u8>::get(data)?;
+            let length = data.len_bytes();
+
+            if length > buffer.len() {
+                return Err(to_py_err::<PyValueError, _>(
+                    "`read` has returned more bytes than requested",
+                ));
+            }
+
+            data.copy_to_slice(py, &mut buffer[..length])?;
+
+            Ok(length)
+        })
+    }
+}
+
+impl Write for PyFile {
+    fn write(&mut self, buffer: &[u8]) -> io::Result<usize> {
+        self.call(|py, file| {
+            file.call_method1("write", (PyBytes::new(py, buffer),))?;
+
+            Ok(buffer.len())
+        })
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.call(|_py, file| {
+            if file.hasattr("flush")? {
+                file.call_method0("flush")?;
+            }
+
+            Ok(())
+        })
+    }
+}
+
+impl Seek for PyFile {
+    fn seek(&mut self, _position: io::SeekFrom) -> io::Result<u64> {
+        Err(io::Error::other("Cannot seek in a standard device"))
+    }
+}
+
+impl wasmer_wasi::VirtualFile for PyFile {
+    fn last_accessed(&self) -> u64 {
+        0
+    }
+
+    fn last_modified(&self) -> u64 {
+        0
+    }
+
+    fn created_time(&self) -> u64 {
+        0
+    }
+
+    fn size(&self) -> u64 {
+        0
+    }
+
+    fn set_len(&mut self, _new_size: u64) -> Result<(), wasmer_wasi::FsError> {
+        Err(wasmer_wasi::FsError::PermissionDenied)
+    }
+
+    fn unlink(&mut self) -> Result<(), wasmer_wasi::FsError> {
+        Ok(())
+    }
+
+    fn bytes_available(&self) -> Result<usize, wasmer_wasi::FsError> {
+        Err(wasmer_wasi::FsError::UnknownError)
+    }
+}
+
+/// Convenient builder API for configuring WASI.
+///
+/// Use the constructor to pass the arguments, environments, preopen
+/// directories and map directories, or use the associated methods to
+/// build the state step-by-steps.
+///
+/// ## Example
+///
+/// ```py
+/// from wasmer import wasi
+///
+/// wasi_state_builder = wasi.StateBuilder('test-program')
+/// ```
+#[pyclass]
+#[pyo3(
+    text_signature = "(arguments=[], environments={}, preopen_directories=[], map_directories={})"
+)]
+pub struct StateBuilder {
+    inner: wasmer_wasi::WasiStateBuilder,
+    file_system: filesystem::WasiFileSystem,
+    sources: deterministic::Sources,
+    sockets: Vec<sockets::Socket>,
+}
+
+impl StateBuilder {
+    pub fn self_arguments(&mut self, arguments: &PyList) {
+        self.inner.args(arguments.iter().map(ToString::to_string));
+    }
+
+    pub fn self_argument(&mut self, argument: String) {
+        self.inner.arg(argument);
+    }
+
+    pub fn self_environments(&mut self, environments: &PyDict) {
+        self.inner.envs(
+            environments
+                .iter()
+                .map(|(any_key, any_value)| (any_key.to_string(), any_value.to_string())),
+        );
+    }
+
+    pub fn self_environment(&mut self, key: String, value: String) {
+        self.inner.env(key, value);
+    }
+
+    pub fn self_preopen_directories(&mut self, preopen_directories: &PyList) -> PyResult<()> {
+        self.inner
+            .preopen_dirs(
+                preopen_directories
+                    .iter()
+                    .map(|any_item| PathBuf::from(any_item.to_string())),
+            )
+            .map_err(to_py_err::<PyRuntimeError, _>)?;
+
+        Ok(())
+    }
+
+    pub(crate) fn self_preopen_directory(
+        &mut self,
+        preopen_directory: String,
+        capabilities: Capabilities,
+    ) -> PyResult<()> {
+        let directory = PathBuf::from(preopen_directory);
+        self.file_system.restrict(directory.clone(), capabilities);
+
+        self.inner
+            .preopen(|builder| {
+                builder
+                    .directory(&directory)
+                    .read(true)
+                    .write(capabilities.write)
+                    .create(capabilities.create)
+            })
+            .map_err(to_py_err::<PyRuntimeError, _>)?;
+
+        Ok(())
+    }
+
+    pub fn self_map_directories(&mut self, map_directories: &PyDict) -> PyResult<()> {
+        for (alias, directory) in map_directories.iter() {
+            self.self_map_directory(alias.to_string(), directory, Capabilities::default())?;
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn self_map_directory(
+        &mut self,
+        alias: String,
+        directory: &PyAny,
+        capabilities: Capabilities,
+    ) -> PyResult<()> {
+        let directory = match directory.downcast::<PyCell<MemoryFileSystem>>() {
+            Ok(file_system) => self
+                .file_system
+                .mount(file_system.borrow().clone(), capabilities),
+            Err(_) => {
+                let directory = directory.extract::<PathBuf>()?;
+                self.file_system.restrict(directory.clone(), capabilities);
+
+                directory
+            }
+        };
+
+        self.inner
+            .preopen(|builder| {
+                builder
+                    .directory(&directory)
+                    .alias(alias.as_str())
+                    .read(true)
+                    .write(capabilities.write)
+                    .create(capabilities.create)
+            })
+            .map_err(to_py_err::<PyRuntimeError, _>)?;
+
+        Ok(())
+    }
+}
+
+#[pymethods]
+impl StateBuilder {
+    #[new]
+    fn new(
+        program_name: String,
+        arguments: Option<&PyList>,
+        environments: Option<&PyDict>,
+        preopen_directories: Option<&PyList>,
+        map_directories: Option<&PyDict>,
+    ) -> PyResult<Self> {
+        let mut wasi = Self {
+            inner: wasmer_wasi::WasiState::new(program_name.as_str()),
+            file_system: Default::default(),
+            sources: Default::default(),
+            sockets: Vec::new(),
+        };
+
+        if let Some(arguments) = arguments {
+            wasi.self_arguments(arguments);
+        }
+
+        if let Some(environments) = environments {
+            wasi.self_environments(environments);
+        }
+
+        if let Some(preopen_directories) = preopen_directories {
+            wasi.self_preopen_directories(preopen_directories)?;
+        }
+
+        if let Some(map_directories) = map_directories {
+            wasi.self_map_directories(map_directories)?;
+        }
+
+        Ok(wasi)
+    }
+
+    /// Add multiple arguments.
+    ///
+    /// Arguments must not contain the nul (`0x0`) byte.
+    ///
+    /// This method returns `self`.
+    ///
+    /// ## Example
+    ///
+    /// ```py
+    /// from wasmer import wasi
+    ///
+    /// wasi_state_builder = \
+    ///     wasi.StateBuilder('test-program'). \
+    ///         arguments(['--verbose --help'])
+    /// ```
+    #[pyo3(text_signature = "($self, arguments)")]
+    pub fn arguments<'py>(
+        slf: &'py PyCell<Self>,
+        arguments: &PyList,
+    ) -> PyResult<&'py PyCell<Self>> {
+        let mut slf_mut = slf.try_borrow_mut()?;
+        slf_mut.self_arguments(arguments);
+
+        Ok(slf)
+    }
+
+    /// Add an argument.
+    ///
+    /// Arguments must not contain the nul (`0x0`) byte.
+    ///
+    /// This method returns `self`.
+    ///
+    /// ## Example
+    ///
+    /// ```py
+    /// from wasmer import wasi
+    ///
+    /// wasi_state_builder = \
+    ///     wasi.StateBuilder('test-program'). \
+    ///         argument('--verbose'). \
+    ///         argument('--help')
+    /// ```
+    #[pyo3(text_signature = "($self, argument)")]
+    pub fn argument<'py>(slf: &'py PyCell<Self>, argument: String) -> PyResult<&'py PyCell<Self>> {
+        let mut slf_mut = slf.try_borrow_mut()?;
+        slf_mut.self_argument(argument);
+
+        Ok(slf)
+    }
+
+    /// Add environment variable pairs.
+    ///
+    /// Environment variable keys and values must not contain the byte
+    /// `=` (`0x3d`) or null (`0x0`).
+    ///
+    /// This method returns `self`.
+    ///
+    /// ## Example
+    ///
+    /// ```py
+    /// from wasmer import wasi
+    ///
+    /// wasi_state_builder = \
+    ///     wasi.StateBuilder('test-program'). \
+    ///         environments({"ABC": "DEF", "X": "YZ"})
+    /// ```
+    #[pyo3(text_signature = "($self, environments)")]
+    pub fn environments<'py>(
+        slf: &'py PyCell<Self>,
+        environments: &PyDict,
+    ) -> PyResult<&'py PyCell<Self>> {
+        let mut slf_mut = slf.try_borrow_mut()?;
+        slf_mut.self_environments(environments);
+
+        Ok(slf)
+    }
+
+    /// Add an environment variable pair.
+    ///
+    /// Environment variable keys and values must not contain the byte
+    /// `=` (`0x3d`) or null (`0x0`).
+    ///
+    /// This method returns `self`.
+    ///
+    /// ## Example
+    ///
+    /// ```py
+    /// from wasmer import wasi
+    ///
+    /// wasi_state_builder = \
+    ///     wasi.StateBuilder('test-program'). \
+    ///         environment("ABC", "DEF"). \
+    ///         environment("X", "YZ")
+    /// ```
+    #[pyo3(text_signature = "($self, key, value)")]
+    pub fn environment<'py>(
+        slf: &'py PyCell<Self>,
+        key: String,
+        value: String,
+    ) -> PyResult<&'py PyCell<Self>> {
+        let mut slf_mut = slf.try_borrow_mut()?;
+        slf_mut.self_environment(key, value);
+
+        Ok(slf)
+    }
+
+    /// Preopen directories.
+    ///
+    /// This opens the given directories at the virtual root, `/`, and
+    /// allows the WASI module to read and write to the given
+    /// directories.
+    ///
+    /// This method returns `self`.
+    ///
+    /// ## Example
+    ///
+    /// ```py
+    /// from wasmer import wasi
+    ///
+    /// wasi_state_builder = \
+    ///     wasi.StateBuilder('test-program'). \
+    ///         preopen_directories(["."])
+    /// ```
+    #[pyo3(text_signature = "($self, preopen_directories)")]
+    pub fn preopen_directories<'py>(
+        slf: &'py PyCell<Self>,
+        preopen_directories: &PyList,
+    ) -> PyResult<&'py PyCell<Self>> {
+        let mut slf_mut = slf.try_borrow_mut()?;
+        slf_mut.self_preopen_directories(preopen_directories)?;
+
+        Ok(slf)
+    }
+
+    /// Preopen a directory.
+    ///
+    /// This opens the given directory at the virtual root, `/`, and
+    /// allows the WASI module to read and write to the given
+    /// directory.
+    ///
+    /// The capabilities of the WASI module in the directory, and all
+    /// its descendants, can be restricted: `read_only` forbids any
+    /// modification, `create=False` forbids creating new files or
+    /// directories but allows modifying the existing ones, and
+    /// `follow_symlinks=False` makes the symbolic links
+    /// inaccessible. A forbidden operation fails with a WASI error.
+    ///
+    /// This method returns `self`.
+    ///
+    /// ## Example
+    ///
+    /// ```py
+    /// from wasmer import wasi
+    ///
+    /// wasi_state_builder = \
+    ///     wasi.StateBuilder('test-program'). \
+    ///         preopen_directory("."). \
+    ///         preopen_directory("tests", read_only=True)
+    /// ```
+    #[pyo3(
+        text_signature = "($self, preopen_directory, /, *, read_only=False, create=True, follow_symlinks=True)"
+    )]
+    #[args("*", read_only = "false", create = "true", follow_symlinks = "true")]
+    pub fn preopen_directory<'py>(
+        slf: &'py PyCell<Self>,
+        preopen_directory: String,
+        read_only: bool,
+        create: bool,
+        follow_symlinks: bool,
+    ) -> PyResult<&'py PyCell<Self>> {
+        let mut slf_mut = slf.try_borrow_mut()?;
+        slf_mut.self_preopen_directory(
+            preopen_directory,
+            to_capabilities(read_only, create, follow_symlinks),
+        )?;
+
+        Ok(slf)
+    }
+
+    /// Preopen directories with different names exposed to the WASI.
+    ///
+    /// This method returns `self`.
+    ///
+    /// ## Example
+    ///
+    /// ```py
+    /// from wasmer import wasi
+    ///
+    /// wasi_state_builder = \
+    ///     wasi.StateBuilder('test-program'). \
+    ///         map_directories({"foo": "."})
+    /// ```
+    #[pyo3(text_signature = "($self, map_directories)")]
+    pub fn map_directories<'py>(
+        slf: &'py PyCell<Self>,
+        map_directories: &PyDict,
+    ) -> PyResult<&'py PyCell<Self>> {
+        let mut slf_mut = slf.try_borrow_mut()?;
+        slf_mut.self_map_directories(map_directories)?;
+
+        Ok(slf)
+    }
+
+    /// Preopen a directory with a different name exposed to the WASI.
+    ///
+    /// `read_only`, `create` and `follow_symlinks` restrict the
+    /// capabilities of the WASI module in the directory, see
+    /// `preopen_directory`.
+    ///
+    /// This method returns `self`.
+    ///
+    /// ## Example
+    ///
+    /// ```py
+    /// from wasmer import wasi
+    ///
+    /// wasi_state_builder = \
+    ///     wasi.StateBuilder('test-program'). \
+    ///         map_directory("foo", "."). \
+    ///         map_directory("data", "tests", read_only=True, follow_symlinks=False)
+    /// ```
+    #[pyo3(
+        text_signature = "($self, alias, directory, /, *, read_only=False, create=True, follow_symlinks=True)"
+    )]
+    #[args("*", read_only = "false", create = "true", follow_symlinks = "true")]
+    pub fn map_directory<'py>(
+        slf: &'py PyCell<Self>,
+        alias: String,
+        directory: &PyAny,
+        read_only: bool,
+        create: bool,
+        follow_symlinks: bool,
+    ) -> PyResult<&'py PyCell<Self>> {
+        let mut slf_mut = slf.try_borrow_mut()?;
+        slf_mut.self_map_directory(
+            alias,
+            directory,
+            to_capabilities(read_only, create, follow_symlinks),
+        )?;
+
+        Ok(slf)
+    }
+
+    /// Provides the standard input of the WASI module, instead of
+    /// reading the standard input of the host. `input` is either the
+    /// data itself, as `bytes` or any bytes-like object, a binary
+    /// file-like object, read as the module reads its standard
+    /// input, or the path of a file to read.
+    ///
+    /// This method returns `self`.
+    ///
+    /// ## Example
+    ///
+    /// ```py
+    /// from wasmer import wasi
+    ///
+    /// wasi_state_builder = \
+    ///     wasi.StateBuilder('test-program'). \
+    ///         stdin(b'hello')
+    /// ```
+    #[pyo3(text_signature = "($self, input)")]
+    pub fn stdin<'py>(slf: &'py PyCell<Self>, input: &PyAny) -> PyResult<&'py PyCell<Self>> {
+        let stdin: Box<dyn wasmer_wasi::VirtualFile> =
+            if let Ok(buffer) = PyBuffer::<u8>::get(input) {
+                let mut pipe = wasmer_wasi::Pipe::new();
+                pipe.write_all(&buffer.to_vec(input.py())?)?;
+
+                Box::new(pipe)
+            } else if input.hasattr("read")? {
+                Box::new(PyFile { file: input.into() })
+            } else {
+                let path = input.extract::<PathBuf>()?;
+                let file = fs::File::open(&path)?;
+
+                Box::new(wasmer_vfs::host_fs::File::new(
+                    file, path, true, false, false,
+                ))
+            };
+
+        let mut slf_mut = slf.try_borrow_mut()?;
+        slf_mut.inner.stdin(stdin);
+
+        Ok(slf)
+    }
+
+    /// Writes the standard output of the WASI module to `file`, a
+    /// binary file-like object, e.g. a file opened with `'wb'`, a
+    /// pipe, a socket file, or an `io.BytesIO`. Data is written as
+    /// the module writes it, without being buffered.
+    ///
+    /// This method returns `self`.
+    ///
+    /// ## Example
+    ///
+    /// ```py
+    /// from wasmer import wasi, Store, Module, Instance
+    /// import io
+    ///
+    /// store = Store()
+    /// module = Module(store, open('tests/wasi.wasm', 'rb').read())
+    ///
+    /// stdout = io.BytesIO()
+    /// wasi_env = wasi.StateBuilder('test-program').stdout(stdout).finalize()
+    /// import_object = wasi_env.generate_import_object(store, wasi.Version.SNAPSHOT1)
+    ///
+    /// Instance(module, import_object).exports._start()
+    ///
+    /// assert stdout.getvalue().startswith(b'Found program name: `test-program`')
+    /// ```
+    #[pyo3(text_signature = "($self, file)")]
+    pub fn stdout<'py>(slf: &'py PyCell<Self>, file: &PyAny) -> PyResult<&'py PyCell<Self>> {
+        let mut slf_mut = slf.try_borrow_mut()?;
+        slf_mut.inner.stdout(Box::new(PyFile { file: file.into() }));
+
+        Ok(slf)
+    }
+
+    /// Writes the standard error of the WASI module to `file`, a
+    /// binary file-like object. See `StateBuilder.stdout`.
+    ///
+    /// This method returns `self`.
+    #[pyo3(text_signature = "($self, file)")]
+    pub fn stderr<'py>(slf: &'py PyCell<Self>, file: &PyAny) -> PyResult<&'py PyCell<Self>> {
+        let mut slf_mut = slf.try_borrow_mut()?;
+        slf_mut.inner.stderr(Box::new(PyFile { file: file.into() }));
+
+        Ok(slf)
+    }
+
+    /// Captures the standard output of the WASI module, instead of
+    /// writing it to the standard output of the host. The captured
+    /// bytes are read with `Environment.stdout`.
+    ///
+    /// This method returns `self`.
+    ///
+    /// ## Example
+    ///
+    /// ```py
+    /// from wasmer import wasi
+    ///
+    /// wasi_env = \
+    ///     wasi.StateBuilder('test-program'). \
+    ///         capture_stdout(). \
+    ///         finalize()
+    ///
+    /// assert wasi_env.stdout() == b''
+    /// ```
+    #[pyo3(text_signature = "($self)")]
+    pub fn capture_stdout(slf: &PyCell<Self>) -> PyResult<&PyCell<Self>> {
+        let mut slf_mut = slf.try_borrow_mut()?;
+        slf_mut.inner.stdout(Box::new(wasmer_wasi::Pipe::new()));
+
+        Ok(slf)
+    }
+
+    /// Captures the standard error of the WASI module, instead of
+    /// writing it to the standard error of the host. The captured
+    /// bytes are read with `Environment.stderr`.
+    ///
+    /// This method returns `self`.
+    ///
+    /// ## Example
+    ///
+    /// ```py
+    /// from wasmer import wasi
+    ///
+    /// wasi_env = \
+    ///     wasi.StateBuilder('test-program'). \
+    ///         capture_stderr(). \
+    ///         finalize()
+    ///
+    /// assert wasi_env.stderr() == b''
+    /// ```
+    #[pyo3(text_signature = "($self)")]
+    pub fn capture_stderr(slf: &PyCell<Self>) -> PyResult<&PyCell<Self>> {
+        let mut slf_mut = slf.try_borrow_mut()?;
+        slf_mut.inner.stderr(Box::new(wasmer_wasi::Pipe::new()));
+
+        Ok(slf)
+    }
+
+    /// Replaces the WASI clocks by a deterministic clock, for
+    /// instance to replay an execution. The first reading returns
+    /// `time`, in nanoseconds, and each reading advances the clock
+    /// by `step` nanoseconds. All the clocks, e.g. the realtime and
+    /// the monotonic ones, share the same readings, and their
+    /// resolution is 1 nanosecond.
+    ///
+    /// This method returns `self`.
+    ///
+    /// ## Example
+    ///
+    /// ```py
+    /// from wasmer import wasi
+    ///
+    /// wasi_env = \
+    ///     wasi.StateBuilder('test-program'). \
+    ///         clock(1_600_000_000_000_000_000, step=1_000). \
+    ///         finalize()
+    /// ```
+    #[pyo3(text_signature = "($self, time, /, step=0)")]
+    #[args(step = "0")]
+    pub fn clock(slf: &PyCell<Self>, time: u64, step: u64) -> PyResult<&PyCell<Self>> {
+        let mut slf_mut = slf.try_borrow_mut()?;
+        slf_mut.sources.clock = Some(deterministic::Clock { time, step });
+
+        Ok(slf)
+    }
+
+    /// Replaces the WASI random source by a pseudo-random generator
+    /// seeded with `seed`: the same seed produces the same bytes on
+    /// every run and every platform. It is not cryptographically
+    /// secure.
+    ///
+    /// This method returns `self`.
+    ///
+    /// ## Example
+    ///
+    /// ```py
+    /// from wasmer import wasi
+    ///
+    /// wasi_env = \
+    ///     wasi.StateBuilder('test-program'). \
+    ///         random_seed(42). \
+    ///         finalize()
+    /// ```
+    #[pyo3(text_signature = "($self, seed)")]
+    pub fn random_seed(slf: &PyCell<Self>, seed: u64) -> PyResult<&PyCell<Self>> {
+        let mut slf_mut = slf.try_borrow_mut()?;
+        slf_mut.sources.random_seed = Some(seed);
+
+        Ok(slf)
+    }
+
+    /// Listens for TCP connections on the host `address`, e.g.
+    /// `'127.0.0.1:8080'`; the port 0 picks a free port. The guest
+    /// accepts the connections with `sock_accept` on the listener
+    /// file descriptor, see `Environment.sockets`, and uses them
+    /// with `sock_recv`, `sock_send`, `sock_shutdown`, `fd_read`,
+    /// `fd_write`, and `fd_close`.
+    ///
+    /// If `allow` is given, only the connections from this list of
+    /// IP addresses are accepted: the other ones are closed, and
+    /// `sock_accept` returns `ECONNABORTED`.
+    ///
+    /// The socket is bound immediately, and is given to the first
+    /// environment produced by `finalize`. WASI networking is
+    /// experimental.
+    ///
+    /// This method returns `self`.
+    ///
+    /// ## Example
+    ///
+    /// ```py
+    /// from wasmer import wasi
+    ///
+    /// wasi_env = \
+    ///     wasi.StateBuilder('test-program'). \
+    ///         tcp_listen('127.0.0.1:0', allow=['127.0.0.1']). \
+    ///         finalize()
+    ///
+    /// (fd, (host, port)), = wasi_env.sockets.items()
+    ///
+    /// assert fd == 4
+    /// assert host == '127.0.0.1'
+    /// ```
+    #[pyo3(text_signature = "($self, address, /, allow=None)")]
+    #[args(allow = "None")]
+    pub fn tcp_listen<'py>(
+        slf: &'py PyCell<Self>,
+        address: &str,
+        allow: Option<Vec<&str>>,
+    ) -> PyResult<&'py PyCell<Self>> {
+        let allow = allow
+            .map(|allow| {
+                allow
+                    .into_iter()
+                    .map(|ip| {
+                        ip.parse::<IpAddr>().map_err(|error| {
+                            to_py_err::<PyValueError, _>(format!(
+                                "Invalid IP address `{}`: {}",
+                                ip, error
+                            ))
+                        })
+                    })
+                    .collect::<PyResult<Vec<_>>>()
+            })
+            .transpose()?;
+
+        let socket = sockets::Socket::listen(address, allow)?;
+        slf.try_borrow_mut()?.sockets.push(socket);
+
+        Ok(slf)
+    }
+
+    /// Opens a TCP connection to the host `address`, given to the
+    /// guest as a new file descriptor, see `Environment.sockets`. A
+    /// WASI guest cannot open connections by itself: it can only
+    /// reach the addresses connected to here.
+    ///
+    /// The connection is opened immediately, and is given to the
+    /// first environment produced by `finalize`. WASI networking is
+    /// experimental.
+    ///
+    /// This method returns `self`.
+    ///
+    /// ## Example
+    ///
+    /// ```py
+    /// from wasmer import wasi
+    /// import socket
+    ///
+    /// server = socket.create_server(('127.0.0.1', 0))
+    /// host, port = server.getsockname()
+    ///
+    /// wasi_env = \
+    ///     wasi.StateBuilder('test-program'). \
+    ///         tcp_connect(f'{host}:{port}'). \
+    ///         finalize()
+    ///
+    /// assert wasi_env.sockets == {4: (host, port)}
+    /// ```
+    #[pyo3(text_signature = "($self, address)")]
+    pub fn tcp_connect<'py>(slf: &'py PyCell<Self>, address: &str) -> PyResult<&'py PyCell<Self>> {
+        let socket = sockets::Socket::connect(address)?;
+        slf.try_borrow_mut()?.sockets.push(socket);
+
+        Ok(slf)
+    }
+
+    /// Produces a WASI `Environment` based on this state builder.
+    ///
+    /// ## Example
+    ///
+    /// ```py
+    /// from wasmer import wasi
+    ///
+    /// wasi_env = \
+    ///     wasi.StateBuilder('test-program'). \
+    ///         argument('--foo'). \
+    ///         finalize()
+    /// ```
+    #[pyo3(text_signature = "($self)")]
+    pub fn finalize(&mut self) -> PyResult<Environment> {
+        if self.file_system.has_mounts() {
+            self.inner.set_fs(Box::new(self.file_system.clone()));
+        }
+
+        let inner = self
+            .inner
+            .finalize()
+            .map_err(to_py_err::<PyRuntimeError, _>)?;
+        let sockets = sockets::State::default();
+
+        for socket in self.sockets.drain(..) {
+            sockets
+                .open(&mut inner.state().fs, socket)
+                .map_err(to_py_err::<PyRuntimeError, _>)?;
+        }
+
+        Ok(Environment {
+            inner,
+            sources: deterministic::State::new(self.sources),
+            sockets,
+        })
+    }
+}
+
+/// The environment provided to the WASI imports.
+///
+/// To build it, use `StateBuilder`. See `StateBuilder.finalize` to
+/// learn more.
+#[pyclass(unsendable)]
+pub struct Environment {
+    inner: wasmer_wasi::WasiEnv,
+    sources: deterministic::State,
+    sockets: sockets::State,
+}
+
+impl Environment {
+    fn import_object(&self, store: &wasmer::Store, wasi_version: Version) -> ImportObject {
+        let mut import_object =
+            ImportObject::raw_new(wasmer_wasi::generate_import_object_from_env(
+                store,
+                self.inner.clone(),
+                wasi_version.into(),
+            ));
+
+        let namespace_name = match wasi_version {
+            Version::Snapshot0 => "wasi_unstable",
+            Version::Latest | Version::Snapshot1 => "wasi_snapshot_preview1",
+        };
+        self.sources
+            .override_imports(store, &self.inner, namespace_name, &mut import_object);
+
+        if !self.sockets.is_empty() {
+            self.sockets
+                .override_imports(store, &self.inner, namespace_name, &mut import_object);
+        }
+
+        import_object
+    }
+
+    /// Reads, and empties, a captured standard device.
+    fn read_captured<'py, F>(
+        &self,
+        py: Python<'py>,
+        device_name: &str,
+        device: F,
+    ) -> PyResult<&'py PyBytes>
+    where
+        F: FnOnce(
+            &mut wasmer_wasi::WasiFs,
+        )
+            -> Result<&mut Option<Box<dyn wasmer_wasi::VirtualFile>>, wasmer_wasi::FsError>,
+    {
+        let mut state = self.inner.state();
+        let pipe = device(&mut state.fs)
+            .map_err(to_py_err::<PyRuntimeError, _>)?
+            .as_mut()
+            .and_then(|file| file.downcast_mut::<wasmer_wasi::Pipe>())
+            .ok_or_else(|| {
+                to_py_err::<PyRuntimeError, _>(format!(
+                    "`{0}` is not captured, see `StateBuilder.capture_{0}`",
+                    device_name
+                ))
+            })?;
+
+        let mut bytes = Vec::new();
+        pipe.read_to_end(&mut bytes)
+            .map_err(to_py_err::<PyRuntimeError, _>)?;
+
+        Ok(PyBytes::new(py, &bytes))
+    }
+}
+
+#[pymethods]
+impl Environment {
+    /// Create an `wasmer.ImportObject` with an existing
+    /// `Environment`. The import object will be different according
+    /// to the WASI version.
+    ///
+    /// Use the `Version` enum to use a specific WASI version, or use
+    /// `get_version` to read the WASI version from a `wasmer.Module`.
+    /// A `wasmer.Module` can also be given directly, in which case
+    /// its WASI version is detected with a non-strict `get_version`;
+    /// a `ValueError` is raised if the module does not import WASI.
+    ///
+    /// ## Example
+    ///
+    /// ```py
+    /// from wasmer import wasi, Store, Module
+    ///
+    /// store = Store()
+    /// wasi_env = wasi.StateBuilder('test-program').argument('--foo').finalize()
+    /// import_object = wasi_env.generate_import_object(store, wasi.Version.SNAPSHOT1)
+    ///
+    /// module = Module(store, open('tests/wasi.wasm', 'rb').read())
+    /// import_object = wasi_env.generate_import_object(store, module)
+    ///
+    /// assert "wasi_snapshot_preview1" in import_object
+    /// ```
+    ///
+    /// The import object can be combined with other imports, for
+    /// modules importing both WASI and host functions, with
+    /// `ImportObject.register`, `ImportObject.extend`, or the `|`
+    /// operator:
+    ///
+    /// ```py
+    /// from wasmer import wasi, Store, ImportObject, Function
+    ///
+    /// store = Store()
+    /// wasi_env = wasi.StateBuilder('test-program').finalize()
+    ///
+    /// def log(x: int):
+    ///     print(x)
+    ///
+    /// host = ImportObject()
+    /// host.register("host", {"log": Function(store, log)})
+    ///
+    /// import_object = wasi_env.generate_import_object(store, wasi.Version.SNAPSHOT1) | host
+    ///
+    /// assert list(import_object) == ["host", "wasi_snapshot_preview1"]
+    /// ```
+    //#[pyo3(text_signature = "($self, store, wasi_version)")]
+    fn generate_import_object(
+        &self,
+        store: &Store,
+        wasi_version: &PyAny,
+    ) -> PyResult<ImportObject> {
+        Ok(self.import_object(store.inner()?, to_version(wasi_version)?))
+    }
+
+    /// Create a dictionary of import with an existing
+    /// `Environment`. The import object will be different according
+    /// to the WASI version.
+    ///
+    /// Use the `Version` enum to use a specific WASI version, or use
+    /// `get_version` to read the WASI version from a `wasmer.Module`.
+    ///
+    /// ## Example
+    ///
+    /// ```py
+    /// from wasmer import wasi, Store
+    ///
+    /// store = Store()
+    /// wasi_env = wasi.StateBuilder('test-program').argument('--foo').finalize()
+    /// imports = wasi_env.generate_imports(store, wasi.Version.SNAPSHOT1)
+    /// ```
+    //#[pyo3(text_signature = "($self, store, wasi_version)")]
+    fn generate_imports(&self, store: &Store, wasi_version: &PyAny) -> PyResult<PyObject> {
+        self.generate_import_object(store, wasi_version)?.to_dict()
+    }
+
+    /// Gets the bytes written by the WASI module on its standard
+    /// output since the last call, when it is captured with
+    /// `StateBuilder.capture_stdout`.
+    ///
+    /// ## Example
+    ///
+    /// ```py
+    /// from wasmer import wasi, Store, Module, Instance
+    ///
+    /// store = Store()
+    /// module = Module(store, open('tests/wasi.wasm', 'rb').read())
+    ///
+    /// wasi_env = wasi.StateBuilder('test-program').capture_stdout().finalize()
+    /// import_object = wasi_env.generate_import_object(store, wasi.Version.SNAPSHOT1)
+    ///
+    /// Instance(module, import_object).exports._start()
+    ///
+    /// assert wasi_env.stdout().startswith(b'Found program name: `test-program`')
+    /// ```
+    #[pyo3(text_signature = "($self)")]
+    fn stdout<'py>(&self, py: Python<'py>) -> PyResult<&'py PyBytes> {
+        self.read_captured(py, "stdout", wasmer_wasi::WasiFs::stdout_mut)
+    }
+
+    /// Gets the bytes written by the WASI module on its standard
+    /// error since the last call, when it is captured with
+    /// `StateBuilder.capture_stderr`.
+    ///
+    /// ## Example
+    ///
+    /// See `Environment.stdout`.
+    #[pyo3(text_signature = "($self)")]
+    fn stderr<'py>(&self, py: Python<'py>) -> PyResult<&'py PyBytes> {
+        self.read_captured(py, "stderr", wasmer_wasi::WasiFs::stderr_mut)
+    }
+    /// Gets the open sockets of the environment, see
+    /// `StateBuilder.tcp_listen` and `StateBuilder.tcp_connect`, as
+    /// a dictionary from file descriptors to `(host, port)` tuples:
+    /// the local address of the listeners, and the peer address of
+    /// the connections, including the accepted ones.
+    ///
+    /// ## Example
+    ///
+    /// See `StateBuilder.tcp_listen`.
+    #[getter]
+    fn sockets<'py>(&self, py: Python<'py>) -> PyResult<&'py PyDict> {
+        Ok(self
+            .sockets
+            .addresses()
+            .map_err(to_py_err::<PyRuntimeError, _>)?
+            .into_iter()
+            .map(|(fd, address)| (fd, (address.ip().to_string(), address.port())))
+            .into_py_dict(py))
+    }
+}
+
+pub fn get_version(module: &Module, strict: bool) -> Option<Version> {
+    wasmer_wasi::get_wasi_version(&module.inner(), strict).map(Into::into)
+}
+
+fn to_capabilities(read_only: bool, create: bool, follow_symlinks: bool) -> Capabilities {
+    Capabilities {
+        write: !read_only,
+        create: !read_only && create,
+        follow_symlinks,
+    }
+}
+
+/// Reads a WASI version from a `Version`, or detects it from a
+/// `Module`.
+fn to_version(value: &PyAny) -> PyResult<Version> {
+    match value.downcast::<PyCell<Module>>() {
+        Ok(module) => get_version(&module.borrow(), false).ok_or_else(|| {
+            to_py_err::<PyValueError, _>("The module does not import any WASI namespace")
+        }),
+        Err(_) => value.extract(),
+    }
+}
+
+/// The result of `run`, like `subprocess.CompletedProcess`.
+///
+/// ## Example
+///
+/// See the `run` function.
+#[pyclass(unsendable)]
+pub struct CompletedProcess {
+    /// The arguments of the program, starting with its name.
+    #[pyo3(get)]
+    args: Vec<String>,
+
+    /// The exit code of the program: the code given to `proc_exit`,
+    /// or 0 if the program returned normally.
+    #[pyo3(get)]
+    returncode: u32,
+
+    /// The standard output of the program, as `bytes`.
+    #[pyo3(get)]
+    stdout: Py<PyBytes>,
+
+    /// The standard error of the program, as `bytes`.
+    #[pyo3(get)]
+    stderr: Py<PyBytes>,
+}
+
+#[pyproto]
+impl PyObjectProtocol for CompletedProcess {
+    fn __repr__(&self) -> PyResult<String> {
+        Python::with_gil(|py| {
+            Ok(format!(
+                "CompletedProcess(args={}, returncode={})",
+                PyList::new(py, &self.args).repr()?,
+                self.returncode
+            ))
+        })
+    }
+}
+
+/// Compiles the module at `path`. With a `timeout`, the module is
+/// compiled with the `Interruptible` middleware.
+fn compile_file(py: Python, path: PathBuf, timeout: Option<f64>) -> PyResult<Module> {
+    let bytes = fs::read(&path).map_err(|error| {
+        to_py_err::<PyFileNotFoundError, _>(format!("{}: `{}`", error, path.display()))
+    })?;
+
+    let store = match timeout {
+        Some(_) => {
+            let compiler = py
+                .import("wasmer_compiler_cranelift")
+                .or_else(|_| py.import("wasmer_compiler_llvm"))
+                .or_else(|_| py.import("wasmer_compiler_singlepass"))?
+                .getattr("Compiler")?
+                .call(
+                    (),
+                    Some(
+                        [("middlewares", vec![py.get_type::<Interruptible>().call0()?])]
+                            .into_py_dict(py),
+                    ),
+                )?;
+            let engine = py.get_type::<engines::Universal>().call1((compiler,))?;
+
+            py.get_type::<Store>().call1((engine,))?
+        }
+        None => py.get_type::<Store>().call0()?,
+    };
+    let store = store.downcast::<PyCell<Store>>()?.borrow();
+
+    Ok(Module::raw_new(compile(py, &store, &bytes, None)?))
+}
+
+pub fn run(
+    py: Python,
+    module_or_path: &PyAny,
+    args: Vec<String>,
+    env: Option<&PyDict>,
+    stdin: &PyAny,
+    timeout: Option<f64>,
+) -> PyResult<CompletedProcess> {
+    let (module, program_name) = match module_or_path.downcast::<PyCell<Module>>() {
+        Ok(module) => {
+            let module = Module::raw_new(module.borrow().inner().clone());
+            let program_name = module.inner().name().unwrap_or("main").to_string();
+
+            (module, program_name)
+        }
+        Err(_) => {
+            let path = module_or_path.extract::<PathBuf>()?;
+            let program_name = path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| "main".to_string());
+
+            (compile_file(py, path, timeout)?, program_name)
+        }
+    };
+
+    let wasi_version = get_version(&module, false).ok_or_else(|| {
+        to_py_err::<PyValueError, _>("The module does not import any WASI namespace")
+    })?;
+
+    let state_builder = PyCell::new(
+        py,
+        StateBuilder::new(program_name.clone(), None, env, None, None)?,
+    )?;
+    state_builder.borrow_mut().inner.args(&args);
+    StateBuilder::stdin(state_builder, stdin)?;
+    StateBuilder::capture_stdout(state_builder)?;
+    StateBuilder::capture_stderr(state_builder)?;
+
+    let environment = state_builder.borrow_mut().finalize()?;
+    let import_object = environment.import_object(module.inner().store(), wasi_version);
+    let instance =
+        Instance::from_import_object(py, module.inner(), &import_object, false, true, None)
+            .map_err(|error| error.into_py_err(module.inner()))?;
+
+    let start = Py::new(py, instance)?
+        .getattr(py, "exports")?
+        .getattr(py, "_start")?;
+    let returncode = match start.call(py, (), Some([("timeout", timeout)].into_py_dict(py))) {
+        Ok(_) => 0,
+        Err(error) if error.is_instance::<WasiExitError>(py) => {
+            error.instance(py).getattr("exit_code")?.extract()?
+        }
+        Err(error) => return Err(error),
+    };
+
+    Ok(CompletedProcess {
+        args: std::iter::once(program_name).chain(args).collect(),
+        returncode,
+        stdout: environment.stdout(py)?.into(),
+        stderr: environment.stderr(py)?.into(),
+    })
+}
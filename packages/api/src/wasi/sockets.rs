@@ -0,0 +1,490 @@
+use crate::{
+    import_object::ImportObject,
+    wasmer_inner::{wasmer, wasmer_vfs, wasmer_wasi},
+};
+use std::{
+    collections::HashMap,
+    io::{self, Read, Seek, Write},
+    net::{IpAddr, Shutdown, SocketAddr, TcpListener, TcpStream},
+    sync::{Arc, Mutex, MutexGuard, Weak},
+};
+use wasmer_wasi::types::{
+    __wasi_ciovec_t, __wasi_errno_t, __wasi_fd_t, __wasi_fdflags_t, __wasi_iovec_t,
+    __wasi_riflags_t, __wasi_rights_t, __wasi_roflags_t, __wasi_sdflags_t, __wasi_siflags_t,
+    __WASI_EBADF, __WASI_ECONNABORTED, __WASI_ECONNREFUSED, __WASI_ECONNRESET, __WASI_EFAULT,
+    __WASI_EINVAL, __WASI_EIO, __WASI_ENOTCONN, __WASI_ENOTSOCK, __WASI_ENOTSUP, __WASI_ESUCCESS,
+    __WASI_RIGHT_FD_FDSTAT_SET_FLAGS, __WASI_RIGHT_FD_READ, __WASI_RIGHT_FD_WRITE,
+    __WASI_RIGHT_POLL_FD_READWRITE, __WASI_RIGHT_SOCK_SHUTDOWN, __WASI_SHUT_RD, __WASI_SHUT_WR,
+};
+
+/// The maximum number of bytes read by `sock_recv`, or written at
+/// once by `sock_send`.
+const BUFFER_SIZE: usize = 64 * 1024;
+
+const RIGHTS: __wasi_rights_t = __WASI_RIGHT_FD_READ
+    | __WASI_RIGHT_FD_WRITE
+    | __WASI_RIGHT_FD_FDSTAT_SET_FLAGS
+    | __WASI_RIGHT_POLL_FD_READWRITE
+    | __WASI_RIGHT_SOCK_SHUTDOWN;
+
+/// A host TCP socket given to a WASI guest.
+#[derive(Debug)]
+pub(crate) enum Socket {
+    /// A listener, accepting the connections from the `allow`ed IP
+    /// addresses only, if any.
+    Listener {
+        inner: TcpListener,
+        allow: Option<Vec<IpAddr>>,
+    },
+    Stream(TcpStream),
+}
+
+impl Socket {
+    pub(crate) fn listen(address: &str, allow: Option<Vec<IpAddr>>) -> io::Result<Self> {
+        Ok(Self::Listener {
+            inner: TcpListener::bind(address)?,
+            allow,
+        })
+    }
+
+    pub(crate) fn connect(address: &str) -> io::Result<Self> {
+        Ok(Self::Stream(TcpStream::connect(address)?))
+    }
+
+    /// The local address of a listener, or the peer address of a
+    /// stream.
+    pub(crate) fn address(&self) -> io::Result<SocketAddr> {
+        match self {
+            Self::Listener { inner, .. } => inner.local_addr(),
+            Self::Stream(stream) => stream.peer_addr(),
+        }
+    }
+
+    fn stream(&self) -> io::Result<&TcpStream> {
+        match self {
+            Self::Listener { .. } => Err(io::ErrorKind::NotConnected.into()),
+            Self::Stream(stream) => Ok(stream),
+        }
+    }
+}
+
+/// The file backing a socket file descriptor. Once the guest closes
+/// it, the socket is closed too.
+#[derive(Debug)]
+struct File(Arc<Socket>);
+
+impl Read for File {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        self.0.stream()?.read(buffer)
+    }
+}
+
+impl Write for File {
+    fn write(&mut self, buffer: &[u8]) -> io::Result<usize> {
+        self.0.stream()?.write(buffer)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.stream()?.flush()
+    }
+}
+
+impl Seek for File {
+    // `fd_read` and `fd_write` seek before every access, which is
+    // meaningless for a socket.
+    fn seek(&mut self, _position: io::SeekFrom) -> io::Result<u64> {
+        Ok(0)
+    }
+}
+
+impl wasmer_vfs::VirtualFile for File {
+    fn last_accessed(&self) -> u64 {
+        0
+    }
+
+    fn last_modified(&self) -> u64 {
+        0
+    }
+
+    fn created_time(&self) -> u64 {
+        0
+    }
+
+    fn size(&self) -> u64 {
+        0
+    }
+
+    fn set_len(&mut self, _new_size: u64) -> Result<(), wasmer_wasi::FsError> {
+        Err(wasmer_wasi::FsError::PermissionDenied)
+    }
+
+    fn unlink(&mut self) -> Result<(), wasmer_wasi::FsError> {
+        Ok(())
+    }
+
+    fn bytes_available(&self) -> Result<usize, wasmer_wasi::FsError> {
+        Ok(0)
+    }
+}
+
+#[derive(Default)]
+struct Table {
+    // The sockets are owned by the file descriptors, and are only
+    // referenced here.
+    sockets: HashMap<__wasi_fd_t, Weak<Socket>>,
+    opened: usize,
+}
+
+/// The sockets of an `Environment`, shared by all the import objects
+/// it generates.
+#[derive(Clone, Default)]
+pub(crate) struct State {
+    table: Arc<Mutex<Table>>,
+}
+
+impl State {
+    fn lock(&self) -> Result<MutexGuard<'_, Table>, wasmer_wasi::FsError> {
+        self.table.lock().map_err(|_| wasmer_wasi::FsError::Lock)
+    }
+
+    /// Whether no socket has been opened. A table that cannot be
+    /// locked is not empty: its sockets then fail with `EIO`.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.lock().is_ok_and(|table| table.opened == 0)
+    }
+
+    /// Opens `socket` as a new file descriptor of `fs`.
+    pub(crate) fn open(
+        &self,
+        fs: &mut wasmer_wasi::WasiFs,
+        socket: Socket,
+    ) -> Result<__wasi_fd_t, wasmer_wasi::FsError> {
+        let mut table = self.lock()?;
+        let socket = Arc::new(socket);
+
+        // The file is named in the root directory, but a name
+        // containing a `/` cannot be reached by `path_open`.
+        let fd = fs.open_file_at(
+            wasmer_wasi::VIRTUAL_ROOT_FD,
+            Box::new(File(socket.clone())),
+            wasmer_wasi::Fd::READ | wasmer_wasi::Fd::WRITE,
+            format!("socket/{}", table.opened),
+            RIGHTS,
+            RIGHTS,
+            0,
+        )?;
+
+        table.opened += 1;
+        table.sockets.insert(fd, Arc::downgrade(&socket));
+
+        Ok(fd)
+    }
+
+    /// The addresses of the open sockets, see `Socket::address`.
+    pub(crate) fn addresses(&self) -> Result<Vec<(__wasi_fd_t, SocketAddr)>, wasmer_wasi::FsError> {
+        let table = self.lock()?;
+        let mut addresses = table
+            .sockets
+            .iter()
+            .filter_map(|(fd, socket)| Some((*fd, socket.upgrade()?.address().ok()?)))
+            .collect::<Vec<_>>();
+        addresses.sort_by_key(|(fd, _)| *fd);
+
+        Ok(addresses)
+    }
+
+    fn get(&self, fd: __wasi_fd_t) -> Result<Arc<Socket>, __wasi_errno_t> {
+        self.lock()
+            .map_err(|_| __WASI_EIO)?
+            .sockets
+            .get(&fd)
+            .ok_or(__WASI_ENOTSOCK)?
+            .upgrade()
+            .ok_or(__WASI_EBADF)
+    }
+
+    /// Replaces the socket imports of `import_object`, in the
+    /// `namespace_name` WASI namespace, by the ones using the
+    /// sockets of this state, and adds `sock_accept`.
+    pub(crate) fn override_imports(
+        &self,
+        store: &wasmer::Store,
+        wasi_env: &wasmer_wasi::WasiEnv,
+        namespace_name: &str,
+        import_object: &mut ImportObject,
+    ) {
+        let env = Env {
+            wasi: wasi_env.clone(),
+            state: self.clone(),
+        };
+        let mut define = |name: &str, function| {
+            import_object.insert(
+                namespace_name.to_string(),
+                name.to_string(),
+                wasmer::Extern::Function(function),
+            )
+        };
+
+        define(
+            "sock_accept",
+            wasmer::Function::new_native_with_env(store, env.clone(), sock_accept),
+        );
+        define(
+            "sock_recv",
+            wasmer::Function::new_native_with_env(store, env.clone(), sock_recv),
+        );
+        define(
+            "sock_send",
+            wasmer::Function::new_native_with_env(store, env.clone(), sock_send),
+        );
+        define(
+            "sock_shutdown",
+            wasmer::Function::new_native_with_env(store, env, sock_shutdown),
+        );
+    }
+}
+
+#[derive(Clone)]
+struct Env {
+    wasi: wasmer_wasi::WasiEnv,
+    state: State,
+}
+
+impl wasmer::WasmerEnv for Env {
+    fn init_with_instance(
+        &mut self,
+        instance: &wasmer::Instance,
+    ) -> Result<(), wasmer::HostEnvInitError> {
+        self.wasi.init_with_instance(instance)
+    }
+}
+
+fn to_errno(error: &io::Error) -> __wasi_errno_t {
+    match error.kind() {
+        io::ErrorKind::ConnectionAborted => __WASI_ECONNABORTED,
+        io::ErrorKind::ConnectionRefused => __WASI_ECONNREFUSED,
+        io::ErrorKind::ConnectionReset => __WASI_ECONNRESET,
+        io::ErrorKind::NotConnected => __WASI_ENOTCONN,
+        io::ErrorKind::InvalidInput => __WASI_EINVAL,
+        _ => __WASI_EIO,
+    }
+}
+
+/// Reads the `(buf, buf_len)` pairs of an array of `iovec`
+/// or `ciovec`.
+fn buffers<T: Copy + wasmer::ValueType>(
+    memory: &wasmer::Memory,
+    iovs: wasmer::WasmPtr<T, wasmer::Array>,
+    iovs_len: u32,
+    to_pair: fn(T) -> (u32, u32),
+) -> Result<Vec<(u32, u32)>, __wasi_errno_t> {
+    Ok(iovs
+        .deref(memory, 0, iovs_len)
+        .ok_or(__WASI_EFAULT)?
+        .iter()
+        .map(|iov| to_pair(iov.get()))
+        .collect())
+}
+
+fn sock_accept(
+    env: &Env,
+    sock: __wasi_fd_t,
+    _flags: __wasi_fdflags_t,
+    fd: wasmer::WasmPtr<__wasi_fd_t>,
+) -> __wasi_errno_t {
+    let socket = match env.state.get(sock) {
+        Ok(socket) => socket,
+        Err(errno) => return errno,
+    };
+    let (listener, allow) = match &*socket {
+        Socket::Listener { inner, allow } => (inner, allow),
+        Socket::Stream(_) => return __WASI_EINVAL,
+    };
+    let fd = match fd.deref(env.wasi.memory()) {
+        Some(fd) => fd,
+        None => return __WASI_EFAULT,
+    };
+
+    let (stream, peer) = match listener.accept() {
+        Ok(accepted) => accepted,
+        Err(error) => return to_errno(&error),
+    };
+
+    // Dropping the stream closes the refused connection.
+    if let Some(allow) = allow {
+        if !allow.contains(&peer.ip()) {
+            return __WASI_ECONNABORTED;
+        }
+    }
+
+    match env
+        .state
+        .open(&mut env.wasi.state().fs, Socket::Stream(stream))
+    {
+        Ok(new_fd) => {
+            fd.set(new_fd);
+
+            __WASI_ESUCCESS
+        }
+        Err(_) => __WASI_EIO,
+    }
+}
+
+fn sock_recv(
+    env: &Env,
+    sock: __wasi_fd_t,
+    ri_data: wasmer::WasmPtr<__wasi_iovec_t, wasmer::Array>,
+    ri_data_len: u32,
+    ri_flags: __wasi_riflags_t,
+    ro_datalen: wasmer::WasmPtr<u32>,
+    ro_flags: wasmer::WasmPtr<__wasi_roflags_t>,
+) -> __wasi_errno_t {
+    if ri_flags != 0 {
+        return __WASI_ENOTSUP;
+    }
+
+    let socket = match env.state.get(sock) {
+        Ok(socket) => socket,
+        Err(errno) => return errno,
+    };
+    let mut stream = match socket.stream() {
+        Ok(stream) => stream,
+        Err(error) => return to_errno(&error),
+    };
+
+    let memory = env.wasi.memory();
+    let buffers = match buffers(memory, ri_data, ri_data_len, |iov| (iov.buf, iov.buf_len)) {
+        Ok(buffers) => buffers,
+        Err(errno) => return errno,
+    };
+    let (ro_datalen, ro_flags) = match (ro_datalen.deref(memory), ro_flags.deref(memory)) {
+        (Some(ro_datalen), Some(ro_flags)) => (ro_datalen, ro_flags),
+        _ => return __WASI_EFAULT,
+    };
+
+    let view = memory.view::<u8>();
+    let buffers = match buffers
+        .into_iter()
+        .map(|(offset, length)| view.get(offset as usize..offset as usize + length as usize))
+        .collect::<Option<Vec<_>>>()
+    {
+        Some(buffers) => buffers,
+        None => return __WASI_EFAULT,
+    };
+
+    // A single read, of at most `BUFFER_SIZE` bytes, scattered over
+    // the buffers. A shorter read is valid, and more reads could
+    // block.
+    let length = buffers.iter().map(|cells| cells.len()).sum::<usize>();
+    let mut bytes = vec![0; length.min(BUFFER_SIZE)];
+    let read = match stream.read(&mut bytes) {
+        Ok(read) => read,
+        Err(error) => return to_errno(&error),
+    };
+
+    let cells = buffers.iter().flat_map(|cells| cells.iter());
+
+    for (cell, byte) in cells.zip(&bytes[..read]) {
+        cell.set(*byte);
+    }
+
+    ro_datalen.set(read as u32);
+    ro_flags.set(0);
+
+    __WASI_ESUCCESS
+}
+
+fn sock_send(
+    env: &Env,
+    sock: __wasi_fd_t,
+    si_data: wasmer::WasmPtr<__wasi_ciovec_t, wasmer::Array>,
+    si_data_len: u32,
+    _si_flags: __wasi_siflags_t,
+    so_datalen: wasmer::WasmPtr<u32>,
+) -> __wasi_errno_t {
+    let socket = match env.state.get(sock) {
+        Ok(socket) => socket,
+        Err(errno) => return errno,
+    };
+    let mut stream = match socket.stream() {
+        Ok(stream) => stream,
+        Err(error) => return to_errno(&error),
+    };
+
+    let memory = env.wasi.memory();
+    let buffers = match buffers(memory, si_data, si_data_len, |iov| (iov.buf, iov.buf_len)) {
+        Ok(buffers) => buffers,
+        Err(errno) => return errno,
+    };
+    let so_datalen = match so_datalen.deref(memory) {
+        Some(so_datalen) => so_datalen,
+        None => return __WASI_EFAULT,
+    };
+
+    let view = memory.view::<u8>();
+    let buffers = match buffers
+        .into_iter()
+        .map(|(offset, length)| view.get(offset as usize..offset as usize + length as usize))
+        .collect::<Option<Vec<_>>>()
+    {
+        Some(buffers) => buffers,
+        None => return __WASI_EFAULT,
+    };
+
+    // The buffers are gathered by chunks of at most `BUFFER_SIZE`
+    // bytes, written until one is written partially.
+    let mut cells = buffers.iter().flat_map(|cells| cells.iter());
+    let mut bytes = Vec::with_capacity(BUFFER_SIZE);
+    let mut written = 0;
+
+    loop {
+        bytes.clear();
+        bytes.extend(cells.by_ref().take(BUFFER_SIZE).map(|cell| cell.get()));
+
+        if bytes.is_empty() {
+            break;
+        }
+
+        match stream.write(&bytes) {
+            Ok(chunk_written) => {
+                written += chunk_written;
+
+                if chunk_written < bytes.len() {
+                    break;
+                }
+            }
+
+            // The bytes already written are reported, like a short
+            // write.
+            Err(_) if written > 0 => break,
+            Err(error) => return to_errno(&error),
+        }
+    }
+
+    so_datalen.set(written as u32);
+
+    __WASI_ESUCCESS
+}
+
+fn sock_shutdown(env: &Env, sock: __wasi_fd_t, how: __wasi_sdflags_t) -> __wasi_errno_t {
+    let socket = match env.state.get(sock) {
+        Ok(socket) => socket,
+        Err(errno) => return errno,
+    };
+    let stream = match socket.stream() {
+        Ok(stream) => stream,
+        Err(error) => return to_errno(&error),
+    };
+
+    let how = match how {
+        __WASI_SHUT_RD => Shutdown::Read,
+        __WASI_SHUT_WR => Shutdown::Write,
+        how if how == __WASI_SHUT_RD | __WASI_SHUT_WR => Shutdown::Both,
+        _ => return __WASI_EINVAL,
+    };
+
+    match stream.shutdown(how) {
+        Ok(()) => __WASI_ESUCCESS,
+        Err(error) => to_errno(&error),
+    }
+}
@@ -1,6 +1,8 @@
-use pyo3::prelude::*;
+use pyo3::{exceptions::PyValueError, prelude::*};
 
-use wasmer_engines::OpaqueCompiler;
+use wasmer_compiler::CompilerConfig;
+use wasmer_compiler_cranelift::CraneliftOptLevel;
+use wasmer_engines::{Middlewares, OpaqueCompiler, ThreadPool};
 
 /// The Cranelift compiler, designed for the `wasmer` Python package
 /// (a WebAssembly runtime).
@@ -15,6 +17,24 @@ fn wasmer_compiler_cranelift(_py: Python, module: &PyModule) -> PyResult<()> {
 
 /// The Cranelift compiler.
 ///
+/// The `Compiler` class can be given as is to an engine, in which
+/// case the default configuration is used. Otherwise, a `Compiler`
+/// instance can be configured with the following arguments:
+///
+/// * `opt_level`, the optimization level, either `"none"` (to
+///   minimize the compilation time), `"speed"` (the default) or
+///   `"speed_and_size"`,
+/// * `enable_verifier`, to run the Cranelift IR verifier, which is
+///   useful to debug the compiler (`False` by default),
+/// * `canonicalize_nans`, to canonicalize the NaN values, so that the
+///   floating-point results are deterministic across different
+///   architectures (`False` by default),
+/// * `threads`, the number of threads compiling the functions of a
+///   module in parallel, `1` to compile them one after the other (one
+///   per CPU by default),
+/// * `middlewares`, a list of middlewares, e.g. `wasmer.Metering`,
+///   applied in order to the compiled modules (empty by default).
+///
 /// ## Example
 ///
 /// ```py
@@ -22,18 +42,73 @@ fn wasmer_compiler_cranelift(_py: Python, module: &PyModule) -> PyResult<()> {
 /// from wasmer_compiler_cranelift import Compiler
 ///
 /// store = Store(engine.JIT(Compiler))
+///
+/// # Trade runtime performance for a faster compilation.
+/// store = Store(engine.JIT(Compiler(opt_level="none")))
+///
+/// # Compile the functions on two threads only.
+/// store = Store(engine.JIT(Compiler(threads=2)))
 /// ```
 #[pyclass]
-struct Compiler {}
+#[pyo3(text_signature = "(/, opt_level, enable_verifier, canonicalize_nans, threads, middlewares)")]
+struct Compiler {
+    inner: wasmer_compiler_cranelift::Cranelift,
+    middlewares: Middlewares,
+    thread_pool: ThreadPool,
+}
 
 #[pymethods]
 impl Compiler {
+    #[new]
+    #[args(
+        opt_level = "\"speed\"",
+        enable_verifier = "false",
+        canonicalize_nans = "false",
+        threads = "None",
+        middlewares = "None"
+    )]
+    fn new(
+        opt_level: &str,
+        enable_verifier: bool,
+        canonicalize_nans: bool,
+        threads: Option<usize>,
+        middlewares: Option<Vec<&PyAny>>,
+    ) -> PyResult<Self> {
+        let mut inner = wasmer_compiler_cranelift::Cranelift::new();
+
+        inner.opt_level(match opt_level {
+            "none" => CraneliftOptLevel::None,
+            "speed" => CraneliftOptLevel::Speed,
+            "speed_and_size" => CraneliftOptLevel::SpeedAndSize,
+            _ => {
+                return Err(PyValueError::new_err(format!(
+                    "Unknown optimization level `{}`, expected `none`, `speed` or `speed_and_size`",
+                    opt_level
+                )))
+            }
+        });
+
+        if enable_verifier {
+            inner.enable_verifier();
+        }
+
+        inner.canonicalize_nans(canonicalize_nans);
+
+        Ok(Self {
+            inner,
+            middlewares: Middlewares::new(middlewares)?,
+            thread_pool: ThreadPool::new(threads)?,
+        })
+    }
+
     /// Please don't use it. Internal use only.
-    #[staticmethod]
-    fn into_opaque_compiler() -> OpaqueCompiler {
+    #[allow(clippy::wrong_self_convention)]
+    fn into_opaque_compiler(&self) -> OpaqueCompiler {
         OpaqueCompiler::raw_with_compiler(
-            wasmer_compiler_cranelift::Cranelift::default(),
+            self.inner.clone(),
             "cranelift".to_string(),
+            &self.middlewares,
+            &self.thread_pool,
         )
     }
 }
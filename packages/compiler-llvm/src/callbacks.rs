@@ -0,0 +1,137 @@
+use pyo3::{exceptions::PyRuntimeWarning, PyErr, Python};
+use std::{
+    fmt,
+    fs::{self, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+use wasmer_compiler_llvm::{CompiledKind, InkwellMemoryBuffer, InkwellModule, LLVMCallbacks};
+use wasmer_types::{entity::EntityRef, Type};
+
+/// LLVM callbacks that dump the IR, before and after the
+/// optimizations, and the object files into a directory.
+///
+/// A file that cannot be dumped does not fail the compilation: the
+/// first failure is reported with a `RuntimeWarning`, and the next
+/// ones are ignored.
+#[derive(Clone)]
+pub struct DebugDirectory {
+    path: PathBuf,
+    warned: Arc<AtomicBool>,
+}
+
+impl DebugDirectory {
+    /// Creates the directory at `path` if it does not exist, and
+    /// checks that files can be written in it.
+    pub fn new(path: PathBuf) -> io::Result<Self> {
+        fs::create_dir_all(&path)?;
+
+        let probe = path.join(".wasmer-debug-dir");
+        File::create(&probe)?;
+        fs::remove_file(&probe)?;
+
+        Ok(Self {
+            path,
+            warned: Arc::new(AtomicBool::new(false)),
+        })
+    }
+
+    fn dump(
+        &self,
+        kind: &CompiledKind,
+        extension: &str,
+        dump: impl FnOnce(&Path) -> Result<(), String>,
+    ) {
+        let path = self
+            .path
+            .join(format!("{}.{}", function_kind_to_filename(kind), extension));
+
+        if let Err(error) = dump(&path) {
+            if !self.warned.swap(true, Ordering::SeqCst) {
+                let message = format!(
+                    "Failed to dump `{}` in the LLVM debug directory: {}",
+                    path.display(),
+                    error
+                );
+
+                // The warning is lost if the warning filters turn it
+                // into an exception, since it cannot be raised from
+                // the compiler.
+                Python::with_gil(|py| {
+                    PyErr::warn(py, py.get_type::<PyRuntimeWarning>(), &message, 0).ok()
+                });
+            }
+        }
+    }
+}
+
+impl fmt::Debug for DebugDirectory {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter
+            .debug_struct("DebugDirectory")
+            .field("path", &self.path)
+            .finish()
+    }
+}
+
+impl LLVMCallbacks for DebugDirectory {
+    fn preopt_ir(&self, kind: &CompiledKind, module: &InkwellModule) {
+        self.dump(kind, "preopt.ll", |path| {
+            module
+                .print_to_file(path)
+                .map_err(|error| error.to_string())
+        });
+    }
+
+    fn postopt_ir(&self, kind: &CompiledKind, module: &InkwellModule) {
+        self.dump(kind, "postopt.ll", |path| {
+            module
+                .print_to_file(path)
+                .map_err(|error| error.to_string())
+        });
+    }
+
+    fn obj_memory_buffer(&self, kind: &CompiledKind, memory_buffer: &InkwellMemoryBuffer) {
+        self.dump(kind, "o", |path| {
+            File::create(path)
+                .and_then(|mut file| file.write_all(memory_buffer.as_slice()))
+                .map_err(|error| error.to_string())
+        });
+    }
+}
+
+fn function_kind_to_filename(kind: &CompiledKind) -> String {
+    match kind {
+        CompiledKind::Local(local_index) => format!("function_{}", local_index.index()),
+        CompiledKind::FunctionCallTrampoline(function_type) => format!(
+            "trampoline_call_{}_{}",
+            types_to_signature(function_type.params()),
+            types_to_signature(function_type.results())
+        ),
+        CompiledKind::DynamicFunctionTrampoline(function_type) => format!(
+            "trampoline_dynamic_{}_{}",
+            types_to_signature(function_type.params()),
+            types_to_signature(function_type.results())
+        ),
+        CompiledKind::Module => "module".to_string(),
+    }
+}
+
+fn types_to_signature(types: &[Type]) -> String {
+    types
+        .iter()
+        .map(|ty| match ty {
+            Type::I32 => 'i',
+            Type::I64 => 'I',
+            Type::F32 => 'f',
+            Type::F64 => 'F',
+            Type::V128 => 'v',
+            Type::ExternRef => 'e',
+            Type::FuncRef => 'r',
+        })
+        .collect()
+}
@@ -1,6 +1,14 @@
-use pyo3::prelude::*;
+mod callbacks;
 
-use wasmer_engines::OpaqueCompiler;
+use crate::callbacks::DebugDirectory;
+use pyo3::{
+    exceptions::{PyRuntimeError, PyValueError},
+    prelude::*,
+};
+use std::{path::PathBuf, sync::Arc};
+use wasmer_compiler::CompilerConfig;
+use wasmer_compiler_llvm::LLVMOptLevel;
+use wasmer_engines::{Middlewares, OpaqueCompiler, ThreadPool};
 
 /// The LLVM compiler, designed for the `wasmer` Python package (a
 /// WebAssembly runtime).
@@ -15,22 +23,116 @@ fn wasmer_compiler_llvm(_py: Python, module: &PyModule) -> PyResult<()> {
 
 /// The LLVM compiler.
 ///
+/// The `Compiler` class can be given as is to an engine, in which
+/// case the default configuration is used. Otherwise, a `Compiler`
+/// instance can be configured with the following arguments:
+///
+/// * `opt_level`, the optimization level, either `"none"`, `"less"`,
+///   `"default"` or `"aggressive"` (the default, similar to `-O3`),
+/// * `enable_verifier`, to run the LLVM IR verifier, which is useful
+///   to debug the compiler (`False` by default),
+/// * `debug_dir`, a directory where the LLVM IR, before and after the
+///   optimizations (`.preopt.ll` and `.postopt.ll` files), and the
+///   object files (`.o` files) of every compiled function are
+///   dumped. The directory is created if it does not exist, and must
+///   be writable,
+/// * `canonicalize_nans`, to canonicalize the NaN values, so that the
+///   floating-point results are deterministic across different
+///   architectures (`False` by default),
+/// * `threads`, the number of threads compiling the functions of a
+///   module in parallel, `1` to compile them one after the other (one
+///   per CPU by default),
+/// * `middlewares`, a list of middlewares, e.g. `wasmer.Metering`,
+///   applied in order to the compiled modules (empty by default).
+///
 /// ## Example
 ///
 /// ```py
 /// from wasmer import engine, Store
-/// from wasmer_compiler_cranelift import Compiler
+/// from wasmer_compiler_llvm import Compiler
 ///
 /// store = Store(engine.JIT(Compiler))
+///
+/// # Dump the LLVM IR and the object files of the compiled modules.
+/// store = Store(engine.JIT(Compiler(opt_level="aggressive", debug_dir="/tmp/llvm")))
 /// ```
 #[pyclass]
-struct Compiler {}
+#[pyo3(
+    text_signature = "(/, opt_level, enable_verifier, debug_dir, canonicalize_nans, threads, middlewares)"
+)]
+struct Compiler {
+    inner: wasmer_compiler_llvm::LLVM,
+    middlewares: Middlewares,
+    thread_pool: ThreadPool,
+}
 
 #[pymethods]
 impl Compiler {
+    #[new]
+    #[args(
+        opt_level = "\"aggressive\"",
+        enable_verifier = "false",
+        debug_dir = "None",
+        canonicalize_nans = "false",
+        threads = "None",
+        middlewares = "None"
+    )]
+    fn new(
+        opt_level: &str,
+        enable_verifier: bool,
+        debug_dir: Option<PathBuf>,
+        canonicalize_nans: bool,
+        threads: Option<usize>,
+        middlewares: Option<Vec<&PyAny>>,
+    ) -> PyResult<Self> {
+        let mut inner = wasmer_compiler_llvm::LLVM::new();
+
+        inner.opt_level(match opt_level {
+            "none" => LLVMOptLevel::None,
+            "less" => LLVMOptLevel::Less,
+            "default" => LLVMOptLevel::Default,
+            "aggressive" => LLVMOptLevel::Aggressive,
+            _ => {
+                return Err(PyValueError::new_err(format!(
+                    "Unknown optimization level `{}`, expected `none`, `less`, `default` or `aggressive`",
+                    opt_level
+                )))
+            }
+        });
+
+        if enable_verifier {
+            inner.enable_verifier();
+        }
+
+        if let Some(debug_dir) = debug_dir {
+            let debug_directory = DebugDirectory::new(debug_dir.clone()).map_err(|error| {
+                PyRuntimeError::new_err(format!(
+                    "Cannot use `{}` as the debug directory: {}",
+                    debug_dir.display(),
+                    error
+                ))
+            })?;
+
+            inner.callbacks(Some(Arc::new(debug_directory)));
+        }
+
+        inner.canonicalize_nans(canonicalize_nans);
+
+        Ok(Self {
+            inner,
+            middlewares: Middlewares::new(middlewares)?,
+            thread_pool: ThreadPool::new(threads)?,
+        })
+    }
+
     /// Please don't use it. Internal use only.
-    #[staticmethod]
-    fn into_opaque_compiler() -> OpaqueCompiler {
-        OpaqueCompiler::raw_with_compiler(wasmer_compiler_llvm::LLVM::default(), "llvm".to_string())
+    #[allow(clippy::wrong_self_convention)]
+    fn into_opaque_compiler(&self) -> OpaqueCompiler {
+        OpaqueCompiler::raw_with_compiler(
+            self.inner.clone(),
+            "llvm".to_string(),
+            &self.middlewares,
+            &self.thread_pool,
+        )
     }
 }
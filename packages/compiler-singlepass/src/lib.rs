@@ -1,6 +1,6 @@
 use pyo3::prelude::*;
 
-use wasmer_engines::OpaqueCompiler;
+use wasmer_engines::{Middlewares, OpaqueCompiler, ThreadPool};
 
 /// The Singlepass compiler, designed for the `wasmer` Python package
 /// (a WebAssembly runtime).
@@ -15,6 +15,23 @@ fn wasmer_compiler_singlepass(_py: Python, module: &PyModule) -> PyResult<()> {
 
 /// The Singlepass compiler.
 ///
+/// The `Compiler` class can be given as is to an engine, in which
+/// case the default configuration is used. Otherwise, a `Compiler`
+/// instance can be configured with the following arguments:
+///
+/// * `enable_stack_check`, to perform an explicit stack depth check
+///   on entry to each function to prevent stack overflows (`False`
+///   by default). Note that it doesn't guarantee a deterministic
+///   execution across different platforms,
+/// * `canonicalize_nans`, to canonicalize the NaN values, so that the
+///   floating-point results are deterministic across different
+///   architectures (`True` by default),
+/// * `threads`, the number of threads compiling the functions of a
+///   module in parallel, `1` to compile them one after the other (one
+///   per CPU by default),
+/// * `middlewares`, a list of middlewares, e.g. `wasmer.Metering`,
+///   applied in order to the compiled modules (empty by default).
+///
 /// ## Example
 ///
 /// ```py
@@ -22,18 +39,52 @@ fn wasmer_compiler_singlepass(_py: Python, module: &PyModule) -> PyResult<()> {
 /// from wasmer_compiler_singlepass import Compiler
 ///
 /// store = Store(engine.JIT(Compiler))
+///
+/// # Check the stack depth on entry to each function.
+/// store = Store(engine.JIT(Compiler(enable_stack_check=True)))
 /// ```
 #[pyclass]
-struct Compiler {}
+#[pyo3(text_signature = "(/, enable_stack_check, canonicalize_nans, threads, middlewares)")]
+struct Compiler {
+    inner: wasmer_compiler_singlepass::Singlepass,
+    middlewares: Middlewares,
+    thread_pool: ThreadPool,
+}
 
 #[pymethods]
 impl Compiler {
+    #[new]
+    #[args(
+        enable_stack_check = "false",
+        canonicalize_nans = "true",
+        threads = "None",
+        middlewares = "None"
+    )]
+    fn new(
+        enable_stack_check: bool,
+        canonicalize_nans: bool,
+        threads: Option<usize>,
+        middlewares: Option<Vec<&PyAny>>,
+    ) -> PyResult<Self> {
+        let mut inner = wasmer_compiler_singlepass::Singlepass::new();
+        inner.enable_stack_check(enable_stack_check);
+        inner.canonicalize_nans(canonicalize_nans);
+
+        Ok(Self {
+            inner,
+            middlewares: Middlewares::new(middlewares)?,
+            thread_pool: ThreadPool::new(threads)?,
+        })
+    }
+
     /// Please don't use it. Internal use only.
-    #[staticmethod]
-    fn into_opaque_compiler() -> OpaqueCompiler {
+    #[allow(clippy::wrong_self_convention)]
+    fn into_opaque_compiler(&self) -> OpaqueCompiler {
         OpaqueCompiler::raw_with_compiler(
-            wasmer_compiler_singlepass::Singlepass::default(),
+            self.inner.clone(),
             "singlepass".to_string(),
+            &self.middlewares,
+            &self.thread_pool,
         )
     }
 }
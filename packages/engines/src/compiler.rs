@@ -0,0 +1,154 @@
+use crate::{middlewares::Middleware, thread_pool::ThreadPool};
+use loupe::{MemoryUsage, MemoryUsageTracker};
+use std::{mem, sync::Arc};
+use wasmer_compiler::{
+    Compilation, CompileError, CompileModuleInfo, Compiler, CompilerConfig, FunctionBodyData,
+    ModuleMiddleware, ModuleTranslationState, SymbolRegistry, Target,
+};
+use wasmer_types::{entity::PrimaryMap, Features, LocalFunctionIndex};
+
+/// The configuration of a compiler of a compiler package, e.g.
+/// `wasmer_compiler_cranelift`, built by
+/// `OpaqueCompiler::raw_with_compiler`.
+///
+/// The middlewares are kept apart from the configuration of the
+/// compiler, and pushed onto a copy of it for each compiled module,
+/// so that the `PerModuleMiddleware`s are generated for that module.
+/// The module is compiled on the thread pool.
+pub(crate) struct PythonCompilerConfig<C> {
+    inner: C,
+    middlewares: Vec<Middleware>,
+    thread_pool: ThreadPool,
+}
+
+impl<C> PythonCompilerConfig<C> {
+    pub(crate) fn new(inner: C, middlewares: Vec<Middleware>, thread_pool: ThreadPool) -> Self {
+        Self {
+            inner,
+            middlewares,
+            thread_pool,
+        }
+    }
+}
+
+impl<C> CompilerConfig for PythonCompilerConfig<C>
+where
+    C: CompilerConfig + Clone + Send + Sync + 'static,
+{
+    fn enable_pic(&mut self) {
+        self.inner.enable_pic();
+    }
+
+    fn enable_verifier(&mut self) {
+        self.inner.enable_verifier();
+    }
+
+    fn canonicalize_nans(&mut self, enable: bool) {
+        self.inner.canonicalize_nans(enable);
+    }
+
+    fn compiler(self: Box<Self>) -> Box<dyn Compiler> {
+        Box::new(PythonCompiler {
+            validator: Box::new(self.inner.clone()).compiler(),
+            module_middlewares: self.middlewares.iter().map(Middleware::unbound).collect(),
+            inner: self.inner,
+            middlewares: self.middlewares,
+            thread_pool: self.thread_pool,
+        })
+    }
+
+    fn default_features_for_target(&self, target: &Target) -> Features {
+        self.inner.default_features_for_target(target)
+    }
+
+    fn push_middleware(&mut self, middleware: Arc<dyn ModuleMiddleware>) {
+        self.middlewares.push(Middleware::Module(middleware));
+    }
+}
+
+struct PythonCompiler<C> {
+    inner: C,
+    middlewares: Vec<Middleware>,
+
+    /// The middlewares transforming the module info before it is
+    /// compiled, see `Compiler::get_middlewares`.
+    module_middlewares: Vec<Arc<dyn ModuleMiddleware>>,
+
+    /// The compiler validating the modules, which does not depend on
+    /// the middlewares.
+    validator: Box<dyn Compiler>,
+    thread_pool: ThreadPool,
+}
+
+impl<C> PythonCompiler<C>
+where
+    C: CompilerConfig + Clone + Send + Sync + 'static,
+{
+    /// Builds the compiler of `module`, whose module info has been
+    /// transformed by `module_middlewares`.
+    fn compiler_for(&self, module: &CompileModuleInfo) -> Box<dyn Compiler> {
+        let mut compiler_config = self.inner.clone();
+
+        for middleware in &self.middlewares {
+            compiler_config.push_middleware(middleware.for_module(&module.module));
+        }
+
+        Box::new(compiler_config).compiler()
+    }
+}
+
+impl<C> Compiler for PythonCompiler<C>
+where
+    C: CompilerConfig + Clone + Send + Sync + 'static,
+{
+    fn validate_module(&self, features: &Features, data: &[u8]) -> Result<(), CompileError> {
+        self.validator.validate_module(features, data)
+    }
+
+    fn compile_module<'data, 'module>(
+        &self,
+        target: &Target,
+        module: &'module CompileModuleInfo,
+        module_translation: &ModuleTranslationState,
+        function_body_inputs: PrimaryMap<LocalFunctionIndex, FunctionBodyData<'data>>,
+    ) -> Result<Compilation, CompileError> {
+        let compiler = self.compiler_for(module);
+
+        self.thread_pool.install(move || {
+            compiler.compile_module(target, module, module_translation, function_body_inputs)
+        })
+    }
+
+    fn experimental_native_compile_module<'data, 'module>(
+        &self,
+        target: &Target,
+        module: &'module CompileModuleInfo,
+        module_translation: &ModuleTranslationState,
+        function_body_inputs: &PrimaryMap<LocalFunctionIndex, FunctionBodyData<'data>>,
+        symbol_registry: &dyn SymbolRegistry,
+        wasmer_metadata: &[u8],
+    ) -> Option<Result<Vec<u8>, CompileError>> {
+        let compiler = self.compiler_for(module);
+
+        self.thread_pool.install(move || {
+            compiler.experimental_native_compile_module(
+                target,
+                module,
+                module_translation,
+                function_body_inputs,
+                symbol_registry,
+                wasmer_metadata,
+            )
+        })
+    }
+
+    fn get_middlewares(&self) -> &[Arc<dyn ModuleMiddleware>] {
+        &self.module_middlewares
+    }
+}
+
+impl<C> MemoryUsage for PythonCompiler<C> {
+    fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
+        mem::size_of_val(self) + self.validator.size_of_val(tracker)
+    }
+}
@@ -1,6 +1,131 @@
-use crate::target_lexicon::Target;
-use pyo3::{exceptions::PyRuntimeError, prelude::*};
-use std::mem::ManuallyDrop;
+use crate::{
+    compiler::PythonCompilerConfig,
+    features::Features,
+    middlewares::{Middlewares, Progress},
+    target_lexicon::{Target, Triple},
+    thread_pool::ThreadPool,
+};
+use pyo3::{exceptions::PyRuntimeError, prelude::*, types::PyType};
+use std::{fmt, mem::ManuallyDrop, sync::Arc};
+use wasmer::Engine;
+
+/// Implements the methods shared by the engines: `$engine` wraps an
+/// `$inner` engine named `$name`, which is built by `$builder`. The
+/// `$field`s are the other fields of the engine, set to their initial
+/// value by `raw_new`, and `$methods` are its other Python methods,
+/// e.g. its constructor.
+macro_rules! engine {
+    (
+        $engine:ident($inner:ty) named $name:literal built by $builder:path,
+        { $($field:ident: $value:expr),* $(,)? }
+        $($methods:tt)*
+    ) => {
+        impl $engine {
+            pub fn raw_new(
+                compiler: Option<&PyAny>,
+                target: Option<&Target>,
+                features: Option<&Features>,
+            ) -> PyResult<Self> {
+                let progress = Progress::default();
+                let (inner, compiler_name, fingerprint) = match compiler {
+                    None => (<$builder>::headless().engine(), None, Some(String::new())),
+                    Some(compiler) => {
+                        let (compiler_config, compiler_name, compiler_fingerprint) =
+                            take_compiler_config(compiler, &progress)?;
+                        let mut engine_builder = <$builder>::new(compiler_config);
+
+                        if let Some(target) = target {
+                            engine_builder = engine_builder.target(target.inner().clone());
+                        }
+
+                        if let Some(features) = features {
+                            engine_builder = engine_builder.features(features.inner());
+                        }
+
+                        (
+                            engine_builder.engine(),
+                            Some(compiler_name),
+                            fingerprint(compiler_fingerprint, features),
+                        )
+                    }
+                };
+
+                Ok(Self {
+                    inner,
+                    compiler_name,
+                    fingerprint,
+                    progress,
+                    $($field: $value),*
+                })
+            }
+
+            pub fn name() -> &'static str {
+                $name
+            }
+
+            pub fn inner(&self) -> &$inner {
+                &self.inner
+            }
+
+            pub fn compiler_name(&self) -> Option<&String> {
+                self.compiler_name.as_ref()
+            }
+
+            pub fn fingerprint(&self) -> Option<&String> {
+                self.fingerprint.as_ref()
+            }
+
+            pub fn progress(&self) -> &Progress {
+                &self.progress
+            }
+        }
+
+        #[pymethods]
+        impl $engine {
+            $($methods)*
+
+            /// Returns the `Target` this engine compiles for, or loads
+            /// artifacts for in headless mode.
+            ///
+            /// ## Example
+            ///
+            /// This is an example for the `Universal` engine, but it is
+            /// the same for its siblings!
+            ///
+            /// ```py
+            /// from wasmer import engine, target
+            ///
+            /// engine_ = engine.Universal()
+            ///
+            /// assert isinstance(engine_.target, target.Target)
+            /// ```
+            #[getter]
+            fn target(&self) -> Target {
+                Target::raw_new(self.inner.target().clone())
+            }
+
+            /// Returns the `Triple` of the engine's target. It is a
+            /// shortcut to `engine.target.triple`.
+            ///
+            /// ## Example
+            ///
+            /// This is an example for the `Universal` engine, but it is
+            /// the same for its siblings!
+            ///
+            /// ```py
+            /// from wasmer import engine, target
+            ///
+            /// engine_ = engine.Universal()
+            ///
+            /// assert str(engine_.triple) == str(target.Triple.host())
+            /// ```
+            #[getter]
+            fn triple(&self) -> Triple {
+                Triple::raw_new(self.inner.target().triple().clone())
+            }
+        }
+    };
+}
 
 /// Universal engine for Wasmer compilers.
 ///
@@ -11,82 +136,68 @@ use std::mem::ManuallyDrop;
 ///
 /// It is possible to specify a `Target` to possibly cross-compile for
 /// a different target. It requires a compiler.
+///
+/// It is possible to specify `Features` to enable or disable
+/// WebAssembly proposals. It requires a compiler.
+///
+/// With `perf_map=True`, the address, the size and the name of each
+/// compiled function are written to `/tmp/perf-<pid>.map`, so that
+/// `perf` and the flamegraph tools attribute their samples to the
+/// WebAssembly functions rather than to anonymous memory. A function
+/// is named after the `name` custom section of its module, if any,
+/// otherwise after its index.
+///
+/// ## Example
+///
+/// ```py
+/// from wasmer import engine, Store, Module
+/// from wasmer_compiler_cranelift import Compiler
+/// import os
+///
+/// store = Store(engine.Universal(Compiler, perf_map=True))
+/// module = Module(store, '(module $calc (func $sum (export "sum")))')
+///
+/// with open(f'/tmp/perf-{os.getpid()}.map') as perf_map:
+///     assert any(line.endswith(' wasm::calc::sum\n') for line in perf_map)
+/// ```
 #[pyclass(unsendable, subclass)]
-#[pyo3(text_signature = "(/, compiler, target)")]
+#[pyo3(text_signature = "(/, compiler, target, features, *, perf_map)")]
 pub struct Universal {
     inner: wasmer::UniversalEngine,
     compiler_name: Option<String>,
+    fingerprint: Option<String>,
+    progress: Progress,
+    perf_map: bool,
 }
 
-impl Universal {
-    pub fn raw_new(compiler: Option<&PyAny>, target: Option<&Target>) -> PyResult<Self> {
-        let (inner, compiler_name) = match compiler {
-            None => (wasmer::Universal::headless().engine(), None),
-            Some(compiler) => {
-                let opaque_compiler = compiler.call_method0("into_opaque_compiler")?;
-                let opaque_compiler_inner_ptr = opaque_compiler
-                    .call_method0("__inner_as_ptr")?
-                    .extract::<usize>()?;
-
-                let opaque_compiler_inner_ptr: *mut OpaqueCompilerInner =
-                    opaque_compiler_inner_ptr as *const OpaqueCompilerInner as *mut _;
-
-                let opaque_compiler_inner_ref: &mut OpaqueCompilerInner = unsafe {
-                    opaque_compiler_inner_ptr.as_mut().ok_or_else(|| {
-                        PyRuntimeError::new_err(
-                            "Failed to transfer the opaque compiler from the compiler",
-                        )
-                    })?
-                };
-
-                // SAFETY: `ManuallyDrop::take` semantically moves out the contained value. The
-                // danger here is when the container is used by someone else. It doesn't happen in
-                // this codebase.
-                let compiler_config =
-                    unsafe { ManuallyDrop::take(&mut opaque_compiler_inner_ref.compiler_config) };
-
-                let mut engine_builder = wasmer::Universal::new(compiler_config);
-
-                if let Some(target) = target {
-                    engine_builder = engine_builder.target(target.inner().clone());
-                }
-
-                (
-                    engine_builder.engine(),
-                    Some(
-                        opaque_compiler
-                            .getattr("name")?
-                            .extract::<String>()
-                            .map_err(PyErr::from)?,
-                    ),
-                )
-            }
-        };
+engine!(
+    Universal(wasmer::UniversalEngine) named "universal" built by wasmer::Universal,
+    { perf_map: false }
 
+    #[new]
+    #[args(
+        compiler = "None",
+        target = "None",
+        features = "None",
+        "*",
+        perf_map = "false"
+    )]
+    fn new(
+        compiler: Option<&PyAny>,
+        target: Option<&Target>,
+        features: Option<&Features>,
+        perf_map: bool,
+    ) -> PyResult<Self> {
         Ok(Self {
-            inner,
-            compiler_name,
+            perf_map,
+            ..Self::raw_new(compiler, target, features)?
         })
     }
+);
 
-    pub fn name() -> &'static str {
-        "universal"
-    }
-
-    pub fn inner(&self) -> &wasmer::UniversalEngine {
-        &self.inner
-    }
-
-    pub fn compiler_name(&self) -> Option<&String> {
-        self.compiler_name.as_ref()
-    }
-}
-
-#[pymethods]
 impl Universal {
-    #[new]
-    fn new(compiler: Option<&PyAny>, target: Option<&Target>) -> PyResult<Self> {
-        Self::raw_new(compiler, target)
+    pub fn perf_map(&self) -> bool {
+        self.perf_map
     }
 }
 
@@ -101,83 +212,135 @@ impl Universal {
 ///
 /// It is possible to specify a `Target` to possibly cross-compile for
 /// a different target. It requires a compiler.
+///
+/// It is possible to specify `Features` to enable or disable
+/// WebAssembly proposals. It requires a compiler.
 #[pyclass(unsendable, subclass)]
-#[pyo3(text_signature = "(/, compiler, target)")]
+#[pyo3(text_signature = "(/, compiler, target, features)")]
 pub struct Dylib {
     inner: wasmer::DylibEngine,
     compiler_name: Option<String>,
+    fingerprint: Option<String>,
+    progress: Progress,
 }
 
-impl Dylib {
-    pub fn raw_new(compiler: Option<&PyAny>, target: Option<&Target>) -> PyResult<Self> {
-        let (inner, compiler_name) = match compiler {
-            None => (wasmer::Dylib::headless().engine(), None),
-            Some(compiler) => {
-                let opaque_compiler = compiler.call_method0("into_opaque_compiler")?;
-                let opaque_compiler_inner_ptr = opaque_compiler
-                    .call_method0("__inner_as_ptr")?
-                    .extract::<usize>()?;
-
-                let opaque_compiler_inner_ptr: *mut OpaqueCompilerInner =
-                    opaque_compiler_inner_ptr as *const OpaqueCompilerInner as *mut _;
-
-                let opaque_compiler_inner_ref: &mut OpaqueCompilerInner = unsafe {
-                    opaque_compiler_inner_ptr.as_mut().ok_or_else(|| {
-                        PyRuntimeError::new_err(
-                            "Failed to transfer the opaque compiler from the compiler",
-                        )
-                    })?
-                };
-
-                // SAFETY: `ManuallyDrop::take` semantically moves out the contained value. The
-                // danger here is when the container is used by someone else. It doesn't happen in
-                // this codebase.
-                let compiler_config =
-                    unsafe { ManuallyDrop::take(&mut opaque_compiler_inner_ref.compiler_config) };
-
-                let mut engine_builder = wasmer::Dylib::new(compiler_config);
-
-                if let Some(target) = target {
-                    engine_builder = engine_builder.target(target.inner().clone());
-                }
-
-                (
-                    engine_builder.engine(),
-                    Some(
-                        opaque_compiler
-                            .getattr("name")?
-                            .extract::<String>()
-                            .map_err(PyErr::from)?,
-                    ),
-                )
-            }
-        };
+engine!(
+    Dylib(wasmer::DylibEngine) named "dylib" built by wasmer::Dylib,
+    {}
 
-        Ok(Self {
-            inner,
-            compiler_name,
-        })
+    #[new]
+    fn new(
+        compiler: Option<&PyAny>,
+        target: Option<&Target>,
+        features: Option<&Features>,
+    ) -> PyResult<Self> {
+        Self::raw_new(compiler, target, features)
     }
+);
 
-    pub fn name() -> &'static str {
-        "dylib"
-    }
+/// Staticlib engine for Wasmer compilers.
+///
+/// Given an optional compiler, it generates a static object file
+/// (`.o` file) and metadata, which can be obtained with
+/// `Module.serialize` and linked into a native program.
+///
+/// If the compiler is absent, it will generate a headless engine.
+///
+/// It is possible to specify a `Target` to possibly cross-compile for
+/// a different target. It requires a compiler.
+///
+/// It is possible to specify `Features` to enable or disable
+/// WebAssembly proposals. It requires a compiler.
+#[pyclass(unsendable, subclass)]
+#[pyo3(text_signature = "(/, compiler, target, features)")]
+pub struct Staticlib {
+    inner: wasmer_engine_staticlib::StaticlibEngine,
+    compiler_name: Option<String>,
+    fingerprint: Option<String>,
+    progress: Progress,
+}
 
-    pub fn inner(&self) -> &wasmer::DylibEngine {
-        &self.inner
-    }
+engine!(
+    Staticlib(wasmer_engine_staticlib::StaticlibEngine) named "staticlib" built by wasmer_engine_staticlib::Staticlib,
+    {}
 
-    pub fn compiler_name(&self) -> Option<&String> {
-        self.compiler_name.as_ref()
+    #[new]
+    fn new(
+        compiler: Option<&PyAny>,
+        target: Option<&Target>,
+        features: Option<&Features>,
+    ) -> PyResult<Self> {
+        Self::raw_new(compiler, target, features)
     }
+);
+
+/// Describes the configuration of an engine with a compiler, to tell
+/// apart the artifacts it compiles, e.g. in a `wasmer.Cache`: the
+/// fingerprint of the compiler, see `OpaqueCompiler::fingerprint`,
+/// and the features. It is `None` if the compiler cannot be
+/// described.
+fn fingerprint(
+    compiler_fingerprint: Option<String>,
+    features: Option<&Features>,
+) -> Option<String> {
+    compiler_fingerprint.map(|compiler_fingerprint| {
+        format!(
+            "{}\0{:?}",
+            compiler_fingerprint,
+            features.map(Features::inner)
+        )
+    })
 }
 
-#[pymethods]
-impl Dylib {
-    #[new]
-    fn new(compiler: Option<&PyAny>, target: Option<&Target>) -> PyResult<Self> {
-        Self::raw_new(compiler, target)
-    }
+/// Take the compiler configuration out of a compiler, along with the
+/// compiler name and fingerprint.
+///
+/// The compiler can be a compiler class, e.g. `Compiler` from
+/// `wasmer_compiler_cranelift`, in which case it is instantiated with
+/// its default configuration, or a compiler instance. The `progress`
+/// middleware is pushed at the end of its middleware chain.
+fn take_compiler_config(
+    compiler: &PyAny,
+    progress: &Progress,
+) -> PyResult<(
+    Box<dyn wasmer_compiler::CompilerConfig>,
+    String,
+    Option<String>,
+)> {
+    let compiler = if compiler.is_instance::<PyType>()? {
+        compiler.call0()?
+    } else {
+        compiler
+    };
+
+    let opaque_compiler = compiler.call_method0("into_opaque_compiler")?;
+    let opaque_compiler_inner_ptr = opaque_compiler
+        .call_method0("__inner_as_ptr")?
+        .extract::<usize>()?;
+
+    let opaque_compiler_inner_ptr: *mut OpaqueCompilerInner =
+        opaque_compiler_inner_ptr as *const OpaqueCompilerInner as *mut _;
+
+    let opaque_compiler_inner_ref: &mut OpaqueCompilerInner = unsafe {
+        opaque_compiler_inner_ptr.as_mut().ok_or_else(|| {
+            PyRuntimeError::new_err("Failed to transfer the opaque compiler from the compiler")
+        })?
+    };
+
+    // SAFETY: `ManuallyDrop::take` semantically moves out the contained value. The
+    // danger here is when the container is used by someone else. It doesn't happen in
+    // this codebase.
+    let mut compiler_config =
+        unsafe { ManuallyDrop::take(&mut opaque_compiler_inner_ref.compiler_config) };
+    compiler_config.push_middleware(Arc::new(progress.clone()));
+
+    Ok((
+        compiler_config,
+        opaque_compiler.getattr("name")?.extract::<String>()?,
+        opaque_compiler
+            .getattr("fingerprint")?
+            .extract::<Option<String>>()?,
+    ))
 }
 
 struct OpaqueCompilerInner {
@@ -191,17 +354,41 @@ struct OpaqueCompilerInner {
 pub struct OpaqueCompiler {
     inner: OpaqueCompilerInner,
     compiler_name: String,
+    fingerprint: Option<String>,
 }
 
 impl OpaqueCompiler {
-    pub fn raw_with_compiler<C>(compiler_config: C, compiler_name: String) -> Self
+    /// Wraps `compiler_config`, whose modules are compiled with
+    /// `middlewares` on `thread_pool`.
+    pub fn raw_with_compiler<C>(
+        compiler_config: C,
+        compiler_name: String,
+        middlewares: &Middlewares,
+        thread_pool: &ThreadPool,
+    ) -> Self
     where
-        C: wasmer_compiler::CompilerConfig + Send + Sync + 'static,
+        C: wasmer_compiler::CompilerConfig + Clone + fmt::Debug + Send + Sync + 'static,
     {
+        let fingerprint = middlewares.fingerprints().map(|fingerprints| {
+            let mut fingerprint = format!("{}\0{:?}", compiler_name, compiler_config);
+
+            for middleware_fingerprint in fingerprints {
+                fingerprint.push('\0');
+                fingerprint.push_str(middleware_fingerprint);
+            }
+
+            format!("{}\0{:?}", fingerprint, thread_pool.threads())
+        });
+
         Self {
             inner: OpaqueCompilerInner {
-                compiler_config: ManuallyDrop::new(Box::new(compiler_config)),
+                compiler_config: ManuallyDrop::new(Box::new(PythonCompilerConfig::new(
+                    compiler_config,
+                    middlewares.middlewares(),
+                    thread_pool.clone(),
+                ))),
             },
+            fingerprint,
             compiler_name,
         }
     }
@@ -220,30 +407,75 @@ impl OpaqueCompiler {
     fn name(&self) -> &String {
         &self.compiler_name
     }
+
+    /// Describes the configuration of the compiler, including its
+    /// middlewares and its threads, or `None` if a middleware
+    /// cannot be described.
+    #[getter]
+    fn fingerprint(&self) -> Option<&String> {
+        self.fingerprint.as_ref()
+    }
 }
 
 /// Deprecated engine. Please use the parent engine instead,
 /// i.e. `Universal`.
+///
+/// It accepts the same arguments as `Universal`: an optional
+/// compiler (headless mode if absent) and an optional `Target`.
+///
+/// ## Example
+///
+/// ```py
+/// from wasmer import engine, Store
+///
+/// store = Store(engine.JIT())
+///
+/// assert store.engine_name == 'universal'
+/// ```
 #[pyclass(extends=Universal)]
+#[pyo3(text_signature = "(/, compiler, target, features)")]
 pub struct JIT {}
 
 #[pymethods]
 impl JIT {
     #[new]
-    fn new(compiler: Option<&PyAny>, target: Option<&Target>) -> PyResult<(Self, Universal)> {
-        Ok((Self {}, Universal::raw_new(compiler, target)?))
+    fn new(
+        compiler: Option<&PyAny>,
+        target: Option<&Target>,
+        features: Option<&Features>,
+    ) -> PyResult<(Self, Universal)> {
+        Ok((Self {}, Universal::raw_new(compiler, target, features)?))
     }
 }
 
 /// Deprecated engine. Please use the parent engine instead,
 /// i.e. `Dylib`.
+///
+/// It accepts the same arguments as `Dylib`: an optional compiler
+/// (headless mode if absent) and an optional `Target`.
+///
+/// ## Example
+///
+/// ```py
+/// from wasmer import engine, Store
+///
+/// store = Store(engine.Native())
+///
+/// assert store.engine_name == 'dylib'
+/// assert store.compiler_name == None
+/// ```
 #[pyclass(extends=Dylib)]
+#[pyo3(text_signature = "(/, compiler, target, features)")]
 pub struct Native {}
 
 #[pymethods]
 impl Native {
     #[new]
-    fn new(compiler: Option<&PyAny>, target: Option<&Target>) -> PyResult<(Self, Dylib)> {
-        Ok((Self {}, Dylib::raw_new(compiler, target)?))
+    fn new(
+        compiler: Option<&PyAny>,
+        target: Option<&Target>,
+        features: Option<&Features>,
+    ) -> PyResult<(Self, Dylib)> {
+        Ok((Self {}, Dylib::raw_new(compiler, target, features)?))
     }
 }
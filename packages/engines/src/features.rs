@@ -0,0 +1,140 @@
+use pyo3::{class::basic::PyObjectProtocol, prelude::*};
+
+/// Controls which WebAssembly proposals are enabled when compiling
+/// and validating modules.
+///
+/// A `Features` object is passed to an engine, along with a
+/// compiler. The default values are the Wasmer default features.
+///
+/// ## Example
+///
+/// ```py
+/// from wasmer import engine, wat2wasm, Store, Module
+/// from wasmer_compiler_cranelift import Compiler
+///
+/// features = engine.Features(simd=False)
+///
+/// assert features.simd == False
+/// assert features.bulk_memory == True
+///
+/// # Features can also be changed after construction.
+/// features.threads = True
+///
+/// store = Store(engine.Universal(Compiler, features=features))
+///
+/// # SIMD is disabled, a module using SIMD is invalid.
+/// assert not Module.validate(store, wat2wasm('(module (func (param v128)))'))
+/// ```
+#[pyclass]
+#[pyo3(
+    text_signature = "(/, threads, reference_types, simd, bulk_memory, multi_value, tail_call, module_linking, multi_memory, memory64, exceptions)"
+)]
+#[derive(Clone)]
+pub struct Features {
+    /// Whether the threads proposal is enabled.
+    #[pyo3(get, set)]
+    pub threads: bool,
+
+    /// Whether the reference types proposal is enabled.
+    #[pyo3(get, set)]
+    pub reference_types: bool,
+
+    /// Whether the SIMD proposal is enabled.
+    #[pyo3(get, set)]
+    pub simd: bool,
+
+    /// Whether the bulk memory proposal is enabled.
+    #[pyo3(get, set)]
+    pub bulk_memory: bool,
+
+    /// Whether the multi-value proposal is enabled.
+    #[pyo3(get, set)]
+    pub multi_value: bool,
+
+    /// Whether the tail call proposal is enabled.
+    #[pyo3(get, set)]
+    pub tail_call: bool,
+
+    /// Whether the module linking proposal is enabled.
+    #[pyo3(get, set)]
+    pub module_linking: bool,
+
+    /// Whether the multi-memory proposal is enabled.
+    #[pyo3(get, set)]
+    pub multi_memory: bool,
+
+    /// Whether the 64-bit memory proposal is enabled.
+    #[pyo3(get, set)]
+    pub memory64: bool,
+
+    /// Whether the exceptions proposal is enabled.
+    #[pyo3(get, set)]
+    pub exceptions: bool,
+}
+
+impl Features {
+    pub(crate) fn inner(&self) -> wasmer::Features {
+        wasmer::Features {
+            threads: self.threads,
+            reference_types: self.reference_types,
+            simd: self.simd,
+            bulk_memory: self.bulk_memory,
+            multi_value: self.multi_value,
+            tail_call: self.tail_call,
+            module_linking: self.module_linking,
+            multi_memory: self.multi_memory,
+            memory64: self.memory64,
+            exceptions: self.exceptions,
+        }
+    }
+}
+
+#[pymethods]
+impl Features {
+    #[new]
+    #[args(
+        threads = "false",
+        reference_types = "true",
+        simd = "true",
+        bulk_memory = "true",
+        multi_value = "true",
+        tail_call = "false",
+        module_linking = "false",
+        multi_memory = "false",
+        memory64 = "false",
+        exceptions = "false"
+    )]
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        threads: bool,
+        reference_types: bool,
+        simd: bool,
+        bulk_memory: bool,
+        multi_value: bool,
+        tail_call: bool,
+        module_linking: bool,
+        multi_memory: bool,
+        memory64: bool,
+        exceptions: bool,
+    ) -> Self {
+        Self {
+            threads,
+            reference_types,
+            simd,
+            bulk_memory,
+            multi_value,
+            tail_call,
+            module_linking,
+            multi_memory,
+            memory64,
+            exceptions,
+        }
+    }
+}
+
+#[pyproto]
+impl PyObjectProtocol for Features {
+    fn __str__(&self) -> String {
+        format!("{:?}", self.inner())
+    }
+}
@@ -1,7 +1,16 @@
+mod compiler;
 mod engines;
+mod features;
+mod middlewares;
 mod target_lexicon;
+mod thread_pool;
 
-pub use crate::engines::{Dylib, OpaqueCompiler, Universal};
+pub use crate::engines::{Dylib, OpaqueCompiler, Staticlib, Universal};
 // Deprecated engines.
 pub use crate::engines::{Native, JIT};
+pub use crate::features::Features;
+pub use crate::middlewares::{
+    Middleware, Middlewares, OpaqueMiddleware, PerModuleMiddleware, Progress, ProgressCallback,
+};
 pub use crate::target_lexicon::{CpuFeatures, Target, Triple};
+pub use crate::thread_pool::ThreadPool;
@@ -0,0 +1,379 @@
+use loupe::{MemoryUsage, MemoryUsageTracker};
+use pyo3::{exceptions::PyRuntimeError, prelude::*};
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex, RwLock,
+    },
+};
+use wasmer_compiler::{
+    wasmparser::Operator, FunctionMiddleware, MiddlewareError, MiddlewareReaderState,
+    ModuleMiddleware,
+};
+use wasmer_types::{LocalFunctionIndex, ModuleInfo};
+
+/// A middleware generating the function middlewares of each
+/// compiled module from its module info, once it has been
+/// transformed.
+///
+/// Unlike a `ModuleMiddleware`, it does not have to remember the
+/// module it has transformed, so one middleware can be used to
+/// compile several modules, even concurrently.
+pub trait PerModuleMiddleware: fmt::Debug + Send + Sync {
+    /// Transforms the module info before the module is compiled.
+    fn transform_module_info(&self, _module_info: &mut ModuleInfo) {}
+
+    /// Generates the middleware of the function
+    /// `local_function_index` of the module described by
+    /// `module_info`. The error is reported when the function is
+    /// compiled.
+    fn generate_function_middleware(
+        &self,
+        module_info: &ModuleInfo,
+        local_function_index: LocalFunctionIndex,
+    ) -> Result<Box<dyn FunctionMiddleware>, MiddlewareError>;
+}
+
+/// A middleware taken out of a Python middleware object, see
+/// `Middlewares::new`.
+#[derive(Clone, Debug)]
+pub enum Middleware {
+    Module(Arc<dyn ModuleMiddleware>),
+    PerModule(Arc<dyn PerModuleMiddleware>),
+}
+
+impl Middleware {
+    /// The middleware transforming the module info, before the module
+    /// is compiled.
+    pub(crate) fn unbound(&self) -> Arc<dyn ModuleMiddleware> {
+        match self {
+            Self::Module(middleware) => middleware.clone(),
+            Self::PerModule(middleware) => Arc::new(UnboundMiddleware {
+                inner: middleware.clone(),
+            }),
+        }
+    }
+
+    /// The middleware generating the function middlewares of the
+    /// module described by `module_info`, once it has been
+    /// transformed.
+    pub(crate) fn for_module(&self, module_info: &Arc<ModuleInfo>) -> Arc<dyn ModuleMiddleware> {
+        match self {
+            Self::Module(middleware) => middleware.clone(),
+            Self::PerModule(middleware) => Arc::new(BoundMiddleware {
+                inner: middleware.clone(),
+                module_info: module_info.clone(),
+            }),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct UnboundMiddleware {
+    inner: Arc<dyn PerModuleMiddleware>,
+}
+
+impl MemoryUsage for UnboundMiddleware {
+    fn size_of_val(&self, _tracker: &mut dyn MemoryUsageTracker) -> usize {
+        std::mem::size_of_val(self)
+    }
+}
+
+impl ModuleMiddleware for UnboundMiddleware {
+    fn generate_function_middleware(&self, _: LocalFunctionIndex) -> Box<dyn FunctionMiddleware> {
+        Box::new(FailingFunctionMiddleware {
+            error: Some(MiddlewareError::new(
+                "per-module",
+                "The middleware has not been bound to the compiled module",
+            )),
+        })
+    }
+
+    fn transform_module_info(&self, module_info: &mut ModuleInfo) {
+        self.inner.transform_module_info(module_info);
+    }
+}
+
+struct BoundMiddleware {
+    inner: Arc<dyn PerModuleMiddleware>,
+    module_info: Arc<ModuleInfo>,
+}
+
+impl fmt::Debug for BoundMiddleware {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter
+            .debug_struct("BoundMiddleware")
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+impl MemoryUsage for BoundMiddleware {
+    fn size_of_val(&self, _tracker: &mut dyn MemoryUsageTracker) -> usize {
+        std::mem::size_of_val(self)
+    }
+}
+
+impl ModuleMiddleware for BoundMiddleware {
+    fn generate_function_middleware(
+        &self,
+        local_function_index: LocalFunctionIndex,
+    ) -> Box<dyn FunctionMiddleware> {
+        self.inner
+            .generate_function_middleware(&self.module_info, local_function_index)
+            .unwrap_or_else(|error| Box::new(FailingFunctionMiddleware { error: Some(error) }))
+    }
+}
+
+/// A function middleware failing on the first operator.
+#[derive(Debug)]
+struct FailingFunctionMiddleware {
+    error: Option<MiddlewareError>,
+}
+
+impl FunctionMiddleware for FailingFunctionMiddleware {
+    fn feed<'a>(
+        &mut self,
+        operator: Operator<'a>,
+        state: &mut MiddlewareReaderState<'a>,
+    ) -> Result<(), MiddlewareError> {
+        if let Some(error) = self.error.take() {
+            return Err(error);
+        }
+
+        state.push_operator(operator);
+
+        Ok(())
+    }
+}
+
+/// The middlewares taken out of Python middleware objects, e.g.
+/// `wasmer.Metering`, given to a compiler.
+#[derive(Clone, Default)]
+pub struct Middlewares {
+    middlewares: Vec<Middleware>,
+    fingerprints: Option<Vec<String>>,
+}
+
+impl Middlewares {
+    /// Takes the middlewares out of `middlewares`.
+    ///
+    /// A middleware object is any object with an
+    /// `into_opaque_middleware` method returning an `OpaqueMiddleware`.
+    pub fn new(middlewares: Option<Vec<&PyAny>>) -> PyResult<Self> {
+        let mut result = Self {
+            middlewares: Vec::new(),
+            fingerprints: Some(Vec::new()),
+        };
+
+        for middleware in middlewares.unwrap_or_default() {
+            let (middleware, fingerprint) = take_middleware(middleware)?;
+
+            result.middlewares.push(middleware);
+            result.fingerprints =
+                result
+                    .fingerprints
+                    .zip(fingerprint)
+                    .map(|(mut fingerprints, fingerprint)| {
+                        fingerprints.push(fingerprint);
+                        fingerprints
+                    });
+        }
+
+        Ok(result)
+    }
+
+    pub(crate) fn middlewares(&self) -> Vec<Middleware> {
+        self.middlewares.clone()
+    }
+
+    /// The fingerprints of the middlewares, see
+    /// `OpaqueMiddleware::raw_new`, or `None` if a middleware has
+    /// none.
+    pub(crate) fn fingerprints(&self) -> Option<&[String]> {
+        self.fingerprints.as_deref()
+    }
+}
+
+/// Take the middleware out of a Python middleware object, along with
+/// its fingerprint, see `OpaqueMiddleware::raw_new`.
+fn take_middleware(middleware: &PyAny) -> PyResult<(Middleware, Option<String>)> {
+    let opaque_middleware = middleware.call_method0("into_opaque_middleware")?;
+    let fingerprint = opaque_middleware
+        .call_method0("__fingerprint")?
+        .extract::<Option<String>>()?;
+    let opaque_middleware_inner_ptr = opaque_middleware
+        .call_method0("__inner_as_ptr")?
+        .extract::<usize>()?;
+
+    let opaque_middleware_inner_ptr: *const Middleware = opaque_middleware_inner_ptr as *const _;
+
+    // SAFETY: The pointer comes from an `OpaqueMiddleware` that is
+    // alive as long as `opaque_middleware` is, and the middleware is
+    // cloned, not moved.
+    let opaque_middleware_inner_ref: &Middleware = unsafe {
+        opaque_middleware_inner_ptr.as_ref().ok_or_else(|| {
+            PyRuntimeError::new_err("Failed to transfer the opaque middleware from the middleware")
+        })?
+    };
+
+    Ok((opaque_middleware_inner_ref.clone(), fingerprint))
+}
+
+#[pyclass(unsendable)]
+pub struct OpaqueMiddleware {
+    inner: Middleware,
+    fingerprint: Option<String>,
+}
+
+impl OpaqueMiddleware {
+    /// Wraps `middleware`. Its `fingerprint` describes how it
+    /// transforms the modules, so that the artifacts compiled with
+    /// it are told apart from the others, see
+    /// `OpaqueCompiler::fingerprint`. It is `None` if it cannot be
+    /// described, e.g. if the middleware calls a Python function.
+    pub fn raw_new(middleware: Arc<dyn ModuleMiddleware>, fingerprint: Option<String>) -> Self {
+        Self {
+            inner: Middleware::Module(middleware),
+            fingerprint,
+        }
+    }
+
+    /// Wraps `middleware`, like `raw_new`.
+    pub fn raw_per_module(
+        middleware: Arc<dyn PerModuleMiddleware>,
+        fingerprint: Option<String>,
+    ) -> Self {
+        Self {
+            inner: Middleware::PerModule(middleware),
+            fingerprint,
+        }
+    }
+}
+
+#[pymethods]
+impl OpaqueMiddleware {
+    pub fn __inner_as_ptr(&self) -> usize {
+        let inner_ptr: *const Middleware = &self.inner;
+        let inner_usize: usize = inner_ptr as _;
+
+        inner_usize
+    }
+
+    pub fn __fingerprint(&self) -> Option<String> {
+        self.fingerprint.clone()
+    }
+}
+
+/// A function called with the number of compiled functions, and the
+/// total number of functions of the module.
+pub type ProgressCallback = Box<dyn Fn(usize, usize) + Send + Sync>;
+
+/// A middleware reporting the progress of the compilations of an
+/// engine, pushed at the end of the middleware chain of its compiler.
+///
+/// A function is counted when its middleware is dropped, i.e. once
+/// the compiler is done with it, or at least with its translation.
+#[derive(Clone, Default)]
+pub struct Progress {
+    inner: Arc<ProgressInner>,
+}
+
+#[derive(Default)]
+struct ProgressInner {
+    /// Held for writing by the compilation reporting its progress,
+    /// and for reading by the others, so that the functions of
+    /// several modules are not counted together.
+    compilation: RwLock<()>,
+    callback: Mutex<Option<ProgressCallback>>,
+    compiled: AtomicUsize,
+    total: AtomicUsize,
+}
+
+impl Progress {
+    /// Runs `compile`, and calls `callback`, if any, each time a
+    /// function has been compiled.
+    pub fn report<T>(&self, callback: Option<ProgressCallback>, compile: impl FnOnce() -> T) -> T {
+        let callback = match callback {
+            Some(callback) => callback,
+            None => {
+                let _compilation = self.inner.compilation.read().unwrap();
+
+                return compile();
+            }
+        };
+
+        let _compilation = self.inner.compilation.write().unwrap();
+        *self.inner.callback.lock().unwrap() = Some(callback);
+
+        let result = compile();
+        *self.inner.callback.lock().unwrap() = None;
+
+        result
+    }
+}
+
+impl fmt::Debug for Progress {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.debug_struct("Progress").finish()
+    }
+}
+
+impl MemoryUsage for Progress {
+    fn size_of_val(&self, _tracker: &mut dyn MemoryUsageTracker) -> usize {
+        std::mem::size_of_val(self)
+    }
+}
+
+impl ModuleMiddleware for Progress {
+    fn generate_function_middleware(&self, _: LocalFunctionIndex) -> Box<dyn FunctionMiddleware> {
+        Box::new(FunctionProgress {
+            inner: self.inner.clone(),
+        })
+    }
+
+    fn transform_module_info(&self, module_info: &mut ModuleInfo) {
+        self.inner.compiled.store(0, Ordering::SeqCst);
+        self.inner.total.store(
+            module_info.functions.len() - module_info.num_imported_functions,
+            Ordering::SeqCst,
+        );
+    }
+}
+
+struct FunctionProgress {
+    inner: Arc<ProgressInner>,
+}
+
+impl fmt::Debug for FunctionProgress {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.debug_struct("FunctionProgress").finish()
+    }
+}
+
+impl FunctionMiddleware for FunctionProgress {
+    fn feed<'a>(
+        &mut self,
+        operator: Operator<'a>,
+        state: &mut MiddlewareReaderState<'a>,
+    ) -> Result<(), MiddlewareError> {
+        state.push_operator(operator);
+
+        Ok(())
+    }
+}
+
+impl Drop for FunctionProgress {
+    fn drop(&mut self) {
+        // The callback is locked while it runs, so that the calls are
+        // ordered.
+        let callback = self.inner.callback.lock().unwrap();
+
+        if let Some(callback) = callback.as_ref() {
+            let compiled = self.inner.compiled.fetch_add(1, Ordering::SeqCst) + 1;
+
+            callback(compiled, self.inner.total.load(Ordering::SeqCst));
+        }
+    }
+}
@@ -1,5 +1,10 @@
 use enumset::EnumSet;
-use pyo3::{class::basic::PyObjectProtocol, exceptions::PyValueError, prelude::*};
+use pyo3::{
+    class::{basic::PyObjectProtocol, iter::PyIterProtocol, sequence::PySequenceProtocol},
+    exceptions::PyValueError,
+    prelude::*,
+    types::PyList,
+};
 use std::str::FromStr;
 
 /// Represents a `Triple` + `CpuFeatures` pair.
@@ -26,6 +31,10 @@ pub struct Target {
 }
 
 impl Target {
+    pub(crate) fn raw_new(inner: wasmer_compiler::Target) -> Self {
+        Self { inner }
+    }
+
     pub(crate) fn inner(&self) -> &wasmer_compiler::Target {
         &self.inner
     }
@@ -45,6 +54,54 @@ impl Target {
             ),
         }
     }
+
+    /// Returns the `Triple` of this target.
+    ///
+    /// ## Example
+    ///
+    /// ```py
+    /// from wasmer import target
+    ///
+    /// target_ = target.Target(target.Triple('x86_64-apple-darwin'))
+    ///
+    /// assert str(target_.triple) == 'x86_64-apple-darwin'
+    /// ```
+    #[getter]
+    fn triple(&self) -> Triple {
+        Triple::raw_new(self.inner.triple().clone())
+    }
+
+    /// Returns the `CpuFeatures` of this target.
+    ///
+    /// ## Example
+    ///
+    /// ```py
+    /// from wasmer import target
+    ///
+    /// cpu_features = target.CpuFeatures()
+    /// cpu_features.add('sse2')
+    ///
+    /// target_ = target.Target(target.Triple.host(), cpu_features)
+    ///
+    /// assert 'sse2' in target_.cpu_features
+    /// ```
+    #[getter]
+    fn cpu_features(&self) -> CpuFeatures {
+        CpuFeatures {
+            inner: *self.inner.cpu_features(),
+        }
+    }
+}
+
+#[pyproto]
+impl PyObjectProtocol for Target {
+    fn __str__(&self) -> PyResult<String> {
+        Ok(format!(
+            "Target(triple: {}, cpu_features: [{}])",
+            self.inner.triple(),
+            cpu_features_to_strings(self.inner.cpu_features()).join(", "),
+        ))
+    }
 }
 
 /// A target “triple”.
@@ -76,6 +133,10 @@ pub struct Triple {
 }
 
 impl Triple {
+    pub(crate) fn raw_new(inner: wasmer_compiler::Triple) -> Self {
+        Self { inner }
+    }
+
     pub(crate) fn inner(&self) -> &wasmer_compiler::Triple {
         &self.inner
     }
@@ -256,6 +317,13 @@ impl CpuFeatures {
     }
 }
 
+fn cpu_features_to_strings(cpu_features: &EnumSet<wasmer_compiler::CpuFeature>) -> Vec<String> {
+    cpu_features
+        .iter()
+        .map(|cpu_feature| cpu_feature.to_string())
+        .collect()
+}
+
 #[pymethods]
 impl CpuFeatures {
     #[new]
@@ -265,6 +333,22 @@ impl CpuFeatures {
         }
     }
 
+    /// Build the CPU features detected on the current host.
+    ///
+    /// ## Example
+    ///
+    /// ```py
+    /// from wasmer import target
+    ///
+    /// this_cpu_features = target.CpuFeatures.host()
+    /// ```
+    #[staticmethod]
+    pub fn host() -> Self {
+        Self {
+            inner: wasmer_compiler::CpuFeature::for_host(),
+        }
+    }
+
     /// Add a new CPU feature.
     #[pyo3(text_signature = "($self, feature)")]
     fn add(&mut self, feature: &str) -> PyResult<()> {
@@ -276,3 +360,34 @@ impl CpuFeatures {
         Ok(())
     }
 }
+
+#[pyproto]
+impl PyObjectProtocol for CpuFeatures {
+    fn __str__(&self) -> PyResult<String> {
+        Ok(cpu_features_to_strings(&self.inner).join(", "))
+    }
+}
+
+#[pyproto]
+impl PySequenceProtocol for CpuFeatures {
+    fn __len__(&self) -> usize {
+        self.inner.len()
+    }
+
+    fn __contains__(&self, feature: &str) -> bool {
+        wasmer_compiler::CpuFeature::from_str(feature)
+            .map(|cpu_feature| self.inner.contains(cpu_feature))
+            .unwrap_or(false)
+    }
+}
+
+#[pyproto]
+impl PyIterProtocol for CpuFeatures {
+    fn __iter__(slf: PyRef<Self>) -> PyResult<PyObject> {
+        let py = slf.py();
+
+        Ok(PyList::new(py, cpu_features_to_strings(&slf.inner))
+            .call_method0("__iter__")?
+            .to_object(py))
+    }
+}
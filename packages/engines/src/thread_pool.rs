@@ -0,0 +1,75 @@
+use pyo3::{exceptions::PyValueError, prelude::*};
+use std::sync::Arc;
+
+/// The threads compiling the functions of a module in parallel, see
+/// `OpaqueCompiler::raw_with_compiler`. By default, the compilers use
+/// the global Rayon thread pool, which has one thread per CPU.
+///
+/// The thread pool must be installed by the compiler package itself:
+/// each Python extension embeds its own copy of Rayon, and
+/// `OpaqueCompiler::raw_with_compiler` is monomorphized in it.
+#[derive(Clone, Default)]
+pub struct ThreadPool {
+    inner: Option<Arc<rayon::ThreadPool>>,
+}
+
+impl ThreadPool {
+    /// Creates a pool of `threads` threads, or uses the global thread
+    /// pool with `None`.
+    pub fn new(threads: Option<usize>) -> PyResult<Self> {
+        let threads = match threads {
+            Some(0) => {
+                return Err(PyValueError::new_err(
+                    "The number of threads must be positive",
+                ))
+            }
+            Some(threads) => threads,
+            None => return Ok(Self::default()),
+        };
+
+        let thread_pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .thread_name(|index| format!("wasmer-compiler-{}", index))
+            .build()
+            .map_err(|error| PyValueError::new_err(error.to_string()))?;
+
+        Ok(Self {
+            inner: Some(Arc::new(thread_pool)),
+        })
+    }
+
+    /// The number of threads of the pool, or `None` for the global
+    /// thread pool.
+    pub(crate) fn threads(&self) -> Option<usize> {
+        self.inner
+            .as_ref()
+            .map(|thread_pool| thread_pool.current_num_threads())
+    }
+
+    /// Runs `compile` in the thread pool, if any, so that the
+    /// parallel iterators of the compiler use its threads.
+    pub(crate) fn install<T>(&self, compile: impl FnOnce() -> T) -> T {
+        let thread_pool = match &self.inner {
+            Some(thread_pool) => thread_pool,
+            None => return compile(),
+        };
+
+        let compile = AssumeSend(compile);
+
+        thread_pool
+            .install(move || {
+                let compile = compile;
+
+                AssumeSend((compile.0)())
+            })
+            .0
+    }
+}
+
+/// Sends the compilation, its inputs and its outputs to and from the
+/// thread pool, whose `install` blocks until the compilation is over.
+struct AssumeSend<T>(T);
+
+// SAFETY: the compilation is only run by one thread of the pool at a
+// time, while the calling thread is blocked.
+unsafe impl<T> Send for AssumeSend<T> {}
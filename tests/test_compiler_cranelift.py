@@ -0,0 +1,59 @@
+from wasmer import engine, Store, Module, Instance
+from wasmer_compiler_cranelift import Compiler
+import itertools
+import os
+import pytest
+
+NAN_WAT = '(module (func (export "div") (param f32 f32) (result i32) local.get 0 local.get 1 f32.div i32.reinterpret_f32))'
+CANONICAL_NAN = 0x7fc00000
+TEST_WAT = '(module (func (export "sum") (param i32 i32) (result i32) local.get 0 local.get 1 i32.add))'
+
+def test_compiler_class():
+    store = Store(engine.Universal(Compiler))
+
+    assert store.compiler_name == 'cranelift'
+    assert Instance(Module(store, TEST_WAT)).exports.sum(1, 2) == 3
+
+def test_compiler_options():
+    for (opt_level, enable_verifier) in itertools.product(['none', 'speed', 'speed_and_size'], [False, True]):
+        store = Store(engine.Universal(Compiler(opt_level=opt_level, enable_verifier=enable_verifier)))
+
+        assert store.compiler_name == 'cranelift'
+        assert Instance(Module(store, TEST_WAT)).exports.sum(1, 2) == 3
+
+def test_compiler_invalid_opt_level():
+    with pytest.raises(ValueError) as context_manager:
+        Compiler(opt_level='fast')
+
+    exception = context_manager.value
+    assert str(exception) == 'Unknown optimization level `fast`, expected `none`, `speed` or `speed_and_size`'
+
+def test_compiler_instance_is_reusable():
+    compiler = Compiler(opt_level='none')
+
+    assert Instance(Module(Store(engine.Universal(compiler)), TEST_WAT)).exports.sum(1, 2) == 3
+    assert Instance(Module(Store(engine.Universal(compiler)), TEST_WAT)).exports.sum(3, 4) == 7
+
+def test_compiler_canonicalize_nans():
+    store = Store(engine.Universal(Compiler(canonicalize_nans=True)))
+
+    assert Instance(Module(store, NAN_WAT)).exports.div(0.0, 0.0) == CANONICAL_NAN
+
+def test_engine_perf_map():
+    store = Store(engine.Universal(Compiler, perf_map=True))
+    module = Module(store, '(module $calc (func $sum (export "sum")) (func (export "anonymous")))')
+
+    with open(f'/tmp/perf-{os.getpid()}.map') as perf_map:
+        lines = [line.split() for line in perf_map]
+
+    entries = {name: (int(start, 16), int(size, 16)) for (start, size, name) in lines}
+
+    assert 'wasm::calc::sum' in entries
+    assert 'wasm::calc::function1' in entries
+    assert all(size > 0 for (_, size) in entries.values())
+
+    # Without the option, nothing is written.
+    Module(Store(engine.Universal(Compiler)), '(module $other (func $f))')
+
+    with open(f'/tmp/perf-{os.getpid()}.map') as perf_map:
+        assert not any('wasm::other::' in line for line in perf_map)
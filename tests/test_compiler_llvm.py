@@ -0,0 +1,80 @@
+from wasmer import engine, Store, Module, Instance
+import os
+import pytest
+
+try:
+    from wasmer_compiler_llvm import Compiler
+except ImportError:
+    Compiler = None
+
+NAN_WAT = '(module (func (export "div") (param f32 f32) (result i32) local.get 0 local.get 1 f32.div i32.reinterpret_f32))'
+CANONICAL_NAN = 0x7fc00000
+TEST_WAT = '(module (func (export "sum") (param i32 i32) (result i32) local.get 0 local.get 1 i32.add))'
+
+requires_llvm = pytest.mark.skipif(Compiler is None, reason='`wasmer_compiler_llvm` is not installed')
+
+@requires_llvm
+def test_compiler_options():
+    for opt_level in ['none', 'less', 'default', 'aggressive']:
+        store = Store(engine.Universal(Compiler(opt_level=opt_level, enable_verifier=True)))
+
+        assert store.compiler_name == 'llvm'
+        assert Instance(Module(store, TEST_WAT)).exports.sum(1, 2) == 3
+
+@requires_llvm
+def test_compiler_invalid_opt_level():
+    with pytest.raises(ValueError) as context_manager:
+        Compiler(opt_level='O3')
+
+    exception = context_manager.value
+    assert str(exception) == 'Unknown optimization level `O3`, expected `none`, `less`, `default` or `aggressive`'
+
+@requires_llvm
+def test_compiler_debug_dir(tmp_path):
+    debug_dir = tmp_path / 'llvm'
+    store = Store(engine.Universal(Compiler(debug_dir=debug_dir)))
+
+    assert Instance(Module(store, TEST_WAT)).exports.sum(1, 2) == 3
+
+    files = os.listdir(debug_dir)
+
+    assert 'function_0.preopt.ll' in files
+    assert 'function_0.postopt.ll' in files
+    assert 'function_0.o' in files
+
+@requires_llvm
+def test_compiler_debug_dir_must_be_writable(tmp_path):
+    debug_dir = tmp_path / 'file'
+    debug_dir.write_text('')
+
+    with pytest.raises(RuntimeError) as context_manager:
+        Compiler(debug_dir=debug_dir)
+
+    assert str(context_manager.value).startswith(f'Cannot use `{debug_dir}` as the debug directory: ')
+
+@requires_llvm
+def test_compiler_debug_dir_removed(tmp_path):
+    import shutil
+
+    debug_dir = tmp_path / 'llvm'
+    store = Store(engine.Universal(Compiler(debug_dir=debug_dir)))
+    shutil.rmtree(debug_dir)
+
+    # The compilation does not fail, but warns once.
+    with pytest.warns(RuntimeWarning) as warnings:
+        assert Instance(Module(store, TEST_WAT)).exports.sum(1, 2) == 3
+
+    assert len(warnings) == 1
+    assert 'Failed to dump' in str(warnings[0].message)
+
+@requires_llvm
+def test_compiler_canonicalize_nans():
+    store = Store(engine.Universal(Compiler(canonicalize_nans=True)))
+
+    assert Instance(Module(store, NAN_WAT)).exports.div(0.0, 0.0) == CANONICAL_NAN
+
+@requires_llvm
+def test_compiler_threads():
+    store = Store(engine.Universal(Compiler(threads=1)))
+
+    assert Instance(Module(store, TEST_WAT)).exports.sum(1, 2) == 3
@@ -0,0 +1,25 @@
+from wasmer import engine, Store, Module, Instance
+from wasmer_compiler_singlepass import Compiler
+import pytest
+
+NAN_WAT = '(module (func (export "div") (param f32 f32) (result i32) local.get 0 local.get 1 f32.div i32.reinterpret_f32))'
+CANONICAL_NAN = 0x7fc00000
+TEST_WAT = '(module (func (export "sum") (param i32 i32) (result i32) local.get 0 local.get 1 i32.add))'
+
+def test_compiler_class():
+    store = Store(engine.Universal(Compiler))
+
+    assert store.compiler_name == 'singlepass'
+    assert Instance(Module(store, TEST_WAT)).exports.sum(1, 2) == 3
+
+def test_compiler_options():
+    for enable_stack_check in [False, True]:
+        store = Store(engine.Universal(Compiler(enable_stack_check=enable_stack_check)))
+
+        assert store.compiler_name == 'singlepass'
+        assert Instance(Module(store, TEST_WAT)).exports.sum(1, 2) == 3
+
+def test_compiler_canonicalize_nans():
+    store = Store(engine.Universal(Compiler(canonicalize_nans=True)))
+
+    assert Instance(Module(store, NAN_WAT)).exports.div(0.0, 0.0) == CANONICAL_NAN
@@ -0,0 +1,115 @@
+from wasmer import engine, Store, Module, Instance, InstancePre, DeferredStart, Memory, MemoryType
+from wasmer_compiler_cranelift import Compiler as Cranelift
+from wasmer_compiler_singlepass import Compiler as Singlepass
+import pytest
+
+# The start function reads the first byte of the imported memory.
+TEST_WAT = """
+(module
+  (import "env" "memory" (memory 1))
+  (global $first_byte (export "first_byte") (mut i32) (i32.const -1))
+  (func $start
+    i32.const 0
+    i32.load8_u
+    global.set $first_byte)
+  (start $start))
+"""
+
+def deferred_start_store(compiler=Cranelift):
+    return Store(engine.Universal(compiler(middlewares=[DeferredStart()])))
+
+def test_deferred_start():
+    for compiler in [Cranelift, Singlepass]:
+        store = deferred_start_store(compiler)
+        memory = Memory(store, MemoryType(minimum=1))
+        instance = Instance(Module(store, TEST_WAT), {"env": {"memory": memory}}, run_start=False)
+
+        assert instance.exports.first_byte.value == -1
+
+        # The memory is populated before the start function runs.
+        memory.uint8_view()[0] = 42
+        instance.run_start()
+
+        assert instance.exports.first_byte.value == 42
+
+def test_start_runs_by_default():
+    store = deferred_start_store()
+    memory = Memory(store, MemoryType(minimum=1))
+    instance = Instance(Module(store, TEST_WAT), {"env": {"memory": memory}})
+
+    assert instance.exports.first_byte.value == 0
+
+def test_start_runs_once():
+    store = deferred_start_store()
+
+    for run_start in [False, True]:
+        instance = Instance(
+            Module(store, TEST_WAT),
+            {"env": {"memory": Memory(store, MemoryType(minimum=1))}},
+            run_start=run_start,
+        )
+
+        if not run_start:
+            instance.run_start()
+
+        with pytest.raises(RuntimeError) as context_manager:
+            instance.run_start()
+
+        assert str(context_manager.value) == 'The start function of the instance has already run'
+
+def test_start_is_not_exported():
+    store = deferred_start_store()
+    module = Module(store, TEST_WAT)
+    instance = Instance(module, {"env": {"memory": Memory(store, MemoryType(minimum=1))}})
+    exports = instance.exports
+
+    assert [export.name for export in module.exports] == ['first_byte']
+    assert exports.keys() == ['first_byte']
+    assert [name for (name, _) in exports] == ['first_byte']
+    assert len(exports) == 1
+    assert 'wasmer_start' not in exports
+    assert 'wasmer_start' not in dir(exports)
+    assert exports.get('wasmer_start') is None
+
+    with pytest.raises(LookupError):
+        exports.wasmer_start
+
+    with pytest.raises(KeyError):
+        exports['wasmer_start']
+
+def test_start_trap():
+    store = deferred_start_store()
+    instance = Instance(Module(store, '(module (func $start unreachable) (start $start))'), run_start=False)
+
+    with pytest.raises(RuntimeError) as context_manager:
+        instance.run_start()
+
+    assert 'unreachable' in str(context_manager.value)
+
+def test_run_start_without_start_function():
+    instance = Instance(Module(Store(), '(module)'), run_start=False)
+    instance.run_start()
+
+def test_run_start_requires_the_middleware():
+    store = Store()
+    module = Module(store, TEST_WAT)
+
+    with pytest.raises(ValueError) as context_manager:
+        Instance(module, {"env": {"memory": Memory(store, MemoryType(minimum=1))}}, run_start=False)
+
+    assert str(context_manager.value) == (
+        "The start function of the module can't be deferred, "
+        "the module must be compiled with a `DeferredStart` middleware"
+    )
+
+def test_instance_pre():
+    store = deferred_start_store()
+    memory = Memory(store, MemoryType(minimum=1))
+    instance_pre = InstancePre(Module(store, TEST_WAT), {"env": {"memory": memory}})
+
+    instance = instance_pre.instantiate(run_start=False)
+    memory.uint8_view()[0] = 7
+    instance.run_start()
+
+    assert instance.exports.first_byte.value == 7
+    assert instance_pre.instantiate().exports.first_byte.value == 7
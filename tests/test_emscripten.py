@@ -0,0 +1,156 @@
+from wasmer import emscripten, Store, Module, Instance, Memory
+import pytest
+
+# The memory and the table expected by the Emscripten runtime.
+HEADER = """
+  (import "env" "memory" (memory 256 256))
+  (import "env" "table" (table 0 funcref))
+"""
+
+PROLOGUE = HEADER + """
+  (import "env" "_emscripten_memcpy_big" (func $memcpy_big (param i32 i32 i32) (result i32)))
+  (global $stack_top (mut i32) (i32.const 65536))
+  (func (export "stackAlloc") (param $size i32) (result i32)
+    global.get $stack_top
+    local.get $size
+    i32.sub
+    global.set $stack_top
+    global.get $stack_top)
+"""
+
+def instantiate(wat):
+    store = Store()
+    module = Module(store, wat)
+    emscripten_env = emscripten.Environment(store, module)
+
+    return emscripten_env, Instance(module, emscripten_env.generate_import_object(store))
+
+def test_is_emscripten_module():
+    assert emscripten.is_emscripten_module(Module(Store(), '(module %s)' % PROLOGUE))
+    assert not emscripten.is_emscripten_module(Module(Store(), '(module)'))
+
+def test_run():
+    emscripten_env, instance = instantiate(
+        """
+        (module
+          %s
+          ;; Stores `argc`, and copies `argv[0]` and `argv[1]`.
+          (func (export "_main") (param $argc i32) (param $argv i32) (result i32)
+            i32.const 0
+            local.get $argc
+            i32.store
+            i32.const 4
+            local.get $argv
+            i32.load
+            i32.const 12
+            call $memcpy_big
+            drop
+            i32.const 16
+            local.get $argv
+            i32.load offset=4
+            i32.const 3
+            call $memcpy_big
+            drop
+            i32.const 0))
+        """ % PROLOGUE
+    )
+
+    emscripten_env.run(instance, 'test-program', ['foo', 'bar'])
+    memory = emscripten_env.memory
+
+    assert isinstance(memory, Memory)
+    assert memory.uint8_view()[0] == 3
+    assert bytes(memory.uint8_view()[4:16]) == b'test-program'
+    assert bytes(memory.uint8_view()[16:19]) == b'foo'
+
+def test_run_without_arguments():
+    emscripten_env, instance = instantiate(
+        """
+        (module
+          %s
+          (func (export "_main")
+            i32.const 0
+            i32.const 42
+            i32.store))
+        """ % HEADER
+    )
+
+    emscripten_env.run(instance, 'test-program')
+
+    assert emscripten_env.memory.uint8_view()[0] == 42
+
+def test_run_requires_stack_alloc():
+    emscripten_env, instance = instantiate(
+        """
+        (module
+          %s
+          (func (export "_main") (param i32 i32) (result i32)
+            i32.const 0))
+        """ % HEADER
+    )
+
+    with pytest.raises(RuntimeError) as context_manager:
+        emscripten_env.run(instance, 'test-program')
+
+    assert str(context_manager.value) == 'The Emscripten program must export `stackAlloc` to receive its arguments'
+
+def test_exit():
+    for name in ['exit', '_exit', '__exit']:
+        emscripten_env, instance = instantiate(
+            """
+            (module
+              %s
+              (import "env" "%s" (func $exit (param i32)))
+              (func (export "_main")
+                i32.const 3
+                call $exit))
+            """ % (HEADER, name)
+        )
+
+        with pytest.raises(emscripten.EmscriptenExitError) as context_manager:
+            emscripten_env.run(instance, 'test-program')
+
+        assert context_manager.value.exit_code == 3
+        assert str(context_manager.value) == 'Emscripten exited with code 3'
+
+def test_exit_syscall():
+    emscripten_env, instance = instantiate(
+        """
+        (module
+          %s
+          (import "env" "___syscall1" (func $syscall1 (param i32 i32)))
+          (data (i32.const 8) "\\07\\00\\00\\00")
+          (func (export "_main")
+            i32.const 1
+            i32.const 8
+            call $syscall1))
+        """ % HEADER
+    )
+
+    with pytest.raises(emscripten.EmscriptenExitError) as context_manager:
+        emscripten_env.run(instance, 'test-program')
+
+    assert context_manager.value.exit_code == 7
+
+def test_abort():
+    emscripten_env, instance = instantiate(
+        """
+        (module
+          %s
+          (import "env" "abort" (func $abort (param i32)))
+          (func (export "_main")
+            i32.const 0
+            call $abort))
+        """ % HEADER
+    )
+
+    with pytest.raises(RuntimeError) as context_manager:
+        emscripten_env.run(instance, 'test-program')
+
+    assert str(context_manager.value) == 'The Emscripten program has aborted'
+
+def test_environment_requires_a_memory_and_a_table():
+    with pytest.raises(ValueError) as context_manager:
+        emscripten.Environment(Store(), Module(Store(), '(module)'))
+
+    assert str(context_manager.value) == 'Emscripten requires at least one imported table'
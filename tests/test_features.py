@@ -0,0 +1,44 @@
+from wasmer import engine, wat2wasm, Store, Module
+from wasmer_compiler_cranelift import Compiler
+import pytest
+
+SIMD_BYTES = wat2wasm('(module (func (param v128)))')
+REFERENCE_TYPES_BYTES = wat2wasm('(module (table 1 externref))')
+
+def test_features_defaults():
+    features = engine.Features()
+
+    assert features.threads == False
+    assert features.reference_types == True
+    assert features.simd == True
+    assert features.bulk_memory == True
+    assert features.multi_value == True
+    assert features.tail_call == False
+    assert features.module_linking == False
+    assert features.multi_memory == False
+    assert features.memory64 == False
+    assert features.exceptions == False
+
+def test_features_setters():
+    features = engine.Features(simd=False)
+    assert features.simd == False
+
+    features.simd = True
+    assert features.simd == True
+
+def test_features_disable_simd():
+    assert Module.validate(Store(engine.Universal(Compiler, features=engine.Features())), SIMD_BYTES)
+    assert not Module.validate(Store(engine.Universal(Compiler, features=engine.Features(simd=False))), SIMD_BYTES)
+
+def test_features_disable_reference_types():
+    store = Store(engine.Universal(Compiler, features=engine.Features(reference_types=False)))
+
+    assert not Module.validate(store, REFERENCE_TYPES_BYTES)
+
+    with pytest.raises(RuntimeError):
+        Module(store, REFERENCE_TYPES_BYTES)
+
+def test_features_dylib():
+    store = Store(engine.Dylib(Compiler, features=engine.Features(simd=False)))
+
+    assert not Module.validate(store, SIMD_BYTES)
@@ -1,6 +1,9 @@
 import wasmer
 from wasmer import Instance, Module, Store, Function, FunctionType, Type, ImportObject
 import os
+import threading
+import traceback
+import asyncio
 import pytest
 
 here = os.path.dirname(os.path.realpath(__file__))
@@ -190,3 +193,347 @@ def test_return_multiple_values_from_host_function():
     instance = Instance(module, import_object)
 
     assert instance.exports.test(41, 42) == (42, 41)
+
+def test_host_function_with_explicit_type_returns_too_many_values():
+    store = Store()
+    module = Module(
+        store,
+        """
+        (module
+          (import "env" "pair" (func $pair (result i32 i32)))
+          (func (export "run") (result i32 i32)
+            call $pair))
+        """
+    )
+
+    def pair():
+        return (1, 2, 3)
+
+    instance = Instance(module, {"env": {"pair": Function(store, pair, FunctionType([], [Type.I32, Type.I32]))}})
+
+    with pytest.raises(RuntimeError) as context_manager:
+        instance.exports.run()
+
+    exception = context_manager.value
+    assert str(exception) == 'The host function has returned 3 values, expected 2'
+
+def test_host_function_with_env():
+    class Env:
+        def __init__(self):
+            self.exports = None
+
+        def init_with_exports(self, exports):
+            self.exports = exports
+
+    def read(env: Env, offset: int) -> int:
+        return env.exports.memory.uint8_view(offset)[0] + env.exports.base.value
+
+    store = Store()
+    module = Module(
+        store,
+        """
+        (module
+          (import "env" "read" (func $read (param i32) (result i32)))
+          (memory (export "memory") 1)
+          (global (export "base") i32 (i32.const 100))
+          (data (i32.const 7) "\\2a")
+          (func (export "run") (result i32)
+            i32.const 7
+            call $read))
+        """
+    )
+
+    env = Env()
+    function = Function(store, read, env=env)
+
+    assert function.type.params == [Type.I32]
+    assert function.type.results == [Type.I32]
+
+    instance = Instance(module, {"env": {"read": function}})
+
+    assert hasattr(env.exports, 'memory')
+    assert instance.exports.run() == 142
+
+def test_host_function_with_env_without_init():
+    store = Store()
+    module = Module(
+        store,
+        """
+        (module
+          (import "env" "get" (func $get (result i32)))
+          (func (export "run") (result i32)
+            call $get))
+        """
+    )
+
+    function = Function(store, lambda env: env['value'], FunctionType([], [Type.I32]), env={'value': 42})
+    instance = Instance(module, {"env": {"get": function}})
+
+    assert instance.exports.run() == 42
+
+def test_host_function_exception_is_propagated_unchanged():
+    error = KeyError('missing')
+
+    def lookup(key: int) -> int:
+        raise error
+
+    store = Store()
+    module = Module(
+        store,
+        """
+        (module
+          (import "env" "lookup" (func $lookup (param i32) (result i32)))
+          (func (export "run") (param i32) (result i32)
+            local.get 0
+            call $lookup))
+        """
+    )
+    instance = Instance(module, {"env": {"lookup": Function(store, lookup)}})
+
+    # Directly, and through another WebAssembly call made by a host
+    # function.
+    def nested(key: int) -> int:
+        return instance.exports.run(key)
+
+    outer = Instance(module, {"env": {"lookup": Function(store, nested)}})
+
+    for function in [instance.exports.run, outer.exports.run]:
+        with pytest.raises(KeyError) as context_manager:
+            function(7)
+
+        assert context_manager.value is error
+        assert traceback.extract_tb(error.__traceback__)[-1].name == 'lookup'
+
+def test_host_function_with_env_failing_init():
+    class Env:
+        def init_with_exports(self, exports):
+            raise ValueError('oops')
+
+    store = Store()
+    module = Module(store, '(module (import "env" "f" (func)))')
+    function = Function(store, lambda env: None, FunctionType([], []), env=Env())
+
+    with pytest.raises(ValueError) as context_manager:
+        Instance(module, {"env": {"f": function}})
+
+    exception = context_manager.value
+    assert str(exception) == 'oops'
+
+def test_return_multiple_values_of_different_types():
+    store = Store()
+    module = Module(
+        store,
+        """
+        (module
+          (import "env" "split" (func $split (param f64) (result i64 f32 i32)))
+          (func (export "split") (param f64) (result i64 f32 i32)
+            local.get 0
+            call $split))
+        """
+    )
+
+    def split(x: 'f64') -> ('i64', 'f32', 'i32'):
+        return (int(x), x - int(x), 1)
+
+    instance = Instance(module, {"env": {"split": Function(store, split)}})
+
+    assert instance.exports.split(2.5) == (2, 0.5, 1)
+
+def test_call_releases_the_gil():
+    iterations = [0]
+    progress = []
+
+    # Instances can't be shared between threads, the instance is
+    # created by the thread.
+    def spin():
+        module = Module(
+            Store(),
+            """
+            (module
+              (func (export "spin") (param i32) (result i32)
+                (loop
+                  local.get 0
+                  i32.const 1
+                  i32.sub
+                  local.tee 0
+                  br_if 0)
+                local.get 0))
+            """
+        )
+        instance = Instance(module)
+
+        before = iterations[0]
+        assert instance.exports.spin(300_000_000) == 0
+        progress.append(iterations[0] - before)
+
+    thread = threading.Thread(target=spin)
+    thread.start()
+
+    # The main thread keeps running while the function runs.
+    while thread.is_alive():
+        iterations[0] += 1
+
+    thread.join()
+
+    assert len(progress) == 1
+    assert progress[0] > 10_000
+
+def test_call_async():
+    instance = Instance(Module(Store(), TEST_BYTES))
+    sum = instance.exports.sum
+
+    async def main():
+        return await asyncio.gather(sum.call_async(1, 2), sum.call_async(3, 4))
+
+    assert asyncio.run(main()) == [3, 7]
+
+def test_call_async_error():
+    def fail() -> int:
+        raise ValueError('oops')
+
+    store = Store()
+    module = Module(
+        store,
+        """
+        (module
+          (import "env" "fail" (func $fail (result i32)))
+          (func (export "run") (result i32)
+            call $fail))
+        """
+    )
+    instance = Instance(module, {"env": {"fail": Function(store, fail)}})
+
+    async def main():
+        return await instance.exports.run.call_async()
+
+    with pytest.raises(ValueError) as context_manager:
+        asyncio.run(main())
+
+    exception = context_manager.value
+    assert str(exception) == 'oops'
+
+def test_call_async_without_event_loop():
+    instance = Instance(Module(Store(), TEST_BYTES))
+
+    with pytest.raises(RuntimeError):
+        instance.exports.sum.call_async(1, 2)
+
+def test_call_async_with_references():
+    instance = Instance(Module(Store(), '(module (func (export "id") (param externref) (result externref) local.get 0))'))
+
+    async def main():
+        return await instance.exports.id.call_async([1, 2, 3])
+
+    with pytest.raises(TypeError) as context_manager:
+        asyncio.run(main())
+
+    exception = context_manager.value
+    assert str(exception) == 'The function passes references, which cannot be sent to another thread'
+
+def test_typed():
+    exports = instance().exports
+
+    assert value_with_type(exports.sum.typed('(ii)->i')(1, 2)) == (3, int)
+    assert exports.void.typed('()->')() == None
+    assert round(exports.i32_i64_f32_f64_f64.typed('(iIfF)->F')(1, 2, 3.4, 5.6), 6) == (
+        1 + 2 + 3.4 + 5.6
+    )
+
+def test_typed_host_function():
+    def sum(x: 'f64', y: 'f64', z: 'f64') -> 'f64':
+        return x + y + z
+
+    assert Function(Store(), sum).typed('(FFF)->F')(1.5, 2.0, 3.0) == 6.5
+
+def test_typed_invalid_signature():
+    sum = instance().exports.sum
+
+    for signature in ['(ii)->I', '(i)->i', 'ii->i', '(ix)->i']:
+        with pytest.raises(TypeError) as context_manager:
+            sum.typed(signature)
+
+        exception = context_manager.value
+        assert str(exception) == 'The function has the signature `(ii)->i`, not `{}`'.format(signature)
+
+def test_typed_invalid_arity():
+    sum = instance().exports.sum.typed('(ii)->i')
+
+    with pytest.raises(TypeError) as context_manager:
+        sum(1)
+
+    exception = context_manager.value
+    assert str(exception) == 'The function expects 2 arguments, got 1'
+
+def test_typed_unsigned():
+    exports = instance().exports
+    sum = exports.sum.typed('(uu)->u')
+
+    assert sum(2 ** 32 - 2, 1) == 2 ** 32 - 1
+    assert exports.sum.typed('(ii)->i')(2 ** 32 - 2, 1) == -1
+    assert exports.i64_i64.typed('(U)->U')(2 ** 64 - 1) == 2 ** 64 - 1
+    assert exports.i64_i64.typed('(I)->I')(2 ** 64 - 1) == -1
+
+    with pytest.raises(OverflowError):
+        sum(-1, 1)
+
+def test_typed_overflow():
+    exports = instance().exports
+
+    for sum in [exports.sum.typed('(ii)->i'), exports.sum.typed('(uu)->u')]:
+        with pytest.raises(OverflowError):
+            sum(2 ** 32, 1)
+
+def test_typed_wrap():
+    exports = instance().exports
+
+    assert exports.sum.typed('(ii)->i', wrap=True)(2 ** 32 + 1, 1) == 2
+    assert exports.sum.typed('(uu)->u', wrap=True)(-1, 0) == 2 ** 32 - 1
+    assert exports.i64_i64.typed('(I)->I', wrap=True)(2 ** 64 + 5) == 5
+    assert exports.i64_i64.typed('(U)->U', wrap=True)(-2) == 2 ** 64 - 2
+
+def test_typed_type():
+    exports = instance().exports
+
+    for signature in ['(ii)->i', '(uu)->u']:
+        assert exports.sum.typed(signature).type == exports.sum.type
+
+def test_host_function_type():
+    def sum(x: int, y: int) -> int:
+        return x + y
+
+    function_type = FunctionType([Type.I32, Type.I32], [Type.I32])
+    store = Store()
+
+    assert Function(store, sum).type == function_type
+    assert Function(store, lambda x, y: x + y, function_type).type == function_type
+    assert Function(store, sum).type == instance().exports.sum.type
+
+def test_call_batch():
+    sum = instance().exports.sum
+
+    assert sum.call_batch([(1, 2), (3, 4)]) == [3, 7]
+    assert sum.call_batch((x, x) for x in range(3)) == [0, 2, 4]
+    assert sum.call_batch([]) == []
+
+def test_call_batch_error():
+    store = Store()
+    module = Module(
+        store,
+        """
+        (module
+          (func (export "div") (param i32 i32) (result i32)
+            local.get 0
+            local.get 1
+            i32.div_s))
+        """
+    )
+    div = Instance(module).exports.div
+
+    with pytest.raises(RuntimeError) as context_manager:
+        div.call_batch([(4, 2), (1, 0)])
+
+    exception = context_manager.value
+    assert 'integer divide by zero' in str(exception)
+
+    with pytest.raises(TypeError):
+        div.call_batch([[4, 2]])
@@ -84,3 +84,66 @@ def test_import_global():
     assert instance.exports.read_g() == 153
     instance.exports.write_g(11)
     assert global_.value == 11
+
+def test_import_nested_dict_with_callable():
+    store = Store()
+    module = Module(
+        store,
+        """
+        (module
+          (import "env" "log" (func $log (param i32)))
+          (import "env" "memory" (memory $memory 1))
+          (func (export "run")
+            i32.const 0
+            i32.load
+            call $log))
+        """
+    )
+
+    logged = []
+    memory = Memory(store, MemoryType(minimum=1))
+    memory.uint8_view(offset=0)[0] = 42
+
+    # The callable has no annotations: its type is the type of the import.
+    instance = Instance(module, {"env": {"log": lambda value: logged.append(value), "memory": memory}})
+    instance.exports.run()
+
+    assert logged == [42]
+
+def test_import_nested_dict_with_invalid_item():
+    store = Store()
+    module = Module(store, '(module (import "env" "value" (global i32)))')
+
+    with pytest.raises(TypeError) as context_manager:
+        Instance(module, {"env": {"value": 42}})
+
+    exception = context_manager.value
+    assert str(exception).startswith("`ImportObject` cannot register the given type")
+
+def test_import_memory_shared_between_instances():
+    store = Store()
+    module = Module(
+        store,
+        """
+        (module
+          (import "env" "memory" (memory 1))
+          (func (export "increment")
+            i32.const 0
+            i32.const 0
+            i32.load
+            i32.const 1
+            i32.add
+            i32.store))
+        """
+    )
+
+    memory = Memory(store, MemoryType(minimum=1))
+    memory.write(0, (41).to_bytes(4, 'little'))
+
+    first = Instance(module, {"env": {"memory": memory}})
+    second = Instance(module, {"env": {"memory": memory}})
+
+    first.exports.increment()
+    second.exports.increment()
+
+    assert memory.int32_view()[0] == 43
@@ -107,3 +107,84 @@ def test_import_global():
     assert instance.exports.read_g() == 153
     instance.exports.write_g(11)
     assert global_.value == 11
+
+def test_extend():
+    store = Store()
+
+    def log(x: int):
+        pass
+
+    def sum(x: int, y: int) -> int:
+        return x + y
+
+    import_object = ImportObject()
+    import_object.register("env", {"log": Function(store, log), "sum": Function(store, log)})
+
+    other = ImportObject()
+    other.register("env", {"sum": Function(store, sum)})
+    other.register("math", {"memory": Memory(store, MemoryType(minimum=1))})
+
+    import_object.extend(other)
+    import_object.extend({"wasi": {"global": Global(store, Value.i32(7))}})
+
+    assert list(import_object) == ["env", "math", "wasi"]
+    assert len(import_object) == 3
+    assert "math" in import_object
+    assert "foo" not in import_object
+    assert import_object.names("env") == ["log", "sum"]
+
+    # `env.sum` has been replaced by `other`.
+    module = Module(
+        store,
+        """
+        (module
+          (import "env" "sum" (func $sum (param i32 i32) (result i32)))
+          (func (export "add_one") (param i32) (result i32)
+            local.get 0
+            i32.const 1
+            call $sum))
+        """
+    )
+
+    assert Instance(module, import_object).exports.add_one(1) == 2
+
+def test_extend_invalid_type():
+    with pytest.raises(TypeError) as context_manager:
+        ImportObject().extend(42)
+
+    exception = context_manager.value
+    assert str(exception) == "`ImportObject` cannot be extended with the given type `int`"
+
+def test_or():
+    store = Store()
+
+    left = ImportObject()
+    left.register("env", {"memory": Memory(store, MemoryType(minimum=1))})
+
+    right = ImportObject()
+    right.register("env", {"global": Global(store, Value.i32(7))})
+
+    import_object = left | right
+
+    assert import_object.names("env") == ["global", "memory"]
+    # The operands are left untouched.
+    assert left.names("env") == ["memory"]
+    assert right.names("env") == ["global"]
+
+    with pytest.raises(TypeError):
+        left | {}
+
+def test_names_missing_namespace():
+    with pytest.raises(LookupError) as context_manager:
+        ImportObject().names("env")
+
+    exception = context_manager.value
+    assert str(exception) == "Namespace `env` does not exist."
+
+def test_repr():
+    store = Store()
+
+    import_object = ImportObject()
+    import_object.register("env", {"memory": Memory(store, MemoryType(minimum=1))})
+
+    assert repr(import_object) == "ImportObject({'env': ['memory']})"
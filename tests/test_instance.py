@@ -1,6 +1,7 @@
 import wasmer
 from wasmer import Instance, Module, Store, Exports, ExportsIterator, Function, Global, Table, Memory
 import os
+import time
 import pytest
 
 here = os.path.dirname(os.path.realpath(__file__))
@@ -98,9 +99,645 @@ def test_exports_iterable():
 
     assert [name for (name, _) in instance.exports] == ["func", "glob", "tab", "mem"]
 
+MAPPING_WAT = """
+(module
+  (func (export "func") (param i32 i64))
+  (global (export "glob") i32 (i32.const 7))
+  (table (export "tab") 0 funcref)
+  (memory (export "mem") 1))
+"""
+
+def test_exports_mapping():
+    exports = Instance(Module(Store(), MAPPING_WAT)).exports
+
+    for (name, kind) in [("func", Function), ("glob", Global), ("tab", Table), ("mem", Memory)]:
+        assert name in exports
+        assert isinstance(exports[name], kind)
+        assert isinstance(exports.get(name), kind)
+
+    assert "foo" not in exports
+    assert exports.get("foo") == None
+    assert exports.get("foo", 42) == 42
+    assert exports.keys() == ["func", "glob", "tab", "mem"]
+    assert [type(export) for export in exports.values()] == [Function, Global, Table, Memory]
+    assert [name for (name, _) in exports.items()] == exports.keys()
+
+def test_exports_named_like_a_mapping_method():
+    exports = Instance(Module(Store(), '(module (func (export "keys") (result i32) i32.const 42))')).exports
+
+    # The export takes precedence over the method.
+    assert exports.keys() == 42
+    assert exports["keys"]() == 42
+    assert exports.get("keys")() == 42
+
+def test_exports_getitem_does_not_exist():
+    with pytest.raises(KeyError) as context_manager:
+        Instance(Module(Store(), MAPPING_WAT)).exports["foo"]
+
+    assert context_manager.value.args[0] == 'Export `foo` does not exist.'
+
+def test_exports_dir():
+    attributes = dir(Instance(Module(Store(), MAPPING_WAT)).exports)
+
+    for name in ["func", "glob", "tab", "mem", "get", "items", "keys", "values"]:
+        assert name in attributes
+
+    assert "_get" not in attributes
+
 def test_export_does_not_exist():
     with pytest.raises(LookupError) as context_manager:
         Instance(Module(Store(), TEST_BYTES)).exports.foo
 
     exception = context_manager.value
     assert str(exception) == 'Export `foo` does not exist.'
+
+def test_instantiation_error_mentions_module_name():
+    module = Module(Store(), '(module (import "ns" "function" (func)))')
+    module.name = 'hello'
+
+    with pytest.raises(RuntimeError) as context_manager:
+        Instance(module)
+
+    exception = context_manager.value
+    assert str(exception).startswith('Failed to instantiate module `hello`: Error while importing "ns"."function"')
+
+def test_trap_mentions_module_name():
+    module = Module(Store(), '(module (func (export "f") unreachable))')
+    module.name = 'hello'
+
+    with pytest.raises(RuntimeError) as context_manager:
+        Instance(module).exports.f()
+
+    exception = context_manager.value
+    assert '(hello[0]:' in str(exception)
+
+MISSING_IMPORTS_WAT = """
+(module
+  (import "env" "log" (func $log (param i32)))
+  (import "env" "sum" (func $sum (param i32 i32) (result i32)))
+  (func (export "add_one") (param i32) (result i32)
+    local.get 0
+    i32.const 1
+    call $sum)
+  (func (export "log")
+    i32.const 42
+    call $log))
+"""
+
+def test_allow_missing_imports():
+    module = Module(Store(), MISSING_IMPORTS_WAT)
+
+    for import_object in [None, {}, {"env": {"sum": lambda x, y: x + y}}]:
+        instance = Instance(module, import_object, allow_missing_imports=True)
+
+        with pytest.raises(RuntimeError) as context_manager:
+            instance.exports.log()
+
+        exception = context_manager.value
+        assert str(exception) == 'Missing import `env.log` has been called'
+
+    assert instance.exports.add_one(41) == 42
+
+def test_allow_missing_imports_does_not_modify_import_object():
+    def sum(x: int, y: int) -> int:
+        return x + y
+
+    store = Store()
+    module = Module(store, MISSING_IMPORTS_WAT)
+    import_object = wasmer.ImportObject()
+    import_object.register("env", {"sum": Function(store, sum)})
+
+    Instance(module, import_object, allow_missing_imports=True)
+
+    assert import_object.names("env") == ["sum"]
+
+def test_missing_imports_are_not_allowed_by_default():
+    with pytest.raises(RuntimeError):
+        Instance(Module(Store(), MISSING_IMPORTS_WAT))
+
+def test_allow_missing_imports_only_stubs_functions():
+    module = Module(Store(), '(module (import "env" "memory" (memory 1)))')
+
+    with pytest.raises(RuntimeError):
+        Instance(module, allow_missing_imports=True)
+
+def test_error_hierarchy():
+    for error in [wasmer.CompileError, wasmer.InstantiationError, wasmer.LinkError, wasmer.Trap, wasmer.ExecutionTimeout]:
+        assert issubclass(error, wasmer.WasmerError)
+
+    assert issubclass(wasmer.WasmerError, RuntimeError)
+    assert issubclass(wasmer.wasi.WasiExitError, wasmer.WasmerError)
+
+def test_link_error():
+    with pytest.raises(wasmer.LinkError) as context_manager:
+        Instance(Module(Store(), MISSING_IMPORTS_WAT))
+
+    exception = context_manager.value
+    assert str(exception).startswith('Error while importing "env"."log"')
+
+def test_trap_codes():
+    module = Module(
+        Store(),
+        """
+        (module
+          (memory 1)
+          (func $recurse (export "recurse")
+            call $recurse)
+          (func (export "unreachable")
+            unreachable)
+          (func (export "out_of_bounds") (result i32)
+            i32.const 65536
+            i32.load)
+          (func (export "divide_by_zero") (result i32)
+            i32.const 1
+            i32.const 0
+            i32.div_s))
+        """
+    )
+    exports = Instance(module).exports
+
+    for function, code in [
+            (exports.recurse, wasmer.TrapCode.STACK_OVERFLOW),
+            (exports.unreachable, wasmer.TrapCode.UNREACHABLE_CODE_REACHED),
+            (exports.out_of_bounds, wasmer.TrapCode.HEAP_ACCESS_OUT_OF_BOUNDS),
+            (exports.divide_by_zero, wasmer.TrapCode.INTEGER_DIVISION_BY_ZERO),
+    ]:
+        with pytest.raises(wasmer.Trap) as context_manager:
+            function()
+
+        assert context_manager.value.code == code
+
+def test_trap_in_start_function():
+    module = Module(Store(), '(module (func $start unreachable) (start $start))')
+
+    with pytest.raises(wasmer.Trap) as context_manager:
+        Instance(module)
+
+    assert context_manager.value.code == wasmer.TrapCode.UNREACHABLE_CODE_REACHED
+
+def test_trap_frames():
+    module = Module(
+        Store(),
+        """
+        (module $app
+          (func $crash
+            unreachable)
+          (func (export "run")
+            call $crash))
+        """
+    )
+
+    with pytest.raises(wasmer.Trap) as context_manager:
+        Instance(module).exports.run()
+
+    trap = context_manager.value
+    crash, run = trap.frames
+
+    for frame, index, name in [(crash, 0, 'crash'), (run, 1, 'run')]:
+        assert isinstance(frame, wasmer.Frame)
+        assert frame.module_name == 'app'
+        assert frame.function_index == index
+        assert frame.module_offset > frame.function_offset
+
+    assert crash.function_name == 'crash'
+    assert run.function_name == None
+    assert str(trap).splitlines() == [
+        'unreachable',
+        f'    at crash (app[0]:0x{crash.module_offset:x})',
+        f'    at <unnamed> (app[1]:0x{run.module_offset:x})',
+    ]
+    assert repr(crash) == f'Frame(crash (app[0]:0x{crash.module_offset:x}))'
+
+def test_trap_frames_of_a_stack_overflow():
+    module = Module(Store(), '(module (func $recurse (export "recurse") call $recurse))')
+
+    with pytest.raises(wasmer.Trap) as context_manager:
+        Instance(module).exports.recurse()
+
+    trap = context_manager.value
+    lines = str(trap).splitlines()
+
+    assert len(trap.frames) > 64
+    assert len(lines) == 66
+    assert lines[-1] == f'    ... {len(trap.frames) - 64} more frames'
+
+def test_instance_pre():
+    from wasmer import InstancePre, ImportObject, MemoryType
+
+    store = Store()
+    module = Module(
+        store,
+        """
+        (module
+          (import "env" "log" (func $log (param i32)))
+          (import "env" "memory" (memory 1))
+          (func (export "run") (param i32)
+            local.get 0
+            call $log)
+          (func (export "size") (result i32)
+            memory.size))
+        """
+    )
+    logged = []
+    memory = Memory(store, MemoryType(minimum=1))
+
+    def log(x: int):
+        logged.append(x)
+
+    import_object = ImportObject()
+    import_object.register("env", {"log": Function(store, log), "memory": memory})
+
+    for imports in [import_object, {"env": {"log": log, "memory": memory}}]:
+        instance_pre = InstancePre(module, imports)
+        first = instance_pre.instantiate()
+        second = instance_pre.instantiate()
+
+        assert isinstance(first, Instance)
+
+        first.exports.run(1)
+        second.exports.run(2)
+        memory.grow(1)
+
+        assert first.exports.size() == second.exports.size() == memory.size
+
+    assert logged == [1, 2, 1, 2]
+
+def test_instance_pre_missing_imports():
+    from wasmer import InstancePre
+
+    module = Module(Store(), MISSING_IMPORTS_WAT)
+
+    with pytest.raises(wasmer.LinkError) as context_manager:
+        InstancePre(module, {"env": {"sum": lambda x, y: x + y}})
+
+    assert str(context_manager.value) == 'Import `env.log` is missing'
+
+    instance = InstancePre(module, {"env": {"sum": lambda x, y: x + y}}, allow_missing_imports=True).instantiate()
+
+    assert instance.exports.add_one(41) == 42
+
+def test_instance_pre_checks_imports_on_instantiation():
+    from wasmer import InstancePre, FunctionType, Type
+
+    store = Store()
+    module = Module(store, MISSING_IMPORTS_WAT)
+    instance_pre = InstancePre(
+        module,
+        {"env": {"log": Function(store, lambda: None, FunctionType([], [])), "sum": lambda x, y: x + y}}
+    )
+
+    with pytest.raises(wasmer.LinkError) as context_manager:
+        instance_pre.instantiate()
+
+    assert str(context_manager.value).startswith('Error while importing "env"."log"')
+
+    with pytest.raises(wasmer.LinkError):
+        InstancePre(module, {"env": {"log": Function(Store(), lambda x: None, FunctionType([Type.I32], [])), "sum": lambda x, y: x + y}})
+
+def test_clone():
+    logged = []
+
+    def log(x: int):
+        logged.append(x)
+
+    store = Store()
+    module = Module(
+        store,
+        """
+        (module
+          (import "env" "log" (func $log (param i32)))
+          (memory (export "memory") 1)
+          (global $counter (export "counter") (mut i32) (i32.const 0))
+          (global (export "answer") i32 (i32.const 42))
+          (func (export "increment")
+            global.get $counter
+            i32.const 1
+            i32.add
+            global.set $counter
+            global.get $counter
+            call $log))
+        """
+    )
+    instance = Instance(module, {"env": {"log": Function(store, log)}})
+    instance.exports.increment()
+    instance.exports.memory.grow(2)
+    instance.exports.memory.uint8_view()[0x20000] = 7
+
+    clone = instance.clone()
+
+    assert isinstance(clone, Instance)
+    assert clone.exports.memory.size == 3
+    assert clone.exports.memory.uint8_view()[0x20000] == 7
+    assert clone.exports.counter.value == 1
+    assert clone.exports.answer.value == 42
+
+    clone.exports.increment()
+    clone.exports.memory.uint8_view()[0] = 1
+
+    assert instance.exports.counter.value == 1
+    assert instance.exports.memory.uint8_view()[0] == 0
+    assert logged == [1, 2]
+
+def test_clone_of_an_unreachable_state():
+    for wat in ['(module (memory 1))', '(module (global (mut i32) (i32.const 0)))']:
+        with pytest.raises(RuntimeError) as context_manager:
+            Instance(Module(Store(), wat)).clone()
+
+        assert str(context_manager.value).endswith('is not exported, the state of the instance is not reachable')
+
+    # Immutable globals and imported memories are not part of the state.
+    store = Store()
+    memory = Memory(store, wasmer.MemoryType(minimum=1))
+    instance = Instance(
+        Module(store, '(module (import "env" "memory" (memory 1)) (global i32 (i32.const 0)))'),
+        {"env": {"memory": memory}}
+    )
+
+    assert isinstance(instance.clone(), Instance)
+
+
+def test_snapshot():
+    store = Store()
+    module = Module(
+        store,
+        """
+        (module
+          (import "env" "answer" (func $answer (result i32)))
+          (export "answer" (func $answer))
+          (memory (export "memory") 1)
+          (global $counter (export "counter") (mut i32) (i32.const 0))
+          (global (export "ratio") (mut f64) (f64.const 0))
+          (global (export "callback") (mut funcref) (ref.null func))
+          (table (export "table") 2 funcref)
+          (func $one (export "one") (result i32) i32.const 1)
+          (func (export "two") (result i32) i32.const 2)
+          (func (export "call") (param i32) (result i32)
+            local.get 0
+            call_indirect (result i32))
+          (func (export "setup")
+            i32.const 0
+            ref.func $one
+            table.set
+            i32.const 1
+            ref.func $answer
+            table.set
+            ref.func $one
+            global.set 2)
+          (elem declare func $one $answer))
+        """
+    )
+    import_object = {"env": {"answer": Function(store, lambda: 42, wasmer.FunctionType([], [wasmer.Type.I32]))}}
+    instance = Instance(module, import_object)
+    instance.exports.setup()
+    instance.exports.memory.grow(1)
+    instance.exports.memory.uint8_view()[0x10000] = 7
+    instance.exports.counter.value = 3
+    instance.exports.ratio.value = 0.5
+
+    snapshot = instance.snapshot()
+
+    assert isinstance(snapshot, bytes)
+
+    other = Instance(module, import_object)
+    other.restore(snapshot)
+
+    assert other.exports.memory.size == 2
+    assert other.exports.memory.uint8_view()[0x10000] == 7
+    assert other.exports.counter.value == 3
+    assert other.exports.ratio.value == 0.5
+    assert other.exports.table.size == 2
+    assert other.exports.call(0) == 1
+    assert other.exports.call(1) == 42
+    assert isinstance(other.exports.callback.value, Function)
+
+    # Restoring rewinds the instance.
+    instance.exports.counter.value = 4
+    instance.exports.memory.grow(1)
+    instance.exports.memory.uint8_view()[0x20000] = 1
+    instance.restore(snapshot)
+
+    assert instance.exports.counter.value == 3
+    assert instance.exports.memory.size == 3
+    assert instance.exports.memory.uint8_view()[0x20000] == 0
+
+
+def test_snapshot_of_another_module():
+    store = Store()
+    instance = Instance(Module(store, '(module (global (export "counter") (mut i32) (i32.const 0)))'))
+    snapshot = instance.snapshot()
+
+    for wat in [
+        '(module (global (export "count") (mut i32) (i32.const 0)))',
+        '(module (global (export "counter") (mut i64) (i64.const 0)))',
+        '(module (memory (export "memory") 1))',
+    ]:
+        other = Instance(Module(store, wat))
+
+        with pytest.raises(ValueError) as context_manager:
+            other.restore(snapshot)
+
+        assert str(context_manager.value) == 'The snapshot does not match the module of the instance'
+
+    for invalid in [b'', b'snapshot', snapshot[:-1], snapshot + b'\0']:
+        with pytest.raises(ValueError):
+            instance.restore(invalid)
+
+
+def test_snapshot_of_an_unreachable_state():
+    for wat in ['(module (memory 1))', '(module (table 1 funcref))']:
+        with pytest.raises(RuntimeError) as context_manager:
+            Instance(Module(Store(), wat)).snapshot()
+
+        assert str(context_manager.value).endswith('is not exported, the state of the instance is not reachable')
+
+
+def test_snapshot_of_an_unexported_function():
+    store = Store()
+    wat = """
+    (module
+      (import "env" "function" (func $imported (result i32)))
+      (table (export "table") 1 funcref)
+      (func $hidden %s (result i32) i32.const 1)
+      (func (export "set_hidden")
+        i32.const 0
+        ref.func $hidden
+        table.set)
+      (func (export "set") (param funcref)
+        i32.const 0
+        local.get 0
+        table.set)
+      (elem declare func $hidden))
+    """
+    function = Function(store, lambda: 0, wasmer.FunctionType([], [wasmer.Type.I32]))
+    hidden = Module(store, wat % "")
+    instance = Instance(hidden, {"env": {"function": function}})
+    instance.exports.set_hidden()
+
+    with pytest.raises(RuntimeError) as context_manager:
+        instance.snapshot()
+
+    assert str(context_manager.value) == 'A function referenced by the instance is not exported by it, or comes from another instance'
+
+    # A function of another instance of the same module is rejected
+    # too, rather than restored as a function of this instance.
+    module = Module(store, wat % '(export "hidden")')
+    instance, other = Instance(module, {"env": {"function": function}}), Instance(module, {"env": {"function": function}})
+    instance.exports.set(other.exports.hidden)
+
+    with pytest.raises(RuntimeError) as context_manager:
+        instance.snapshot()
+
+    assert str(context_manager.value) == 'A function referenced by the instance is not exported by it, or comes from another instance'
+
+    instance.exports.set_hidden()
+    snapshot = instance.snapshot()
+
+    # The function of the snapshot must be exported by the instance
+    # the snapshot is restored on.
+    with pytest.raises(RuntimeError) as context_manager:
+        Instance(hidden, {"env": {"function": function}}).restore(snapshot)
+
+    assert str(context_manager.value) == 'The function 0 of the snapshot is not exported by the instance'
+
+    other.restore(snapshot)
+
+    assert other.exports.table.get(0) is not None
+
+
+def test_migrate():
+    store = Store()
+    instance = Instance(
+        Module(
+            store,
+            """
+            (module
+              (memory (export "memory") 1)
+              (global (export "counter") (mut i32) (i32.const 0))
+              (global (export "dropped") (mut i32) (i32.const 0)))
+            """
+        )
+    )
+    instance.exports.memory.grow(1)
+    instance.exports.memory.uint8_view()[0x10000] = 7
+    instance.exports.counter.value = 3
+
+    migrated = instance.migrate(
+        Module(
+            store,
+            """
+            (module
+              (memory (export "memory") 1)
+              (global (export "counter") (mut i32) (i32.const 0))
+              (global (export "added") (mut i64) (i64.const 5))
+              (func (export "read") (result i32)
+                i32.const 0x10000
+                i32.load8_u))
+            """
+        )
+    )
+
+    assert migrated.exports.memory.size == 2
+    assert migrated.exports.read() == 7
+    assert migrated.exports.counter.value == 3
+    assert migrated.exports.added.value == 5
+    assert 'dropped' not in migrated.exports
+
+    # The state is copied.
+    migrated.exports.counter.value = 4
+
+    assert instance.exports.counter.value == 3
+
+
+def test_migrate_sharing_the_memory():
+    store = Store()
+    instance = Instance(Module(store, '(module (memory (export "memory") 1))'))
+    migrated = instance.migrate(
+        Module(store, '(module (import "env" "memory" (memory 1)) (export "memory" (memory 0)))'),
+        {"env": {"memory": instance.exports.memory}}
+    )
+    migrated.exports.memory.uint8_view()[0] = 42
+
+    assert instance.exports.memory.uint8_view()[0] == 42
+
+
+def test_migrate_a_global_of_another_type():
+    store = Store()
+    instance = Instance(Module(store, '(module (global (export "counter") (mut i32) (i32.const 0)))'))
+
+    with pytest.raises(ValueError) as context_manager:
+        instance.migrate(Module(store, '(module (global (export "counter") (mut i64) (i64.const 0)))'))
+
+    assert str(context_manager.value) == 'The global `counter` has the type `I32`, but it has the type `I64` in the new module'
+
+
+def test_stats():
+    store = Store(statistics=True)
+    instance = Instance(
+        Module(
+            store,
+            """
+            (module
+              (import "env" "sleep" (func $sleep))
+              (func (export "slow") call $sleep)
+              (func (export "fast"))
+              (func (export "crash") unreachable))
+            """
+        ),
+        {"env": {"sleep": Function(store, lambda: time.sleep(0.01))}}
+    )
+
+    instance.exports.slow()
+    instance.exports.fast.typed("()->")()
+    instance.exports.fast.call_batch([(), ()])
+
+    with pytest.raises(RuntimeError):
+        instance.exports.crash()
+
+    stats = instance.stats()
+
+    assert sorted(stats) == ['crash', 'fast', 'slow']
+    assert stats['slow']['calls'] == 1
+    assert stats['slow']['time'] >= 0.01
+    assert stats['fast']['calls'] == 3
+    assert stats['fast']['time'] < stats['slow']['time']
+    assert stats['crash']['calls'] == 1
+
+
+def test_stats_are_per_instance():
+    module = Module(Store(statistics=True), '(module (func (export "main")))')
+    instance = Instance(module)
+    instance.exports.main()
+
+    assert instance.stats()['main']['calls'] == 1
+    assert instance.clone().stats()['main']['calls'] == 0
+
+
+def test_stats_are_not_collected_by_default():
+    instance = Instance(Module(Store(), '(module (func (export "main")))'))
+
+    with pytest.raises(RuntimeError) as context_manager:
+        instance.stats()
+
+    assert str(context_manager.value) == "The store of the instance doesn't collect statistics, see `Store(statistics=True)`"
+
+def test_close():
+    instance = Instance(Module(Store(), TEST_BYTES))
+    sum = instance.exports.sum
+
+    instance.close()
+    instance.close()
+
+    assert len(instance.exports) == 0
+    assert sum(1, 2) == 3
+
+    with pytest.raises(ValueError) as context_manager:
+        instance.snapshot()
+
+    assert str(context_manager.value) == 'The instance is closed'
+
+def test_context_manager():
+    with Instance(Module(Store(), TEST_BYTES)) as instance:
+        assert instance.exports.sum(1, 2) == 3
+
+    with pytest.raises(ValueError):
+        instance.snapshot()
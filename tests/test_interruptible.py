@@ -0,0 +1,412 @@
+from wasmer import engine, interrupt_handle, Store, Module, Instance, InstancePre, Function, Interruptible, ExecutionTimeout
+from wasmer_compiler_cranelift import Compiler as Cranelift
+from wasmer_compiler_singlepass import Compiler as Singlepass
+import os
+import pytest
+import signal
+import socket
+import sys
+import threading
+import time
+
+TEST_WAT = """
+(module
+  (func $spin (export "spin")
+    (loop
+      br 0))
+
+  (func $recurse (export "recurse")
+    call $recurse)
+
+  (func (export "sum") (param i32 i32) (result i32)
+    local.get 0
+    local.get 1
+    i32.add))
+"""
+
+def interruptible_instance(compiler, **settings):
+    store = Store(engine.Universal(compiler(middlewares=[Interruptible()])), **settings)
+
+    return Instance(Module(store, TEST_WAT))
+
+def test_timeout():
+    for compiler in [Cranelift, Singlepass]:
+        instance = interruptible_instance(compiler)
+
+        with pytest.raises(ExecutionTimeout) as context_manager:
+            instance.exports.spin(timeout=0.05)
+
+        exception = context_manager.value
+        assert str(exception) == 'The execution has exceeded its timeout of 0.05 seconds'
+        assert isinstance(exception, RuntimeError)
+
+def test_timeout_not_exceeded():
+    for compiler in [Cranelift, Singlepass]:
+        instance = interruptible_instance(compiler)
+
+        assert instance.exports.sum(1, 2, timeout=10) == 3
+        # The instance can still be called after a timeout.
+        with pytest.raises(ExecutionTimeout):
+            instance.exports.spin(timeout=0.01)
+
+        assert instance.exports.sum(1, 2) == 3
+
+def test_interruptible_shared_by_several_modules():
+    interruptible = Interruptible()
+    modules = []
+
+    def compile(globals):
+        store = Store(engine.Universal(Cranelift(middlewares=[interruptible])))
+        # The other globals of the module shift the index of the
+        # global checked by the interruptible functions, which are
+        # numerous enough for the compilations to overlap.
+        module = Module(store, """
+        (module
+          {}
+          {}
+          (func (export "spin")
+            (loop
+              br 0)))
+        """.format('(global i32 (i32.const 1))' * globals, '(func (loop br 0))' * 500))
+
+        modules.append(module)
+
+    threads = [threading.Thread(target=compile, args=(globals,)) for globals in range(8)]
+
+    for thread in threads:
+        thread.start()
+
+    for thread in threads:
+        thread.join()
+
+    assert len(modules) == len(threads)
+
+    for module in modules:
+        with pytest.raises(ExecutionTimeout):
+            Instance(module).exports.spin(timeout=0.01)
+
+@pytest.mark.skipif(not os.path.isdir('/proc/self/task'), reason='The threads are counted on Linux only')
+def test_timeouts_share_a_thread():
+    instance = interruptible_instance(Cranelift)
+
+    assert instance.exports.sum(1, 2, timeout=60) == 3
+
+    threads = len(os.listdir('/proc/self/task'))
+    started = threading.Barrier(11)
+
+    def spin():
+        instance = interruptible_instance(Cranelift)
+        started.wait()
+
+        with pytest.raises(ExecutionTimeout):
+            instance.exports.spin(timeout=0.5)
+
+    calls = [threading.Thread(target=spin) for _ in range(10)]
+
+    for call in calls:
+        call.start()
+
+    started.wait()
+    time.sleep(0.25)
+
+    # One thread per call, and no thread per timeout.
+    assert len(os.listdir('/proc/self/task')) == threads + len(calls)
+
+    for call in calls:
+        call.join()
+
+def test_earlier_timeout_expires_first():
+    errors = []
+
+    def spin(timeout):
+        try:
+            interruptible_instance(Cranelift).exports.spin(timeout=timeout)
+        except ExecutionTimeout as error:
+            errors.append((timeout, error))
+
+    long_call = threading.Thread(target=spin, args=(0.5,))
+    long_call.start()
+    spin(0.05)
+
+    # The short call has expired while the long one is still running.
+    assert [timeout for timeout, _ in errors] == [0.05]
+
+    long_call.join()
+
+    assert [timeout for timeout, _ in errors] == [0.05, 0.5]
+
+def test_huge_timeout():
+    instance = interruptible_instance(Cranelift)
+
+    assert instance.exports.sum(1, 2, timeout=1e300) == 3
+
+def test_timeout_without_interruptible():
+    instance = Instance(Module(Store(), TEST_WAT))
+
+    with pytest.raises(RuntimeError) as context_manager:
+        instance.exports.sum(1, 2, timeout=1)
+
+    exception = context_manager.value
+    assert str(exception) == 'The function has not been compiled with an `Interruptible` middleware'
+
+def test_invalid_timeout():
+    instance = interruptible_instance(Cranelift)
+
+    with pytest.raises(ValueError) as context_manager:
+        instance.exports.sum(1, 2, timeout=-1)
+
+    exception = context_manager.value
+    assert str(exception) == 'The timeout must be a positive number of seconds'
+
+def test_interrupt_handle():
+    for compiler in [Cranelift, Singlepass]:
+        instance = interruptible_instance(compiler)
+        handle = interrupt_handle(instance)
+
+        timer = threading.Timer(0.05, handle.interrupt)
+        timer.start()
+
+        with pytest.raises(ExecutionTimeout) as context_manager:
+            instance.exports.spin()
+
+        timer.join()
+
+        exception = context_manager.value
+        assert str(exception) == 'The execution has been interrupted'
+
+def test_typed_interrupted():
+    for compiler in [Cranelift, Singlepass]:
+        instance = interruptible_instance(compiler)
+        # The GIL is held during the call, so it is interrupted before.
+        interrupt_handle(instance).interrupt()
+
+        with pytest.raises(ExecutionTimeout) as context_manager:
+            instance.exports.spin.typed('()->')()
+
+        exception = context_manager.value
+        assert str(exception) == 'The execution has been interrupted'
+        assert instance.exports.sum.typed('(ii)->i')(1, 2) == 3
+
+def test_interrupted_is_internal():
+    for compiler in [Cranelift, Singlepass]:
+        module = Module(Store(engine.Universal(compiler(middlewares=[Interruptible()]))), TEST_WAT)
+        instance = Instance(module)
+        interrupt_handle(instance).interrupt()
+
+        assert [export.name for export in module.exports] == ['spin', 'recurse', 'sum']
+        assert 'wasmer_interrupted' not in instance.exports
+        assert instance.exports.keys() == ['spin', 'recurse', 'sum']
+
+        # A pending interruption is neither cloned nor snapshotted.
+        snapshot = instance.snapshot()
+
+        assert instance.clone().exports.sum(1, 2) == 3
+
+        other = Instance(module)
+        other.restore(snapshot)
+
+        assert other.exports.sum(1, 2) == 3
+
+        with pytest.raises(ExecutionTimeout):
+            instance.exports.sum(1, 2)
+
+def test_interrupt_handle_without_interruptible():
+    instance = Instance(Module(Store(), TEST_WAT))
+
+    with pytest.raises(RuntimeError) as context_manager:
+        interrupt_handle(instance)
+
+    exception = context_manager.value
+    assert str(exception) == 'The instance has not been compiled with an `Interruptible` middleware'
+
+def test_other_traps_are_not_timeouts():
+    instance = interruptible_instance(Cranelift)
+
+    with pytest.raises(RuntimeError) as context_manager:
+        instance.exports.recurse(timeout=10)
+
+    assert not isinstance(context_manager.value, ExecutionTimeout)
+
+@pytest.mark.skipif(sys.platform == 'win32', reason='`SIGINT` cannot be sent to the current process')
+def test_keyboard_interrupt():
+    def interrupt():
+        os.kill(os.getpid(), signal.SIGINT)
+
+    for compiler in [Cranelift, Singlepass]:
+        instance = interruptible_instance(compiler, keyboard_interrupt=True)
+
+        for call in [lambda: instance.exports.spin(), lambda: instance.exports.spin.call_batch([()])]:
+            threading.Timer(0.05, interrupt).start()
+
+            with pytest.raises(KeyboardInterrupt):
+                call()
+
+        # The instance can still be called after an interruption.
+        assert instance.exports.sum(1, 2) == 3
+
+@pytest.mark.skipif(sys.platform == 'win32', reason='`SIGINT` cannot be sent to the current process')
+def test_keyboard_interrupt_restores_the_wakeup_fd():
+    instance = interruptible_instance(Cranelift, keyboard_interrupt=True)
+
+    reader, writer = socket.socketpair()
+    writer.setblocking(False)
+    previous_wakeup_fd = signal.set_wakeup_fd(writer.fileno())
+
+    try:
+        assert instance.exports.sum(1, 2) == 3
+        assert signal.set_wakeup_fd(writer.fileno()) == writer.fileno()
+    finally:
+        signal.set_wakeup_fd(previous_wakeup_fd)
+        reader.close()
+        writer.close()
+
+@pytest.mark.skipif(sys.platform == 'win32', reason='`SIGINT` cannot be sent to the current process')
+def test_keyboard_interrupt_is_forwarded_to_the_wakeup_fd():
+    instance = interruptible_instance(Cranelift, keyboard_interrupt=True)
+
+    reader, writer = socket.socketpair()
+    reader.setblocking(False)
+    writer.setblocking(False)
+    previous_wakeup_fd = signal.set_wakeup_fd(writer.fileno())
+
+    try:
+        threading.Timer(0.05, lambda: os.kill(os.getpid(), signal.SIGINT)).start()
+
+        with pytest.raises(KeyboardInterrupt):
+            instance.exports.spin()
+
+        # Give the watcher thread some time to forward the signal.
+        time.sleep(0.05)
+
+        assert signal.SIGINT in reader.recv(64)
+    finally:
+        signal.set_wakeup_fd(previous_wakeup_fd)
+        reader.close()
+        writer.close()
+
+@pytest.mark.skipif(not os.path.isdir('/proc/self/task'), reason='The threads are counted on Linux only')
+def test_keyboard_interrupt_watcher_is_shared():
+    threads = []
+
+    def count_threads():
+        threads.append(len(os.listdir('/proc/self/task')))
+
+    store = Store(engine.Universal(Cranelift(middlewares=[Interruptible()])))
+    module = Module(store, """
+    (module
+      (import "env" "count_threads" (func $count_threads))
+      (func (export "run")
+        call $count_threads))
+    """)
+    instance = Instance(module, {"env": {"count_threads": Function(store, count_threads)}})
+
+    instance.exports.run()
+
+    for _ in range(10):
+        instance.exports.run()
+
+    # The watcher thread is started by the first call only.
+    assert threads[1:] == [len(os.listdir('/proc/self/task'))] * 10
+
+SPINNING_START_WAT = """
+(module
+  (global $started (export "started") (mut i32) (i32.const 0))
+  (func $start
+    global.get $started
+    i32.const 1
+    i32.add
+    global.set $started
+    (loop
+      br 0))
+  (start $start))
+"""
+
+def test_instantiation_timeout():
+    for compiler in [Cranelift, Singlepass]:
+        store = Store(engine.Universal(compiler(middlewares=[Interruptible()])))
+
+        with pytest.raises(ExecutionTimeout) as context_manager:
+            Instance(Module(store, SPINNING_START_WAT), timeout=0.05)
+
+        assert str(context_manager.value) == 'The execution has exceeded its timeout of 0.05 seconds'
+
+def test_instantiation_timeout_not_exceeded():
+    store = Store(engine.Universal(Cranelift(middlewares=[Interruptible()])))
+    module = Module(store, '(module (global $started (export "started") (mut i32) (i32.const 0)) (func $start i32.const 1 global.set $started) (start $start))')
+
+    assert Instance(module, timeout=10).exports.started.value == 1
+    assert InstancePre(module).instantiate(timeout=10).exports.started.value == 1
+
+def test_deferred_start_timeout():
+    store = Store(engine.Universal(Cranelift(middlewares=[Interruptible()])))
+    instance = Instance(Module(store, SPINNING_START_WAT), run_start=False)
+
+    assert instance.exports.started.value == 0
+
+    with pytest.raises(ExecutionTimeout):
+        instance.run_start(timeout=0.05)
+
+    assert instance.exports.started.value == 1
+
+def test_instantiation_timeout_without_interruptible():
+    with pytest.raises(ValueError) as context_manager:
+        Instance(Module(Store(), TEST_WAT), timeout=1)
+
+    assert str(context_manager.value) == (
+        "The instantiation can't time out, "
+        "the module must be compiled with an `Interruptible` middleware"
+    )
+
+WAKEUP_FD_WAT = """
+(module
+  (import "env" "swap" (func $swap))
+  (func (export "run")
+    call $swap))
+"""
+
+def wakeup_fd_instance(swap, **settings):
+    store = Store(engine.Universal(Cranelift(middlewares=[Interruptible()])), **settings)
+
+    return Instance(Module(store, WAKEUP_FD_WAT), {"env": {"swap": Function(store, swap)}})
+
+@pytest.mark.skipif(sys.platform == 'win32', reason='`SIGINT` cannot be sent to the current process')
+def test_keyboard_interrupt_is_opt_in():
+    reader, writer = socket.socketpair()
+    writer.setblocking(False)
+    previous_wakeup_fd = signal.set_wakeup_fd(writer.fileno())
+    wakeup_fds = []
+
+    def swap():
+        wakeup_fds.append(signal.set_wakeup_fd(writer.fileno()))
+
+    try:
+        wakeup_fd_instance(swap).exports.run()
+
+        # The wakeup fd is left to Python during the call.
+        assert wakeup_fds == [writer.fileno()]
+    finally:
+        signal.set_wakeup_fd(previous_wakeup_fd)
+        reader.close()
+        writer.close()
+
+@pytest.mark.skipif(sys.platform == 'win32', reason='`SIGINT` cannot be sent to the current process')
+def test_keyboard_interrupt_keeps_the_wakeup_fd_set_during_the_call():
+    reader, writer = socket.socketpair()
+    writer.setblocking(False)
+    previous_wakeup_fd = signal.set_wakeup_fd(-1)
+    wakeup_fds = []
+
+    def swap():
+        wakeup_fds.append(signal.set_wakeup_fd(writer.fileno()))
+
+    try:
+        wakeup_fd_instance(swap, keyboard_interrupt=True).exports.run()
+
+        # The wakeup fd of the call has been replaced by the host
+        # function, and is not restored over it.
+        assert wakeup_fds[0] not in (-1, writer.fileno())
+        assert signal.set_wakeup_fd(-1) == writer.fileno()
+    finally:
+        signal.set_wakeup_fd(previous_wakeup_fd)
+        reader.close()
+        writer.close()
@@ -0,0 +1,125 @@
+from wasmer import Store, Module, Instance, Linker, ImportObject, Function, Memory, MemoryType, Global, Value
+import pytest
+
+ALLOCATOR_WAT = """
+(module
+  (memory (export "memory") 1)
+  (global $next (mut i32) (i32.const 0))
+  (func (export "alloc") (param i32) (result i32)
+    global.get $next
+    global.get $next
+    local.get 0
+    i32.add
+    global.set $next))
+"""
+
+STRINGS_WAT = """
+(module
+  (import "allocator" "memory" (memory 1))
+  (import "allocator" "alloc" (func $alloc (param i32) (result i32)))
+  (func (export "new_string") (param i32) (result i32)
+    local.get 0
+    call $alloc))
+"""
+
+APPLICATION_WAT = """
+(module
+  (import "env" "log" (func $log (param i32)))
+  (import "allocator" "alloc" (func $alloc (param i32) (result i32)))
+  (import "strings" "new_string" (func $new_string (param i32) (result i32)))
+  (func (export "run") (result i32)
+    i32.const 8
+    call $alloc
+    drop
+    i32.const 4
+    call $new_string
+    call $log
+    i32.const 1
+    call $alloc))
+"""
+
+def test_instantiate_chain():
+    store = Store()
+    logs = []
+
+    def log(x: int):
+        logs.append(x)
+
+    linker = Linker()
+    linker.define("env", "log", Function(store, log))
+
+    allocator = linker.instantiate(Module(store, ALLOCATOR_WAT), "allocator")
+    linker.instantiate(Module(store, STRINGS_WAT), "strings")
+    application = linker.instantiate(Module(store, APPLICATION_WAT))
+
+    assert isinstance(application, Instance)
+    assert application.exports.run() == 12
+    assert logs == [8]
+    # All the modules share the same allocator.
+    assert allocator.exports.alloc(0) == 13
+
+    assert list(linker.import_object) == ["allocator", "env", "strings"]
+    assert linker.import_object.names("allocator") == ["alloc", "memory"]
+
+def test_define_instance():
+    store = Store()
+    allocator = Instance(Module(store, ALLOCATOR_WAT))
+
+    linker = Linker()
+    linker.define_instance("allocator", allocator)
+    strings = linker.instantiate(Module(store, STRINGS_WAT))
+
+    assert strings.exports.new_string(3) == 0
+    assert allocator.exports.alloc(0) == 3
+
+def test_define_replaces():
+    store = Store()
+    module = Module(store, '(module (import "env" "answer" (global i32)) (func (export "get") (result i32) global.get 0))')
+
+    linker = Linker()
+    linker.define("env", "answer", Global(store, Value.i32(1)))
+    linker.define("env", "answer", Global(store, Value.i32(42)))
+
+    assert linker.instantiate(module).exports.get() == 42
+
+def test_define_invalid_type():
+    with pytest.raises(TypeError) as context_manager:
+        Linker().define("env", "answer", 42)
+
+    exception = context_manager.value
+    assert str(exception) == "`Linker` cannot define the given type `int`"
+
+def test_missing_import():
+    module = Module(Store(), STRINGS_WAT)
+    module.name = 'strings'
+
+    with pytest.raises(RuntimeError) as context_manager:
+        Linker().instantiate(module)
+
+    exception = context_manager.value
+    assert str(exception).startswith('Failed to instantiate module `strings`: Error while importing "allocator"')
+
+def test_allow_missing_imports():
+    store = Store()
+
+    linker = Linker()
+    linker.define("allocator", "memory", Memory(store, MemoryType(minimum=1)))
+    instance = linker.instantiate(Module(store, STRINGS_WAT), allow_missing_imports=True)
+
+    with pytest.raises(RuntimeError) as context_manager:
+        instance.exports.new_string(1)
+
+    exception = context_manager.value
+    assert str(exception) == 'Missing import `allocator.alloc` has been called'
+
+def test_import_object_is_a_copy():
+    store = Store()
+
+    linker = Linker()
+    linker.define("env", "memory", Memory(store, MemoryType(minimum=1)))
+
+    import_object = linker.import_object
+    assert isinstance(import_object, ImportObject)
+
+    import_object.register("other", {})
+    assert list(linker.import_object) == ["env"]
@@ -1,4 +1,4 @@
-from wasmer import Instance, Module, Store, Memory, MemoryType, Buffer, Uint8Array, Int8Array, Uint16Array, Int16Array, Uint32Array, Int32Array, Uint64Array, Int64Array, Float32Array, Float64Array
+from wasmer import Instance, Module, Store, Memory, MemoryType, Tunables, Buffer, Uint8Array, Int8Array, Uint16Array, Int16Array, Uint32Array, Int32Array, Uint64Array, Int64Array, Float32Array, Float64Array
 import ctypes
 import gc
 import inspect
@@ -30,6 +30,24 @@ def test_type():
     assert type.shared == False
     assert str(type) == 'MemoryType(minimum: 17, maximum: None, shared: false)'
 
+def test_type_of_a_host_memory():
+    for (minimum, maximum, shared) in [(1, None, False), (1, 3, False), (2, 4, True)]:
+        memory_type = Memory(Store(), MemoryType(minimum, maximum, shared)).type
+
+        assert memory_type == MemoryType(minimum, maximum, shared)
+        assert memory_type.minimum == minimum
+        assert memory_type.maximum == maximum
+        assert memory_type.shared == shared
+
+def test_type_after_grow():
+    memory = Memory(Store(), MemoryType(minimum=1, maximum=3))
+    memory.grow(2)
+
+    assert memory.type == MemoryType(minimum=3, maximum=3)
+
+    with pytest.raises(RuntimeError):
+        memory.grow(1)
+
 def test_size():
     assert instance().exports.memory.size == 17
 
@@ -244,6 +262,84 @@ def test_memory_grow_too_much():
         'The memory could not grow: current size 17 pages, requested increase: 100000 pages'
     )
 
+GROW_WAT = """
+(module
+  (memory (export "memory") 1 4)
+  (func (export "grow") (param i32) (result i32)
+    local.get 0
+    memory.grow))
+"""
+
+def test_grow_callback():
+    instance = Instance(Module(Store(), GROW_WAT))
+    sizes = []
+    instance.exports.memory.add_grow_callback(lambda old_size, new_size: sizes.append((old_size, new_size)))
+
+    # The callback is registered on the memory, not on a `Memory` object.
+    instance.exports.memory.grow(1)
+    assert instance.exports.grow(1) == 2
+
+    # Failed and empty growths are not notified.
+    assert instance.exports.grow(10) == -1
+    instance.exports.memory.grow(0)
+
+    assert sizes == [(1, 2), (2, 3)]
+
+def test_grow_callback_of_a_host_memory():
+    for tunables in [None, Tunables(limiter=object())]:
+        memory = Memory(Store(tunables=tunables), MemoryType(minimum=1))
+        sizes = []
+        memory.add_grow_callback(lambda old_size, new_size: sizes.append((old_size, new_size)))
+        memory.grow(2)
+
+        assert sizes == [(1, 3)]
+
+def test_remove_grow_callback():
+    class Callbacks:
+        def __init__(self):
+            self.calls = 0
+
+        def grown(self, old_size, new_size):
+            self.calls += 1
+
+    memory = Memory(Store(), MemoryType(minimum=1))
+    callbacks = Callbacks()
+    memory.add_grow_callback(callbacks.grown)
+    memory.grow(1)
+
+    # Bound methods are compared by value.
+    memory.remove_grow_callback(callbacks.grown)
+    memory.grow(1)
+
+    assert callbacks.calls == 1
+
+    with pytest.raises(ValueError) as context_manager:
+        memory.remove_grow_callback(callbacks.grown)
+
+    assert str(context_manager.value) == 'The callback is not registered on the memory'
+
+def test_grow_callback_raising():
+    import sys
+
+    def callback(old_size, new_size):
+        raise ValueError('cannot refresh')
+
+    unraisables = []
+    unraisablehook = sys.unraisablehook
+    sys.unraisablehook = unraisables.append
+
+    try:
+        instance = Instance(Module(Store(), GROW_WAT))
+        instance.exports.memory.add_grow_callback(callback)
+
+        assert instance.exports.grow(1) == 1
+    finally:
+        sys.unraisablehook = unraisablehook
+
+    assert instance.exports.memory.size == 2
+    assert len(unraisables) == 1
+    assert str(unraisables[0].exc_value) == 'cannot refresh'
+
 def test_memory_buffer_memoryview():
     memory = instance().exports.memory
 
@@ -347,3 +443,291 @@ def test_memory_views_length():
     assert len(memory.int64_view()) == PAGE_SIZE/8
     assert len(memory.float32_view()) == PAGE_SIZE/4
     assert len(memory.float64_view()) == PAGE_SIZE/8
+
+def test_memory_memoryview():
+    memory = instance().exports.memory
+
+    int8 = memory.int8_view()
+    int8[0] = 1
+    int8[1] = 2
+    int8[2] = 3
+
+    memory_view = memoryview(memory)
+
+    assert memory_view.nbytes == memory.data_size
+    assert memory_view.readonly == False
+    assert memory_view.format == 'B'
+    assert memory_view.shape == (memory.data_size,)
+    assert memory_view[0:3].tolist() == [1, 2, 3]
+
+    # Writes are visible from the memory, without copies.
+    memory_view[3] = 4
+    assert int8[3] == 4
+
+def test_memory_memoryview_keeps_object_alive():
+    memory_view = memoryview(instance().exports.memory)
+
+    gc.collect()
+    memory_view[0] = 42
+
+    assert memory_view[0] == 42
+
+def test_signed_views_read_negative_values():
+    memory = instance().exports.memory
+
+    uint8 = memory.uint8_view()
+    uint8[0:8] = [0xff] * 8
+
+    assert memory.int8_view()[0] == -1
+    assert memory.int16_view()[0] == -1
+    assert memory.int32_view()[0] == -1
+    assert memory.int64_view()[0] == -1
+
+    int16 = memory.int16_view()
+    int16[0] = -2
+
+    assert uint8[0:2] == [0xfe, 0xff]
+    assert int16[0] == -2
+
+def test_float_views_read_guest_values():
+    store = Store()
+    module = Module(
+        store,
+        """
+        (module
+          (memory (export "memory") 1)
+          (func (export "write")
+            i32.const 0
+            f32.const 1.5
+            f32.store
+            i32.const 8
+            f64.const -0.25
+            f64.store))
+        """
+    )
+    instance = Instance(module)
+    instance.exports.write()
+
+    memory = instance.exports.memory
+
+    assert memory.float32_view()[0] == 1.5
+    assert memory.float64_view()[1] == -0.25
+
+    # The offset is a number of elements, not of bytes.
+    float64 = memory.float64_view(offset=2)
+    float64[0] = 3.125
+
+    assert memory.float64_view()[2] == 3.125
+
+def test_memory_read_write():
+    memory = instance().exports.memory
+
+    memory.write(10, b'hello')
+
+    assert memory.read(10, 5) == b'hello'
+    assert memory.uint8_view()[10:15] == list(b'hello')
+
+    for buffer in [bytearray(b'world'), memoryview(b'world'), memoryview(b'xwxoxrxlxdx')[1::2]]:
+        memory.write(20, buffer)
+
+        assert memory.read(20, 5) == b'world'
+
+def test_memory_read_write_large():
+    memory = instance().exports.memory
+    data = os.urandom(memory.data_size)
+
+    memory.write(0, data)
+
+    assert memory.read(0, memory.data_size) == data
+
+def test_memory_write_overlapping():
+    memory = instance().exports.memory
+    memory.write(0, b'abcdef')
+
+    memory.write(2, memoryview(memory)[0:4])
+
+    assert memory.read(0, 6) == b'ababcd'
+
+def test_memory_read_write_out_of_bound():
+    memory = instance().exports.memory
+    data_size = memory.data_size
+
+    assert memory.read(data_size, 0) == b''
+
+    with pytest.raises(IndexError) as context_manager:
+        memory.read(data_size - 1, 2)
+
+    exception = context_manager.value
+    assert str(exception) == f'Out of bound: Reading or writing 2 bytes at offset {data_size - 1} exceeds the memory size of {data_size} bytes'
+
+    with pytest.raises(IndexError):
+        memory.write(data_size, b'a')
+
+    with pytest.raises(TypeError):
+        memory.write(0, 'not a buffer')
+
+def test_memory_buffer_protocol_is_writable_without_copy():
+    memory = instance().exports.memory
+
+    # `ctypes.from_buffer` requires a writable buffer and shares the
+    # memory, like `numpy.frombuffer` does.
+    floats = (ctypes.c_float * 4).from_buffer(memory, 16)
+    floats[1] = 1.5
+
+    assert memory.float32_view(offset=4)[1] == 1.5
+
+    memory.float32_view(offset=4)[2] = -2.0
+
+    assert floats[2] == -2.0
+
+def test_memory_numpy_frombuffer():
+    numpy = pytest.importorskip('numpy')
+    memory = instance().exports.memory
+
+    array = numpy.frombuffer(memory, dtype=numpy.int32, offset=8, count=4)
+    array[:] = [1, -2, 3, -4]
+
+    assert array.flags.writeable
+    assert memory.int32_view(offset=2)[0:4] == [1, -2, 3, -4]
+
+    memory.int32_view()[2] = 42
+
+    assert array[0] == 42
+
+def test_memory_snapshot_restore():
+    memory = instance().exports.memory
+    memory.write(0, b'initial state')
+
+    snapshot = memory.snapshot()
+
+    assert isinstance(snapshot, bytes)
+    assert len(snapshot) == memory.data_size
+
+    for _ in range(3):
+        memory.write(0, b'modified')
+        memory.grow(1)
+        memory.write(memory.data_size - 1, b'x')
+
+        memory.restore(snapshot)
+
+        assert memory.read(0, 13) == b'initial state'
+        assert memory.read(len(snapshot), memory.data_size - len(snapshot)) == bytes(memory.data_size - len(snapshot))
+
+def test_memory_restore_grows():
+    store = Store()
+    small = Memory(store, MemoryType(minimum=1))
+    large = Memory(store, MemoryType(minimum=2))
+    large.write(65536, b'abc')
+
+    small.restore(large.snapshot())
+
+    assert small.size == 2
+    assert small.read(65536, 3) == b'abc'
+
+def test_memory_restore_invalid_snapshot():
+    memory = Memory(Store(), MemoryType(minimum=1))
+
+    with pytest.raises(ValueError) as context_manager:
+        memory.restore(b'abc')
+
+    exception = context_manager.value
+    assert str(exception) == 'The snapshot size must be a multiple of the page size (65536 bytes), got 3 bytes'
+
+def test_memory_read_write_string():
+    memory = instance().exports.memory
+
+    assert memory.write_string(0, 'Hello, 世界') == 13
+    assert memory.read_string(0, 13) == 'Hello, 世界'
+    assert memory.read_string(0, 5) == 'Hello'
+
+    assert memory.write_string(100, 'Hé', encoding='latin-1') == 2
+    assert memory.read_string(100, 2, encoding='latin-1') == 'Hé'
+
+def test_memory_read_nul_terminated_string():
+    memory = instance().exports.memory
+    memory.write(0, b'abc\0def\0')
+
+    assert memory.read_string(0) == 'abc'
+    assert memory.read_string(4) == 'def'
+    assert memory.read_string(3) == ''
+
+    memory.write(memory.data_size - 3, b'xyz')
+
+    with pytest.raises(IndexError) as context_manager:
+        memory.read_string(memory.data_size - 3)
+
+    exception = context_manager.value
+    assert str(exception) == f'Out of bound: No NUL byte found after offset {memory.data_size - 3}'
+
+def test_memory_read_invalid_string():
+    memory = instance().exports.memory
+    memory.write(0, b'\xff\xfe')
+
+    with pytest.raises(UnicodeDecodeError):
+        memory.read_string(0, 2)
+
+class Pair(ctypes.Structure):
+    _fields_ = [('first', ctypes.c_uint16), ('second', ctypes.c_int64)]
+
+def test_memory_data_ptr():
+    memory = instance().exports.memory
+
+    assert memory.data_ptr() == ctypes.addressof(ctypes.c_uint8.from_buffer(memory))
+
+    pair = Pair.from_address(memory.data_ptr() + 16)
+    pair.first = 7
+    pair.second = -7
+
+    assert memory.uint16_view(offset=8)[0] == 7
+    assert memory.int64_view(offset=3)[0] == -7
+
+def test_memory_ctypes_structure_from_buffer():
+    memory = instance().exports.memory
+    memory.int64_view(offset=5)[0] = 42
+
+    pair = Pair.from_buffer(memory, 32)
+
+    assert pair.second == 42
+
+    pair.first = 3
+
+    assert memory.uint16_view(offset=16)[0] == 3
+
+def test_close():
+    memory = Memory(Store(), MemoryType(minimum=1))
+    view = memory.uint8_view()
+    view[0] = 42
+
+    memory.close()
+    memory.close()
+
+    assert view[0] == 42
+
+    with pytest.raises(ValueError) as context_manager:
+        memory.size
+
+    assert str(context_manager.value) == 'The memory is closed'
+
+def test_context_manager():
+    with Memory(Store(), MemoryType(minimum=1)) as memory:
+        assert memory.size == 1
+
+    with pytest.raises(ValueError):
+        memory.uint8_view()
+
+def test_closed_memory_cannot_be_imported():
+    store = Store()
+    module = Module(
+        store,
+        """
+        (module
+          (import "env" "memory" (memory 1)))
+        """
+    )
+    memory = Memory(store, MemoryType(minimum=1))
+    memory.close()
+
+    with pytest.raises(ValueError) as context_manager:
+        Instance(module, {"env": {"memory": memory}})
+
+    assert str(context_manager.value) == 'The memory is closed'
@@ -0,0 +1,99 @@
+from wasmer import engine, get_remaining_points, set_remaining_points, Store, Module, Instance, Metering, CompileError
+from wasmer_compiler_cranelift import Compiler as Cranelift
+from wasmer_compiler_singlepass import Compiler as Singlepass
+import pytest
+
+TEST_WAT = """
+(module
+  (func (export "sum") (param i32 i32) (result i32)
+    local.get 0
+    local.get 1
+    i32.add))
+"""
+
+def metered_instance(compiler, metering):
+    store = Store(engine.Universal(compiler(middlewares=[metering])))
+
+    return Instance(Module(store, TEST_WAT))
+
+def test_metering_default_cost():
+    for compiler in [Cranelift, Singlepass]:
+        instance = metered_instance(compiler, Metering(10))
+
+        assert get_remaining_points(instance) == 10
+        assert instance.exports.sum(1, 2) == 3
+        # `local.get`, `local.get`, `i32.add` and `end`.
+        assert get_remaining_points(instance) == 6
+
+def test_metering_cost_function():
+    def cost_function(operator):
+        return 3 if operator == 'I32Add' else 0
+
+    instance = metered_instance(Cranelift, Metering(10, cost_function))
+
+    assert instance.exports.sum(1, 2) == 3
+    assert get_remaining_points(instance) == 7
+
+def test_metering_exhausted():
+    instance = metered_instance(Cranelift, Metering(5))
+
+    assert instance.exports.sum(1, 2) == 3
+
+    with pytest.raises(RuntimeError):
+        instance.exports.sum(1, 2)
+
+    assert get_remaining_points(instance) == 0
+
+def test_set_remaining_points():
+    instance = metered_instance(Cranelift, Metering(5))
+
+    assert instance.exports.sum(1, 2) == 3
+
+    set_remaining_points(instance, 100)
+
+    assert get_remaining_points(instance) == 100
+    assert instance.exports.sum(1, 2) == 3
+    assert get_remaining_points(instance) == 96
+
+def test_not_metered():
+    instance = Instance(Module(Store(), TEST_WAT))
+
+    with pytest.raises(RuntimeError) as context_manager:
+        get_remaining_points(instance)
+
+    exception = context_manager.value
+    assert str(exception) == 'The instance has not been compiled with a `Metering` middleware'
+
+    with pytest.raises(RuntimeError):
+        set_remaining_points(instance, 1)
+
+def test_metering_cost_function_error():
+    def cost_function(operator):
+        raise ValueError('oops')
+
+    for compiler in [Cranelift, Singlepass]:
+        with pytest.raises(CompileError) as context_manager:
+            metered_instance(compiler, Metering(10, cost_function))
+
+        assert 'oops' in str(context_manager.value)
+
+def test_metering_several_modules():
+    for compiler in [Cranelift, Singlepass]:
+        store = Store(engine.Universal(compiler(middlewares=[Metering(10)])))
+        instance = Instance(Module(store, TEST_WAT))
+        other_instance = Instance(Module(store, """
+        (module
+          (global $counter (mut i32) (i32.const 0))
+          (func (export "increment") (result i32)
+            global.get $counter
+            i32.const 1
+            i32.add
+            global.set $counter
+            global.get $counter))
+        """))
+
+        assert instance.exports.sum(1, 2) == 3
+        assert other_instance.exports.increment() == 1
+        assert get_remaining_points(instance) == 6
+        # `global.get`, `i32.const`, `i32.add`, `global.set`, `global.get` and `end`.
+        assert get_remaining_points(other_instance) == 4
@@ -0,0 +1,61 @@
+from wasmer import engine, Store, Module, Instance, Metering, Middleware, get_remaining_points
+from wasmer_compiler_cranelift import Compiler as Cranelift
+from wasmer_compiler_singlepass import Compiler as Singlepass
+import pytest
+
+TEST_WAT = """
+(module
+  (func $f (export "f") (param i32) (result i32)
+    local.get 0
+    call $g)
+  (func $g (param i32) (result i32)
+    local.get 0
+    i32.const 1
+    i32.add))
+"""
+
+def test_middleware_feed():
+    for compiler in [Cranelift, Singlepass]:
+        operators = []
+
+        def feed(function_index, operator):
+            operators.append((function_index, operator))
+
+        store = Store(engine.Universal(compiler(middlewares=[Middleware(feed)])))
+        instance = Instance(Module(store, TEST_WAT))
+
+        assert instance.exports.f(41) == 42
+        assert sorted(operators) == [
+            (0, 'Call'),
+            (0, 'End'),
+            (0, 'LocalGet'),
+            (1, 'End'),
+            (1, 'I32Add'),
+            (1, 'I32Const'),
+            (1, 'LocalGet'),
+        ]
+
+def test_middleware_error():
+    def feed(function_index, operator):
+        raise ValueError('oops')
+
+    store = Store(engine.Universal(Cranelift(middlewares=[Middleware(feed)])))
+
+    with pytest.raises(RuntimeError) as context_manager:
+        Module(store, TEST_WAT)
+
+    exception = context_manager.value
+    assert 'oops' in str(exception)
+
+def test_middleware_with_metering():
+    operators = []
+
+    def feed(function_index, operator):
+        operators.append(operator)
+
+    store = Store(engine.Universal(Cranelift(middlewares=[Middleware(feed), Metering(100)])))
+    instance = Instance(Module(store, TEST_WAT))
+
+    assert instance.exports.f(41) == 42
+    assert len(operators) == 7
+    assert get_remaining_points(instance) == 93
@@ -1,8 +1,11 @@
 import wasmer
 from wasmer import Store, Module, ExportType, ImportType, FunctionType, MemoryType, GlobalType, TableType, Type
 from enum import IntEnum
+from concurrent.futures import ThreadPoolExecutor
 import os
+import pickle
 import pytest
+import threading
 
 here = os.path.dirname(os.path.realpath(__file__))
 TEST_BYTES = open(here + '/tests.wasm', 'rb').read()
@@ -14,6 +17,29 @@ def test_validate():
 def test_validate_invalid():
     assert not Module.validate(Store(), INVALID_TEST_BYTES)
 
+def test_validate_diagnostics():
+    result = Module.validate(Store(), TEST_BYTES)
+
+    assert result.valid
+    assert result.message == None
+    assert result.offset == None
+    assert result.section == None
+
+    result = Module.validate(Store(), INVALID_TEST_BYTES)
+
+    assert result.valid == False
+    assert isinstance(result.message, str)
+    assert 'offset' not in result.message
+    assert isinstance(result.offset, int)
+    assert result.section == 'type'
+
+def test_validate_invalid_type():
+    result = Module.validate(Store(), 42)
+
+    assert not result
+    assert result.message == '`Module.validate` accepts Wasm bytes'
+    assert result.offset == None
+
 def test_compile_bytes():
     assert isinstance(Module(Store(), TEST_BYTES), Module)
 
@@ -24,6 +50,11 @@ def test_failed_to_compile():
     with pytest.raises(RuntimeError) as context_manager:
         Module(Store(), INVALID_TEST_BYTES)
 
+def test_compile_error():
+    for source in [INVALID_TEST_BYTES, '(module (func (result i32)))']:
+        with pytest.raises(wasmer.CompileError):
+            Module(Store(), source)
+
 def test_name_some():
     assert Module(Store(), '(module $moduleName)').name == 'moduleName'
 
@@ -111,12 +142,31 @@ def test_imports():
     assert imports[3].type.maximum == 4
     assert imports[3].type.shared == False
 
+def test_import_export_type_str():
+    module = Module(
+        Store(),
+        """
+        (module
+          (import "ns" "function" (func (param i32)))
+          (memory (export "memory") 1))
+        """
+    )
+
+    assert str(module.imports[0]) == 'ImportType(module: "ns", name: "function", type: FunctionType(params: [I32], results: []))'
+    assert str(module.exports[0]) == 'ExportType(name: "memory", type: MemoryType(minimum: 1, maximum: None, shared: false))'
+
 def test_custom_section():
     module = Module(Store(), open(here + '/custom_sections.wasm', 'rb').read())
     assert module.custom_sections('easter_egg') == [b'Wasmer']
     assert module.custom_sections('hello') == [b'World!']
     assert module.custom_sections('foo') == []
 
+def test_custom_section_names():
+    module = Module(Store(), open(here + '/custom_sections.wasm', 'rb').read())
+    assert module.custom_section_names == ['hello', 'easter_egg']
+
+    assert Module(Store(), '(module)').custom_section_names == []
+
 def test_serialize():
     assert type(Module(Store(), "(module)").serialize()) == bytes
 
@@ -140,3 +190,293 @@ def test_deserialize():
     assert isinstance(exports[0].type, FunctionType)
     assert exports[0].type.params == [Type.I32, Type.I64]
     assert exports[0].type.results == []
+
+def test_pickle():
+    module = Module(Store(), '(module (func (export "answer") (result i32) i32.const 42))')
+
+    for protocol in range(pickle.HIGHEST_PROTOCOL + 1):
+        unpickled_module = pickle.loads(pickle.dumps(module, protocol))
+
+        assert isinstance(unpickled_module, Module)
+        assert wasmer.Instance(unpickled_module).exports.answer() == 42
+
+def test_serialize_to_file(tmp_path):
+    module = Module(Store(), "(module)")
+    path = tmp_path / 'module.wasmu'
+    module.serialize_to_file(path)
+
+    assert open(path, 'rb').read() == module.serialize()
+
+def test_deserialize_from_file(tmp_path):
+    store = Store()
+    path = tmp_path / 'module.wasmu'
+
+    Module(
+        store,
+        """
+        (module
+          (func (export "function") (param i32 i64)))
+        """
+    ).serialize_to_file(path)
+
+    module = Module.deserialize_from_file(store, path)
+    exports = module.exports
+
+    assert len(module.exports) == 1
+    assert exports[0].name == "function"
+    assert exports[0].type.params == [Type.I32, Type.I64]
+
+def test_deserialize_from_file_missing(tmp_path):
+    with pytest.raises(RuntimeError):
+        Module.deserialize_from_file(Store(), tmp_path / 'missing.wasmu')
+
+def test_deserialize_from_bytes_like():
+    store = Store()
+    serialized_module = Module(store, '(module (func (export "function")))').serialize()
+
+    for bytes_like in [bytearray(serialized_module), memoryview(serialized_module)]:
+        module = Module.deserialize(store, bytes_like)
+
+        assert module.exports[0].name == "function"
+
+def test_deserialize_invalid_type():
+    with pytest.raises(TypeError):
+        Module.deserialize(Store(), 42)
+
+def test_cache(tmp_path):
+    store = Store()
+    cache = wasmer.Cache(tmp_path)
+
+    module = Module(store, TEST_BYTES, cache=cache)
+    assert len(os.listdir(tmp_path)) == 1
+
+    cached_module = Module(store, TEST_BYTES, cache=cache)
+    assert len(os.listdir(tmp_path)) == 1
+    assert cached_module.serialize() == module.serialize()
+
+    Module(store, '(module)', cache=cache)
+    assert len(os.listdir(tmp_path)) == 2
+
+def test_cache_key_depends_on_compiler(tmp_path):
+    cache = wasmer.Cache(tmp_path)
+
+    from wasmer_compiler_cranelift import Compiler as Cranelift
+    from wasmer_compiler_singlepass import Compiler as Singlepass
+
+    Module(Store(wasmer.engine.Universal(Cranelift)), '(module)', cache=cache)
+    Module(Store(wasmer.engine.Universal(Singlepass)), '(module)', cache=cache)
+
+    assert len(os.listdir(tmp_path)) == 2
+
+def test_cache_key_depends_on_compiler_configuration(tmp_path):
+    cache = wasmer.Cache(tmp_path)
+
+    from wasmer_compiler_cranelift import Compiler
+
+    engines = [
+        wasmer.engine.Universal(Compiler()),
+        wasmer.engine.Universal(Compiler(opt_level="none")),
+        wasmer.engine.Universal(Compiler(canonicalize_nans=True)),
+        wasmer.engine.Universal(Compiler(threads=2)),
+        wasmer.engine.Universal(Compiler(middlewares=[wasmer.Metering(10)])),
+        wasmer.engine.Universal(Compiler(middlewares=[wasmer.Metering(20)])),
+        wasmer.engine.Universal(Compiler(middlewares=[wasmer.Interruptible()])),
+        wasmer.engine.Universal(Compiler(middlewares=[wasmer.Tracing()])),
+        wasmer.engine.Universal(Compiler(), features=wasmer.engine.Features(simd=False)),
+    ]
+
+    for engine in engines:
+        Module(Store(engine), '(module)', cache=cache)
+
+    assert len(os.listdir(tmp_path)) == len(engines)
+
+def test_cache_does_not_bypass_middlewares(tmp_path):
+    cache = wasmer.Cache(tmp_path)
+
+    from wasmer_compiler_cranelift import Compiler
+
+    Module(Store(wasmer.engine.Universal(Compiler())), TEST_BYTES, cache=cache)
+
+    store = Store(wasmer.engine.Universal(Compiler(middlewares=[wasmer.Metering(10)])))
+    instance = wasmer.Instance(Module(store, TEST_BYTES, cache=cache))
+
+    assert wasmer.get_remaining_points(instance) == 10
+    assert len(os.listdir(tmp_path)) == 2
+
+def test_cache_skips_python_middlewares(tmp_path):
+    cache = wasmer.Cache(tmp_path)
+
+    from wasmer_compiler_cranelift import Compiler
+
+    middlewares = [
+        wasmer.Metering(10, lambda operator: 1),
+        wasmer.Middleware(lambda function_index, operator: None),
+    ]
+
+    for middleware in middlewares:
+        store = Store(wasmer.engine.Universal(Compiler(middlewares=[middleware])))
+        Module(store, TEST_BYTES, cache=cache)
+
+    assert os.listdir(tmp_path) == []
+
+def test_progress():
+    from wasmer_compiler_cranelift import Compiler as Cranelift
+    from wasmer_compiler_singlepass import Compiler as Singlepass
+
+    for compiler in [Cranelift, Singlepass]:
+        progress = []
+        Module(
+            Store(wasmer.engine.Universal(compiler)),
+            TEST_BYTES,
+            progress=lambda compiled, total: progress.append((compiled, total))
+        )
+        total = progress[0][1]
+
+        assert total > 1
+        assert progress == [(compiled, total) for compiled in range(1, total + 1)]
+
+def test_progress_exception():
+    def fail(compiled, total):
+        raise ValueError(compiled)
+
+    with pytest.raises(ValueError) as context_manager:
+        Module(Store(), '(module (func) (func))', progress=fail)
+
+    assert str(context_manager.value) == '1'
+
+def test_progress_is_per_compilation():
+    store = Store()
+    progress = []
+    Module(store, '(module (func))', progress=lambda *arguments: progress.append(arguments))
+    Module(store, '(module (func) (func))')
+    Module(store, '(module (func) (func))', progress=lambda *arguments: progress.append(arguments))
+
+    assert progress == [(1, 1), (1, 2), (2, 2)]
+
+def compiler_thread_name():
+    with open(f'/proc/self/task/{threading.get_native_id()}/comm') as comm:
+        return comm.read().strip()
+
+@pytest.mark.skipif(not os.path.exists('/proc/self/task'), reason='The thread names are read from `/proc`')
+def test_compiler_threads():
+    from wasmer_compiler_cranelift import Compiler as Cranelift
+    from wasmer_compiler_singlepass import Compiler as Singlepass
+
+    wat = '(module %s)' % ' '.join(['(func)'] * 64)
+
+    for compiler in [Cranelift, Singlepass]:
+        for threads in [1, 2]:
+            thread_ids, thread_names = set(), set()
+
+            def progress(compiled, total):
+                thread_ids.add(threading.get_native_id())
+                thread_names.add(compiler_thread_name())
+
+            Module(Store(wasmer.engine.Universal(compiler(threads=threads))), wat, progress=progress)
+
+            assert 1 <= len(thread_ids) <= threads
+            # The names are truncated to 15 characters.
+            assert thread_names == {'wasmer-compiler'}
+
+def test_compiler_invalid_threads():
+    from wasmer_compiler_cranelift import Compiler as Cranelift
+    from wasmer_compiler_singlepass import Compiler as Singlepass
+
+    for compiler in [Cranelift, Singlepass]:
+        with pytest.raises(ValueError) as context_manager:
+            compiler(threads=0)
+
+        assert str(context_manager.value) == 'The number of threads must be positive'
+
+def test_compile_report(tmp_path):
+    store = Store()
+    report = Module(store, TEST_BYTES).compile_report
+
+    assert report.compile_time > 0
+    assert report.code_size > 0
+    assert report.functions == len([export for export in Module(store, TEST_BYTES).exports if isinstance(export.type, FunctionType)])
+    assert report.code_size > Module(store, '(module (func))').compile_report.code_size
+
+    cache = wasmer.Cache(tmp_path)
+    Module(store, TEST_BYTES, cache=cache)
+
+    assert Module(store, TEST_BYTES, cache=cache).compile_report.code_size == report.code_size
+
+def test_compile_report_of_a_deserialized_module():
+    store = Store()
+
+    assert Module.deserialize(store, Module(store, '(module)').serialize()).compile_report is None
+
+def test_file():
+    import io
+
+    for file in [open(here + '/tests.wasm', 'rb'), io.BytesIO(TEST_BYTES), io.StringIO('(module)')]:
+        with file:
+            assert isinstance(Module(Store(), file), Module)
+
+def test_large_file():
+    import io
+
+    wat = '(module %s)' % ' '.join(['(func (result i32) i32.const 42)'] * 10_000)
+
+    # Larger than a chunk.
+    assert len(wat) > 64 * 1024
+    assert Module(Store(), io.StringIO(wat)).compile_report.functions == 10_000
+
+def test_invalid_file():
+    class File:
+        def read(self, size):
+            return 42
+
+    with pytest.raises(TypeError) as context_manager:
+        Module(Store(), File())
+
+    assert str(context_manager.value) == 'The `read` method of the file must return bytes or a string'
+
+def test_from_file(tmp_path):
+    store = Store()
+    module = Module.from_file(store, here + '/tests.wasm')
+
+    assert module.serialize() == Module(store, TEST_BYTES).serialize()
+
+    path = tmp_path / 'module.wat'
+    path.write_text('(module (func (export "answer") (result i32) i32.const 42))')
+
+    assert [export.name for export in Module.from_file(store, path).exports] == ['answer']
+
+def test_from_file_with_a_cache(tmp_path):
+    cache = wasmer.Cache(tmp_path / 'cache')
+    Module.from_file(Store(), here + '/tests.wasm', cache=cache)
+
+    assert len(os.listdir(tmp_path / 'cache')) == 1
+
+def test_cache_store_error(tmp_path):
+    import shutil
+
+    cache = wasmer.Cache(tmp_path / 'cache')
+    shutil.rmtree(tmp_path / 'cache')
+
+    # The module is compiled, but not stored.
+    with pytest.warns(RuntimeWarning) as warnings:
+        module = Module(Store(), TEST_BYTES, cache=cache)
+
+    assert len(warnings) == 1
+    assert 'Failed to store the module in the cache' in str(warnings[0].message)
+    assert module.serialize() == Module(Store(), TEST_BYTES).serialize()
+
+def test_from_file_missing(tmp_path):
+    with pytest.raises(FileNotFoundError):
+        Module.from_file(Store(), tmp_path / 'missing.wasm')
+
+def test_from_file_empty(tmp_path):
+    path = tmp_path / 'empty.wasm'
+    path.write_bytes(b'')
+
+    with pytest.raises(wasmer.CompileError):
+        Module.from_file(Store(), path)
+
+def test_compile_in_a_thread():
+    with ThreadPoolExecutor() as executor:
+        module = executor.submit(Module, Store(), TEST_BYTES).result()
+
+    assert module.serialize() == Module(Store(), TEST_BYTES).serialize()
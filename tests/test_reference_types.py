@@ -0,0 +1,237 @@
+from wasmer import Store, Module, Instance, Function, Global, Table, TableType, Type, Value
+import gc
+import sys
+import weakref
+import pytest
+
+EXTERN_REF_WAT = """
+(module
+  (type $identity (func (param externref) (result externref)))
+  (import "env" "identity" (func $host_identity (type $identity)))
+  (table $table (export "table") 2 externref)
+  (global $global (export "global") (mut externref) (ref.null extern))
+
+  (func (export "identity") (type $identity)
+    local.get 0)
+
+  (func (export "host_identity") (type $identity)
+    local.get 0
+    call $host_identity)
+
+  (func (export "is_null") (param externref) (result i32)
+    local.get 0
+    ref.is_null)
+
+  (func (export "store") (param externref)
+    local.get 0
+    global.set $global)
+
+  (func (export "load") (result externref)
+    global.get $global)
+
+  (func (export "table_store") (param i32 externref)
+    local.get 0
+    local.get 1
+    table.set $table)
+
+  (func (export "table_load") (param i32) (result externref)
+    local.get 0
+    table.get $table))
+"""
+
+def instance():
+    store = Store()
+
+    def identity(x: object) -> 'externref':
+        return x
+
+    return Instance(Module(store, EXTERN_REF_WAT), {"env": {"identity": Function(store, identity)}})
+
+class Object:
+    pass
+
+def test_extern_ref_identity():
+    exports = instance().exports
+    objects = [Object(), [1, 2], 'hello', 42, pytest]
+
+    for value in objects:
+        assert exports.identity(value) is value
+        assert exports.host_identity(value) is value
+
+def test_extern_ref_null():
+    exports = instance().exports
+
+    assert exports.identity(None) == None
+    assert exports.is_null(None) == 1
+    assert exports.is_null(Object()) == 0
+    assert exports.load() == None
+
+def test_extern_ref_in_global():
+    exports = instance().exports
+    global_ = getattr(exports, 'global')
+
+    exports.store(Object())
+    gc.collect()
+
+    assert isinstance(exports.load(), Object)
+    assert global_.value is exports.load()
+
+    value = Object()
+    global_.value = value
+
+    assert exports.load() is value
+
+def test_extern_ref_in_table():
+    exports = instance().exports
+    table = exports.table
+
+    assert table.type.type == Type.EXTERN_REF
+    assert table.get(0) == None
+
+    value = Object()
+    exports.table_store(0, value)
+
+    assert table.get(0) is value
+
+    table.set(1, [1, 2])
+
+    assert exports.table_load(1) == [1, 2]
+
+def test_extern_ref_host_global_and_table():
+    store = Store()
+    value = Object()
+
+    global_ = Global(store, Value.externref(value), mutable=True)
+
+    assert global_.type.type == Type.EXTERN_REF
+    assert global_.value is value
+
+    table = Table(store, TableType(Type.EXTERN_REF, minimum=2), value)
+
+    assert table.get(0) is value
+    assert table.get(1) is value
+
+    table.set(0, None)
+    gc.collect()
+
+    assert table.get(0) == None
+    assert table.get(1) is value
+
+def test_extern_ref_released_with_store():
+    exports = instance().exports
+    value = Object()
+    reference = weakref.ref(value)
+    count = sys.getrefcount(value)
+
+    for _ in range(100):
+        assert exports.identity(value) is value
+        assert exports.host_identity(value) is value
+        assert exports.is_null(value) == 0
+        exports.store(value)
+        exports.table_store(0, value)
+
+    # The object is held once by the store, whatever the calls.
+    assert sys.getrefcount(value) == count + 1
+
+    del exports, value
+    gc.collect()
+
+    assert reference() is None
+
+REGISTRY_WAT = """
+(module
+  (type $callback (func (param i32) (result i32)))
+  (table $callbacks (export "callbacks") 4 funcref)
+  (global $count (mut i32) (i32.const 0))
+
+  (func (export "register") (param funcref) (result i32)
+    global.get $count
+    local.get 0
+    table.set $callbacks
+    global.get $count
+    global.get $count
+    i32.const 1
+    i32.add
+    global.set $count)
+
+  (func (export "run") (param i32 i32) (result i32)
+    local.get 1
+    local.get 0
+    call_indirect $callbacks (type $callback))
+
+  (func (export "get") (param i32) (result funcref)
+    local.get 0
+    table.get $callbacks))
+"""
+
+PLUGIN_WAT = """
+(module
+  (func (export "double") (param i32) (result i32)
+    local.get 0
+    i32.const 2
+    i32.mul)
+
+  (func (export "negate") (param i32) (result i32)
+    i32.const 0
+    local.get 0
+    i32.sub))
+"""
+
+def test_func_ref_between_instances():
+    store = Store()
+    registry = Instance(Module(store, REGISTRY_WAT)).exports
+    plugin = Instance(Module(store, PLUGIN_WAT)).exports
+
+    assert registry.register(plugin.double) == 0
+    assert registry.register(plugin.negate) == 1
+
+    assert registry.run(0, 21) == 42
+    assert registry.run(1, 21) == -21
+
+    assert isinstance(registry.get(0), Function)
+    assert registry.get(3) == None
+
+    # A function reference can be given back to WebAssembly.
+    assert registry.register(registry.get(0)) == 2
+    assert registry.run(2, 5) == 10
+
+def test_func_ref_received_by_host_function():
+    store = Store()
+    plugin = Instance(Module(store, PLUGIN_WAT)).exports
+    registry = Instance(Module(store, REGISTRY_WAT)).exports
+
+    received = []
+
+    def receive(callback: 'funcref'):
+        received.append(callback)
+
+    module = Module(
+        store,
+        """
+        (module
+          (import "env" "receive" (func $receive (param funcref)))
+          (func (export "send") (param funcref)
+            local.get 0
+            call $receive))
+        """
+    )
+    sender = Instance(module, {"env": {"receive": Function(store, receive)}}).exports
+
+    sender.send(plugin.double)
+    sender.send(None)
+
+    assert isinstance(received[0], Function)
+    assert received[1] == None
+
+    registry.register(received[0])
+
+    assert registry.run(0, 4) == 8
+
+def test_func_ref_invalid_value():
+    registry = Instance(Module(Store(), REGISTRY_WAT)).exports
+
+    with pytest.raises(TypeError) as context_manager:
+        registry.register(42)
+
+    exception = context_manager.value
+    assert str(exception) == 'A `funcref` value must be a `Function` or `None`, got `int`'
@@ -1,4 +1,5 @@
-from wasmer import engine, Store, Module, Instance
+import wasmer
+from wasmer import engine, Store, Module, Instance, Function
 import itertools
 import os
 import platform
@@ -25,6 +26,26 @@ def test_store_dylib():
     assert store.engine_name == 'dylib'
     assert store.compiler_name == None
 
+def test_store_staticlib():
+    store = Store(engine.Staticlib())
+
+    assert store.engine_name == 'staticlib'
+    assert store.compiler_name == None
+
+def test_store_staticlib_with_compiler():
+    from wasmer_compiler_cranelift import Compiler
+
+    store = Store(engine.Staticlib(Compiler))
+
+    assert store.engine_name == 'staticlib'
+    assert store.compiler_name == 'cranelift'
+
+    module = Module(store, '(module (func (export "f") (result i32) i32.const 42))')
+    object_file = module.serialize()
+
+    assert type(object_file) == bytes
+    assert len(object_file) > 0
+
 def test_store_deprecated_jit():
     store = Store(engine.JIT())
 
@@ -73,3 +94,361 @@ def test_store_deprecated_native():
 #            instance = Instance(module)
 #
 #            assert instance.exports.sum(1, 2)
+
+def test_stores_are_independent():
+    from wasmer import Function
+    import wasmer
+    import wasmer_compiler_cranelift
+    import wasmer_compiler_singlepass
+
+    cranelift = Store(engine.Universal(wasmer_compiler_cranelift.Compiler))
+    singlepass = Store(engine.Universal(wasmer_compiler_singlepass.Compiler))
+
+    wat = """
+    (module
+      (import "env" "add_one" (func $add_one (param i32) (result i32)))
+      (func (export "add_two") (param i32) (result i32)
+        local.get 0
+        call $add_one
+        call $add_one))
+    """
+
+    def add_one(x: int) -> int:
+        return x + 1
+
+    for store in [cranelift, singlepass]:
+        instance = Instance(Module(store, wat), {"env": {"add_one": Function(store, add_one)}})
+
+        assert instance.exports.add_two(1) == 3
+
+    with pytest.raises(wasmer.LinkError) as context_manager:
+        Instance(Module(cranelift, wat), {"env": {"add_one": Function(singlepass, add_one)}})
+
+    assert str(context_manager.value) == "Import `env.add_one` comes from a store with another engine than the module"
+
+def test_stores_sharing_an_engine_are_compatible():
+    from wasmer import Function
+    from wasmer_compiler_cranelift import Compiler
+
+    def answer() -> int:
+        return 42
+
+    engine_ = engine.Universal(Compiler)
+    module = Module(
+        Store(engine_),
+        '(module (import "env" "answer" (func $answer (result i32))) (func (export "f") (result i32) call $answer))'
+    )
+    instance = Instance(module, {"env": {"answer": Function(Store(engine_), answer)}})
+
+    assert instance.exports.f() == 42
+
+def test_tunables():
+    from wasmer import Tunables
+
+    tunables = Tunables(maximum_memory_pages=1024)
+
+    assert tunables.maximum_memory_pages == 1024
+    assert tunables.maximum_table_elements == None
+    assert tunables.static_memory_bound == None
+
+    tunables.maximum_table_elements = 10
+
+    assert tunables.maximum_table_elements == 10
+    assert repr(tunables) == 'Tunables(maximum_memory_pages=1024, maximum_table_elements=10, static_memory_bound=None, static_memory_offset_guard_size=None, dynamic_memory_offset_guard_size=None, limiter=None)'
+
+def test_tunables_cap_memories():
+    from wasmer import Tunables, Memory, MemoryType
+    import wasmer
+
+    store = Store(tunables=Tunables(maximum_memory_pages=1024))
+
+    for (declared, expected) in [('', 1024), (' 10', 10), (' 2000', 1024)]:
+        instance = Instance(Module(store, '(module (memory (export "memory") 1{}) (func (export "grow") (param i32) (result i32) local.get 0 memory.grow))'.format(declared)))
+
+        assert instance.exports.memory.type.maximum == expected
+        assert instance.exports.grow(expected) == -1
+        assert instance.exports.grow(expected - 1) == 1
+        assert instance.exports.memory.size == expected
+
+    with pytest.raises(wasmer.LinkError):
+        Instance(Module(store, '(module (memory 1025))'))
+
+    assert Memory(store, MemoryType(minimum=1)).type.maximum == 1024
+
+    with pytest.raises(RuntimeError):
+        Memory(store, MemoryType(minimum=1025))
+
+def test_tunables_cap_tables():
+    from wasmer import Tunables, Table, TableType, Type
+    import wasmer
+
+    store = Store(tunables=Tunables(maximum_table_elements=10))
+    instance = Instance(Module(store, '(module (table (export "table") 1 funcref))'))
+
+    assert instance.exports.table.type.maximum == 10
+
+    with pytest.raises(wasmer.LinkError):
+        Instance(Module(store, '(module (table 11 funcref))'))
+
+def test_tunables_memory_style():
+    from wasmer import Tunables
+
+    for tunables in [
+        Tunables(static_memory_bound=0x10),
+        Tunables(static_memory_offset_guard_size=0x1_0000, dynamic_memory_offset_guard_size=0x2_0000),
+    ]:
+        store = Store(tunables=tunables)
+
+        for declared in ['', ' 1', ' 0x10', ' 0x100']:
+            instance = Instance(Module(store, '(module (memory (export "memory") 1{}) (func (export "load") (param i32) (result i32) local.get 0 i32.load))'.format(declared)))
+
+            assert instance.exports.load(0xfffc) == 0
+
+            with pytest.raises(RuntimeError):
+                instance.exports.load(0x10000)
+
+def test_tunables_limiter():
+    from wasmer import Tunables
+    import wasmer
+
+    class Limiter:
+        def __init__(self):
+            self.calls = []
+
+        def memory_growing(self, current, desired, maximum):
+            self.calls.append(('memory', current, desired, maximum))
+
+            return desired <= 4
+
+        def table_growing(self, current, desired, maximum):
+            self.calls.append(('table', current, desired, maximum))
+
+            return desired <= 2
+
+    limiter = Limiter()
+    store = Store(tunables=Tunables(limiter=limiter))
+    module = Module(
+        store,
+        """
+        (module
+          (memory (export "memory") 1 10)
+          (table 1 funcref)
+          (func (export "grow_memory") (param i32) (result i32)
+            local.get 0
+            memory.grow)
+          (func (export "grow_table") (param i32) (result i32)
+            ref.null func
+            local.get 0
+            table.grow 0))
+        """
+    )
+    instance = Instance(module)
+
+    assert limiter.calls == [('memory', 0, 1, 10), ('table', 0, 1, None)]
+
+    assert instance.exports.grow_memory(2) == 1
+    assert instance.exports.grow_memory(2) == -1
+    assert instance.exports.memory.size == 3
+    assert instance.exports.grow_table(1) == 1
+    assert instance.exports.grow_table(1) == -1
+
+    assert limiter.calls[2:] == [
+        ('memory', 1, 3, 10),
+        ('memory', 3, 5, 10),
+        ('table', 1, 2, None),
+        ('table', 2, 3, None),
+    ]
+
+    with pytest.raises(RuntimeError):
+        instance.exports.memory.grow(2)
+
+    with pytest.raises(wasmer.LinkError):
+        Instance(Module(store, '(module (memory 5))'))
+
+def test_tunables_limiter_raising_an_exception():
+    from wasmer import Tunables
+    import sys
+
+    class Limiter:
+        def memory_growing(self, current, desired, maximum):
+            if current > 0:
+                raise ValueError('no more memory')
+
+            return True
+
+    unraisables = []
+    unraisablehook = sys.unraisablehook
+    sys.unraisablehook = unraisables.append
+
+    try:
+        store = Store(tunables=Tunables(limiter=Limiter()))
+        instance = Instance(Module(store, '(module (memory (export "memory") 1) (func (export "grow") (result i32) i32.const 1 memory.grow))'))
+
+        assert instance.exports.grow() == -1
+    finally:
+        sys.unraisablehook = unraisablehook
+
+    assert len(unraisables) == 1
+    assert isinstance(unraisables[0].exc_value, ValueError)
+    assert str(unraisables[0].exc_value) == 'no more memory'
+
+DEPTH_WAT = """
+(module
+  (import "env" "leaf" (func $leaf (result i32)))
+  (func $depth (export "depth") (param i32) (result i32)
+    local.get 0
+    i32.eqz
+    if (result i32)
+      call $leaf
+    else
+      local.get 0
+      i32.const 1
+      i32.sub
+      call $depth
+      i32.const 1
+      i32.add
+    end))
+"""
+
+def test_stack_size():
+    import wasmer
+
+    for (stack_size, depth, overflows) in [
+        (None, 1_000_000, True),
+        (64 * 1024 * 1024, 1_000_000, False),
+        (None, 100_000, False),
+        (256 * 1024, 100_000, True),
+    ]:
+        instance = Instance(Module(Store(stack_size=stack_size), DEPTH_WAT), {"env": {"leaf": lambda: 7}})
+
+        for depth_function in [instance.exports.depth, instance.exports.depth.typed('(i)->i')]:
+            if overflows:
+                with pytest.raises(wasmer.Trap) as context_manager:
+                    depth_function(depth)
+
+                assert context_manager.value.code == wasmer.TrapCode.STACK_OVERFLOW
+            else:
+                assert depth_function(depth) == depth + 7
+
+        if not overflows:
+            assert instance.exports.depth.call_batch([(depth,), (1,)]) == [depth + 7, 8]
+
+def test_stack_size_host_function_with_environment():
+    import threading
+
+    class Env:
+        def __init__(self):
+            self.threads = []
+
+    def log(env, value: int):
+        # The host function runs on the thread with the stack of the
+        # store, which the calling thread waits for.
+        env.threads.append((threading.current_thread().name, value))
+
+    store = Store(stack_size=1024 * 1024)
+    env = Env()
+    module = Module(store, """
+    (module
+      (import "env" "log" (func $log (param i32)))
+      (func (export "run")
+        i32.const 42
+        call $log))
+    """)
+    instance = Instance(module, {"env": {"log": Function(store, log, env=env)}})
+
+    instance.exports.run()
+    instance.exports.run.typed('()->')()
+    instance.exports.run.call_batch([(), ()])
+
+    assert len(env.threads) == 4
+    assert all(value == 42 for (_, value) in env.threads)
+    assert all(thread != threading.current_thread().name for (thread, _) in env.threads)
+
+def test_stack_size_must_be_positive():
+    with pytest.raises(ValueError):
+        Store(stack_size=0)
+
+def test_coredump_directory(tmp_path):
+    store = Store(coredump_directory=str(tmp_path))
+    instance = Instance(
+        Module(
+            store,
+            """
+            (module $app
+              (memory (export "memory") 1)
+              (global (export "counter") (mut i32) (i32.const 0))
+              (func $crash
+                unreachable)
+              (func (export "run")
+                call $crash))
+            """
+        )
+    )
+    instance.exports.memory.uint8_view()[0:6] = b'Wasmer'
+    instance.exports.counter.value = 42
+
+    for run in [instance.exports.run, instance.exports.run.typed('()->')]:
+        with pytest.raises(wasmer.Trap) as context_manager:
+            run()
+
+        path = context_manager.value.coredump_path
+
+        assert os.path.dirname(path) == str(tmp_path)
+
+        with open(path, 'rb') as file:
+            coredump = file.read()
+
+        # A core dump is a valid module, whose data section holds the memory.
+        module = Module(store, coredump)
+
+        assert module.custom_sections('core') == [b'\x00\x03app']
+        assert module.custom_sections('coremodules') == [b'\x01\x00\x03app']
+        assert module.custom_sections('coreinstances') == [b'\x01\x00\x00\x01\x00\x01\x00']
+        (stack,) = module.custom_sections('corestack')
+        assert stack.startswith(b'\x00\x04main\x02\x00\x00\x00')
+        assert b'Wasmer' in coredump
+        # The `counter` global: `(mut i32) (i32.const 42)`.
+        assert b'\x7f\x01\x41\x2a\x0b' in coredump
+
+    assert len(os.listdir(tmp_path)) == 2
+
+def test_coredump_directory_is_optional():
+    instance = Instance(Module(Store(), '(module (func (export "crash") unreachable))'))
+
+    with pytest.raises(wasmer.Trap) as context_manager:
+        instance.exports.crash()
+
+    assert context_manager.value.coredump_path is None
+
+    # A host exception isn't a trap of WebAssembly code.
+    store = Store(coredump_directory='/nonexistent')
+    module = Module(store, '(module (import "env" "f" (func $f)) (func (export "run") call $f))')
+
+    def f():
+        raise ValueError('host error')
+
+    with pytest.raises(ValueError):
+        Instance(module, {"env": {"f": wasmer.Function(store, f)}}).exports.run()
+
+def test_close():
+    store = Store()
+    module = Module(store, TEST_BYTES)
+
+    store.close()
+    store.close()
+
+    assert Instance(module).exports.sum(1, 2) == 3
+
+    with pytest.raises(ValueError) as context_manager:
+        Module(store, TEST_BYTES)
+
+    assert str(context_manager.value) == 'The store is closed'
+
+def test_context_manager():
+    with Store() as store:
+        module = Module(store, TEST_BYTES)
+
+    assert Instance(module).exports.sum(1, 2) == 3
+
+    with pytest.raises(ValueError):
+        Module.validate(store, TEST_BYTES)
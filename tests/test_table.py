@@ -1,4 +1,4 @@
-from wasmer import Instance, Module, Store, Table, TableType, Type
+from wasmer import Instance, Module, Store, Function, Table, TableType, Type, Value
 import pytest
 
 TEST_BYTES = """
@@ -22,3 +22,139 @@ def test_type():
 
 def test_size():
     assert instance().exports.table.size == 0
+
+DISPATCH_WAT = """
+(module
+  (type $binary (func (param i32 i32) (result i32)))
+  (import "env" "sub" (func $sub (type $binary)))
+  (table (export "table") 3 funcref)
+  (elem (i32.const 0) $add $sub)
+
+  (func $add (export "add") (param i32 i32) (result i32)
+    local.get 0
+    local.get 1
+    i32.add)
+
+  (func (export "dispatch") (param i32 i32 i32) (result i32)
+    local.get 1
+    local.get 2
+    local.get 0
+    call_indirect (type $binary)))
+"""
+
+def sub(x: int, y: int) -> int:
+    return x - y
+
+def dispatch_instance(store=None):
+    store = store or Store()
+
+    return Instance(Module(store, DISPATCH_WAT), {"env": {"sub": Function(store, sub)}})
+
+def test_get():
+    table = dispatch_instance().exports.table
+
+    assert isinstance(table.get(0), Function)
+    assert isinstance(table.get(1), Function)
+    assert table.get(2) == None
+
+def test_get_cannot_be_called():
+    table = dispatch_instance().exports.table
+
+    with pytest.raises(RuntimeError) as context_manager:
+        table.get(0)(1, 2)
+
+    exception = context_manager.value
+    assert str(exception) == 'A function read from a table cannot be called from the host, only from WebAssembly'
+
+def test_set():
+    instance = dispatch_instance()
+    exports = instance.exports
+    table = exports.table
+
+    assert exports.dispatch(0, 5, 3) == 8
+    assert exports.dispatch(1, 5, 3) == 2
+
+    table.set(2, exports.add)
+
+    assert exports.dispatch(2, 5, 3) == 8
+
+    # A host function imported in a module can be moved around too.
+    table.set(0, table.get(1))
+
+    assert exports.dispatch(0, 5, 3) == 2
+
+    table.set(0, None)
+
+    with pytest.raises(RuntimeError):
+        exports.dispatch(0, 5, 3)
+
+def test_set_host_function():
+    store = Store()
+    table = dispatch_instance(store).exports.table
+
+    with pytest.raises(TypeError) as context_manager:
+        table.set(2, Function(store, sub))
+
+    exception = context_manager.value
+    assert str(exception) == 'A host function cannot be used as a `funcref` directly; import it in a module, and reference it from an element segment instead'
+
+def test_set_invalid_value():
+    table = dispatch_instance().exports.table
+
+    with pytest.raises(TypeError) as context_manager:
+        table.set(0, 42)
+
+    exception = context_manager.value
+    assert str(exception) == 'A `funcref` value must be a `Function` or `None`, got `int`'
+
+def test_get_set_out_of_bound():
+    table = dispatch_instance().exports.table
+
+    with pytest.raises(IndexError) as context_manager:
+        table.get(3)
+
+    exception = context_manager.value
+    assert str(exception) == 'Out of bound: The index 3 is outside the table of 3 elements'
+
+    with pytest.raises(IndexError):
+        table.set(3, None)
+
+def test_grow():
+    instance = dispatch_instance()
+    table = instance.exports.table
+
+    assert table.grow(3, instance.exports.add) == 3
+    assert table.size == 6
+    assert table.get(2) == None
+    assert instance.exports.dispatch(5, 5, 3) == 8
+
+def test_grow_over_maximum():
+    table = Table(Store(), TableType(Type.FUNC_REF, minimum=1, maximum=2))
+
+    assert table.grow(1) == 1
+
+    with pytest.raises(RuntimeError):
+        table.grow(1)
+
+def test_host_table():
+    store = Store()
+    module = Module(
+        store,
+        """
+        (module
+          (type $binary (func (param i32 i32) (result i32)))
+          (import "env" "table" (table 1 funcref))
+          (func (export "call") (param i32 i32) (result i32)
+            local.get 0
+            local.get 1
+            i32.const 0
+            call_indirect (type $binary)))
+        """
+    )
+
+    # The instance defining the function must outlive the table.
+    add = dispatch_instance(store).exports.add
+    table = Table(store, TableType(Type.FUNC_REF, minimum=1), add)
+    instance = Instance(module, {"env": {"table": table}})
+
+    assert instance.exports.call(1, 2) == 3
@@ -1,4 +1,5 @@
 from wasmer import engine, target, Store, Module
+import wasmer
 from wasmer_compiler_cranelift import Compiler
 import itertools
 import os
@@ -68,3 +69,59 @@ def test_cross_compilation_roundtrip():
     )
 
     assert isinstance(module, Module)
+
+def test_cross_compilation_universal():
+    triple = target.Triple('aarch64-unknown-linux-gnu')
+    target_ = target.Target(triple)
+
+    engine_ = engine.Universal(Compiler, target_)
+    store = Store(engine_)
+
+    module = Module(
+        store,
+        """
+        (module
+          (type $sum_t (func (param i32 i32) (result i32)))
+          (func $sum_f (type $sum_t) (param $x i32) (param $y i32) (result i32)
+            local.get $x
+            local.get $y
+            i32.add)
+          (export "sum" (func $sum_f)))
+        """
+    )
+    serialized_module = module.serialize()
+
+    assert type(serialized_module) == bytes
+    assert len(serialized_module) > 0
+
+def test_target_introspection():
+    cpu_features = target.CpuFeatures()
+    cpu_features.add('sse2')
+    cpu_features.add('avx')
+
+    target_ = target.Target(target.Triple('x86_64-linux-musl'), cpu_features)
+
+    assert str(target_.triple) == 'x86_64-unknown-linux-musl'
+    assert len(target_.cpu_features) == 2
+    assert 'sse2' in target_.cpu_features
+    assert 'avx2' not in target_.cpu_features
+    assert sorted(target_.cpu_features) == ['avx', 'sse2']
+
+def test_engine_target():
+    triple = target.Triple('aarch64-unknown-linux-gnu')
+    engine_ = engine.Universal(Compiler, target.Target(triple))
+
+    assert str(engine_.target.triple) == 'aarch64-unknown-linux-gnu'
+    assert str(engine_.triple) == 'aarch64-unknown-linux-gnu'
+
+def test_engine_headless_target():
+    for engine_ in [engine.Universal(), engine.Dylib(), engine.Staticlib()]:
+        assert str(engine_.triple) == str(target.Triple.host())
+
+def test_host_cpu_features():
+    cpu_features = wasmer.host_cpu_features()
+
+    assert isinstance(cpu_features, target.CpuFeatures)
+
+    if platform.machine() in ('x86_64', 'AMD64'):
+        assert 'sse2' in cpu_features
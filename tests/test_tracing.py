@@ -0,0 +1,194 @@
+from wasmer import engine, set_trace, Store, Module, Instance, Function, FunctionType, Type, Tracing
+from wasmer_compiler_cranelift import Compiler as Cranelift
+from wasmer_compiler_singlepass import Compiler as Singlepass
+import pytest
+import threading
+
+TEST_WAT = """
+(module
+  (import "env" "log" (func $log (param i32)))
+
+  (func $early (param i32) (result i32)
+    local.get 0
+    if
+      i32.const 1
+      return
+    end
+    i32.const 2)
+
+  (func $branch (result i32)
+    i32.const 3
+    br 0)
+
+  (func (export "run") (param i32) (result i32)
+    local.get 0
+    call $early
+    call $log
+    call $branch))
+"""
+
+def test_tracing():
+    for compiler in [Cranelift, Singlepass]:
+        store = Store(engine.Universal(compiler(middlewares=[Tracing()])))
+        logged = []
+        log = Function(store, lambda x: logged.append(x), FunctionType([Type.I32], []))
+        instance = Instance(Module(store, TEST_WAT), {"env": {"log": log}})
+        events = []
+
+        set_trace(instance, lambda event, index, name: events.append((event, index, name)))
+
+        for argument in [1, 0]:
+            events.clear()
+
+            assert instance.exports.run(argument) == 3
+            assert events == [
+                ('enter', 3, None),
+                ('enter', 1, 'early'),
+                ('exit', 1, 'early'),
+                ('enter', 2, 'branch'),
+                ('exit', 2, 'branch'),
+                ('exit', 3, None),
+            ]
+
+        assert logged == [1, 2]
+
+def test_tracing_multiple_results():
+    store = Store(engine.Universal(Cranelift(middlewares=[Tracing()])))
+    instance = Instance(
+        Module(
+            store,
+            """
+            (module
+              (func $pair (result i32 i64)
+                i32.const 4
+                i64.const 5)
+              (func (export "run") (result i32)
+                call $pair
+                drop))
+            """
+        )
+    )
+    events = []
+
+    set_trace(instance, lambda event, index, name: events.append((event, name)))
+
+    assert instance.exports.run() == 4
+    assert events == [('enter', None), ('enter', 'pair'), ('exit', 'pair'), ('exit', None)]
+
+def test_tracing_names():
+    store = Store(engine.Universal(Cranelift(middlewares=[Tracing()])))
+    instance = Instance(Module(store, '(module (func $main (export "main")))'))
+    names = []
+
+    set_trace(instance, lambda event, index, name: names.append(name))
+    instance.exports.main()
+
+    assert names == ['main', 'main']
+
+def test_tracing_is_per_instance():
+    store = Store(engine.Universal(Cranelift(middlewares=[Tracing()])))
+    module = Module(store, '(module (func (export "main")))')
+    traced, untraced = Instance(module), Instance(module)
+    events = []
+
+    set_trace(traced, lambda *event: events.append(event))
+    untraced.exports.main()
+
+    assert events == []
+
+    traced.exports.main()
+
+    assert len(events) == 2
+
+    set_trace(traced, None)
+    traced.exports.main()
+
+    assert len(events) == 2
+
+def test_tracing_is_not_cloned():
+    for compiler in [Cranelift, Singlepass]:
+        store = Store(engine.Universal(compiler(middlewares=[Tracing()])))
+        instance = Instance(Module(store, '(module (func (export "main")))'))
+        events, clone_events = [], []
+
+        set_trace(instance, lambda *event: events.append(event))
+        clone = instance.clone()
+        clone.exports.main()
+
+        assert events == []
+
+        set_trace(clone, lambda *event: clone_events.append(event))
+        instance.exports.main()
+        clone.exports.main()
+
+        assert len(events) == 2
+        assert len(clone_events) == 2
+
+        clone.close()
+        del clone
+        instance.exports.main()
+
+        assert len(events) == 4
+
+def test_tracing_shared_by_several_modules():
+    tracing = Tracing()
+    modules = []
+
+    def compile(imports):
+        store = Store(engine.Universal(Cranelift(middlewares=[tracing])))
+        # The imported functions shift the index of the traced
+        # functions, which are numerous enough for the compilations
+        # to overlap.
+        module = Module(store, """
+        (module
+          {}
+          {}
+          (func (export "run")))
+        """.format(
+            ''.join('(import "env" "f{}" (func))'.format(index) for index in range(imports)),
+            '(func)' * 500,
+        ))
+
+        modules.append((imports, store, module))
+
+    threads = [threading.Thread(target=compile, args=(imports,)) for imports in range(8)]
+
+    for thread in threads:
+        thread.start()
+
+    for thread in threads:
+        thread.join()
+
+    assert len(modules) == len(threads)
+
+    for imports, store, module in modules:
+        function = Function(store, lambda: None, FunctionType([], []))
+        instance = Instance(module, {"env": {"f{}".format(index): function for index in range(imports)}})
+        events = []
+
+        set_trace(instance, lambda event, index, name: events.append((event, index)))
+        instance.exports.run()
+
+        assert events == [('enter', imports + 500), ('exit', imports + 500)]
+
+def test_tracing_exception():
+    store = Store(engine.Universal(Cranelift(middlewares=[Tracing()])))
+    instance = Instance(Module(store, '(module (func (export "main")))'))
+
+    def fail(event, index, name):
+        raise ValueError(event)
+
+    set_trace(instance, fail)
+
+    with pytest.raises(ValueError) as context_manager:
+        instance.exports.main()
+
+    assert str(context_manager.value) == 'enter'
+
+def test_tracing_requires_the_middleware():
+    instance = Instance(Module(Store(), '(module)'))
+
+    with pytest.raises(RuntimeError) as context_manager:
+        set_trace(instance, print)
+
+    assert str(context_manager.value) == 'The instance has not been compiled with a `Tracing` middleware'
@@ -1,5 +1,6 @@
-from wasmer import Type
+from wasmer import Store, Module, Type, FunctionType, MemoryType, GlobalType, TableType
 from enum import IntEnum
+import pytest
 
 def test_type():
     assert issubclass(Type, IntEnum)
@@ -11,3 +12,43 @@ def test_type():
     assert Type.V128 == 5
     assert Type.EXTERN_REF == 6
     assert Type.FUNC_REF == 7
+
+def test_type_equality():
+    for (left, same, different) in [
+        (FunctionType([Type.I32], [Type.I64]), FunctionType(params=[Type.I32], results=[Type.I64]), FunctionType([Type.I32], [])),
+        (MemoryType(1, 2), MemoryType(minimum=1, maximum=2), MemoryType(1, 2, shared=True)),
+        (GlobalType(Type.I32), GlobalType(Type.I32, mutable=False), GlobalType(Type.I32, mutable=True)),
+        (TableType(Type.FUNC_REF, 1), TableType(Type.FUNC_REF, minimum=1, maximum=None), TableType(Type.EXTERN_REF, 1)),
+    ]:
+        assert left == same
+        assert not left != same
+        assert hash(left) == hash(same)
+        assert left != different
+        assert left in {same}
+
+def test_type_equality_with_other_objects():
+    for value in [FunctionType([], []), MemoryType(1), GlobalType(Type.I32), TableType(Type.FUNC_REF, 1)]:
+        assert value != 42
+        assert value != MemoryType(2)
+
+        with pytest.raises(TypeError):
+            value < value
+
+def test_types_from_module():
+    module = Module(
+        Store(),
+        """
+        (module
+          (import "env" "f" (func (param i32)))
+          (import "env" "m" (memory 1 2))
+          (import "env" "g" (global (mut i64)))
+          (import "env" "t" (table 3 funcref)))
+        """
+    )
+
+    assert [import_.type for import_ in module.imports] == [
+        FunctionType([Type.I32], []),
+        MemoryType(1, 2),
+        GlobalType(Type.I64, mutable=True),
+        TableType(Type.FUNC_REF, 3),
+    ]
@@ -1,4 +1,4 @@
-from wasmer import Value
+from wasmer import Store, Module, Instance, Function, Value
 import inspect
 import pytest
 
@@ -29,3 +29,110 @@ def test_f64_auto_cast():
 
 def test_v128():
     assert repr(Value.v128(340282366920938463463374607431768211455)) == 'V128(340282366920938463463374607431768211455)'
+
+OVERLOADS_WAT = """
+(module
+  (global (export "wide") (mut i64) (i64.const 0))
+
+  (func (export "narrow") (param i32) (result i32)
+    local.get 0)
+
+  (func (export "widen") (param i64) (result i64)
+    local.get 0)
+
+  (func (export "floats") (param f32 f64) (result f64)
+    local.get 0
+    f64.promote_f32
+    local.get 1
+    f64.add))
+"""
+
+def instance():
+    return Instance(Module(Store(), OVERLOADS_WAT))
+
+def test_value_as_argument():
+    exports = instance().exports
+
+    assert exports.narrow(Value.i32(-7)) == -7
+    assert exports.widen(Value.i64(2 ** 40)) == 2 ** 40
+    assert exports.floats(Value.f32(1.5), Value.f64(0.25)) == 1.75
+    assert exports.widen.typed('(I)->I')(Value.i64(3)) == 3
+    assert exports.narrow.call_batch([(Value.i32(1),), (2,)]) == [1, 2]
+
+def test_value_as_global_value():
+    global_ = instance().exports.wide
+    global_.value = Value.i64(-1)
+
+    assert global_.value == -1
+
+def test_value_with_wrong_type():
+    exports = instance().exports
+
+    with pytest.raises(TypeError) as context_manager:
+        exports.narrow(Value.i64(7))
+
+    exception = context_manager.value
+    assert str(exception) == 'The value `I64(7)` has the type `I64`, expected `I32`'
+
+    with pytest.raises(TypeError):
+        exports.wide.value = Value.i32(7)
+
+SIMD_WAT = """
+(module
+  (global (export "vector") (mut v128) (v128.const i32x4 1 2 3 4))
+
+  (func (export "add") (param v128 v128) (result v128)
+    local.get 0
+    local.get 1
+    i32x4.add))
+"""
+
+def lanes(*values):
+    return b''.join(value.to_bytes(4, 'little', signed=True) for value in values)
+
+def test_v128_from_bytes():
+    assert repr(Value.v128(lanes(1, 0, 0, 0))) == 'V128(1)'
+
+    with pytest.raises(TypeError):
+        Value.v128(b'too short')
+
+def test_v128_as_argument():
+    exports = Instance(Module(Store(), SIMD_WAT)).exports
+
+    result = exports.add(lanes(1, 2, 3, 4), bytearray(lanes(10, 20, 30, -4)))
+
+    assert result.to_bytes(16, 'little') == lanes(11, 22, 33, 0)
+    assert exports.add(1, 2) == 3
+    assert exports.add(-1, 0) == 2 ** 128 - 1
+
+def test_v128_global():
+    vector = Instance(Module(Store(), SIMD_WAT)).exports.vector
+
+    assert vector.value.to_bytes(16, 'little') == lanes(1, 2, 3, 4)
+
+    vector.value = lanes(5, 6, 7, 8)
+
+    assert vector.value.to_bytes(16, 'little') == lanes(5, 6, 7, 8)
+
+def test_v128_host_function():
+    store = Store()
+    module = Module(
+        store,
+        """
+        (module
+          (import "env" "swap" (func $swap (param v128) (result v128)))
+          (func (export "run") (param v128) (result v128)
+            local.get 0
+            call $swap))
+        """
+    )
+
+    def swap(vector: 'v128') -> 'v128':
+        data = vector.to_bytes(16, 'little')
+
+        return data[8:] + data[:8]
+
+    instance = Instance(module, {"env": {"swap": Function(store, swap)}})
+    result = instance.exports.run(lanes(1, 2, 3, 4))
+
+    assert result.to_bytes(16, 'little') == lanes(3, 4, 1, 2)
@@ -1,5 +1,6 @@
-from wasmer import wasi, Store, ImportObject, Module, Instance
+from wasmer import wasi, engine, Store, ImportObject, Module, Instance, Function, Interruptible, ExecutionTimeout
 from enum import IntEnum
+import io
 import os
 import pytest
 import subprocess
@@ -17,6 +18,43 @@ def test_wasi_version():
 
 def test_wasi_get_version():
     assert wasi.get_version(Module(Store(), TEST_BYTES), strict=True) == wasi.Version.SNAPSHOT1
+    assert wasi.get_version(Module(Store(), TEST_BYTES)) == wasi.Version.SNAPSHOT1
+    assert wasi.get_version(Module(Store(), '(module)')) == None
+
+def test_wasi_get_version_snapshot0():
+    module = Module(
+        Store(),
+        """
+        (module
+          (import "wasi_unstable" "proc_exit" (func (param i32)))
+          (import "env" "log" (func (param i32))))
+        """
+    )
+
+    assert wasi.get_version(module) == wasi.Version.SNAPSHOT0
+    assert wasi.get_version(module, strict=True) == None
+
+def test_wasi_import_object_from_module():
+    store = Store()
+    env = wasi.StateBuilder("foo").finalize()
+
+    for namespace_name in ['wasi_unstable', 'wasi_snapshot_preview1']:
+        module = Module(
+            store,
+            '(module (import "%s" "proc_exit" (func (param i32))) (memory (export "memory") 1))' % namespace_name
+        )
+
+        assert list(env.generate_import_object(store, module)) == [namespace_name]
+        assert list(env.generate_imports(store, module)) == [namespace_name]
+        Instance(module, env.generate_import_object(store, module))
+
+def test_wasi_import_object_from_module_without_wasi():
+    env = wasi.StateBuilder("foo").finalize()
+
+    with pytest.raises(ValueError) as context_manager:
+        env.generate_import_object(Store(), Module(Store(), '(module)'))
+
+    assert str(context_manager.value) == 'The module does not import any WASI namespace'
 
 def test_wasi_state_builder():
     state_builder = \
@@ -108,3 +146,860 @@ def test_wasi():
 
     instance = Instance(Module(store, TEST_BYTES), import_object)
     instance.exports._start()
+
+# Exits with the code given as argument.
+PROC_EXIT_WAT = """
+(module
+  (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+  (memory (export "memory") 1)
+  (func (export "exit") (param i32)
+    local.get 0
+    call $proc_exit))
+"""
+
+def test_wasi_exit_error():
+    store = Store()
+    module = Module(store, PROC_EXIT_WAT)
+    wasi_env = wasi.StateBuilder("test-program").finalize()
+    instance = Instance(module, wasi_env.generate_import_object(store, module))
+
+    for exit_code in [0, 1, 42, 2**32 - 1]:
+        with pytest.raises(wasi.WasiExitError) as context_manager:
+            instance.exports.exit(exit_code)
+
+        exception = context_manager.value
+        assert isinstance(exception, RuntimeError)
+        assert exception.exit_code == exit_code
+        assert str(exception) == 'WASI exited with code {}'.format(exit_code)
+
+def test_wasi_exit_error_from_host_function():
+    store = Store()
+    module = Module(
+        store,
+        """
+        (module
+          (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+          (import "env" "callback" (func $callback))
+          (memory (export "memory") 1)
+          (func (export "exit")
+            call $callback
+            i32.const 7
+            call $proc_exit))
+        """
+    )
+    wasi_env = wasi.StateBuilder("test-program").finalize()
+
+    def callback():
+        raise ValueError("from the host")
+
+    import_object = wasi_env.generate_import_object(store, module)
+    import_object.register("env", {"callback": Function(store, callback)})
+    instance = Instance(module, import_object)
+
+    # Other errors are not turned into `WasiExitError`.
+    with pytest.raises(ValueError):
+        instance.exports.exit()
+
+# `time` reads the clock given as argument, `resolution` its
+# resolution, and `random` the 16 bytes returned by `random_get`, as
+# 2 integers.
+SOURCES_WAT = """
+(module
+  (import "wasi_snapshot_preview1" "clock_time_get" (func $clock_time_get (param i32 i64 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "clock_res_get" (func $clock_res_get (param i32 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "random_get" (func $random_get (param i32 i32) (result i32)))
+  (memory (export "memory") 1)
+
+  (func $check (param i32)
+    (if (local.get 0) (then unreachable)))
+
+  (func (export "time") (param i32) (result i64)
+    (call $check (call $clock_time_get (local.get 0) (i64.const 0) (i32.const 0)))
+    (i64.load (i32.const 0)))
+
+  (func (export "resolution") (param i32) (result i64)
+    (call $check (call $clock_res_get (local.get 0) (i32.const 0)))
+    (i64.load (i32.const 0)))
+
+  (func (export "random") (result i64 i64)
+    (call $check (call $random_get (i32.const 16) (i32.const 16)))
+    (i64.load (i32.const 16))
+    (i64.load (i32.const 24))))
+"""
+
+def sources_instance(state_builder, namespace_name='wasi_snapshot_preview1'):
+    store = Store()
+    module = Module(store, SOURCES_WAT.replace('wasi_snapshot_preview1', namespace_name))
+
+    return Instance(module, state_builder.finalize().generate_import_object(store, module))
+
+def test_wasi_clock():
+    instance = sources_instance(wasi.StateBuilder("test-program").clock(1000, step=10))
+
+    assert [instance.exports.time(clock_id) for clock_id in range(4)] == [1000, 1010, 1020, 1030]
+    assert instance.exports.resolution(0) == 1
+
+    with pytest.raises(RuntimeError):
+        instance.exports.time(4)
+
+def test_wasi_clock_fixed():
+    instance = sources_instance(wasi.StateBuilder("test-program").clock(42))
+
+    for _ in range(3):
+        assert instance.exports.time(0) == 42
+
+def test_wasi_random_seed():
+    def random(seed):
+        instance = sources_instance(wasi.StateBuilder("test-program").random_seed(seed))
+
+        return [instance.exports.random() for _ in range(3)]
+
+    numbers = random(42)
+    assert numbers == random(42)
+    assert numbers != random(43)
+    assert len(set(numbers)) == 3
+
+    # The bytes are the same on every platform.
+    assert random(0)[0][0] == 0xe220a8397b1dcdaf - 2**64
+
+def test_wasi_sources_snapshot0():
+    state_builder = wasi.StateBuilder("test-program").clock(7).random_seed(0)
+    instance = sources_instance(state_builder, 'wasi_unstable')
+
+    assert instance.exports.time(0) == 7
+    assert instance.exports.random()[0] == 0xe220a8397b1dcdaf - 2**64
+
+def test_wasi_sources_are_not_deterministic_by_default():
+    instance = sources_instance(wasi.StateBuilder("test-program"))
+
+    assert instance.exports.time(0) > 1_600_000_000_000_000_000
+    assert instance.exports.random() != instance.exports.random()
+
+def test_wasi_with_host_imports():
+    store = Store()
+    module = Module(
+        store,
+        """
+        (module
+          (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+          (import "host" "log" (func $log (param i32)))
+          (memory (export "memory") 1)
+          (data (i32.const 0) "\\10\\00\\00\\00\\05\\00\\00\\00")
+          (data (i32.const 16) "hello")
+          (func (export "_start")
+            (call $log
+              (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))))
+        """
+    )
+    logs = []
+
+    def log(x: int):
+        logs.append(x)
+
+    combinations = [
+        lambda import_object: import_object.register("host", {"log": Function(store, log)}),
+        lambda import_object: import_object.extend({"host": {"log": Function(store, log)}}),
+    ]
+
+    for combine in combinations:
+        wasi_env = wasi.StateBuilder("test-program").capture_stdout().finalize()
+        import_object = wasi_env.generate_import_object(store, module)
+        combine(import_object)
+
+        Instance(module, import_object).exports._start()
+
+        assert wasi_env.stdout() == b"hello"
+
+    wasi_env = wasi.StateBuilder("test-program").capture_stdout().finalize()
+    host = ImportObject()
+    host.register("host", {"log": Function(store, log)})
+
+    Instance(module, host | wasi_env.generate_import_object(store, module)).exports._start()
+
+    assert wasi_env.stdout() == b"hello"
+    assert logs == [0, 0, 0]
+
+# Writes `hello` on the file descriptor given as argument.
+FD_WRITE_WAT = """
+(module
+  (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+  (memory (export "memory") 1)
+  ;; An `iovec` pointing to `hello` at offset 16.
+  (data (i32.const 0) "\\10\\00\\00\\00\\05\\00\\00\\00")
+  (data (i32.const 16) "hello")
+  (func (export "write") (param $fd i32) (result i32)
+    local.get $fd
+    i32.const 0
+    i32.const 1
+    i32.const 8
+    call $fd_write))
+"""
+
+def test_wasi_capture_stdout():
+    store = Store()
+    wasi_env = \
+        wasi.StateBuilder("test-program"). \
+            argument("--foo"). \
+            environments({"ABC": "DEF", "X": "YZ"}). \
+            capture_stdout(). \
+            finalize()
+    import_object = wasi_env.generate_import_object(store, wasi.Version.LATEST)
+
+    instance = Instance(Module(store, TEST_BYTES), import_object)
+    instance.exports._start()
+
+    assert wasi_env.stdout() == \
+        b'Found program name: `test-program`\n' \
+        b'Found 1 arguments: --foo\n' \
+        b'Found 2 environment variables: ABC=DEF, X=YZ\n' \
+        b'Found 0 preopened directories: \n'
+    # The captured bytes have been read.
+    assert wasi_env.stdout() == b''
+
+def test_wasi_capture_stderr():
+    store = Store()
+    wasi_env = wasi.StateBuilder("test-program").capture_stdout().capture_stderr().finalize()
+    import_object = wasi_env.generate_import_object(store, wasi.Version.LATEST)
+
+    instance = Instance(Module(store, FD_WRITE_WAT), import_object)
+
+    assert instance.exports.write(2) == 0
+    assert instance.exports.write(2) == 0
+    assert wasi_env.stderr() == b'hellohello'
+    assert wasi_env.stdout() == b''
+
+def test_wasi_not_captured():
+    wasi_env = wasi.StateBuilder("test-program").finalize()
+
+    for device in ['stdout', 'stderr']:
+        with pytest.raises(RuntimeError) as context_manager:
+            getattr(wasi_env, device)()
+
+        exception = context_manager.value
+        assert str(exception) == f'`{device}` is not captured, see `StateBuilder.capture_{device}`'
+
+# Copies the standard input to the standard output, like `cat`.
+CAT_WAT = """
+(module
+  (import "wasi_snapshot_preview1" "fd_read" (func $fd_read (param i32 i32 i32 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+  (memory (export "memory") 1)
+  ;; An `iovec` pointing to a buffer of 4 bytes at offset 16.
+  (data (i32.const 0) "\\10\\00\\00\\00\\04\\00\\00\\00")
+  (func (export "_start")
+    (loop $copy
+      ;; Read at most 4 bytes.
+      i32.const 0
+      i32.const 0
+      i32.const 1
+      i32.const 8
+      call $fd_read
+      drop
+      ;; Stop at the end of the input.
+      (br_if 1 (i32.eqz (i32.load (i32.const 8))))
+      ;; Write the read bytes.
+      (i32.store (i32.const 4) (i32.load (i32.const 8)))
+      i32.const 1
+      i32.const 0
+      i32.const 1
+      i32.const 12
+      call $fd_write
+      drop
+      ;; Restore the buffer length.
+      (i32.store (i32.const 4) (i32.const 4))
+      br $copy)))
+"""
+
+def run_cat(state_builder):
+    store = Store()
+    wasi_env = state_builder.capture_stdout().finalize()
+    import_object = wasi_env.generate_import_object(store, wasi.Version.LATEST)
+
+    Instance(Module(store, CAT_WAT), import_object).exports._start()
+
+    return wasi_env.stdout()
+
+def test_wasi_stdin_bytes():
+    for input in [b'hello, world!', bytearray(b'hello, world!'), memoryview(b'hello, world!')]:
+        assert run_cat(wasi.StateBuilder("cat").stdin(input)) == b'hello, world!'
+
+    assert run_cat(wasi.StateBuilder("cat").stdin(b'')) == b''
+
+def test_wasi_stdin_file(tmp_path):
+    path = tmp_path / 'input.txt'
+    path.write_bytes(b'from a file\n' * 100)
+
+    for input in [str(path), path]:
+        assert run_cat(wasi.StateBuilder("cat").stdin(input)) == b'from a file\n' * 100
+
+def test_wasi_stdin_missing_file(tmp_path):
+    with pytest.raises(FileNotFoundError):
+        wasi.StateBuilder("cat").stdin(str(tmp_path / 'missing.txt'))
+
+def test_wasi_stdio_file_like():
+    stdin = io.BytesIO(b'hello, world!')
+    stdout = io.BytesIO()
+
+    store = Store()
+    wasi_env = wasi.StateBuilder("cat").stdin(stdin).stdout(stdout).finalize()
+    import_object = wasi_env.generate_import_object(store, wasi.Version.LATEST)
+
+    Instance(Module(store, CAT_WAT), import_object).exports._start()
+
+    assert stdout.getvalue() == b'hello, world!'
+
+def test_wasi_stdio_streaming():
+    class Source:
+        def __init__(self):
+            self.reads = 0
+
+        def read(self, size):
+            self.reads += 1
+
+            return b'abcd'[:size] if self.reads <= 3 else b''
+
+    class Sink:
+        def __init__(self):
+            self.writes = []
+
+        def write(self, data):
+            self.writes.append(bytes(data))
+
+    source = Source()
+    sink = Sink()
+
+    store = Store()
+    wasi_env = wasi.StateBuilder("cat").stdin(source).stdout(sink).finalize()
+    import_object = wasi_env.generate_import_object(store, wasi.Version.LATEST)
+
+    Instance(Module(store, CAT_WAT), import_object).exports._start()
+
+    # The data goes through in chunks, as the module reads and writes it.
+    assert source.reads == 4
+    assert sink.writes == [b'abcd', b'abcd', b'abcd']
+
+def test_wasi_stderr_file(tmp_path):
+    path = tmp_path / 'stderr.txt'
+
+    with open(path, 'wb') as stderr:
+        store = Store()
+        wasi_env = wasi.StateBuilder("test-program").stderr(stderr).finalize()
+        import_object = wasi_env.generate_import_object(store, wasi.Version.LATEST)
+
+        Instance(Module(store, FD_WRITE_WAT), import_object).exports.write(2)
+
+    assert path.read_bytes() == b'hello'
+
+def test_wasi_stdout_error():
+    class Broken:
+        def write(self, data):
+            raise ValueError('broken')
+
+    store = Store()
+    wasi_env = wasi.StateBuilder("test-program").stdout(Broken()).finalize()
+    import_object = wasi_env.generate_import_object(store, wasi.Version.LATEST)
+
+    # The error is reported to the module, as an `errno`.
+    assert Instance(Module(store, FD_WRITE_WAT), import_object).exports.write(1) != 0
+
+# Works in the first preopened directory, i.e. the file descriptor 4
+# after the root at 3: `_start` copies `input.txt` to `output.txt`, and
+# `mkdir` creates the `tmp` directory. Both trap on errors.
+FILES_WAT = """
+(module
+  (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "path_create_directory" (func $path_create_directory (param i32 i32 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "fd_read" (func $fd_read (param i32 i32 i32 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "fd_close" (func $fd_close (param i32) (result i32)))
+  (import "wasi_snapshot_preview1" "fd_seek" (func $fd_seek (param i32 i64 i32 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "fd_filestat_set_size" (func $fd_filestat_set_size (param i32 i64) (result i32)))
+  (memory (export "memory") 1)
+  ;; An `iovec` pointing to a buffer of 1024 bytes at offset 64.
+  (data (i32.const 0) "\\40\\00\\00\\00\\00\\04\\00\\00")
+  (data (i32.const 16) "input.txt")
+  (data (i32.const 32) "output.txt")
+  (data (i32.const 60) "tmp")
+
+  (func $check (param i32)
+    (if (local.get 0) (then unreachable)))
+
+  (func $open (param $path i32) (param $length i32) (param $oflags i32) (param $rights i64) (result i32)
+    (call $check
+      (call $path_open
+        (i32.const 4) (i32.const 0) (local.get $path) (local.get $length) (local.get $oflags)
+        (local.get $rights) (local.get $rights) (i32.const 0) (i32.const 12)))
+    (i32.load (i32.const 12)))
+
+  (func (export "_start")
+    (local $input i32)
+    (local $output i32)
+    ;; Open `input.txt` with `fd_read`, and `output.txt` with
+    ;; `fd_write`, `O_CREAT` and `O_TRUNC`.
+    (local.set $input (call $open (i32.const 16) (i32.const 9) (i32.const 0) (i64.const 2)))
+    (local.set $output (call $open (i32.const 32) (i32.const 10) (i32.const 9) (i64.const 64)))
+    (loop $copy
+      (call $check (call $fd_read (local.get $input) (i32.const 0) (i32.const 1) (i32.const 8)))
+      (br_if 1 (i32.eqz (i32.load (i32.const 8))))
+      (i32.store (i32.const 4) (i32.load (i32.const 8)))
+      (call $check (call $fd_write (local.get $output) (i32.const 0) (i32.const 1) (i32.const 8)))
+      (i32.store (i32.const 4) (i32.const 1024))
+      br $copy)
+    (call $check (call $fd_close (local.get $input)))
+    (call $check (call $fd_close (local.get $output))))
+
+  (func (export "mkdir")
+    (call $check (call $path_create_directory (i32.const 4) (i32.const 60) (i32.const 3))))
+
+  ;; Write the first byte of the buffer at `offset` in `output.txt`,
+  ;; and return the error number.
+  (func (export "write_at") (param $offset i64) (result i32)
+    (local $output i32)
+    (local.set $output (call $open (i32.const 32) (i32.const 10) (i32.const 1) (i64.const 68)))
+    (call $check (call $fd_seek (local.get $output) (local.get $offset) (i32.const 0) (i32.const 8)))
+    (i32.store (i32.const 4) (i32.const 1))
+    (call $fd_write (local.get $output) (i32.const 0) (i32.const 1) (i32.const 8)))
+
+  ;; Set the size of `output.txt`, and return the error number.
+  (func (export "truncate") (param $size i64) (result i32)
+    (call $fd_filestat_set_size
+      (call $open (i32.const 32) (i32.const 10) (i32.const 1) (i64.const 4194304))
+      (local.get $size))))
+"""
+
+def files_instance(state_builder):
+    store = Store()
+    wasi_env = state_builder.finalize()
+    import_object = wasi_env.generate_import_object(store, wasi.Version.LATEST)
+
+    return Instance(Module(store, FILES_WAT), import_object)
+
+def test_wasi_memory_file_system():
+    file_system = wasi.MemoryFileSystem({"input.txt": b"hello, world!" * 100})
+    instance = files_instance(wasi.StateBuilder("files").map_directory("sandbox", file_system))
+
+    instance.exports._start()
+    assert file_system.read_file("/output.txt") == b"hello, world!" * 100
+
+    # The file system can be modified from Python.
+    file_system.write_file("/input.txt", b"again")
+    instance.exports._start()
+    assert file_system.read_file("/output.txt") == b"again"
+
+    instance.exports.mkdir()
+    assert file_system.list_directory() == ["input.txt", "output.txt", "tmp"]
+    assert file_system.list_directory("/tmp") == []
+
+def test_wasi_memory_file_system_max_file_size():
+    file_system = wasi.MemoryFileSystem(max_file_size=1024)
+    instance = files_instance(wasi.StateBuilder("files").map_directory("sandbox", file_system))
+
+    assert instance.exports.write_at(1023) == 0
+    assert len(file_system.read_file("/output.txt")) == 1024
+
+    assert instance.exports.write_at(1024) != 0
+    assert instance.exports.write_at(0x4000000000000000) != 0
+    assert instance.exports.write_at(-1) != 0
+    assert len(file_system.read_file("/output.txt")) == 1024
+
+    assert instance.exports.truncate(0x4000000000000000) != 0
+    assert len(file_system.read_file("/output.txt")) == 1024
+
+    assert instance.exports.truncate(1024) == 0
+    assert instance.exports.truncate(16) == 0
+    assert len(file_system.read_file("/output.txt")) == 16
+
+def test_wasi_memory_file_system_default_max_file_size():
+    file_system = wasi.MemoryFileSystem()
+    instance = files_instance(wasi.StateBuilder("files").map_directory("sandbox", file_system))
+
+    assert instance.exports.write_at(0x4000000000000000) != 0
+    assert file_system.read_file("/output.txt") == b""
+
+def test_wasi_memory_file_system_missing_file():
+    file_system = wasi.MemoryFileSystem()
+    instance = files_instance(wasi.StateBuilder("files").map_directory("sandbox", file_system))
+
+    with pytest.raises(RuntimeError):
+        instance.exports._start()
+
+    assert file_system.to_dict() == {}
+
+def test_wasi_memory_and_host_file_systems(tmp_path):
+    (tmp_path / 'input.txt').write_bytes(b'from the host')
+    file_system = wasi.MemoryFileSystem({"input.txt": b"from memory"})
+
+    # The first preopened directory is the one used by the module.
+    files_instance(
+        wasi.StateBuilder("files"). \
+            map_directory("host", str(tmp_path)). \
+            map_directory("sandbox", file_system)
+    ).exports._start()
+
+    files_instance(
+        wasi.StateBuilder("files"). \
+            map_directories({"sandbox": file_system, "host": tmp_path})
+    ).exports._start()
+
+    assert (tmp_path / 'output.txt').read_bytes() == b'from the host'
+    assert file_system.read_file("/output.txt") == b'from memory'
+
+def test_wasi_memory_file_system_listing():
+    store = Store()
+    wasi_env = \
+        wasi.StateBuilder("test-program"). \
+            map_directory("sandbox", wasi.MemoryFileSystem()). \
+            capture_stdout(). \
+            finalize()
+    import_object = wasi_env.generate_import_object(store, wasi.Version.LATEST)
+
+    Instance(Module(store, TEST_BYTES), import_object).exports._start()
+
+    assert b'Found 1 preopened directories: DirEntry("/sandbox")' in wasi_env.stdout()
+
+def test_memory_file_system_python_api():
+    files = {
+        "a.txt": b"a",
+        "b": {
+            "c.bin": bytes([1, 2, 3]),
+            "d": {},
+        },
+    }
+    file_system = wasi.MemoryFileSystem(files)
+
+    assert file_system.to_dict() == files
+    assert file_system.list_directory("/b") == ["c.bin", "d"]
+    assert file_system.read_file("b/../b/./c.bin") == bytes([1, 2, 3])
+
+    file_system.create_directory("/e")
+    file_system.write_file("/e/f.txt", bytearray(b"f"))
+    assert file_system.to_dict()["e"] == {"f.txt": b"f"}
+
+def test_memory_file_system_errors():
+    file_system = wasi.MemoryFileSystem({"a.txt": b"a", "b": {}})
+
+    with pytest.raises(FileNotFoundError) as context_manager:
+        file_system.read_file("/missing.txt")
+
+    assert str(context_manager.value) == 'entity not found: `/missing.txt`'
+
+    with pytest.raises(IsADirectoryError):
+        file_system.read_file("/b")
+
+    with pytest.raises(FileExistsError):
+        file_system.create_directory("/b")
+
+    with pytest.raises(FileNotFoundError):
+        file_system.write_file("/missing/a.txt", b"a")
+
+    with pytest.raises(NotADirectoryError):
+        file_system.list_directory("/a.txt")
+
+    with pytest.raises(TypeError) as context_manager:
+        wasi.MemoryFileSystem({"a.txt": 42})
+
+    assert str(context_manager.value) == '`MemoryFileSystem` cannot store the given type `int`'
+
+def test_wasi_read_only_directory(tmp_path):
+    (tmp_path / 'input.txt').write_bytes(b'hello')
+
+    state_builders = [
+        wasi.StateBuilder("files").map_directory("sandbox", str(tmp_path), read_only=True),
+        wasi.StateBuilder("files").preopen_directory(str(tmp_path), read_only=True),
+    ]
+
+    for state_builder in state_builders:
+        instance = files_instance(state_builder)
+
+        with pytest.raises(RuntimeError):
+            instance.exports._start()
+
+        with pytest.raises(RuntimeError):
+            instance.exports.mkdir()
+
+        assert os.listdir(tmp_path) == ['input.txt']
+
+def test_wasi_read_only_memory_file_system():
+    file_system = wasi.MemoryFileSystem({"input.txt": b"hello"})
+    instance = files_instance(
+        wasi.StateBuilder("files").map_directory("sandbox", file_system, read_only=True)
+    )
+
+    with pytest.raises(RuntimeError):
+        instance.exports._start()
+
+    assert file_system.to_dict() == {"input.txt": b"hello"}
+
+def test_wasi_no_create_directory(tmp_path):
+    (tmp_path / 'input.txt').write_bytes(b'hello')
+    state_builder = wasi.StateBuilder("files").map_directory("sandbox", str(tmp_path), create=False)
+
+    # `output.txt` cannot be created...
+    with pytest.raises(RuntimeError):
+        files_instance(state_builder).exports._start()
+
+    with pytest.raises(RuntimeError):
+        files_instance(state_builder).exports.mkdir()
+
+    assert os.listdir(tmp_path) == ['input.txt']
+
+    # ... but can be modified once it exists.
+    (tmp_path / 'output.txt').write_bytes(b'previous content')
+    files_instance(state_builder).exports._start()
+
+    assert (tmp_path / 'output.txt').read_bytes() == b'hello'
+
+def test_wasi_no_follow_symlinks(tmp_path):
+    (tmp_path / 'target.txt').write_bytes(b'hello')
+    (tmp_path / 'input.txt').symlink_to('target.txt')
+
+    files_instance(wasi.StateBuilder("files").map_directory("sandbox", str(tmp_path))).exports._start()
+    assert (tmp_path / 'output.txt').read_bytes() == b'hello'
+
+    os.remove(tmp_path / 'output.txt')
+    instance = files_instance(
+        wasi.StateBuilder("files").map_directory("sandbox", str(tmp_path), follow_symlinks=False)
+    )
+
+    with pytest.raises(RuntimeError):
+        instance.exports._start()
+
+    assert not (tmp_path / 'output.txt').exists()
+
+    # Other files are still accessible.
+    os.remove(tmp_path / 'input.txt')
+    (tmp_path / 'input.txt').write_bytes(b'not a link')
+    instance.exports._start()
+
+    assert (tmp_path / 'output.txt').read_bytes() == b'not a link'
+
+def test_wasi_run_path():
+    result = wasi.run(here + '/wasi.wasm', args=['--foo'], env={'ABC': 'DEF'})
+
+    assert isinstance(result, wasi.CompletedProcess)
+    assert result.args == ['wasi.wasm', '--foo']
+    assert result.returncode == 0
+    assert result.stdout == \
+        b'Found program name: `wasi.wasm`\n' \
+        b'Found 1 arguments: --foo\n' \
+        b'Found 1 environment variables: ABC=DEF\n' \
+        b'Found 0 preopened directories: \n'
+    assert result.stderr == b''
+    assert repr(result) == "CompletedProcess(args=['wasi.wasm', '--foo'], returncode=0)"
+
+def test_wasi_run_module():
+    module = Module(Store(), CAT_WAT)
+
+    for stdin in [b'hello', bytearray(b'hello'), io.BytesIO(b'hello')]:
+        result = wasi.run(module, stdin=stdin)
+
+        assert result.args == ['main']
+        assert result.stdout == b'hello'
+
+    assert wasi.run(module).stdout == b''
+
+def test_wasi_run_exit_code():
+    module = Module(
+        Store(),
+        """
+        (module
+          (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+          (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+          (memory (export "memory") 1)
+          (data (i32.const 0) "\\10\\00\\00\\00\\05\\00\\00\\00")
+          (data (i32.const 16) "error")
+          (func (export "_start")
+            (drop (call $fd_write (i32.const 2) (i32.const 0) (i32.const 1) (i32.const 8)))
+            (call $proc_exit (i32.const 3))))
+        """
+    )
+    result = wasi.run(module)
+
+    assert result.returncode == 3
+    assert result.stdout == b''
+    assert result.stderr == b'error'
+
+def test_wasi_run_timeout(tmp_path):
+    spin = '(module (import "wasi_snapshot_preview1" "proc_exit" (func (param i32))) (memory (export "memory") 1) (func (export "_start") (loop br 0)))'
+    path = tmp_path / 'spin.wat'
+    path.write_text(spin)
+
+    with pytest.raises(ExecutionTimeout):
+        wasi.run(path, timeout=0.05)
+
+    from wasmer_compiler_cranelift import Compiler
+
+    module = Module(Store(engine.Universal(Compiler(middlewares=[Interruptible()]))), spin)
+
+    with pytest.raises(ExecutionTimeout):
+        wasi.run(module, timeout=0.05)
+
+def test_wasi_run_errors(tmp_path):
+    with pytest.raises(FileNotFoundError):
+        wasi.run(tmp_path / 'missing.wasm')
+
+    with pytest.raises(ValueError) as context_manager:
+        wasi.run(Module(Store(), '(module (func (export "_start")))'))
+
+    assert str(context_manager.value) == 'The module does not import any WASI namespace'
+
+ECHO_WAT = """
+(module
+  (import "wasi_snapshot_preview1" "sock_accept" (func $accept (param i32 i32 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "sock_recv" (func $recv (param i32 i32 i32 i32 i32 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "sock_send" (func $send (param i32 i32 i32 i32 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "fd_close" (func $close (param i32) (result i32)))
+  (memory (export "memory") 1)
+
+  ;; Accepts a connection on `listener`, and sends back the first
+  ;; bytes it receives. Returns the error number, if any.
+  (func (export "echo") (param $listener i32) (result i32)
+    (local $errno i32)
+
+    (local.set $errno (call $accept (local.get $listener) (i32.const 0) (i32.const 0)))
+    (if (local.get $errno) (then (return (local.get $errno))))
+
+    ;; The iovec at 8 points to 64 bytes at 16.
+    (i32.store (i32.const 8) (i32.const 16))
+    (i32.store (i32.const 12) (i32.const 64))
+
+    (local.set $errno (call $recv (i32.load (i32.const 0)) (i32.const 8) (i32.const 1) (i32.const 0) (i32.const 80) (i32.const 84)))
+    (if (local.get $errno) (then (return (local.get $errno))))
+
+    (i32.store (i32.const 12) (i32.load (i32.const 80)))
+
+    (local.set $errno (call $send (i32.load (i32.const 0)) (i32.const 8) (i32.const 1) (i32.const 0) (i32.const 88)))
+    (if (local.get $errno) (then (return (local.get $errno))))
+
+    (call $close (i32.load (i32.const 0)))))
+"""
+
+def test_wasi_tcp_listen():
+    import socket
+
+    store = Store()
+    wasi_env = wasi.StateBuilder('test-program').tcp_listen('127.0.0.1:0').finalize()
+
+    (listener, address), = wasi_env.sockets.items()
+    assert listener == 4
+
+    client = socket.create_connection(address)
+    client.sendall(b'ping')
+
+    instance = Instance(Module(store, ECHO_WAT), wasi_env.generate_import_object(store, wasi.Version.SNAPSHOT1))
+
+    assert instance.exports.echo(listener) == 0
+    assert client.recv(64) == b'ping'
+    assert client.recv(64) == b''
+    assert list(wasi_env.sockets) == [listener]
+
+def test_wasi_tcp_large_buffers():
+    import socket
+
+    store = Store()
+    wasi_env = wasi.StateBuilder('test-program').tcp_listen('127.0.0.1:0').finalize()
+    client = socket.create_connection(wasi_env.sockets[4])
+    client.sendall(b'ping')
+
+    module = Module(
+        store,
+        """
+        (module
+          (import "wasi_snapshot_preview1" "sock_accept" (func $accept (param i32 i32 i32) (result i32)))
+          (import "wasi_snapshot_preview1" "sock_recv" (func $recv (param i32 i32 i32 i32 i32 i32) (result i32)))
+          (import "wasi_snapshot_preview1" "sock_send" (func $send (param i32 i32 i32 i32 i32) (result i32)))
+          (memory (export "memory") 4)
+
+          (func (export "accept") (result i32)
+            (drop (call $accept (i32.const 4) (i32.const 0) (i32.const 0)))
+            (i32.load (i32.const 0)))
+
+          ;; Receives into, or sends, the `length` bytes at 16, and
+          ;; returns the error number, if any.
+          (func (export "recv") (param $fd i32) (param $length i32) (result i32)
+            (i32.store (i32.const 8) (i32.const 16))
+            (i32.store (i32.const 12) (local.get $length))
+            (call $recv (local.get $fd) (i32.const 8) (i32.const 1) (i32.const 0) (i32.const 0) (i32.const 4)))
+
+          (func (export "send") (param $fd i32) (param $length i32) (result i32)
+            (i32.store (i32.const 8) (i32.const 16))
+            (i32.store (i32.const 12) (local.get $length))
+            (call $send (local.get $fd) (i32.const 8) (i32.const 1) (i32.const 0) (i32.const 0)))
+
+          (func (export "sent") (result i32)
+            (i32.load (i32.const 0))))
+        """
+    )
+    exports = Instance(module, wasi_env.generate_import_object(store, wasi.Version.SNAPSHOT1)).exports
+    fd = exports.accept()
+
+    # `EFAULT`, without allocating a buffer of the size of the iovec.
+    assert exports.recv(fd, -1) == 21
+    assert exports.recv(fd, 200_000) == 0
+    assert bytes(exports.memory.uint8_view()[16:20]) == b'ping'
+
+    exports.memory.uint8_view()[16:200_016] = b'x' * 200_000
+
+    # More than one chunk of 64 KiB.
+    assert exports.send(fd, 200_000) == 0
+    assert exports.sent() == 200_000
+
+    received = b''
+
+    while len(received) < 200_000:
+        received += client.recv(200_000 - len(received))
+
+    assert received == b'x' * 200_000
+
+def test_wasi_tcp_listen_allow():
+    import socket
+
+    store = Store()
+    wasi_env = wasi.StateBuilder('test-program').tcp_listen('127.0.0.1:0', allow=['10.0.0.1']).finalize()
+    address = wasi_env.sockets[4]
+
+    client = socket.create_connection(address)
+    instance = Instance(Module(store, ECHO_WAT), wasi_env.generate_import_object(store, wasi.Version.SNAPSHOT1))
+
+    # `ECONNABORTED`.
+    assert instance.exports.echo(4) == 13
+    assert client.recv(64) == b''
+
+    with pytest.raises(ValueError):
+        wasi.StateBuilder('test-program').tcp_listen('127.0.0.1:0', allow=['localhost'])
+
+def test_wasi_tcp_connect():
+    import socket
+
+    server = socket.create_server(('127.0.0.1', 0))
+    host, port = server.getsockname()
+
+    store = Store()
+    wasi_env = wasi.StateBuilder('test-program').tcp_connect(f'{host}:{port}').finalize()
+    assert wasi_env.sockets == {4: (host, port)}
+
+    module = Module(
+        store,
+        """
+        (module
+          (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+          (import "wasi_snapshot_preview1" "sock_shutdown" (func $shutdown (param i32 i32) (result i32)))
+          (memory (export "memory") 1)
+          (data (i32.const 16) "hello")
+          (func (export "_start")
+            (i32.store (i32.const 0) (i32.const 16))
+            (i32.store (i32.const 4) (i32.const 5))
+            (drop (call $fd_write (i32.const 4) (i32.const 0) (i32.const 1) (i32.const 8)))
+            ;; `SHUT_WR`.
+            (drop (call $shutdown (i32.const 4) (i32.const 2)))))
+        """
+    )
+    Instance(module, wasi_env.generate_import_object(store, wasi.Version.SNAPSHOT1)).exports._start()
+
+    connection, _ = server.accept()
+
+    assert connection.recv(64) == b'hello'
+    assert connection.recv(64) == b''
+
+    with pytest.raises(OSError):
+        wasi.StateBuilder('test-program').tcp_listen(f'{host}:{port}')
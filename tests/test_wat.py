@@ -1,11 +1,33 @@
 from wasmer import wat2wasm, wasm2wat, Instance, Module, Store
+import pytest
 
 def test_wat2wasm():
     assert wat2wasm('(module)') == b'\x00asm\x01\x00\x00\x00'
 
+def test_wat2wasm_invalid():
+    with pytest.raises(RuntimeError) as context_manager:
+        wat2wasm('(module')
+
+    exception = context_manager.value
+    assert str(exception).startswith('expected `)`')
+
 def test_wasm2wat():
     assert wasm2wat(b'\x00asm\x01\x00\x00\x00') == '(module)'
 
+def test_wasm2wat_invalid():
+    with pytest.raises(RuntimeError) as context_manager:
+        wasm2wat(b'\x00asm')
+
+    exception = context_manager.value
+    assert str(exception) == 'Unexpected EOF (at offset 4)'
+
+def test_module_from_invalid_wat():
+    with pytest.raises(RuntimeError) as context_manager:
+        Module(Store(), '(module')
+
+    exception = context_manager.value
+    assert 'expected `)`' in str(exception)
+
 def test_wat2wasm2instance():
     wat = """ (module
                 (type (func (param i32 i32) (result i32)))